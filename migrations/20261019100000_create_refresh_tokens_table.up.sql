@@ -0,0 +1,15 @@
+CREATE TABLE `refresh_tokens`
+(
+    `id`          int(11)     not null auto_increment,
+    `user_id`     int(11)     not null,
+    `family_id`   varchar(36) not null,
+    `token_hash`  varchar(64) not null,
+    `expires_at`  datetime    not null,
+    `used_at`     datetime    default null,
+    `revoked_at`  datetime    default null,
+    `created_at`  datetime    not null,
+    primary key (`id`),
+    unique key `refresh_tokens_token_hash` (`token_hash`),
+    key `refresh_tokens_family_id` (`family_id`),
+    constraint `refresh_tokens_r_users_uid` FOREIGN KEY (`user_id`) REFERENCES `mor`.`r_users` (`uid`) on delete cascade
+);
@@ -0,0 +1,23 @@
+CREATE TABLE `two_factor_auth`
+(
+    `user_id`        int(11)     not null,
+    `secret`         varchar(64) not null,
+    `enabled`        tinyint(1)  not null default 0,
+    `last_used_step` bigint      default null,
+    `created_at`     datetime    not null,
+    `updated_at`     datetime    not null,
+    primary key (`user_id`),
+    constraint `two_factor_auth_r_users_uid` FOREIGN KEY (`user_id`) REFERENCES `mor`.`r_users` (`uid`) on delete cascade
+);
+
+CREATE TABLE `two_factor_recovery_codes`
+(
+    `id`         int(11)     not null auto_increment,
+    `user_id`    int(11)     not null,
+    `code_hash`  varchar(64) not null,
+    `used_at`    datetime    default null,
+    `created_at` datetime    not null,
+    primary key (`id`),
+    key `two_factor_recovery_codes_user_id` (`user_id`),
+    constraint `two_factor_recovery_codes_r_users_uid` FOREIGN KEY (`user_id`) REFERENCES `mor`.`r_users` (`uid`) on delete cascade
+);
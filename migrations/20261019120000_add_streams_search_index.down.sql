@@ -0,0 +1 @@
+ALTER TABLE `r_streams` DROP KEY `NAME_INFO`;
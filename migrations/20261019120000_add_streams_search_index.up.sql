@@ -0,0 +1 @@
+ALTER TABLE `r_streams` ADD FULLTEXT KEY `NAME_INFO` (`name`, `info`);
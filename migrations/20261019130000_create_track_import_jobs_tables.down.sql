@@ -0,0 +1,2 @@
+DROP TABLE `track_import_job_errors`;
+DROP TABLE `track_import_jobs`;
@@ -0,0 +1,30 @@
+CREATE TABLE `track_import_jobs`
+(
+    `id`              int(11)                                             not null auto_increment,
+    `user_id`         int(11)                                             not null,
+    `stream_id`       int(11)                                             default null,
+    `source`          enum ('ZIP','DIRECTORY')                            not null,
+    `status`          enum ('PENDING','RUNNING','FINISHED','FAILED')      not null default 'PENDING',
+    `total_files`     int(11)                                             not null default 0,
+    `processed_files` int(11)                                             not null default 0,
+    `imported_files`  int(11)                                             not null default 0,
+    `skipped_files`   int(11)                                             not null default 0,
+    `failed_files`    int(11)                                             not null default 0,
+    `created_at`      datetime                                            not null,
+    `finished_at`     datetime                                            default null,
+    primary key (`id`),
+    key `track_import_jobs_user_id` (`user_id`),
+    constraint `track_import_jobs_r_users_uid` FOREIGN KEY (`user_id`) REFERENCES `mor`.`r_users` (`uid`) on delete cascade,
+    constraint `track_import_jobs_r_streams_sid` FOREIGN KEY (`stream_id`) REFERENCES `mor`.`r_streams` (`sid`) on delete set null
+);
+
+CREATE TABLE `track_import_job_errors`
+(
+    `id`        int(11)       not null auto_increment,
+    `job_id`    int(11)       not null,
+    `file_name` varchar(1024) not null,
+    `error`     varchar(255)  not null,
+    primary key (`id`),
+    key `track_import_job_errors_job_id` (`job_id`),
+    constraint `track_import_job_errors_track_import_jobs_id` FOREIGN KEY (`job_id`) REFERENCES `mor`.`track_import_jobs` (`id`) on delete cascade
+);
@@ -0,0 +1,9 @@
+CREATE TABLE `track_artworks`
+(
+    `track_id`     int(11)      not null,
+    `artwork_hash` varchar(128) not null,
+    `created_at`   datetime     not null,
+    primary key (`track_id`),
+    key `track_artworks_artwork_hash` (`artwork_hash`),
+    constraint `track_artworks_r_tracks_tid` FOREIGN KEY (`track_id`) REFERENCES `mor`.`r_tracks` (`tid`) on delete cascade
+);
@@ -0,0 +1,17 @@
+UPDATE `r_tracks`
+SET `color` = 0
+WHERE `color` IN (SELECT `color_id` FROM `r_colors` WHERE `user_id` IS NOT NULL);
+
+DELETE FROM `r_colors` WHERE `user_id` IS NOT NULL;
+
+ALTER TABLE `r_tracks`
+    DROP FOREIGN KEY `r_tracks_ibfk_2`;
+
+ALTER TABLE `r_colors`
+    DROP FOREIGN KEY `r_colors_r_users_uid`,
+    DROP KEY `r_colors_user_id`,
+    DROP COLUMN `user_id`,
+    MODIFY `color_id` int(11) NOT NULL;
+
+ALTER TABLE `r_tracks`
+    ADD CONSTRAINT `r_tracks_ibfk_2` FOREIGN KEY (`color`) REFERENCES `r_colors` (`color_id`);
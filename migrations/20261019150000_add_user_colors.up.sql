@@ -0,0 +1,18 @@
+-- The column referenced by the track colors can't be changed while the foreign key exists.
+ALTER TABLE `r_tracks`
+    DROP FOREIGN KEY `r_tracks_ibfk_2`;
+
+-- The preset color has id 0, which must not be renumbered when the column becomes auto-incremented.
+SET @previous_sql_mode = @@SESSION.sql_mode;
+SET SESSION sql_mode = CONCAT(@@SESSION.sql_mode, ',NO_AUTO_VALUE_ON_ZERO');
+
+ALTER TABLE `r_colors`
+    MODIFY `color_id` int(11) NOT NULL AUTO_INCREMENT,
+    ADD COLUMN `user_id` int(11) DEFAULT NULL,
+    ADD KEY `r_colors_user_id` (`user_id`),
+    ADD CONSTRAINT `r_colors_r_users_uid` FOREIGN KEY (`user_id`) REFERENCES `mor`.`r_users` (`uid`) ON DELETE CASCADE;
+
+SET SESSION sql_mode = @previous_sql_mode;
+
+ALTER TABLE `r_tracks`
+    ADD CONSTRAINT `r_tracks_ibfk_2` FOREIGN KEY (`color`) REFERENCES `r_colors` (`color_id`);
@@ -0,0 +1,13 @@
+-- Tracks added to any stream are no longer new.
+DROP TRIGGER IF EXISTS `rlink.when.added`;
+
+CREATE TRIGGER `rlink.when.added` AFTER INSERT ON `r_link` FOR EACH ROW BEGIN
+
+    SELECT duration INTO @duration FROM r_tracks WHERE tid = NEW.track_id;
+    UPDATE r_tracks SET used_count = used_count + 1, is_new = 0 WHERE tid = NEW.track_id;
+
+    INSERT INTO `r_static_stream_vars` SET `stream_id` = NEW.`stream_id`, `tracks_count` = 1, `tracks_duration` = @duration ON DUPLICATE KEY UPDATE  `tracks_count` = `tracks_count` + 1, `tracks_duration` = `tracks_duration` + @duration;
+
+END;
+
+UPDATE `r_tracks` SET `is_new` = 0 WHERE `is_new` = 1 AND `used_count` > 0;
@@ -0,0 +1,5 @@
+ALTER TABLE `mor_track_stat`
+    DROP COLUMN `last_played_at`,
+    DROP COLUMN `plays`;
+
+DROP TABLE `track_plays`;
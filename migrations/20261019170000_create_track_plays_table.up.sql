@@ -0,0 +1,24 @@
+CREATE TABLE `track_plays`
+(
+    `id`         bigint(20)   not null auto_increment,
+    `user_id`    int(11)      not null,
+    `stream_id`  int(11)      default null,
+    `track_id`   int(11)      default null,
+    `artist`     varchar(255) not null,
+    `title`      varchar(255) not null,
+    `album`      varchar(255) not null,
+    `duration`   int(11)      not null,
+    `started_at` datetime(3)  not null,
+    `listeners`  int(11)      not null default 0,
+    primary key (`id`),
+    unique key `track_plays_stream_id_started_at` (`stream_id`, `started_at`),
+    key `track_plays_user_id_started_at` (`user_id`, `started_at`),
+    key `track_plays_track_id` (`track_id`),
+    constraint `track_plays_r_users_uid` FOREIGN KEY (`user_id`) REFERENCES `mor`.`r_users` (`uid`) on delete cascade,
+    constraint `track_plays_r_streams_sid` FOREIGN KEY (`stream_id`) REFERENCES `mor`.`r_streams` (`sid`) on delete set null,
+    constraint `track_plays_r_tracks_tid` FOREIGN KEY (`track_id`) REFERENCES `mor`.`r_tracks` (`tid`) on delete set null
+);
+
+ALTER TABLE `mor_track_stat`
+    ADD COLUMN `plays`          int(11)     not null default 0,
+    ADD COLUMN `last_played_at` datetime(3) default null;
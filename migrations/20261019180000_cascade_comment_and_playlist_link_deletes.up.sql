@@ -0,0 +1,13 @@
+ALTER TABLE `mor_comment`
+    DROP FOREIGN KEY `mor_comment_ibfk_1`,
+    DROP FOREIGN KEY `mor_comment_ibfk_2`;
+
+ALTER TABLE `mor_comment`
+    ADD CONSTRAINT `mor_comment_ibfk_1` FOREIGN KEY (`comment_stream`) REFERENCES `r_streams` (`sid`) ON DELETE CASCADE ON UPDATE NO ACTION,
+    ADD CONSTRAINT `mor_comment_ibfk_2` FOREIGN KEY (`comment_user`) REFERENCES `r_users` (`uid`) ON DELETE CASCADE ON UPDATE NO ACTION;
+
+ALTER TABLE `mor_playlists_link`
+    DROP FOREIGN KEY `mor_playlists_link_ibfk_2`;
+
+ALTER TABLE `mor_playlists_link`
+    ADD CONSTRAINT `mor_playlists_link_ibfk_2` FOREIGN KEY (`track_id`) REFERENCES `r_tracks` (`tid`) ON DELETE CASCADE;
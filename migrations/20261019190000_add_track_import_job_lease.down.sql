@@ -0,0 +1,3 @@
+ALTER TABLE `track_import_jobs`
+    DROP KEY `track_import_jobs_status_lease_expires_at`,
+    DROP COLUMN `lease_expires_at`;
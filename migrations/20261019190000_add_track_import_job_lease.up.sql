@@ -0,0 +1,3 @@
+ALTER TABLE `track_import_jobs`
+    ADD COLUMN `lease_expires_at` datetime default null,
+    ADD KEY `track_import_jobs_status_lease_expires_at` (`status`, `lease_expires_at`);
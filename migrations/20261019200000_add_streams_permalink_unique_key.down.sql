@@ -0,0 +1 @@
+ALTER TABLE `r_streams` DROP KEY `r_streams_permalink`;
@@ -0,0 +1 @@
+ALTER TABLE `r_streams` ADD UNIQUE KEY `r_streams_permalink` (`permalink`);
@@ -18,7 +18,10 @@ class DoGetCollection implements Controller {
         $response->setData([
             "countries"     => $query->selectFrom("mor_countries")->fetchAll(),
             "categories"    => $query->selectFrom("r_categories")->fetchAll(),
-            "groups"        => $query->selectFrom("r_colors")->fetchAll(),
+            "groups"        => $query->selectFrom("r_colors")
+                ->select("color_id", "color_name", "color_code")
+                ->where("user_id IS NULL")
+                ->fetchAll(),
             "genres"        => $query->selectFrom("mor_genres")->fetchAll(),
             "access"        => $query->selectFrom("mor_access")->fetchAll()
         ]);
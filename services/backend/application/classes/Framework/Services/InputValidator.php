@@ -291,7 +291,8 @@ class InputValidator implements Injectable {
     }
 
     public function validateTrackColor($color) {
-        Color::getByID($color)
+        // Colors of the users are managed by the new API only
+        Color::getByFilter("color_id = :id AND user_id IS NULL", [":id" => $color])
             ->justThrow(new ControllerException(I18n::tr("VALIDATOR_TRACK_COLOR", [$color])));
     }
 
@@ -1,5 +1,6 @@
 use crate::system::which;
 use serde::Deserialize;
+use std::net::IpAddr;
 use tracing::Level;
 
 #[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
@@ -36,6 +37,10 @@ fn default_shutdown_timeout() -> u64 {
     30u64
 }
 
+fn default_trash_retention_days() -> u64 {
+    30u64
+}
+
 fn default_path_to_ffprobe() -> String {
     match which("ffprobe") {
         Some(path) => path,
@@ -97,6 +102,116 @@ pub(crate) struct WebEgressControllerConfig {
     pub(crate) stream_player_url_prefix: String,
 }
 
+/// Numbers in flattened configs have to be parsed manually since `envy` provides
+/// all values as strings.
+fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = String::deserialize(deserializer)?;
+
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+/// Parses a comma-separated list of IP addresses.
+fn deserialize_ip_addrs<'de, D>(deserializer: D) -> Result<Vec<IpAddr>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_email_sender_email() -> String {
+    "noreply@radioter.io".to_string()
+}
+
+fn default_email_sender_name() -> String {
+    "Radioterio".to_string()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct SmtpConfig {
+    #[serde(rename = "smtp_host")]
+    pub(crate) host: String,
+    #[serde(
+        rename = "smtp_port",
+        default = "default_smtp_port",
+        deserialize_with = "deserialize_from_str"
+    )]
+    pub(crate) port: u16,
+    #[serde(rename = "smtp_user")]
+    pub(crate) user: String,
+    #[serde(rename = "smtp_password")]
+    pub(crate) password: String,
+    #[serde(rename = "email_sender_email", default = "default_email_sender_email")]
+    pub(crate) sender_email: String,
+    #[serde(rename = "email_sender_name", default = "default_email_sender_name")]
+    pub(crate) sender_name: String,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FileSystemBackend {
+    Local,
+    S3,
+}
+
+fn default_file_system_backend() -> FileSystemBackend {
+    FileSystemBackend::Local
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_s3_force_path_style() -> bool {
+    true
+}
+
+fn default_s3_presigned_url_expires_in() -> u64 {
+    3600
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct S3Config {
+    #[serde(rename = "s3_endpoint", default)]
+    pub(crate) endpoint: String,
+    #[serde(rename = "s3_region", default = "default_s3_region")]
+    pub(crate) region: String,
+    #[serde(rename = "s3_bucket", default)]
+    pub(crate) bucket: String,
+    #[serde(rename = "s3_access_key_id", default)]
+    pub(crate) access_key_id: String,
+    #[serde(rename = "s3_secret_access_key", default)]
+    pub(crate) secret_access_key: String,
+    /// Use `{endpoint}/{bucket}/{key}` URLs instead of `{bucket}.{endpoint}/{key}`.
+    #[serde(
+        rename = "s3_force_path_style",
+        default = "default_s3_force_path_style",
+        deserialize_with = "deserialize_from_str"
+    )]
+    pub(crate) force_path_style: bool,
+    #[serde(
+        rename = "s3_presigned_url_expires_in",
+        default = "default_s3_presigned_url_expires_in",
+        deserialize_with = "deserialize_from_str"
+    )]
+    pub(crate) presigned_url_expires_in: u64,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub(crate) struct Config {
     #[serde(default = "default_bind_address")]
@@ -119,8 +234,38 @@ pub(crate) struct Config {
     pub(crate) pubsub: PubsubBackendConfig,
     #[serde(flatten)]
     pub(crate) web_egress_controller: WebEgressControllerConfig,
+    /// Sending emails is disabled if SMTP is not configured.
+    #[serde(flatten)]
+    pub(crate) smtp: Option<SmtpConfig>,
+    /// Address of the web app used in the links sent by email.
+    #[serde(default)]
+    pub(crate) web_server_own_address: Option<String>,
     pub(crate) file_server_endpoint: String,
+    #[serde(default = "default_file_system_backend")]
+    pub(crate) file_system_backend: FileSystemBackend,
+    #[serde(default)]
     pub(crate) file_system_root_path: String,
+    #[serde(flatten)]
+    pub(crate) s3: S3Config,
+    /// Interval in seconds between file garbage collector runs. Disabled if not set.
+    #[serde(default)]
+    pub(crate) file_gc_interval: Option<u64>,
+    #[serde(default)]
+    pub(crate) file_gc_dry_run: bool,
+    /// Interval in seconds between purges of the tracks kept in the trash longer than
+    /// the retention period. Disabled if not set.
+    #[serde(default)]
+    pub(crate) trash_purge_interval: Option<u64>,
+    #[serde(default = "default_trash_retention_days")]
+    pub(crate) trash_retention_days: u64,
+    /// Server-side directory whose subdirectories can be imported through the internal
+    /// admin API. Disabled if not set.
+    #[serde(default)]
+    pub(crate) import_directory_root: Option<String>,
+    /// Comma-separated addresses of the reverse proxies whose `X-Forwarded-For` header
+    /// is trusted to tell the client address.
+    #[serde(default, deserialize_with = "deserialize_ip_addrs")]
+    pub(crate) trusted_proxies: Vec<IpAddr>,
     pub(crate) auth_jwt_secret_key: String,
     pub(crate) legacy_auth_jwt_secret_key: String,
 }
@@ -133,3 +278,41 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct TestConfig {
+        #[serde(flatten)]
+        smtp: SmtpConfig,
+    }
+
+    #[test]
+    fn test_parses_flattened_smtp_config() {
+        let config: TestConfig = envy::from_iter(vec![
+            ("SMTP_HOST".to_string(), "localhost".to_string()),
+            ("SMTP_PORT".to_string(), "2525".to_string()),
+            ("SMTP_USER".to_string(), "user".to_string()),
+            ("SMTP_PASSWORD".to_string(), "password".to_string()),
+        ])
+        .unwrap();
+
+        assert_eq!(2525, config.smtp.port);
+    }
+
+    #[derive(Deserialize)]
+    struct OptionalSmtpTestConfig {
+        #[serde(flatten)]
+        smtp: Option<SmtpConfig>,
+    }
+
+    #[test]
+    fn test_smtp_config_is_optional() {
+        let config: OptionalSmtpTestConfig =
+            envy::from_iter(Vec::<(String, String)>::new()).unwrap();
+
+        assert!(config.smtp.is_none());
+    }
+}
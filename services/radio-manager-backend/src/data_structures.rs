@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::ops::Deref;
 
-#[derive(Serialize_repr, Deserialize_repr, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Copy, Debug, PartialEq)]
 #[repr(u8)]
 pub(crate) enum SortingColumn {
     TrackId,
@@ -31,7 +31,7 @@ impl SortingColumn {
     }
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Copy, Debug, PartialEq)]
 #[repr(u8)]
 pub(crate) enum SortingOrder {
     Desc,
@@ -53,6 +53,25 @@ impl SortingOrder {
     }
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum StreamsSorting {
+    #[default]
+    Listeners,
+    Activity,
+}
+
+impl StreamsSorting {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            StreamsSorting::Listeners => "`listeners_count` DESC, `r_streams`.`sid` DESC",
+            StreamsSorting::Activity => {
+                "`r_streams`.`status` = 1 DESC, COALESCE(`r_streams`.`started`, `r_streams`.`created` * 1000) DESC"
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug, Eq, PartialEq)]
 #[sqlx(transparent)]
 pub(crate) struct UserId(i32);
@@ -83,7 +102,13 @@ impl Deref for TrackId {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug)]
+impl From<i32> for TrackId {
+    fn from(id: i32) -> Self {
+        TrackId(id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug, PartialEq)]
 #[sqlx(transparent)]
 pub(crate) struct FileId(i32);
 
@@ -95,7 +120,98 @@ impl Deref for FileId {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug)]
+impl From<i32> for FileId {
+    fn from(id: i32) -> Self {
+        FileId(id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub(crate) enum StreamAccess {
+    Public,
+    Unlisted,
+    Private,
+}
+
+impl StreamAccess {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            StreamAccess::Public => "PUBLIC",
+            StreamAccess::Unlisted => "UNLISTED",
+            StreamAccess::Private => "PRIVATE",
+        }
+    }
+}
+
+/// Unknown access values are treated as private.
+impl From<&str> for StreamAccess {
+    fn from(access: &str) -> Self {
+        match access {
+            "PUBLIC" => StreamAccess::Public,
+            "UNLISTED" => StreamAccess::Unlisted,
+            _ => StreamAccess::Private,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub(crate) enum TrackImportSource {
+    Zip,
+    Directory,
+}
+
+impl TrackImportSource {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            TrackImportSource::Zip => "ZIP",
+            TrackImportSource::Directory => "DIRECTORY",
+        }
+    }
+}
+
+impl From<&str> for TrackImportSource {
+    fn from(source: &str) -> Self {
+        match source {
+            "DIRECTORY" => TrackImportSource::Directory,
+            _ => TrackImportSource::Zip,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub(crate) enum TrackImportStatus {
+    Pending,
+    Running,
+    Finished,
+    Failed,
+}
+
+impl TrackImportStatus {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            TrackImportStatus::Pending => "PENDING",
+            TrackImportStatus::Running => "RUNNING",
+            TrackImportStatus::Finished => "FINISHED",
+            TrackImportStatus::Failed => "FAILED",
+        }
+    }
+}
+
+impl From<&str> for TrackImportStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "RUNNING" => TrackImportStatus::Running,
+            "FINISHED" => TrackImportStatus::Finished,
+            "FAILED" => TrackImportStatus::Failed,
+            _ => TrackImportStatus::Pending,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug, PartialEq, Eq, Hash)]
 #[sqlx(transparent)]
 pub(crate) struct StreamId(i32);
 
@@ -107,7 +223,40 @@ impl Deref for StreamId {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug)]
+impl From<i32> for StreamId {
+    fn from(id: i32) -> Self {
+        StreamId(id)
+    }
+}
+
+/// Reference to a stream in a URL path: either the numeric stream id or the stream permalink.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum StreamRef {
+    Id(StreamId),
+    Permalink(String),
+}
+
+impl From<&str> for StreamRef {
+    fn from(value: &str) -> Self {
+        match value.parse::<i32>() {
+            Ok(id) => StreamRef::Id(StreamId(id)),
+            Err(_) => StreamRef::Permalink(value.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamRef {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        Ok(StreamRef::from(value.as_str()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug, PartialEq)]
 #[sqlx(transparent)]
 pub(crate) struct LinkId(i64);
 
@@ -119,7 +268,7 @@ impl Deref for LinkId {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug)]
+#[derive(Serialize, Deserialize, Clone, sqlx::Type, Debug, PartialEq)]
 #[sqlx(transparent)]
 pub(crate) struct OrderId(i32);
 
@@ -149,3 +298,30 @@ impl std::ops::Sub<i32> for OrderId {
 
 // Copied from Defaults.php
 pub(crate) const DEFAULT_TRACKS_PER_REQUEST: i64 = 200;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_stream_ref() {
+        assert_eq!(StreamRef::Id(StreamId(42)), StreamRef::from("42"));
+        assert_eq!(
+            StreamRef::Permalink("my-jazz-radio".to_string()),
+            StreamRef::from("my-jazz-radio")
+        );
+        assert_eq!(
+            StreamRef::Permalink("42-jazz".to_string()),
+            StreamRef::from("42-jazz")
+        );
+    }
+
+    #[test]
+    fn test_parses_stream_access() {
+        assert_eq!(StreamAccess::Public, StreamAccess::from("PUBLIC"));
+        assert_eq!(StreamAccess::Unlisted, StreamAccess::from("UNLISTED"));
+        assert_eq!(StreamAccess::Private, StreamAccess::from("PRIVATE"));
+        assert_eq!(StreamAccess::Private, StreamAccess::from("public"));
+        assert_eq!(StreamAccess::Private, StreamAccess::from(""));
+    }
+}
@@ -0,0 +1,74 @@
+use actix_web::HttpRequest;
+use std::net::IpAddr;
+
+/// Returns the address of the client that sent the request. `X-Forwarded-For` is only
+/// followed through the trusted proxies, so clients can't spoof their address.
+pub(crate) fn client_ip(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let peer_ip = req.peer_addr()?.ip();
+    let forwarded_for = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    Some(resolve_client_ip(peer_ip, &forwarded_for, trusted_proxies).to_string())
+}
+
+/// Walks the `X-Forwarded-For` hops from the closest one and returns the first address
+/// not belonging to a trusted proxy.
+fn resolve_client_ip(peer_ip: IpAddr, forwarded_for: &str, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let mut client_ip = peer_ip;
+
+    for hop in forwarded_for.rsplit(',') {
+        if !trusted_proxies.contains(&client_ip) {
+            break;
+        }
+
+        match hop.trim().parse() {
+            Ok(hop_ip) => client_ip = hop_ip,
+            Err(_) => break,
+        }
+    }
+
+    client_ip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ignores_forwarded_for_from_untrusted_peer() {
+        assert_eq!(
+            ip("10.0.0.1"),
+            resolve_client_ip(ip("10.0.0.1"), "1.2.3.4", &[ip("10.0.0.2")])
+        );
+    }
+
+    #[test]
+    fn test_follows_forwarded_for_through_trusted_proxies() {
+        let trusted_proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+        assert_eq!(
+            ip("5.6.7.8"),
+            resolve_client_ip(
+                ip("10.0.0.1"),
+                "1.2.3.4, 5.6.7.8, 10.0.0.2",
+                &trusted_proxies
+            )
+        );
+        assert_eq!(
+            ip("10.0.0.1"),
+            resolve_client_ip(ip("10.0.0.1"), "", &trusted_proxies)
+        );
+        assert_eq!(
+            ip("10.0.0.2"),
+            resolve_client_ip(ip("10.0.0.1"), "garbage, 10.0.0.2", &trusted_proxies)
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Encodes the pagination position as an opaque string, so clients don't depend on its
+/// structure.
+pub(crate) fn encode_cursor<T: Serialize>(position: &T) -> String {
+    hex::encode(serde_json::to_vec(position).expect("Cursor position is serializable"))
+}
+
+/// Returns `None` if the string is not a cursor produced by [`encode_cursor`].
+pub(crate) fn decode_cursor<T: DeserializeOwned>(cursor: &str) -> Option<T> {
+    let data = hex::decode(cursor).ok()?;
+
+    serde_json::from_slice(&data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Position {
+        id: i32,
+        title: String,
+    }
+
+    #[test]
+    fn test_encodes_and_decodes_cursor() {
+        let position = Position {
+            id: 42,
+            title: "Song".to_string(),
+        };
+
+        assert_eq!(
+            Some(position),
+            decode_cursor(&encode_cursor(&Position {
+                id: 42,
+                title: "Song".to_string(),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_cursor() {
+        assert_eq!(None, decode_cursor::<Position>("not a cursor"));
+        assert_eq!(None, decode_cursor::<Position>(&hex::encode("{\"id\":1}")));
+    }
+}
@@ -0,0 +1,194 @@
+/// Byte range of the file requested using the `Range` header.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ByteRange {
+    pub(crate) offset: u64,
+    pub(crate) length: u64,
+}
+
+impl ByteRange {
+    pub(crate) fn content_range(&self, file_size: u64) -> String {
+        format!(
+            "bytes {}-{}/{}",
+            self.offset,
+            self.offset + self.length - 1,
+            file_size
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum RangeRequest {
+    /// No range requested or the range can't be served, so the whole file is returned.
+    Full,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
+
+/// Parses the `Range` header value against the file of the given size.
+/// Only single ranges are supported: multiple ranges and malformed values are ignored
+/// as allowed by RFC 7233.
+pub(crate) fn parse_range_header(value: &str, file_size: u64) -> RangeRequest {
+    let spec = match value.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return RangeRequest::Full,
+    };
+
+    let (start, end) = match spec.split_once('-') {
+        Some(parts) => parts,
+        None => return RangeRequest::Full,
+    };
+
+    match (start.parse::<u64>(), end.parse::<u64>()) {
+        // bytes=-500: the last 500 bytes
+        (Err(_), Ok(suffix_length)) if start.is_empty() => {
+            if suffix_length == 0 || file_size == 0 {
+                return RangeRequest::Unsatisfiable;
+            }
+
+            let length = suffix_length.min(file_size);
+
+            RangeRequest::Partial(ByteRange {
+                offset: file_size - length,
+                length,
+            })
+        }
+        // bytes=500-
+        (Ok(start), Err(_)) if end.is_empty() => match start < file_size {
+            true => RangeRequest::Partial(ByteRange {
+                offset: start,
+                length: file_size - start,
+            }),
+            false => RangeRequest::Unsatisfiable,
+        },
+        // bytes=500-999
+        (Ok(start), Ok(end)) if start <= end => match start < file_size {
+            true => RangeRequest::Partial(ByteRange {
+                offset: start,
+                length: end.min(file_size - 1) - start + 1,
+            }),
+            false => RangeRequest::Unsatisfiable,
+        },
+        _ => RangeRequest::Full,
+    }
+}
+
+/// Strong entity tag of the file derived from its content hash.
+pub(crate) fn file_etag(file_hash: &str) -> String {
+    format!("\"{}\"", file_hash)
+}
+
+/// Checks whether the `If-None-Match` header value matches the entity tag
+/// using the weak comparison.
+pub(crate) fn if_none_match(value: &str, etag: &str) -> bool {
+    value.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+/// Builds the `Content-Disposition` header value as described in RFC 6266:
+/// an ASCII fallback in `filename` and the UTF-8 encoded name in `filename*`.
+pub(crate) fn attachment_content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        urlencoding::encode(file_name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_range_header() {
+        assert_eq!(
+            RangeRequest::Partial(ByteRange {
+                offset: 0,
+                length: 500
+            }),
+            parse_range_header("bytes=0-499", 1000)
+        );
+        assert_eq!(
+            RangeRequest::Partial(ByteRange {
+                offset: 500,
+                length: 500
+            }),
+            parse_range_header("bytes=500-", 1000)
+        );
+        assert_eq!(
+            RangeRequest::Partial(ByteRange {
+                offset: 900,
+                length: 100
+            }),
+            parse_range_header("bytes=-100", 1000)
+        );
+        assert_eq!(
+            RangeRequest::Partial(ByteRange {
+                offset: 900,
+                length: 100
+            }),
+            parse_range_header("bytes=900-5000", 1000)
+        );
+        assert_eq!(
+            RangeRequest::Partial(ByteRange {
+                offset: 0,
+                length: 1000
+            }),
+            parse_range_header("bytes=-5000", 1000)
+        );
+        assert_eq!(
+            RangeRequest::Unsatisfiable,
+            parse_range_header("bytes=1000-", 1000)
+        );
+        assert_eq!(
+            RangeRequest::Unsatisfiable,
+            parse_range_header("bytes=-0", 1000)
+        );
+        assert_eq!(
+            RangeRequest::Full,
+            parse_range_header("bytes=0-1,5-6", 1000)
+        );
+        assert_eq!(RangeRequest::Full, parse_range_header("bytes=5-1", 1000));
+        assert_eq!(RangeRequest::Full, parse_range_header("items=0-1", 1000));
+    }
+
+    #[test]
+    fn test_formats_content_range() {
+        let range = ByteRange {
+            offset: 500,
+            length: 500,
+        };
+
+        assert_eq!("bytes 500-999/1000", range.content_range(1000));
+    }
+
+    #[test]
+    fn test_matches_if_none_match() {
+        let etag = file_etag("abcdef");
+
+        assert!(if_none_match("\"abcdef\"", &etag));
+        assert!(if_none_match("\"123\", W/\"abcdef\"", &etag));
+        assert!(if_none_match("*", &etag));
+        assert!(!if_none_match("\"123\"", &etag));
+    }
+
+    #[test]
+    fn test_builds_attachment_content_disposition() {
+        assert_eq!(
+            "attachment; filename=\"track.mp3\"; filename*=UTF-8''track.mp3",
+            attachment_content_disposition("track.mp3")
+        );
+        assert_eq!(
+            "attachment; filename=\"_ _ _.mp3\"; filename*=UTF-8''%C3%A9%20%22%20%D0%AF.mp3",
+            attachment_content_disposition("é \" Я.mp3")
+        );
+    }
+}
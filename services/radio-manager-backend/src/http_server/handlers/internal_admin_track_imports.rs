@@ -0,0 +1,38 @@
+use crate::data_structures::{StreamRef, UserId};
+use crate::http_server::handlers::user_track_imports::{job_json, track_import_error_response};
+use crate::http_server::response::Response;
+use crate::services::TrackImportService;
+use crate::storage::fs::FileSystem;
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StartDirectoryImportPayload {
+    user_id: UserId,
+    /// Path of the directory relative to the configured import root.
+    path: String,
+    #[serde(default)]
+    stream_id: Option<StreamRef>,
+}
+
+/// Starts importing the server-side directory into the user's library in the background.
+pub(crate) async fn start_directory_import<FS: FileSystem + Clone + 'static>(
+    payload: Json<StartDirectoryImportPayload>,
+    track_import_service: Data<TrackImportService>,
+    file_system: Data<FS>,
+) -> Response {
+    match track_import_service
+        .start_directory_import(
+            &payload.user_id,
+            &payload.stream_id,
+            std::path::Path::new(&payload.path),
+            file_system.get_ref(),
+        )
+        .await
+    {
+        Ok(job) => Ok(HttpResponse::Accepted().json(job_json(&job))),
+        Err(error) => track_import_error_response(error),
+    }
+}
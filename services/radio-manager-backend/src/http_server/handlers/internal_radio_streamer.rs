@@ -1,9 +1,16 @@
-use crate::data_structures::StreamId;
+use crate::data_structures::{StreamId, TrackId};
+use crate::http_server::handlers::user_play_history::play_history_error_response;
 use crate::http_server::response::Response;
+use crate::services::auth::AuthTokenService;
+use crate::services::PlayHistoryService;
 use crate::storage::db::repositories::streams;
 use crate::storage::db::repositories::user_stream_tracks::TrackFileLinkMergedRow;
+use crate::storage::fs::utils::get_audio_file_url;
+use crate::storage::fs::FileSystem;
 use crate::{services, Config, MySqlClient, StreamServiceFactory};
 use actix_web::{web, HttpResponse};
+use chrono::{TimeZone, Utc};
+use serde::Deserialize;
 use std::time::UNIX_EPOCH;
 
 fn get_artist_and_title(row: &TrackFileLinkMergedRow) -> String {
@@ -20,10 +27,24 @@ fn get_file_path(row: &TrackFileLinkMergedRow) -> String {
     )
 }
 
-pub(crate) async fn get_playing_at(
+async fn get_track_url<FS: FileSystem>(
+    row: &TrackFileLinkMergedRow,
+    config: &Config,
+    file_system: &FS,
+) -> std::io::Result<String> {
+    get_audio_file_url(
+        file_system,
+        &config.file_server_endpoint,
+        &get_file_path(row),
+    )
+    .await
+}
+
+pub(crate) async fn get_playing_at<FS: FileSystem>(
     path: web::Path<(StreamId, u64)>,
     config: web::Data<Config>,
     mysql_client: web::Data<MySqlClient>,
+    file_system: web::Data<FS>,
 ) -> Response {
     let (stream_id, unix_time) = path.into_inner();
     let system_time = UNIX_EPOCH + std::time::Duration::from_millis(unix_time);
@@ -44,6 +65,9 @@ pub(crate) async fn get_playing_at(
         }
     };
 
+    let current_track_url = get_track_url(&current_track, &config, file_system.get_ref()).await?;
+    let next_track_url = get_track_url(&next_track, &config, file_system.get_ref()).await?;
+
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "code": 1i32,
         "message": "OK",
@@ -53,13 +77,15 @@ pub(crate) async fn get_playing_at(
             "current_track": {
                 "offset": current_position.num_milliseconds(),
                 "title": get_artist_and_title(&current_track),
-                "url": format!("{}audio/{}", config.file_server_endpoint, get_file_path(&current_track)),
+                "url": current_track_url,
                 "duration": current_track.track.duration,
+                "track_id": current_track.track.tid,
             },
             "next_track": {
                 "title": get_artist_and_title(&next_track),
-                "url": format!("{}audio/{}", config.file_server_endpoint, get_file_path(&next_track)),
+                "url": next_track_url,
                 "duration": next_track.track.duration,
+                "track_id": next_track.track.tid,
             },
         },
     })))
@@ -76,3 +102,63 @@ pub(crate) async fn skip_track(
 
     Ok(HttpResponse::Ok().finish())
 }
+
+#[derive(Deserialize)]
+pub(crate) struct TrackStartedBody {
+    track_id: TrackId,
+    /// Unix time in milliseconds.
+    started_at: i64,
+    listeners: u32,
+}
+
+/// Records the track the radio streamer started to play from the beginning.
+pub(crate) async fn track_started(
+    path: web::Path<StreamId>,
+    body: web::Json<TrackStartedBody>,
+    play_history_service: web::Data<PlayHistoryService>,
+) -> Response {
+    let started_at = match Utc.timestamp_millis_opt(body.started_at).single() {
+        Some(started_at) => started_at,
+        None => return Ok(HttpResponse::BadRequest().finish()),
+    };
+
+    match play_history_service
+        .record_track_start(
+            &path.into_inner(),
+            &body.track_id,
+            &started_at,
+            body.listeners.min(i32::MAX as u32) as i32,
+        )
+        .await
+    {
+        Ok(()) => Ok(HttpResponse::Ok().finish()),
+        Err(error) => play_history_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct VerifyListenTokenQuery {
+    token: String,
+}
+
+/// Tells the radio streamer whether the listen token grants access to the stream.
+pub(crate) async fn verify_listen_token(
+    path: web::Path<StreamId>,
+    query: web::Query<VerifyListenTokenQuery>,
+    mysql_client: web::Data<MySqlClient>,
+    auth_token_service: web::Data<AuthTokenService>,
+) -> Response {
+    let stream_id = path.into_inner();
+
+    let mut connection = mysql_client.connection().await?;
+
+    let stream = match streams::get_single_stream_by_id(&mut connection, &stream_id).await? {
+        Some(stream) => stream,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    match auth_token_service.verify_listen_claims(&query.token) {
+        Some(claims) if *claims.stream_id == *stream.sid => Ok(HttpResponse::Ok().finish()),
+        _ => Ok(HttpResponse::Forbidden().finish()),
+    }
+}
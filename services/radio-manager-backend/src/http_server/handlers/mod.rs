@@ -1,13 +1,26 @@
 pub(crate) mod forward_auth;
+pub(crate) mod internal_admin_track_imports;
 pub(crate) mod internal_egress_process;
 pub(crate) mod internal_radio_streamer;
+pub(crate) mod public_artworks;
 pub(crate) mod public_auth_v0;
+pub(crate) mod public_auth_v1;
 pub(crate) mod public_schedule;
 pub(crate) mod public_streams;
+pub(crate) mod user_airplay_reports;
 pub(crate) mod user_audio_stream;
 pub(crate) mod user_audio_tracks;
 pub(crate) mod user_audio_tracks_v2;
+pub(crate) mod user_colors;
+pub(crate) mod user_library_export;
 pub(crate) mod user_outgoing_stream;
+pub(crate) mod user_play_history;
+pub(crate) mod user_profile;
+pub(crate) mod user_shared_tracks;
 pub(crate) mod user_stream_control;
 pub(crate) mod user_stream_destinations;
 pub(crate) mod user_streams;
+pub(crate) mod user_track_imports;
+pub(crate) mod user_track_trash;
+pub(crate) mod user_tracks_bulk;
+pub(crate) mod user_two_factor;
@@ -0,0 +1,34 @@
+use crate::http_server::handlers::user_audio_tracks::{
+    artwork_error_response, artwork_response, GetAudioTrackArtworkQuery,
+};
+use crate::http_server::response::Response;
+use crate::services::ArtworkService;
+use crate::storage::fs::FileSystem;
+use actix_web::web::{Data, Path, Query};
+use actix_web::HttpRequest;
+
+/// Serves the artwork by its hash. The artwork files never change, so they may be cached
+/// by anyone for as long as they want.
+pub(crate) async fn get_artwork<FS: FileSystem>(
+    path: Path<String>,
+    query: Query<GetAudioTrackArtworkQuery>,
+    request: HttpRequest,
+    artwork_service: Data<ArtworkService>,
+    file_system: Data<FS>,
+) -> Response {
+    let size = query.size;
+
+    let artwork = match artwork_service.get_artwork(&path.into_inner(), size).await {
+        Ok(artwork) => artwork,
+        Err(error) => return artwork_error_response(error),
+    };
+
+    artwork_response(
+        artwork,
+        size,
+        &request,
+        file_system.get_ref(),
+        "public, max-age=31536000, immutable",
+    )
+    .await
+}
@@ -1,9 +1,11 @@
+use crate::http_server::client_ip::client_ip;
 use crate::http_server::constants::{LEGACY_SESSION_COOKIE_NAME, YEAR};
-use crate::http_server::response::Response;
+use crate::http_server::response::{too_many_attempts_response, Response};
 use crate::services::auth::{
-    Action, AuthService, AuthTokenService, LegacyLoginError, LegacyLogoutError,
-    LegacyResetPasswordError, LegacySignupError, LegacySignupResult,
+    Action, AuthService, AuthTokenService, LegacyLoginError, LegacyLoginResult, LegacyLogoutError,
+    LegacyResetPasswordError, LegacySignupError, LegacySignupResult, TwoFactorLoginError,
 };
+use crate::Config;
 use actix_web::cookie::time::OffsetDateTime;
 use actix_web::cookie::CookieBuilder;
 use actix_web::{post, web, HttpRequest, HttpResponse};
@@ -19,25 +21,82 @@ pub(crate) struct LoginBody {
 
 #[post("/login")]
 pub(crate) async fn login(
+    req: HttpRequest,
     body: web::Json<LoginBody>,
     auth_service: web::Data<AuthService>,
+    config: web::Data<Config>,
 ) -> Response {
-    match auth_service.legacy_login(&body.email, &body.password).await {
-        Ok((user, token)) => {
+    let ip = client_ip(&req, &config.trusted_proxies);
+
+    match auth_service
+        .legacy_login(&body.email, &body.password, ip.as_deref())
+        .await
+    {
+        Ok(LegacyLoginResult::LoggedIn(user, token)) => {
             let cookie = CookieBuilder::new(LEGACY_SESSION_COOKIE_NAME, token.clone())
                 .expires(OffsetDateTime::now_utc() + YEAR)
                 .finish();
 
             Ok(HttpResponse::Ok().cookie(cookie).json(user))
         }
+        Ok(LegacyLoginResult::TwoFactorRequired { challenge_token }) => Ok(HttpResponse::Ok()
+            .json(json!({
+                "result": "TWO_FACTOR_REQUIRED",
+                "challenge_token": challenge_token,
+            }))),
         Err(LegacyLoginError::BadCredentials) => Ok(HttpResponse::Unauthorized().json(json!({
             "error": "BAD_CREDENTIALS"
         }))),
+        Err(LegacyLoginError::TooManyAttempts { retry_after }) => {
+            Ok(too_many_attempts_response(&retry_after))
+        }
         Err(LegacyLoginError::DatabaseError(err)) => Err(err.into()),
         Err(LegacyLoginError::RepositoryError(err)) => Err(err.into()),
     }
 }
 
+#[derive(Deserialize)]
+pub(crate) struct TwoFactorLoginBody {
+    pub(crate) challenge_token: String,
+    pub(crate) code: String,
+}
+
+#[post("/login/two-factor")]
+pub(crate) async fn login_two_factor(
+    req: HttpRequest,
+    body: web::Json<TwoFactorLoginBody>,
+    auth_service: web::Data<AuthService>,
+    config: web::Data<Config>,
+) -> Response {
+    let ip = client_ip(&req, &config.trusted_proxies);
+
+    match auth_service
+        .legacy_complete_two_factor_login(&body.challenge_token, &body.code, ip.as_deref())
+        .await
+    {
+        Ok((user, token)) => {
+            let cookie = CookieBuilder::new(LEGACY_SESSION_COOKIE_NAME, token.clone())
+                .expires(OffsetDateTime::now_utc() + YEAR)
+                .finish();
+
+            Ok(HttpResponse::Ok().cookie(cookie).json(user))
+        }
+        Err(TwoFactorLoginError::InvalidChallenge) => {
+            Ok(HttpResponse::Unauthorized().json(json!({
+                "error": "INVALID_CHALLENGE"
+            })))
+        }
+        Err(TwoFactorLoginError::InvalidCode) => Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "INVALID_CODE"
+        }))),
+        Err(TwoFactorLoginError::TooManyAttempts { retry_after }) => {
+            Ok(too_many_attempts_response(&retry_after))
+        }
+        Err(TwoFactorLoginError::DatabaseError(err)) => Err(err.into()),
+        Err(TwoFactorLoginError::RepositoryError(err)) => Err(err.into()),
+    }
+}
+
 #[post("/logout")]
 pub(crate) async fn logout(
     req: HttpRequest,
@@ -0,0 +1,128 @@
+use crate::http_server::client_ip::client_ip;
+use crate::http_server::response::{too_many_attempts_response, Response};
+use crate::services::auth::{
+    AuthService, LoginError, LoginResult, LogoutError, RefreshError, TwoFactorLoginError,
+};
+use crate::Config;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+pub(crate) struct LoginBody {
+    pub(crate) email: String,
+    pub(crate) password: String,
+}
+
+#[post("/login")]
+pub(crate) async fn login(
+    req: HttpRequest,
+    body: web::Json<LoginBody>,
+    auth_service: web::Data<AuthService>,
+    config: web::Data<Config>,
+) -> Response {
+    let ip = client_ip(&req, &config.trusted_proxies);
+
+    match auth_service
+        .login(&body.email, &body.password, ip.as_deref())
+        .await
+    {
+        Ok(LoginResult::LoggedIn(user, tokens)) => Ok(HttpResponse::Ok().json(json!({
+            "user": user,
+            "tokens": tokens,
+        }))),
+        Ok(LoginResult::TwoFactorRequired { challenge_token }) => {
+            Ok(HttpResponse::Ok().json(json!({
+                "result": "TWO_FACTOR_REQUIRED",
+                "challengeToken": challenge_token,
+            })))
+        }
+        Err(LoginError::BadCredentials) => Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "BAD_CREDENTIALS"
+        }))),
+        Err(LoginError::TooManyAttempts { retry_after }) => {
+            Ok(too_many_attempts_response(&retry_after))
+        }
+        Err(LoginError::DatabaseError(err)) => Err(err.into()),
+        Err(LoginError::RepositoryError(err)) => Err(err.into()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TwoFactorLoginBody {
+    pub(crate) challenge_token: String,
+    pub(crate) code: String,
+}
+
+#[post("/login/two-factor")]
+pub(crate) async fn login_two_factor(
+    req: HttpRequest,
+    body: web::Json<TwoFactorLoginBody>,
+    auth_service: web::Data<AuthService>,
+    config: web::Data<Config>,
+) -> Response {
+    let ip = client_ip(&req, &config.trusted_proxies);
+
+    match auth_service
+        .complete_two_factor_login(&body.challenge_token, &body.code, ip.as_deref())
+        .await
+    {
+        Ok((user, tokens)) => Ok(HttpResponse::Ok().json(json!({
+            "user": user,
+            "tokens": tokens,
+        }))),
+        Err(TwoFactorLoginError::InvalidChallenge) => {
+            Ok(HttpResponse::Unauthorized().json(json!({
+                "error": "INVALID_CHALLENGE"
+            })))
+        }
+        Err(TwoFactorLoginError::InvalidCode) => Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "INVALID_CODE"
+        }))),
+        Err(TwoFactorLoginError::TooManyAttempts { retry_after }) => {
+            Ok(too_many_attempts_response(&retry_after))
+        }
+        Err(TwoFactorLoginError::DatabaseError(err)) => Err(err.into()),
+        Err(TwoFactorLoginError::RepositoryError(err)) => Err(err.into()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RefreshTokenBody {
+    pub(crate) refresh_token: String,
+}
+
+#[post("/refresh")]
+pub(crate) async fn refresh(
+    body: web::Json<RefreshTokenBody>,
+    auth_service: web::Data<AuthService>,
+) -> Response {
+    match auth_service.refresh(&body.refresh_token).await {
+        Ok(tokens) => Ok(HttpResponse::Ok().json(json!({ "tokens": tokens }))),
+        Err(RefreshError::InvalidRefreshToken) => Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "INVALID_REFRESH_TOKEN"
+        }))),
+        Err(RefreshError::RefreshTokenExpired) => Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "REFRESH_TOKEN_EXPIRED"
+        }))),
+        Err(RefreshError::RefreshTokenReused) => Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "REFRESH_TOKEN_REUSED"
+        }))),
+        Err(RefreshError::DatabaseError(err)) => Err(err.into()),
+        Err(RefreshError::RepositoryError(err)) => Err(err.into()),
+    }
+}
+
+#[post("/logout")]
+pub(crate) async fn logout(
+    body: web::Json<RefreshTokenBody>,
+    auth_service: web::Data<AuthService>,
+) -> Response {
+    match auth_service.logout(&body.refresh_token).await {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(LogoutError::DatabaseError(err)) => Err(err.into()),
+        Err(LogoutError::RepositoryError(err)) => Err(err.into()),
+    }
+}
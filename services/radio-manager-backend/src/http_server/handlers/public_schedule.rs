@@ -1,12 +1,15 @@
-use crate::data_structures::StreamId;
+use crate::data_structures::{StreamAccess, StreamRef};
 use crate::http_server::response::Response;
 use crate::storage::db::repositories::streams::{
-    get_single_stream_by_id, get_stream_playlist_duration,
+    get_single_stream_by_ref, get_stream_playlist_duration,
 };
+use crate::storage::db::repositories::track_artworks::get_track_artwork_hash;
 use crate::storage::db::repositories::user_stream_tracks::{
     get_single_stream_track_at_time_offset, TrackFileLinkMergedRow,
 };
 use crate::storage::db::repositories::StreamStatus;
+use crate::storage::fs::utils::get_audio_file_url;
+use crate::storage::fs::FileSystem;
 use crate::utils::TeeResultUtils;
 use crate::{services, Config, MySqlClient};
 use actix_web::{web, HttpResponse};
@@ -15,11 +18,10 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::error;
 
 pub(crate) async fn get_current_track(
-    path: web::Path<StreamId>,
-    config: web::Data<Config>,
+    path: web::Path<StreamRef>,
     mysql_client: web::Data<MySqlClient>,
 ) -> Response {
-    let stream_id = path.into_inner();
+    let stream_ref = path.into_inner();
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
@@ -27,13 +29,21 @@ pub(crate) async fn get_current_track(
 
     let mut connection = mysql_client.connection().await?;
 
-    let stream = match get_single_stream_by_id(&mut connection, &stream_id)
+    let stream = match get_single_stream_by_ref(&mut connection, &stream_ref)
         .await
         .tee_err(|error| error!("Unable to get stream information"))?
     {
-        Some(stream) => stream,
-        None => return Ok(HttpResponse::NotFound().finish()),
+        Some(stream)
+            if matches!(
+                StreamAccess::from(stream.access.as_str()),
+                StreamAccess::Public
+            ) =>
+        {
+            stream
+        }
+        _ => return Ok(HttpResponse::NotFound().finish()),
     };
+    let stream_id = stream.sid.clone();
 
     let playlist_duration = get_stream_playlist_duration(&mut connection, &stream_id)
         .await
@@ -50,6 +60,10 @@ pub(crate) async fn get_current_track(
                 .await
                 .tee_err(|error| error!("Unable to get stream playlist track"))?
         {
+            let artwork_hash = get_track_artwork_hash(&mut connection, &row.track.tid)
+                .await
+                .tee_err(|error| error!(?error, "Unable to get track artwork"))?;
+
             return Ok(HttpResponse::Ok().json(serde_json::json!({
                 "code": 1i32,
                 "message": "OK",
@@ -71,7 +85,8 @@ pub(crate) async fn get_current_track(
                     "is_new": row.track.is_new,
                     "tid": row.track.tid,
                     "title": row.track.title,
-                    "track_number": row.track.track_number
+                    "track_number": row.track.track_number,
+                    "artwork_url": get_artwork_url(artwork_hash),
                 },
             })));
         }
@@ -94,27 +109,42 @@ fn get_file_path(row: &TrackFileLinkMergedRow) -> String {
     )
 }
 
+/// Artworks are served by the backend from whichever file system stores them,
+/// so the URL doesn't depend on the file server.
+fn get_artwork_url(artwork_hash: Option<String>) -> Option<String> {
+    artwork_hash.map(|artwork_hash| format!("/pub/v0/artworks/{}?size=medium", artwork_hash))
+}
+
 #[derive(Deserialize)]
 pub(crate) struct GetNowPlayingQuery {
     #[serde(rename = "ts")]
     timestamp: u64,
 }
 
-pub(crate) async fn get_now_playing(
-    path: web::Path<StreamId>,
+pub(crate) async fn get_now_playing<FS: FileSystem>(
+    path: web::Path<StreamRef>,
     query: web::Query<GetNowPlayingQuery>,
     config: web::Data<Config>,
     mysql_client: web::Data<MySqlClient>,
+    file_system: web::Data<FS>,
 ) -> Response {
-    let stream_id = path.into_inner();
+    let stream_ref = path.into_inner();
     let params = query.into_inner();
 
     let mut connection = mysql_client.connection().await?;
 
-    let stream = match get_single_stream_by_id(&mut connection, &stream_id).await? {
-        Some(stream) => stream,
-        None => return Ok(HttpResponse::NotFound().finish()),
+    let stream = match get_single_stream_by_ref(&mut connection, &stream_ref).await? {
+        Some(stream)
+            if matches!(
+                StreamAccess::from(stream.access.as_str()),
+                StreamAccess::Public
+            ) =>
+        {
+            stream
+        }
+        _ => return Ok(HttpResponse::NotFound().finish()),
     };
+    let stream_id = stream.sid.clone();
 
     let time = UNIX_EPOCH + Duration::from_millis(params.timestamp);
     let (current_track, next_track, current_position, status) =
@@ -123,6 +153,23 @@ pub(crate) async fn get_now_playing(
             None => return Ok(HttpResponse::Conflict().finish()),
         };
 
+    let current_artwork_hash =
+        get_track_artwork_hash(&mut connection, &current_track.track.tid).await?;
+    let next_artwork_hash = get_track_artwork_hash(&mut connection, &next_track.track.tid).await?;
+
+    let current_track_url = get_audio_file_url(
+        file_system.get_ref(),
+        &config.file_server_endpoint,
+        &get_file_path(&current_track),
+    )
+    .await?;
+    let next_track_url = get_audio_file_url(
+        file_system.get_ref(),
+        &config.file_server_endpoint,
+        &get_file_path(&next_track),
+    )
+    .await?;
+
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "code": 1i32,
         "message": "OK",
@@ -133,16 +180,108 @@ pub(crate) async fn get_now_playing(
             "current_track": {
                 "offset": current_position.num_milliseconds(),
                 "title": get_artist_and_title(&current_track),
-                "url": format!("{}audio/{}", config.file_server_endpoint, get_file_path(&current_track)),
+                "url": current_track_url,
                 "duration": current_track.track.duration,
                 "track_id": current_track.track.tid,
+                "artwork_url": get_artwork_url(current_artwork_hash),
             },
             "next_track": {
                 "title": get_artist_and_title(&next_track),
-                "url": format!("{}audio/{}", config.file_server_endpoint, get_file_path(&next_track)),
+                "url": next_track_url,
                 "duration": next_track.track.duration,
                 "track_id": next_track.track.tid,
+                "artwork_url": get_artwork_url(next_artwork_hash),
             },
         },
     })))
 }
+
+const DEFAULT_SCHEDULE_WINDOW: i64 = 60 * 60 * 1000;
+
+const MAX_SCHEDULE_WINDOW: i64 = 24 * 60 * 60 * 1000;
+
+const MAX_SCHEDULE_TRACKS: usize = 500;
+
+/// Window of the schedule, unix times in milliseconds. Defaults to an hour before and
+/// after now.
+#[derive(Deserialize)]
+pub(crate) struct GetScheduleQuery {
+    #[serde(default)]
+    from: Option<i64>,
+    #[serde(default)]
+    to: Option<i64>,
+}
+
+pub(crate) async fn get_schedule(
+    path: web::Path<StreamRef>,
+    query: web::Query<GetScheduleQuery>,
+    mysql_client: web::Data<MySqlClient>,
+) -> Response {
+    let stream_ref = path.into_inner();
+    let time = SystemTime::now();
+    let now = time.duration_since(UNIX_EPOCH).unwrap().as_millis() as i64;
+    let from = query.from.unwrap_or(now - DEFAULT_SCHEDULE_WINDOW);
+    let to = query.to.unwrap_or(now + DEFAULT_SCHEDULE_WINDOW);
+
+    let is_valid_window = matches!(
+        to.checked_sub(from),
+        Some(window) if window > 0 && window <= MAX_SCHEDULE_WINDOW
+    );
+
+    if !is_valid_window {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "INVALID_WINDOW"
+        })));
+    }
+
+    let mut connection = mysql_client.connection().await?;
+
+    let stream = match get_single_stream_by_ref(&mut connection, &stream_ref).await? {
+        Some(stream)
+            if matches!(
+                StreamAccess::from(stream.access.as_str()),
+                StreamAccess::Public
+            ) =>
+        {
+            stream
+        }
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    let schedule = services::get_schedule(
+        &time,
+        from,
+        to,
+        MAX_SCHEDULE_TRACKS,
+        &stream.sid,
+        &mut connection,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 1i32,
+        "message": "OK",
+        "data": {
+            "from": from,
+            "to": to,
+            "time": now,
+            "playback_status": schedule.status,
+            "position": schedule.position,
+            "tracks": schedule
+                .tracks
+                .iter()
+                .map(|scheduled| serde_json::json!({
+                    "starts_at": scheduled.starts_at,
+                    "ends_at": scheduled.starts_at + scheduled.track.track.duration,
+                    "playlist_position": scheduled.track.link.t_order,
+                    "unique_id": scheduled.track.link.unique_id,
+                    "track_id": scheduled.track.track.tid,
+                    "title": get_artist_and_title(&scheduled.track),
+                    "artist": scheduled.track.track.artist,
+                    "album": scheduled.track.track.album,
+                    "duration": scheduled.track.track.duration,
+                }))
+                .collect::<Vec<_>>(),
+        },
+    })))
+}
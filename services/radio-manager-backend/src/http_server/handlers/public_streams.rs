@@ -1,20 +1,31 @@
-use crate::data_structures::StreamId;
+use crate::data_structures::{StreamRef, StreamsSorting};
 use crate::http_server::response::Response;
-use crate::storage::db::repositories::streams::get_single_stream_by_id;
-use crate::{Config, MySqlClient};
+use crate::storage::db::repositories::streams::{
+    get_public_streams_count, get_single_stream_by_ref, GetPublicStreamsParams,
+};
+use crate::storage::db::repositories::{categories, streams};
+use crate::utils::{to_boolean_phrase_query, to_boolean_search_query, TeeResultUtils};
+use crate::{services, Config, MySqlClient};
 use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::SystemTime;
 use tracing::error;
 
+const MAX_STREAMS_PER_REQUEST: i64 = 100;
+
+const DEFAULT_STREAMS_PER_REQUEST: i64 = 20;
+
 pub(crate) async fn get_stream_info(
-    path: web::Path<StreamId>,
+    path: web::Path<StreamRef>,
     config: web::Data<Config>,
     mysql_client: web::Data<MySqlClient>,
 ) -> Response {
-    let stream_id = path.into_inner();
+    let stream_ref = path.into_inner();
 
     let mut conn = mysql_client.connection().await?;
 
-    let stream = match get_single_stream_by_id(&mut conn, &stream_id).await {
+    let stream = match get_single_stream_by_ref(&mut conn, &stream_ref).await {
         Ok(Some(stream)) => stream,
         Ok(None) => return Ok(HttpResponse::NotFound().finish()),
         Err(error) => {
@@ -28,8 +39,135 @@ pub(crate) async fn get_stream_info(
         "code": 1i32,
         "message": "OK",
         "data": {
+            "sid": stream.sid,
             "name": stream.name,
             "status": stream.status,
+            "access": stream.access,
         }
     })))
 }
+
+#[derive(Deserialize)]
+pub(crate) struct GetPublicStreamsQuery {
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    category: Option<i32>,
+    #[serde(default)]
+    hashtag: Option<String>,
+    #[serde(default, rename = "q")]
+    search: Option<String>,
+    #[serde(default, rename = "sort")]
+    sorting: StreamsSorting,
+}
+
+pub(crate) async fn get_public_streams(
+    query: web::Query<GetPublicStreamsQuery>,
+    mysql_client: web::Data<MySqlClient>,
+) -> Response {
+    let query = query.into_inner();
+
+    let offset = query.offset.max(0);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_STREAMS_PER_REQUEST)
+        .clamp(1, MAX_STREAMS_PER_REQUEST);
+
+    let params = GetPublicStreamsParams {
+        category: query.category,
+        hashtag: query
+            .hashtag
+            .filter(|hashtag| !hashtag.trim().is_empty())
+            .map(|hashtag| to_boolean_phrase_query(&hashtag)),
+        search: query
+            .search
+            .map(|search| to_boolean_search_query(&search))
+            .filter(|search| !search.is_empty()),
+        sorting: query.sorting,
+    };
+
+    let mut connection = mysql_client.connection().await?;
+
+    let streams_count = get_public_streams_count(&mut connection, &params)
+        .await
+        .tee_err(|error| error!(?error, "Failed to get public streams total"))?;
+
+    let stream_rows = streams::get_public_streams(&mut connection, &params, &offset, &limit)
+        .await
+        .tee_err(|error| error!(?error, "Failed to get public streams"))?;
+
+    let now = SystemTime::now();
+    let rows: Vec<_> = stream_rows.iter().map(|row| &row.stream).collect();
+    let now_playing_tracks =
+        match services::get_now_playing_tracks(&now, &rows, &mut connection).await {
+            Ok(now_playing_tracks) => now_playing_tracks,
+            Err(error) => {
+                error!(?error, "Unable to get now playing tracks of public streams");
+
+                HashMap::new()
+            }
+        };
+    let mut streams_json = Vec::with_capacity(stream_rows.len());
+
+    for row in stream_rows {
+        let now_playing = now_playing_tracks
+            .get(&row.stream.sid)
+            .map(|current_track| {
+                format!(
+                    "{} - {}",
+                    current_track.track.artist, current_track.track.title
+                )
+            });
+
+        streams_json.push(serde_json::json!({
+            "sid": row.stream.sid,
+            "name": row.stream.name,
+            "permalink": row.stream.permalink,
+            "info": row.stream.info,
+            "status": row.stream.status,
+            "category": row.stream.category,
+            "hashtags": row.stream.hashtags,
+            "cover": row.stream.cover,
+            "coverBackground": row.stream.cover_background,
+            "listenersCount": row.listeners_count,
+            "nowPlaying": now_playing,
+        }));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "totalCount": streams_count,
+        "items": streams_json,
+        "paginationData": {
+            "limit": limit,
+            "offset": offset
+        }
+    })))
+}
+
+pub(crate) async fn get_categories(mysql_client: web::Data<MySqlClient>) -> Response {
+    let mut connection = mysql_client.connection().await?;
+
+    let categories = categories::get_categories(&mut connection)
+        .await
+        .tee_err(|error| error!(?error, "Failed to get categories"))?;
+
+    let categories_json: Vec<_> = categories
+        .into_iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.category_id,
+                "name": row.category_name,
+                "permalink": row.category_permalink,
+                "streamsCount": row.streams_count,
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 1i32,
+        "message": "OK",
+        "data": categories_json,
+    })))
+}
@@ -0,0 +1,74 @@
+use crate::data_structures::{StreamRef, UserId};
+use crate::http_server::download::attachment_content_disposition;
+use crate::http_server::response::Response;
+use crate::services::{AirplayReportError, AirplayReportService, ReportPeriod};
+use actix_web::web::{Data, Path, Query};
+use actix_web::HttpResponse;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::json;
+
+fn airplay_report_error_response(error: AirplayReportError) -> Response {
+    match error {
+        AirplayReportError::StreamNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "STREAM_NOT_FOUND" })))
+        }
+        AirplayReportError::InvalidPeriod => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_PERIOD" })))
+        }
+        AirplayReportError::DatabaseError(err) => Err(err.into()),
+        AirplayReportError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AirplayReportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetAirplayReportQuery {
+    from: NaiveDate,
+    to: NaiveDate,
+    #[serde(default)]
+    format: AirplayReportFormat,
+}
+
+pub(crate) async fn get_airplay_report(
+    user_id: UserId,
+    path: Path<StreamRef>,
+    query: Query<GetAirplayReportQuery>,
+    airplay_report_service: Data<AirplayReportService>,
+) -> Response {
+    let period = match ReportPeriod::new(query.from, query.to) {
+        Ok(period) => period,
+        Err(error) => return airplay_report_error_response(error),
+    };
+
+    let report = match airplay_report_service
+        .generate_report(Some(&user_id), &path.into_inner(), &period)
+        .await
+    {
+        Ok(report) => report,
+        Err(error) => return airplay_report_error_response(error),
+    };
+
+    match query.format {
+        AirplayReportFormat::Csv => Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                "Content-Disposition",
+                attachment_content_disposition(&report.file_name("csv")),
+            ))
+            .body(report.render_csv())),
+        AirplayReportFormat::Json => Ok(HttpResponse::Ok()
+            .insert_header((
+                "Content-Disposition",
+                attachment_content_disposition(&report.file_name("json")),
+            ))
+            .json(report)),
+    }
+}
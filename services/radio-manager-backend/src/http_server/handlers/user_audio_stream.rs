@@ -5,6 +5,8 @@ use crate::mysql_client::MySqlClient;
 use crate::services::ffmpeg_service;
 use crate::storage::db::repositories::user_tracks;
 use crate::storage::db::row_utils::GetFilePath;
+use crate::storage::fs::utils::get_audio_file_url;
+use crate::storage::fs::FileSystem;
 use crate::utils::TeeResultUtils;
 use actix_web::{web, HttpResponse};
 use futures::channel::mpsc;
@@ -38,11 +40,12 @@ pub(crate) struct TranscodeAudioTrackQuery {
     pub(crate) audio_format: Format,
 }
 
-pub(crate) async fn transcode_audio_track(
+pub(crate) async fn transcode_audio_track<FS: FileSystem>(
     user_id: UserId,
     path: web::Path<TrackId>,
     mysql_client: web::Data<MySqlClient>,
     config: web::Data<Config>,
+    file_system: web::Data<FS>,
     json: web::Query<TranscodeAudioTrackQuery>,
 ) -> Response {
     let mut connection = mysql_client.connection().await?;
@@ -60,8 +63,12 @@ pub(crate) async fn transcode_audio_track(
         return Ok(HttpResponse::Forbidden().finish());
     }
 
-    let source_path = track_row.get_file_path();
-    let source_url = format!("{}audio/{}", config.file_server_endpoint, source_path);
+    let source_url = get_audio_file_url(
+        file_system.get_ref(),
+        &config.file_server_endpoint,
+        &track_row.get_file_path(),
+    )
+    .await?;
 
     let (response_tx, response_rx) = mpsc::channel(32);
 
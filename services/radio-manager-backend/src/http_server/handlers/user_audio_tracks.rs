@@ -1,25 +1,35 @@
 use crate::data_structures::{
-    SortingColumn, SortingOrder, StreamId, TrackId, UserId, DEFAULT_TRACKS_PER_REQUEST,
+    SortingColumn, SortingOrder, StreamId, StreamRef, TrackId, UserId, DEFAULT_TRACKS_PER_REQUEST,
 };
+use crate::http_server::download::{
+    attachment_content_disposition, file_etag, if_none_match, parse_range_header, RangeRequest,
+};
+use crate::http_server::handlers::user_tracks_bulk::track_bulk_error_response;
 use crate::http_server::response::Response;
-use crate::storage::db::repositories::streams::{
-    get_single_stream_by_id, get_user_streams_having_track,
+use crate::services::{
+    Artwork, ArtworkError, ArtworkService, ArtworkSize, TrackBulkItemStatus, TrackBulkOperation,
+    TrackBulkService, TrackMetadataError, TrackMetadataService, TrackMetadataUpdate,
+    TrackSelection,
 };
+use crate::storage::db::repositories::streams::get_single_stream_by_ref;
 use crate::storage::db::repositories::user_stream_tracks::{
     get_stream_tracks, GetUserStreamTracksParams,
 };
 use crate::storage::db::repositories::user_tracks::{
-    delete_user_track, get_single_user_track, get_user_tracks, GetUserTracksParams,
+    get_single_user_track, get_user_tracks, GetUserTracksParams,
 };
+use crate::storage::db::repositories::TrackRow;
+use crate::storage::db::track_query::TrackQuery;
 use crate::storage::fs::utils::GetPath;
 use crate::storage::fs::FileSystem;
 use crate::utils::TeeResultUtils;
 use crate::MySqlClient;
-use actix_web::web::{Data, Form, Path, Query};
-use actix_web::{web, HttpResponse};
+use actix_web::web::{Data, Form, Json, Path, Query};
+use actix_web::{web, HttpRequest, HttpResponse};
 use bytes::Bytes;
 use futures::{SinkExt, StreamExt};
 use serde::Deserialize;
+use serde_json::json;
 use tracing::error;
 
 #[derive(Deserialize)]
@@ -53,6 +63,8 @@ pub(crate) async fn get_user_audio_tracks(
         Some(str) => str.parse::<u32>().ok(),
     };
 
+    let filter = params.filter.as_deref().map(TrackQuery::boolean_mode);
+
     let mut conn = mysql_client.connection().await?;
 
     let offset = params.offset;
@@ -66,10 +78,11 @@ pub(crate) async fn get_user_audio_tracks(
         &user_id,
         &GetUserTracksParams {
             color: color_id,
-            filter: params.filter,
+            filter,
             sorting_column: params.row,
             sorting_order: params.order,
             unused: params.unused,
+            new_only: false,
         },
         &Some(offset),
         &Some(limit),
@@ -121,12 +134,12 @@ pub(crate) struct GetUserPlaylistAudioTracksQuery {
 }
 
 pub(crate) async fn get_user_stream_audio_tracks(
-    path: Path<StreamId>,
+    path: Path<StreamRef>,
     user_id: UserId,
     query: Query<GetUserPlaylistAudioTracksQuery>,
     mysql_client: Data<MySqlClient>,
 ) -> Response {
-    let stream_id = path.into_inner();
+    let stream_ref = path.into_inner();
     let params = query.into_inner();
 
     let color_id = match params.color_id {
@@ -135,10 +148,12 @@ pub(crate) async fn get_user_stream_audio_tracks(
         Some(str) => str.parse::<u32>().ok(),
     };
 
+    let filter = params.filter.as_deref().map(TrackQuery::boolean_mode);
+
     let mut connection = mysql_client.connection().await?;
 
-    match get_single_stream_by_id(&mut connection, &stream_id).await {
-        Ok(Some(stream)) if stream.uid == user_id => (),
+    let stream_id = match get_single_stream_by_ref(&mut connection, &stream_ref).await {
+        Ok(Some(stream)) if stream.uid == user_id => stream.sid,
         Ok(Some(_)) => return Ok(HttpResponse::Forbidden().finish()),
         Ok(None) => return Ok(HttpResponse::NotFound().finish()),
         Err(error) => {
@@ -146,7 +161,7 @@ pub(crate) async fn get_user_stream_audio_tracks(
 
             return Ok(HttpResponse::InternalServerError().finish());
         }
-    }
+    };
 
     let offset = params.offset;
     let limit = params
@@ -159,7 +174,8 @@ pub(crate) async fn get_user_stream_audio_tracks(
         &stream_id,
         &GetUserStreamTracksParams {
             color: color_id,
-            filter: params.filter,
+            filter,
+            ..GetUserStreamTracksParams::default()
         },
         &Some(offset),
         &Some(limit),
@@ -216,6 +232,8 @@ pub(crate) struct UploadAudioTrackForm {
     file: UploadedFile,
 }
 
+/// Not implemented yet: uploads are still handled by the legacy backend, which doesn't extract
+/// embedded artwork. Only tracks added by imports get their artwork.
 pub(crate) async fn upload_audio_track(
     user_id: UserId,
     form: Form<UploadAudioTrackForm>,
@@ -224,9 +242,34 @@ pub(crate) async fn upload_audio_track(
     Ok(HttpResponse::Ok().finish())
 }
 
-pub(crate) async fn delete_audio_track<FS: FileSystem>(
+/// Moves the track to the trash and removes it from the playlists.
+pub(crate) async fn delete_audio_track(
+    user_id: UserId,
+    path: Path<TrackId>,
+    track_bulk_service: Data<TrackBulkService>,
+) -> Response {
+    let selection = TrackSelection::TrackIds(vec![path.into_inner()]);
+
+    match track_bulk_service
+        .run_operation(&user_id, &selection, &TrackBulkOperation::Delete)
+        .await
+    {
+        Ok(items)
+            if items
+                .iter()
+                .all(|item| item.status == TrackBulkItemStatus::Done) =>
+        {
+            Ok(HttpResponse::Ok().finish())
+        }
+        Ok(_) => Ok(HttpResponse::NotFound().finish()),
+        Err(error) => track_bulk_error_response(error),
+    }
+}
+
+pub(crate) async fn download_audio_track<FS: FileSystem>(
     user_id: UserId,
     path: Path<TrackId>,
+    request: HttpRequest,
     mysql_client: Data<MySqlClient>,
     file_system: Data<FS>,
 ) -> Response {
@@ -246,71 +289,313 @@ pub(crate) async fn delete_audio_track<FS: FileSystem>(
         return Ok(HttpResponse::Forbidden().finish());
     }
 
-    let stream_rows = get_user_streams_having_track(&mut connection, &track_row.track.tid)
-        .await
-        .tee_err(|error| {
-            error!(
-                ?error,
-                "Unable to get user streams having given track from database"
-            )
-        })?;
-
-    for stream_row in stream_rows.iter() {
-        todo!()
+    let file_size = track_row.file.file_size as u64;
+    let file_path = format!("audio/{}", track_row.file.get_path());
+    let etag = file_etag(&track_row.file.file_hash);
+    let content_disposition = attachment_content_disposition(&track_row.track.filename);
+
+    let header_value = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+
+    if header_value("If-None-Match").is_some_and(|value| if_none_match(value, &etag)) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(("ETag", etag))
+            .finish());
+    }
+
+    // The storage serves ranges itself when the file can be downloaded directly.
+    if let Some(url) = file_system
+        .presigned_url(&file_path, Some(&content_disposition))
+        .await?
+    {
+        return Ok(HttpResponse::Found()
+            .insert_header(("Location", url))
+            .finish());
     }
 
-    delete_user_track(&mut connection, &*track_row).await?;
+    let range_request = match header_value("Range") {
+        Some(value) => parse_range_header(value, file_size),
+        None => RangeRequest::Full,
+    };
 
-    file_system
-        .delete_file(&format!("audio/{}", track_row.file.get_path()))
-        .await?;
+    let (mut response, file_contents) = match range_request {
+        RangeRequest::Full => {
+            let mut response = HttpResponse::Ok();
+            response.no_chunking(file_size);
 
-    connection.commit().await?;
+            (response, file_system.get_file_contents(&file_path).await?)
+        }
+        RangeRequest::Partial(range) => {
+            let mut response = HttpResponse::PartialContent();
+            response.insert_header(("Content-Range", range.content_range(file_size)));
+            response.no_chunking(range.length);
 
-    Ok(HttpResponse::Ok().finish())
+            let file_contents = file_system
+                .get_file_range(&file_path, range.offset, range.length)
+                .await?;
+
+            (response, file_contents)
+        }
+        RangeRequest::Unsatisfiable => {
+            return Ok(HttpResponse::RangeNotSatisfiable()
+                .insert_header(("Content-Range", format!("bytes */{}", file_size)))
+                .finish());
+        }
+    };
+
+    response.insert_header(("Accept-Ranges", "bytes"));
+    response.insert_header(("ETag", etag));
+    response.insert_header(("Content-Disposition", content_disposition));
+
+    let response = response
+        .streaming::<_, actix_web::Error>(file_contents.map(|chunk| Ok(Bytes::from(chunk?))));
+
+    Ok(response)
 }
 
-pub(crate) async fn download_audio_track<FS: FileSystem>(
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrackMetadataBody {
+    #[serde(default)]
+    artist: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    album: Option<String>,
+    #[serde(default)]
+    track_number: Option<String>,
+    #[serde(default)]
+    genre: Option<String>,
+    #[serde(default)]
+    date: Option<String>,
+    /// Rewrites the tags of the audio file in addition to the library metadata.
+    #[serde(default)]
+    write_tags: bool,
+}
+
+impl From<TrackMetadataBody> for TrackMetadataUpdate {
+    fn from(body: TrackMetadataBody) -> Self {
+        Self {
+            artist: body.artist,
+            title: body.title,
+            album: body.album,
+            track_number: body.track_number,
+            genre: body.genre,
+            date: body.date,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UpdateAudioTracksBody {
+    track_ids: Vec<TrackId>,
+    #[serde(flatten)]
+    metadata: TrackMetadataBody,
+}
+
+fn track_metadata_error_response(error: TrackMetadataError) -> Response {
+    match error {
+        TrackMetadataError::TrackNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "TRACK_NOT_FOUND" })))
+        }
+        TrackMetadataError::NoTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "NO_TRACKS" })))
+        }
+        TrackMetadataError::TooManyTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "TOO_MANY_TRACKS" })))
+        }
+        TrackMetadataError::InvalidArtist => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_ARTIST" })))
+        }
+        TrackMetadataError::InvalidTitle => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_TITLE" })))
+        }
+        TrackMetadataError::InvalidAlbum => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_ALBUM" })))
+        }
+        TrackMetadataError::InvalidTrackNumber => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_TRACK_NUMBER" })))
+        }
+        TrackMetadataError::InvalidGenre => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_GENRE" })))
+        }
+        TrackMetadataError::InvalidDate => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_DATE" })))
+        }
+        TrackMetadataError::DatabaseError(err) => Err(err.into()),
+        TrackMetadataError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+/// `tags_pending` tells whether the tags are being written into the audio file in the background.
+fn updated_track_json(track: &TrackRow, tags_pending: bool) -> serde_json::Value {
+    json!({
+        "album": track.album,
+        "artist": track.artist,
+        "buy": track.buy,
+        "can_be_shared": track.can_be_shared,
+        "color": track.color,
+        "cue": track.cue,
+        "date": track.date,
+        "duration": track.duration,
+        "filename": track.filename,
+        "genre": track.genre,
+        "is_new": track.is_new,
+        "tid": track.tid,
+        "title": track.title,
+        "track_number": track.track_number,
+        "tags_pending": tags_pending
+    })
+}
+
+pub(crate) async fn update_audio_track<FS: FileSystem + Clone + 'static>(
     user_id: UserId,
     path: Path<TrackId>,
-    mysql_client: Data<MySqlClient>,
+    body: Json<TrackMetadataBody>,
+    track_metadata_service: Data<TrackMetadataService>,
     file_system: Data<FS>,
 ) -> Response {
-    let track_id = path.into_inner();
-
-    let mut connection = mysql_client.transaction().await?;
+    let body = body.into_inner();
+    let write_tags = body.write_tags;
+
+    match track_metadata_service
+        .update_tracks(
+            &user_id,
+            &[path.into_inner()],
+            &body.into(),
+            write_tags,
+            file_system.get_ref(),
+        )
+        .await
+    {
+        Ok(updated_tracks) => Ok(HttpResponse::Ok().json(json!({
+            "code": 1i32,
+            "message": "OK",
+            "data": updated_tracks
+                .first()
+                .map(|track| updated_track_json(track, write_tags)),
+        }))),
+        Err(error) => track_metadata_error_response(error),
+    }
+}
 
-    let track_row = match get_single_user_track(&mut connection, &track_id)
+pub(crate) async fn update_audio_tracks<FS: FileSystem + Clone + 'static>(
+    user_id: UserId,
+    body: Json<UpdateAudioTracksBody>,
+    track_metadata_service: Data<TrackMetadataService>,
+    file_system: Data<FS>,
+) -> Response {
+    let body = body.into_inner();
+    let write_tags = body.metadata.write_tags;
+
+    match track_metadata_service
+        .update_tracks(
+            &user_id,
+            &body.track_ids,
+            &body.metadata.into(),
+            write_tags,
+            file_system.get_ref(),
+        )
         .await
-        .tee_err(|error| error!(?error, "Unable to get user track from database"))?
     {
-        Some(track_row) => track_row,
-        None => return Ok(HttpResponse::NotFound().finish()),
-    };
+        Ok(updated_tracks) => Ok(HttpResponse::Ok().json(json!({
+            "code": 1i32,
+            "message": "OK",
+            "data": updated_tracks
+                .iter()
+                .map(|track| updated_track_json(track, write_tags))
+                .collect::<Vec<_>>(),
+        }))),
+        Err(error) => track_metadata_error_response(error),
+    }
+}
 
-    if track_row.track.uid != user_id {
-        return Ok(HttpResponse::Forbidden().finish());
+pub(crate) fn artwork_error_response(error: ArtworkError) -> Response {
+    match error {
+        ArtworkError::TrackNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "TRACK_NOT_FOUND" })))
+        }
+        ArtworkError::ArtworkNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "ARTWORK_NOT_FOUND" })))
+        }
+        ArtworkError::InvalidImage => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_IMAGE" })))
+        }
+        ArtworkError::ExtractionError(error) => {
+            error!(?error, "Unable to extract track artwork");
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+        ArtworkError::FileSystemError(err) => Err(err.into()),
+        ArtworkError::DatabaseError(err) => Err(err.into()),
+        ArtworkError::RepositoryError(err) => Err(err.into()),
     }
+}
 
-    let file_name = track_row.track.filename;
-    let file_size = track_row.file.file_size;
-    let file_path = track_row.file.get_path();
+#[derive(Deserialize)]
+pub(crate) struct GetAudioTrackArtworkQuery {
+    #[serde(default)]
+    pub(crate) size: ArtworkSize,
+}
 
-    let file_contents = file_system
-        .get_file_contents(&format!("audio/{}", file_path))
-        .await?;
+pub(crate) async fn get_audio_track_artwork<FS: FileSystem>(
+    user_id: UserId,
+    path: Path<TrackId>,
+    query: Query<GetAudioTrackArtworkQuery>,
+    request: HttpRequest,
+    artwork_service: Data<ArtworkService>,
+    file_system: Data<FS>,
+) -> Response {
+    let size = query.size;
 
-    let mut response = HttpResponse::Ok();
+    let artwork = match artwork_service
+        .get_track_artwork(&user_id, &path.into_inner(), size)
+        .await
+    {
+        Ok(artwork) => artwork,
+        Err(error) => return artwork_error_response(error),
+    };
 
-    response.insert_header((
-        "Content-Disposition",
-        format!("attachment; filename={}", file_name),
-    ));
-    response.insert_header(("Content-Length", format!("{}", file_size)));
+    artwork_response(
+        artwork,
+        size,
+        &request,
+        file_system.get_ref(),
+        "private, max-age=86400",
+    )
+    .await
+}
 
-    let response = response.streaming::<_, actix_web::Error>(
-        file_contents.map(|data| Ok(Bytes::copy_from_slice(&data))),
-    );
+/// Streams the artwork variant, or responds with `304 Not Modified` if the client has it.
+pub(crate) async fn artwork_response<FS: FileSystem>(
+    artwork: Artwork,
+    size: ArtworkSize,
+    request: &HttpRequest,
+    file_system: &FS,
+    cache_control: &str,
+) -> Response {
+    let etag = file_etag(&format!("{}_{}", artwork.hash, size.pixels()));
 
-    Ok(response)
+    if request
+        .headers()
+        .get("If-None-Match")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| if_none_match(value, &etag))
+    {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(("ETag", etag))
+            .finish());
+    }
+
+    let file_contents = file_system.get_file_contents(&artwork.path).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/png")
+        .insert_header(("ETag", etag))
+        .insert_header(("Cache-Control", cache_control))
+        .streaming::<_, actix_web::Error>(file_contents.map(|chunk| Ok(Bytes::from(chunk?)))))
 }
@@ -1,9 +1,12 @@
-use crate::data_structures::{SortingColumn, SortingOrder, StreamId, UserId};
-use crate::http_server::response::Response;
+use crate::data_structures::{SortingColumn, SortingOrder, StreamRef, TrackId, UserId};
+use crate::http_server::cursor::{decode_cursor, encode_cursor};
+use crate::http_server::response::{invalid_track_query_response, Response};
 use crate::mysql_client::MySqlClient;
+use crate::storage::db::repositories::user_tracks::UserTracksCursor;
 use crate::storage::db::repositories::{
     streams, user_stream_tracks, user_tracks, LinkRow, TrackRow,
 };
+use crate::storage::db::track_query::TrackQuery;
 use crate::utils::TeeResultUtils;
 use actix_web::{web, HttpResponse};
 use serde::Deserialize;
@@ -13,6 +16,8 @@ const MAX_TRACKS_PER_REQUEST: i64 = 500;
 
 const DEFAULT_TRACKS_PER_REQUEST: i64 = 50;
 
+const MAX_SEEN_TRACKS: usize = 1000;
+
 fn serialize_track_row(track: &TrackRow) -> serde_json::Value {
     serde_json::json!({
         "album": track.album,
@@ -21,6 +26,7 @@ fn serialize_track_row(track: &TrackRow) -> serde_json::Value {
         "duration": track.duration,
         "filename": track.filename,
         "genre": track.genre,
+        "isNew": track.is_new,
         "tid": track.tid,
         "title": track.title,
         "trackNumber": track.track_number
@@ -33,6 +39,18 @@ fn serialize_link_row(link: &LinkRow) -> serde_json::Value {
     })
 }
 
+/// Part of the user's library listed by the endpoint.
+#[derive(Clone, Copy)]
+enum TrackListing {
+    All,
+    Unused,
+    New,
+}
+
+fn invalid_cursor_response() -> Response {
+    Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": "INVALID_CURSOR" })))
+}
+
 #[derive(Deserialize)]
 pub(crate) struct GetUserAudioTracksQuery {
     filter: Option<String>,
@@ -40,6 +58,13 @@ pub(crate) struct GetUserAudioTracksQuery {
     offset: i64,
     #[serde(default)]
     limit: Option<i64>,
+    /// Continues from the `nextCursor` of the previous page; `offset` is ignored then.
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    row: SortingColumn,
+    #[serde(default)]
+    order: SortingOrder,
 }
 
 pub(crate) async fn get_user_audio_tracks(
@@ -47,72 +72,127 @@ pub(crate) async fn get_user_audio_tracks(
     query: web::Query<GetUserAudioTracksQuery>,
     mysql_client: web::Data<MySqlClient>,
 ) -> Response {
-    let params = query.into_inner();
-
-    let mut conn = mysql_client.connection().await?;
+    get_user_audio_tracks_page(
+        &user_id,
+        query.into_inner(),
+        TrackListing::All,
+        &mysql_client,
+    )
+    .await
+}
 
-    let offset = params.offset;
-    let limit = params
-        .limit
-        .unwrap_or(DEFAULT_TRACKS_PER_REQUEST)
-        .min(MAX_TRACKS_PER_REQUEST);
+pub(crate) async fn get_unused_user_audio_tracks(
+    user_id: UserId,
+    query: web::Query<GetUserAudioTracksQuery>,
+    mysql_client: web::Data<MySqlClient>,
+) -> Response {
+    get_user_audio_tracks_page(
+        &user_id,
+        query.into_inner(),
+        TrackListing::Unused,
+        &mysql_client,
+    )
+    .await
+}
 
-    let tracks_count = user_tracks::get_user_tracks_count(
-        &mut conn,
+/// Tracks the user hasn't seen yet: fresh uploads not added to any stream nor marked as seen.
+pub(crate) async fn get_new_user_audio_tracks(
+    user_id: UserId,
+    query: web::Query<GetUserAudioTracksQuery>,
+    mysql_client: web::Data<MySqlClient>,
+) -> Response {
+    get_user_audio_tracks_page(
         &user_id,
-        &user_tracks::GetUserTracksTotalParams {
-            color: None,
-            filter: params.filter.clone(),
-            unused: false,
-        },
+        query.into_inner(),
+        TrackListing::New,
+        &mysql_client,
     )
     .await
-    .tee_err(|error| {
-        error!(
-            ?error,
-            "Failed to get user audio tracks total from repository"
+}
+
+/// Exactly one of the fields selects the tracks to mark as seen.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MarkTracksSeenBody {
+    #[serde(default)]
+    track_ids: Option<Vec<TrackId>>,
+    #[serde(default)]
+    filter: Option<String>,
+    #[serde(default)]
+    all: bool,
+}
+
+pub(crate) async fn mark_user_audio_tracks_seen(
+    user_id: UserId,
+    body: web::Json<MarkTracksSeenBody>,
+    mysql_client: web::Data<MySqlClient>,
+) -> Response {
+    let body = body.into_inner();
+
+    if matches!(&body.track_ids, Some(track_ids) if track_ids.len() > MAX_SEEN_TRACKS) {
+        return Ok(
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": "TOO_MANY_TRACKS" }))
         );
-    })?;
+    }
 
-    let track_items = user_tracks::get_user_tracks(
-        &mut conn,
-        &user_id,
-        &user_tracks::GetUserTracksParams {
-            color: None,
-            filter: params.filter,
-            sorting_column: SortingColumn::TrackId,
-            sorting_order: SortingOrder::Desc,
-            unused: false,
+    let filter = match (&body.track_ids, &body.filter, body.all) {
+        (Some(_), None, false) => None,
+        (None, Some(filter), false) => match TrackQuery::parse(filter) {
+            Ok(filter) => Some(filter),
+            Err(error) => return Ok(invalid_track_query_response(&error)),
         },
-        &Some(offset),
-        &Some(limit),
+        (None, None, true) => None,
+        _ => {
+            return Ok(HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "INVALID_SELECTION" })));
+        }
+    };
+
+    let mut connection = mysql_client.connection().await?;
+
+    let marked_count = user_tracks::mark_user_tracks_seen(
+        &mut connection,
+        &user_id,
+        body.track_ids.as_deref(),
+        filter.as_ref(),
     )
     .await
     .tee_err(|error| {
-        error!(?error, "Failed to get user audio tracks from repository");
+        error!(?error, "Failed to mark user audio tracks as seen");
     })?;
 
-    let track_items_json: Vec<_> = track_items
-        .into_iter()
-        .map(|row| serialize_track_row(&row.track))
-        .collect();
-
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "totalCount": tracks_count,
-        "items": track_items_json,
-        "paginationData": {
-            "limit": limit,
-            "offset": offset
-        }
+        "markedCount": marked_count,
     })))
 }
 
-pub(crate) async fn get_unused_user_audio_tracks(
-    user_id: UserId,
-    query: web::Query<GetUserAudioTracksQuery>,
-    mysql_client: web::Data<MySqlClient>,
+async fn get_user_audio_tracks_page(
+    user_id: &UserId,
+    params: GetUserAudioTracksQuery,
+    listing: TrackListing,
+    mysql_client: &MySqlClient,
 ) -> Response {
-    let params = query.into_inner();
+    let cursor = match params
+        .cursor
+        .as_deref()
+        .map(decode_cursor::<UserTracksCursor>)
+    {
+        None => None,
+        Some(Some(cursor))
+            if cursor.sorting_column == params.row && cursor.sorting_order == params.order =>
+        {
+            Some(cursor)
+        }
+        Some(_) => return invalid_cursor_response(),
+    };
+
+    let filter = match params.filter.as_deref().map(TrackQuery::parse).transpose() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(invalid_track_query_response(&error)),
+    };
+
+    let unused = matches!(listing, TrackListing::Unused);
+    let new_only = matches!(listing, TrackListing::New);
 
     let mut conn = mysql_client.connection().await?;
 
@@ -124,11 +204,12 @@ pub(crate) async fn get_unused_user_audio_tracks(
 
     let tracks_count = user_tracks::get_user_tracks_count(
         &mut conn,
-        &user_id,
+        user_id,
         &user_tracks::GetUserTracksTotalParams {
             color: None,
-            filter: params.filter.clone(),
-            unused: true,
+            filter: filter.clone(),
+            unused,
+            new_only,
         },
     )
     .await
@@ -139,24 +220,56 @@ pub(crate) async fn get_unused_user_audio_tracks(
         );
     })?;
 
-    let track_items = user_tracks::get_user_tracks(
-        &mut conn,
-        &user_id,
-        &user_tracks::GetUserTracksParams {
-            color: None,
-            filter: params.filter,
-            sorting_column: SortingColumn::TrackId,
-            sorting_order: SortingOrder::Desc,
-            unused: true,
-        },
-        &Some(offset),
-        &Some(limit),
-    )
-    .await
+    let tracks_params = user_tracks::GetUserTracksParams {
+        color: None,
+        filter,
+        sorting_column: params.row,
+        sorting_order: params.order,
+        unused,
+        new_only,
+    };
+
+    // One extra track tells whether there is a next page.
+    let mut track_items = match &cursor {
+        Some(cursor) => {
+            user_tracks::get_user_tracks_after(
+                &mut conn,
+                user_id,
+                &tracks_params,
+                cursor,
+                limit + 1,
+            )
+            .await
+        }
+        None => {
+            user_tracks::get_user_tracks(
+                &mut conn,
+                user_id,
+                &tracks_params,
+                &Some(offset),
+                &Some(limit + 1),
+            )
+            .await
+        }
+    }
     .tee_err(|error| {
         error!(?error, "Failed to get user audio tracks from repository");
     })?;
 
+    let next_cursor = if track_items.len() as i64 > limit {
+        track_items.truncate(limit as usize);
+
+        track_items.last().map(|row| {
+            encode_cursor(&UserTracksCursor::after(
+                &row.track,
+                params.row,
+                params.order,
+            ))
+        })
+    } else {
+        None
+    };
+
     let track_items_json: Vec<_> = track_items
         .into_iter()
         .map(|row| serialize_track_row(&row.track))
@@ -165,6 +278,7 @@ pub(crate) async fn get_unused_user_audio_tracks(
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "totalCount": tracks_count,
         "items": track_items_json,
+        "nextCursor": next_cursor,
         "paginationData": {
             "limit": limit,
             "offset": offset
@@ -180,17 +294,35 @@ pub(crate) struct GetChannelAudioTracksQuery {
     offset: i64,
     #[serde(default)]
     limit: Option<i64>,
+    /// Continues from the `nextCursor` of the previous page; `offset` is ignored then.
+    #[serde(default)]
+    cursor: Option<String>,
 }
 
 pub(crate) async fn get_channel_audio_tracks(
-    path: web::Path<StreamId>,
+    path: web::Path<StreamRef>,
     user_id: UserId,
     query: web::Query<GetChannelAudioTracksQuery>,
     mysql_client: web::Data<MySqlClient>,
 ) -> Response {
-    let stream_id = path.into_inner();
+    let stream_ref = path.into_inner();
     let params = query.into_inner();
 
+    let cursor = match params
+        .cursor
+        .as_deref()
+        .map(decode_cursor::<user_stream_tracks::StreamTracksCursor>)
+    {
+        None => None,
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => return invalid_cursor_response(),
+    };
+
+    let filter = match params.filter.as_deref().map(TrackQuery::parse).transpose() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(invalid_track_query_response(&error)),
+    };
+
     let offset = params.offset;
     let limit = params
         .limit
@@ -199,8 +331,8 @@ pub(crate) async fn get_channel_audio_tracks(
 
     let mut connection = mysql_client.connection().await?;
 
-    match streams::get_single_stream_by_id(&mut connection, &stream_id).await {
-        Ok(Some(stream)) if stream.uid == user_id => (),
+    let stream_id = match streams::get_single_stream_by_ref(&mut connection, &stream_ref).await {
+        Ok(Some(stream)) if stream.uid == user_id => stream.sid,
         Ok(Some(_)) => return Ok(HttpResponse::Forbidden().finish()),
         Ok(None) => return Ok(HttpResponse::NotFound().finish()),
         Err(error) => {
@@ -208,35 +340,46 @@ pub(crate) async fn get_channel_audio_tracks(
 
             return Ok(HttpResponse::InternalServerError().finish());
         }
-    }
+    };
 
-    let total_count = user_stream_tracks::get_stream_tracks_count(
-        &mut connection,
-        &stream_id,
-        &user_stream_tracks::GetUserStreamTracksParams {
-            color: None,
-            filter: params.filter.clone(),
-        },
-    )
-    .await
-    .tee_err(|error| {
-        error!(
-            ?error,
-            "Failed to get user stream audio tracks from repository"
-        );
-    })?;
+    let tracks_params = user_stream_tracks::GetUserStreamTracksParams {
+        filter,
+        ..user_stream_tracks::GetUserStreamTracksParams::default()
+    };
 
-    let track_items = user_stream_tracks::get_stream_tracks(
-        &mut connection,
-        &stream_id,
-        &user_stream_tracks::GetUserStreamTracksParams {
-            color: None,
-            filter: params.filter,
-        },
-        &Some(offset),
-        &Some(limit),
-    )
-    .await
+    let total_count =
+        user_stream_tracks::get_stream_tracks_count(&mut connection, &stream_id, &tracks_params)
+            .await
+            .tee_err(|error| {
+                error!(
+                    ?error,
+                    "Failed to get user stream audio tracks from repository"
+                );
+            })?;
+
+    // One extra entry tells whether there is a next page.
+    let mut track_items = match &cursor {
+        Some(cursor) => {
+            user_stream_tracks::get_stream_tracks_after(
+                &mut connection,
+                &stream_id,
+                &tracks_params,
+                cursor,
+                limit + 1,
+            )
+            .await
+        }
+        None => {
+            user_stream_tracks::get_stream_tracks(
+                &mut connection,
+                &stream_id,
+                &tracks_params,
+                &Some(offset),
+                &Some(limit + 1),
+            )
+            .await
+        }
+    }
     .tee_err(|error| {
         error!(
             ?error,
@@ -244,6 +387,16 @@ pub(crate) async fn get_channel_audio_tracks(
         );
     })?;
 
+    let next_cursor = if track_items.len() as i64 > limit {
+        track_items.truncate(limit as usize);
+
+        track_items
+            .last()
+            .map(|row| encode_cursor(&user_stream_tracks::StreamTracksCursor::after(&row.link)))
+    } else {
+        None
+    };
+
     let track_items_json: Vec<_> = track_items
         .into_iter()
         .map(|row| {
@@ -257,6 +410,7 @@ pub(crate) async fn get_channel_audio_tracks(
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "totalCount": total_count,
         "items": track_items_json,
+        "nextCursor": next_cursor,
         "paginationData": {
             "limit": limit,
             "offset": offset
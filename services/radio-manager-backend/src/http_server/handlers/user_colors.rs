@@ -0,0 +1,145 @@
+use crate::data_structures::{TrackId, UserId};
+use crate::http_server::response::Response;
+use crate::services::{ColorLabel, ColorLabelError, ColorLabelService, ColorLabelUpdate};
+use actix_web::web::{Data, Json, Path};
+use actix_web::HttpResponse;
+use serde::Deserialize;
+use serde_json::json;
+
+fn color_json(color: &ColorLabel) -> serde_json::Value {
+    json!({
+        "id": color.id,
+        "name": color.name,
+        "code": color.code,
+        "isPreset": color.is_preset,
+        "tracksCount": color.tracks_count,
+        "tracksDuration": color.tracks_duration,
+    })
+}
+
+fn color_label_error_response(error: ColorLabelError) -> Response {
+    match error {
+        ColorLabelError::ColorNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "COLOR_NOT_FOUND" })))
+        }
+        ColorLabelError::ColorNotEditable => {
+            Ok(HttpResponse::Forbidden().json(json!({ "error": "COLOR_NOT_EDITABLE" })))
+        }
+        ColorLabelError::InvalidName => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_NAME" })))
+        }
+        ColorLabelError::InvalidCode => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_CODE" })))
+        }
+        ColorLabelError::TooManyColors => {
+            Ok(HttpResponse::Conflict().json(json!({ "error": "TOO_MANY_COLORS" })))
+        }
+        ColorLabelError::TrackNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "TRACK_NOT_FOUND" })))
+        }
+        ColorLabelError::NoTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "NO_TRACKS" })))
+        }
+        ColorLabelError::TooManyTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "TOO_MANY_TRACKS" })))
+        }
+        ColorLabelError::DatabaseError(err) => Err(err.into()),
+        ColorLabelError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+pub(crate) async fn get_colors(
+    user_id: UserId,
+    color_label_service: Data<ColorLabelService>,
+) -> Response {
+    match color_label_service.get_colors(&user_id).await {
+        Ok(colors) => Ok(HttpResponse::Ok().json(json!({
+            "items": colors.iter().map(color_json).collect::<Vec<_>>(),
+        }))),
+        Err(error) => color_label_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CreateColorBody {
+    name: String,
+    code: String,
+}
+
+pub(crate) async fn create_color(
+    user_id: UserId,
+    body: Json<CreateColorBody>,
+    color_label_service: Data<ColorLabelService>,
+) -> Response {
+    match color_label_service
+        .create_color(&user_id, &body.name, &body.code)
+        .await
+    {
+        Ok(color) => Ok(HttpResponse::Created().json(color_json(&color))),
+        Err(error) => color_label_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct UpdateColorBody {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+}
+
+pub(crate) async fn update_color(
+    user_id: UserId,
+    path: Path<i32>,
+    body: Json<UpdateColorBody>,
+    color_label_service: Data<ColorLabelService>,
+) -> Response {
+    let body = body.into_inner();
+    let update = ColorLabelUpdate {
+        name: body.name,
+        code: body.code,
+    };
+
+    match color_label_service
+        .update_color(&user_id, path.into_inner(), &update)
+        .await
+    {
+        Ok(color) => Ok(HttpResponse::Ok().json(color_json(&color))),
+        Err(error) => color_label_error_response(error),
+    }
+}
+
+pub(crate) async fn delete_color(
+    user_id: UserId,
+    path: Path<i32>,
+    color_label_service: Data<ColorLabelService>,
+) -> Response {
+    match color_label_service
+        .delete_color(&user_id, path.into_inner())
+        .await
+    {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(error) => color_label_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SetTracksColorBody {
+    track_ids: Vec<TrackId>,
+    color_id: i32,
+}
+
+pub(crate) async fn set_tracks_color(
+    user_id: UserId,
+    body: Json<SetTracksColorBody>,
+    color_label_service: Data<ColorLabelService>,
+) -> Response {
+    match color_label_service
+        .set_tracks_color(&user_id, &body.track_ids, body.color_id)
+        .await
+    {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(error) => color_label_error_response(error),
+    }
+}
@@ -0,0 +1,80 @@
+use crate::data_structures::{StreamRef, TrackId, UserId};
+use crate::http_server::download::attachment_content_disposition;
+use crate::http_server::response::Response;
+use crate::services::{LibraryExport, LibraryExportError, LibraryExportService};
+use crate::storage::fs::FileSystem;
+use actix_web::web::{Bytes, Data, Json, Path};
+use actix_web::HttpResponse;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+
+fn library_export_error_response(error: LibraryExportError) -> Response {
+    match error {
+        LibraryExportError::StreamNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "STREAM_NOT_FOUND" })))
+        }
+        LibraryExportError::TrackNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "TRACK_NOT_FOUND" })))
+        }
+        LibraryExportError::TooManyTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "TOO_MANY_TRACKS" })))
+        }
+        LibraryExportError::DatabaseError(err) => Err(err.into()),
+        LibraryExportError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+fn archive_response<FS: FileSystem + Clone + 'static>(
+    export: LibraryExport,
+    library_export_service: &LibraryExportService,
+    file_system: &FS,
+) -> Response {
+    let content_disposition = attachment_content_disposition(&format!("{}.zip", export.title));
+    let contents = library_export_service.stream_archive(export, file_system.clone());
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(("Content-Disposition", content_disposition))
+        .streaming(contents.map(|chunk| {
+            chunk
+                .map(Bytes::from)
+                .map_err(actix_web::error::ErrorInternalServerError)
+        })))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExportTracksBody {
+    track_ids: Vec<TrackId>,
+}
+
+pub(crate) async fn export_audio_tracks<FS: FileSystem + Clone + 'static>(
+    user_id: UserId,
+    body: Json<ExportTracksBody>,
+    library_export_service: Data<LibraryExportService>,
+    file_system: Data<FS>,
+) -> Response {
+    match library_export_service
+        .export_tracks(&user_id, &body.track_ids)
+        .await
+    {
+        Ok(export) => archive_response(export, &library_export_service, file_system.get_ref()),
+        Err(error) => library_export_error_response(error),
+    }
+}
+
+pub(crate) async fn export_stream_audio_tracks<FS: FileSystem + Clone + 'static>(
+    user_id: UserId,
+    stream_ref: Path<StreamRef>,
+    library_export_service: Data<LibraryExportService>,
+    file_system: Data<FS>,
+) -> Response {
+    match library_export_service
+        .export_stream(&user_id, &stream_ref)
+        .await
+    {
+        Ok(export) => archive_response(export, &library_export_service, file_system.get_ref()),
+        Err(error) => library_export_error_response(error),
+    }
+}
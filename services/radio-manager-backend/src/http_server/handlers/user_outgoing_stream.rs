@@ -1,4 +1,4 @@
-use crate::data_structures::{StreamId, UserId};
+use crate::data_structures::{StreamRef, UserId};
 use crate::http_server::response::Response;
 use crate::mysql_client::MySqlClient;
 use crate::services::auth::{AuthTokenClaim, AuthTokenClaims, AuthTokenService};
@@ -14,10 +14,20 @@ use tracing::error;
 
 pub(crate) async fn get_outgoing_stream(
     user_id: UserId,
-    channel_id: Path<StreamId>,
+    channel_ref: Path<StreamRef>,
     mysql_client: Data<MySqlClient>,
     web_egress_client: Data<WebEgressControllerClient>,
 ) -> Response {
+    let mut conn = mysql_client.connection().await?;
+
+    let channel_id = match streams::get_single_stream_by_ref(&mut conn, &channel_ref).await? {
+        Some(row) => row.sid,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({ "error": "CHANNEL_NOT_FOUND" })));
+        }
+    };
+    drop(conn);
+
     let stream = web_egress_client.get_stream(&channel_id, &user_id).await?;
 
     let response = match stream {
@@ -36,19 +46,20 @@ pub(crate) async fn get_outgoing_stream(
 
 pub(crate) async fn start_outgoing_stream(
     user_id: UserId,
-    channel_id: Path<StreamId>,
+    channel_ref: Path<StreamRef>,
     mysql_client: Data<MySqlClient>,
     web_egress_client: Data<WebEgressControllerClient>,
     auth_token_service: Data<AuthTokenService>,
 ) -> Response {
     let mut conn = mysql_client.connection().await?;
 
-    let stream_row = match streams::get_single_stream_by_id(&mut conn, &channel_id).await? {
+    let stream_row = match streams::get_single_stream_by_ref(&mut conn, &channel_ref).await? {
         Some(row) => row,
         None => {
             return Ok(HttpResponse::NotFound().json(json!({ "error": "CHANNEL_NOT_FOUND" })));
         }
     };
+    let channel_id = stream_row.sid.clone();
 
     let now_timestamp = chrono::Utc::now().timestamp() as usize;
     let claims = AuthTokenClaims {
@@ -100,10 +111,20 @@ pub(crate) async fn start_outgoing_stream(
 
 pub(crate) async fn stop_outgoing_stream(
     user_id: UserId,
-    channel_id: Path<StreamId>,
+    channel_ref: Path<StreamRef>,
     mysql_client: Data<MySqlClient>,
     web_egress_client: Data<WebEgressControllerClient>,
 ) -> Response {
+    let mut conn = mysql_client.connection().await?;
+
+    let channel_id = match streams::get_single_stream_by_ref(&mut conn, &channel_ref).await? {
+        Some(row) => row.sid,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({ "error": "CHANNEL_NOT_FOUND" })));
+        }
+    };
+    drop(conn);
+
     let response = match web_egress_client.stop_stream(&channel_id, &user_id).await {
         Ok(_) => HttpResponse::Ok().finish(),
         Err(OutgoingStreamStoppingError::AlreadyStopped) => HttpResponse::Conflict().finish(),
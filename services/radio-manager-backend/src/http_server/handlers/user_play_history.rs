@@ -0,0 +1,103 @@
+use crate::data_structures::{StreamRef, UserId, DEFAULT_TRACKS_PER_REQUEST};
+use crate::http_server::response::Response;
+use crate::services::{PlayHistoryError, PlayHistoryService};
+use actix_web::web::{Data, Path, Query};
+use actix_web::HttpResponse;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+pub(crate) fn play_history_error_response(error: PlayHistoryError) -> Response {
+    match error {
+        PlayHistoryError::StreamNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "STREAM_NOT_FOUND" })))
+        }
+        PlayHistoryError::TrackNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "TRACK_NOT_FOUND" })))
+        }
+        PlayHistoryError::DatabaseError(err) => Err(err.into()),
+        PlayHistoryError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetStreamPlayHistoryQuery {
+    /// Continues from the `startedAt` of the last entry of the previous page.
+    #[serde(default)]
+    before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    limit: Option<i64>,
+}
+
+pub(crate) async fn get_stream_play_history(
+    user_id: UserId,
+    path: Path<StreamRef>,
+    query: Query<GetStreamPlayHistoryQuery>,
+    play_history_service: Data<PlayHistoryService>,
+) -> Response {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TRACKS_PER_REQUEST)
+        .clamp(1, DEFAULT_TRACKS_PER_REQUEST);
+
+    match play_history_service
+        .get_stream_play_history(&user_id, &path.into_inner(), &query.before, limit)
+        .await
+    {
+        Ok(plays) => Ok(HttpResponse::Ok().json(json!({
+            "items": plays
+                .iter()
+                .map(|play| json!({
+                    "album": play.album,
+                    "artist": play.artist,
+                    "duration": play.duration,
+                    "listeners": play.listeners,
+                    "startedAt": play.started_at,
+                    "title": play.title,
+                    "trackId": play.track_id,
+                }))
+                .collect::<Vec<_>>(),
+        }))),
+        Err(error) => play_history_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetMostPlayedTracksQuery {
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    limit: Option<i64>,
+}
+
+pub(crate) async fn get_most_played_tracks(
+    user_id: UserId,
+    query: Query<GetMostPlayedTracksQuery>,
+    play_history_service: Data<PlayHistoryService>,
+) -> Response {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TRACKS_PER_REQUEST)
+        .clamp(1, DEFAULT_TRACKS_PER_REQUEST);
+
+    match play_history_service
+        .get_most_played_tracks(&user_id, query.offset.max(0), limit)
+        .await
+    {
+        Ok(tracks) => Ok(HttpResponse::Ok().json(json!({
+            "items": tracks
+                .iter()
+                .map(|track| json!({
+                    "album": track.album,
+                    "artist": track.artist,
+                    "duration": track.duration,
+                    "lastPlayedAt": track.last_played_at,
+                    "plays": track.plays,
+                    "tid": track.track_id,
+                    "title": track.title,
+                }))
+                .collect::<Vec<_>>(),
+        }))),
+        Err(error) => play_history_error_response(error),
+    }
+}
@@ -0,0 +1,254 @@
+use crate::data_structures::UserId;
+use crate::http_server::response::Response;
+use crate::services::email_service::EmailServiceError;
+use crate::services::{
+    ChangeEmailError, DeleteAccountError, ProfileError, ProfileService, ProfileUpdate,
+    StreamServiceFactory, UpdateAvatarError, UpdateProfileError,
+};
+use crate::storage::db::repositories::streams::get_user_streams_by_user_id;
+use crate::storage::db::repositories::UserRow;
+use crate::storage::fs::FileSystem;
+use crate::web_egress_controller_client::{OutgoingStreamStoppingError, WebEgressControllerClient};
+use crate::MySqlClient;
+use actix_web::web::{Bytes, Data, Json};
+use actix_web::HttpResponse;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+
+fn profile_json(user: &UserRow) -> serde_json::Value {
+    json!({
+        "uid": user.uid,
+        "email": user.mail,
+        "login": user.login,
+        "name": user.name,
+        "info": user.info,
+        "countryId": user.country_id,
+        "permalink": user.permalink,
+        "avatar": user.avatar,
+        "registrationDate": user.registration_date,
+    })
+}
+
+fn change_email_error_response(error: ChangeEmailError) -> Response {
+    match error {
+        ChangeEmailError::UserNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "USER_NOT_FOUND" })))
+        }
+        ChangeEmailError::InvalidEmailAddress => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_EMAIL_ADDRESS" })))
+        }
+        ChangeEmailError::NonUniqueEmailAddress => {
+            Ok(HttpResponse::Conflict().json(json!({ "error": "NON_UNIQUE_EMAIL_ADDRESS" })))
+        }
+        ChangeEmailError::InvalidConfirmationToken => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_CONFIRMATION_TOKEN" })))
+        }
+        ChangeEmailError::EmailServiceError(EmailServiceError::NotConfigured) => {
+            Ok(HttpResponse::ServiceUnavailable().json(json!({ "error": "EMAIL_NOT_CONFIGURED" })))
+        }
+        ChangeEmailError::EmailServiceError(error) => {
+            error!(?error, "Unable to send email change confirmation");
+
+            Ok(HttpResponse::ServiceUnavailable().json(json!({ "error": "EMAIL_NOT_SENT" })))
+        }
+        ChangeEmailError::DatabaseError(err) => Err(err.into()),
+        ChangeEmailError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+fn update_avatar_error_response(error: UpdateAvatarError) -> Response {
+    match error {
+        UpdateAvatarError::UserNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "USER_NOT_FOUND" })))
+        }
+        UpdateAvatarError::InvalidImage => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_IMAGE" })))
+        }
+        UpdateAvatarError::FileSystemError(err) => Err(err.into()),
+        UpdateAvatarError::DatabaseError(err) => Err(err.into()),
+        UpdateAvatarError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+pub(crate) async fn get_profile(
+    user_id: UserId,
+    profile_service: Data<ProfileService>,
+) -> Response {
+    match profile_service.get_profile(&user_id).await {
+        Ok(user) => Ok(HttpResponse::Ok().json(profile_json(&user))),
+        Err(ProfileError::UserNotFound) => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "USER_NOT_FOUND" })))
+        }
+        Err(ProfileError::DatabaseError(err)) => Err(err.into()),
+        Err(ProfileError::RepositoryError(err)) => Err(err.into()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UpdateProfileBody {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    info: Option<String>,
+    #[serde(default)]
+    permalink: Option<String>,
+    #[serde(default)]
+    country_id: Option<i64>,
+    #[serde(default)]
+    email: Option<String>,
+}
+
+pub(crate) async fn update_profile(
+    user_id: UserId,
+    body: Json<UpdateProfileBody>,
+    profile_service: Data<ProfileService>,
+) -> Response {
+    let body = body.into_inner();
+
+    let update = ProfileUpdate {
+        name: body.name,
+        info: body.info,
+        permalink: body.permalink,
+        country_id: body.country_id,
+    };
+
+    let user = match profile_service.update_profile(&user_id, update).await {
+        Ok(user) => user,
+        Err(UpdateProfileError::UserNotFound) => {
+            return Ok(HttpResponse::NotFound().json(json!({ "error": "USER_NOT_FOUND" })))
+        }
+        Err(UpdateProfileError::InvalidName) => {
+            return Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_NAME" })))
+        }
+        Err(UpdateProfileError::InvalidInfo) => {
+            return Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_INFO" })))
+        }
+        Err(UpdateProfileError::InvalidPermalink) => {
+            return Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_PERMALINK" })))
+        }
+        Err(UpdateProfileError::NonUniquePermalink) => {
+            return Ok(HttpResponse::Conflict().json(json!({ "error": "NON_UNIQUE_PERMALINK" })))
+        }
+        Err(UpdateProfileError::InvalidCountry) => {
+            return Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_COUNTRY" })))
+        }
+        Err(UpdateProfileError::DatabaseError(err)) => return Err(err.into()),
+        Err(UpdateProfileError::RepositoryError(err)) => return Err(err.into()),
+    };
+
+    let is_email_change_pending = match body.email {
+        Some(email) if email != user.mail => {
+            if let Err(error) = profile_service.request_email_change(&user_id, &email).await {
+                return change_email_error_response(error);
+            }
+
+            true
+        }
+        _ => false,
+    };
+
+    let mut profile = profile_json(&user);
+    profile["emailChangePending"] = json!(is_email_change_pending);
+
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ConfirmEmailChangeBody {
+    token: String,
+}
+
+pub(crate) async fn confirm_email_change(
+    user_id: UserId,
+    body: Json<ConfirmEmailChangeBody>,
+    profile_service: Data<ProfileService>,
+) -> Response {
+    match profile_service
+        .confirm_email_change(&user_id, &body.token)
+        .await
+    {
+        Ok(email) => Ok(HttpResponse::Ok().json(json!({ "email": email }))),
+        Err(error) => change_email_error_response(error),
+    }
+}
+
+pub(crate) async fn update_avatar<FS: FileSystem>(
+    user_id: UserId,
+    body: Bytes,
+    profile_service: Data<ProfileService>,
+    file_system: Data<FS>,
+) -> Response {
+    match profile_service
+        .update_avatar(&user_id, body.to_vec(), file_system.get_ref())
+        .await
+    {
+        Ok(avatar) => Ok(HttpResponse::Ok().json(json!({ "avatar": avatar }))),
+        Err(error) => update_avatar_error_response(error),
+    }
+}
+
+pub(crate) async fn delete_avatar<FS: FileSystem>(
+    user_id: UserId,
+    profile_service: Data<ProfileService>,
+    file_system: Data<FS>,
+) -> Response {
+    match profile_service
+        .delete_avatar(&user_id, file_system.get_ref())
+        .await
+    {
+        Ok(()) => Ok(HttpResponse::Ok().finish()),
+        Err(error) => update_avatar_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DeleteAccountBody {
+    password: String,
+}
+
+pub(crate) async fn delete_account<FS: FileSystem>(
+    user_id: UserId,
+    body: Json<DeleteAccountBody>,
+    mysql_client: Data<MySqlClient>,
+    profile_service: Data<ProfileService>,
+    stream_service_factory: Data<StreamServiceFactory>,
+    web_egress_client: Data<WebEgressControllerClient>,
+    file_system: Data<FS>,
+) -> Response {
+    let mut connection = mysql_client.connection().await?;
+    let streams = get_user_streams_by_user_id(&mut connection, &user_id).await?;
+    drop(connection);
+
+    match profile_service
+        .delete_account(&user_id, &body.password, file_system.get_ref())
+        .await
+    {
+        Ok(()) => (),
+        Err(DeleteAccountError::UserNotFound) => {
+            return Ok(HttpResponse::NotFound().json(json!({ "error": "USER_NOT_FOUND" })))
+        }
+        Err(DeleteAccountError::InvalidPassword) => {
+            return Ok(HttpResponse::Forbidden().json(json!({ "error": "INVALID_PASSWORD" })))
+        }
+        Err(DeleteAccountError::DatabaseError(err)) => return Err(err.into()),
+        Err(DeleteAccountError::RepositoryError(err)) => return Err(err.into()),
+    }
+
+    for stream in streams.iter() {
+        match web_egress_client.stop_stream(&stream.sid, &user_id).await {
+            Ok(()) | Err(OutgoingStreamStoppingError::AlreadyStopped) => (),
+            Err(error) => error!(?error, "Unable to stop outgoing stream of deleted account"),
+        }
+
+        if let Err(error) = stream_service_factory
+            .notify_stream_deleted(&stream.sid, &user_id)
+            .await
+        {
+            error!(?error, "Unable to notify about stream of deleted account");
+        }
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,105 @@
+use crate::data_structures::{StreamRef, TrackId, UserId, DEFAULT_TRACKS_PER_REQUEST};
+use crate::http_server::response::Response;
+use crate::services::{TrackSharingError, TrackSharingService};
+use crate::storage::db::repositories::TrackRow;
+use actix_web::web::{Data, Path, Query};
+use actix_web::HttpResponse;
+use serde::Deserialize;
+use serde_json::json;
+
+fn track_sharing_error_response(error: TrackSharingError) -> Response {
+    match error {
+        TrackSharingError::StreamNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "STREAM_NOT_FOUND" })))
+        }
+        TrackSharingError::TrackNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "TRACK_NOT_FOUND" })))
+        }
+        TrackSharingError::TrackNotShared => {
+            Ok(HttpResponse::Forbidden().json(json!({ "error": "TRACK_NOT_SHARED" })))
+        }
+        TrackSharingError::AlreadyInLibrary => {
+            Ok(HttpResponse::Conflict().json(json!({ "error": "ALREADY_IN_LIBRARY" })))
+        }
+        TrackSharingError::DatabaseError(err) => Err(err.into()),
+        TrackSharingError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+fn shared_track_json(track: &TrackRow) -> serde_json::Value {
+    json!({
+        "album": track.album,
+        "artist": track.artist,
+        "date": track.date,
+        "duration": track.duration,
+        "filename": track.filename,
+        "genre": track.genre,
+        "tid": track.tid,
+        "title": track.title,
+        "track_number": track.track_number
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetSharedTracksQuery {
+    stream_id: StreamRef,
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    limit: Option<i64>,
+}
+
+pub(crate) async fn get_shared_tracks(
+    query: Query<GetSharedTracksQuery>,
+    track_sharing_service: Data<TrackSharingService>,
+) -> Response {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TRACKS_PER_REQUEST)
+        .min(DEFAULT_TRACKS_PER_REQUEST);
+
+    match track_sharing_service
+        .get_shared_tracks(&query.stream_id, query.offset.max(0), limit)
+        .await
+    {
+        Ok(shared_tracks) => Ok(HttpResponse::Ok().json(json!({
+            "items": shared_tracks
+                .tracks
+                .iter()
+                .map(|row| shared_track_json(&row.track))
+                .collect::<Vec<_>>(),
+            "totalCount": shared_tracks.total_count,
+        }))),
+        Err(error) => track_sharing_error_response(error),
+    }
+}
+
+pub(crate) async fn copy_shared_track(
+    user_id: UserId,
+    path: Path<TrackId>,
+    track_sharing_service: Data<TrackSharingService>,
+) -> Response {
+    match track_sharing_service
+        .copy_track(&user_id, &path.into_inner())
+        .await
+    {
+        Ok(copy) => Ok(HttpResponse::Created().json(json!({
+            "album": copy.track.album,
+            "artist": copy.track.artist,
+            "buy": copy.track.buy,
+            "can_be_shared": copy.track.can_be_shared,
+            "color": copy.track.color,
+            "copy_of": copy.track.copy_of,
+            "cue": copy.track.cue,
+            "date": copy.track.date,
+            "duration": copy.track.duration,
+            "filename": copy.track.filename,
+            "genre": copy.track.genre,
+            "is_new": copy.track.is_new,
+            "tid": copy.track.tid,
+            "title": copy.track.title,
+            "track_number": copy.track.track_number
+        }))),
+        Err(error) => track_sharing_error_response(error),
+    }
+}
@@ -1,4 +1,4 @@
-use crate::data_structures::{OrderId, StreamId, UserId};
+use crate::data_structures::{OrderId, StreamRef, UserId};
 use crate::http_server::response::Response;
 use crate::services::StreamServiceFactory;
 use actix_web::{web, HttpResponse};
@@ -7,7 +7,7 @@ use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub(crate) struct PlayPathParams {
-    pub(crate) stream_id: StreamId,
+    pub(crate) stream_id: StreamRef,
 }
 
 pub(crate) async fn play(
@@ -26,7 +26,7 @@ pub(crate) async fn play(
 
 #[derive(Deserialize)]
 pub(crate) struct PausePathParams {
-    pub(crate) stream_id: StreamId,
+    pub(crate) stream_id: StreamRef,
 }
 
 pub(crate) async fn pause(
@@ -44,7 +44,7 @@ pub(crate) async fn pause(
 }
 
 pub(crate) async fn stop(
-    params: web::Path<StreamId>,
+    params: web::Path<StreamRef>,
     stream_service_factory: web::Data<StreamServiceFactory>,
     user_id: UserId,
 ) -> Response {
@@ -60,7 +60,7 @@ pub(crate) async fn stop(
 
 #[derive(Deserialize)]
 pub(crate) struct SeekPathParams {
-    pub(crate) stream_id: StreamId,
+    pub(crate) stream_id: StreamRef,
     pub(crate) position: i64,
 }
 
@@ -81,7 +81,7 @@ pub(crate) async fn seek(
 }
 
 pub(crate) async fn play_next(
-    params: web::Path<StreamId>,
+    params: web::Path<StreamRef>,
     stream_service_factory: web::Data<StreamServiceFactory>,
     user_id: UserId,
 ) -> Response {
@@ -96,7 +96,7 @@ pub(crate) async fn play_next(
 }
 
 pub(crate) async fn play_prev(
-    params: web::Path<StreamId>,
+    params: web::Path<StreamRef>,
     stream_service_factory: web::Data<StreamServiceFactory>,
     user_id: UserId,
 ) -> Response {
@@ -111,7 +111,7 @@ pub(crate) async fn play_prev(
 }
 
 pub(crate) async fn play_from(
-    params: web::Path<(StreamId, OrderId)>,
+    params: web::Path<(StreamRef, OrderId)>,
     stream_service_factory: web::Data<StreamServiceFactory>,
     user_id: UserId,
 ) -> Response {
@@ -1,13 +1,118 @@
-use crate::data_structures::{StreamId, UserId};
+use crate::data_structures::{StreamAccess, StreamRef, UserId};
 use crate::http_server::response::Response;
+use crate::services::auth::AuthTokenService;
+use crate::services::{ChannelError, ChannelService, ChannelUpdate, StreamServiceFactory};
 use crate::storage::db::repositories::streams;
 use crate::storage::db::repositories::streams::get_user_streams_by_user_id;
+use crate::storage::db::repositories::StreamRow;
+use crate::storage::fs::FileSystem;
+use crate::web_egress_controller_client::{OutgoingStreamStoppingError, WebEgressControllerClient};
 use crate::MySqlClient;
-use actix_web::web::{Data, Json, Path};
+use actix_web::web::{Bytes, Data, Json, Path};
 use actix_web::HttpResponse;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use serde_json::json;
+use std::time::Duration;
 use tracing::error;
 
+const LISTEN_URL_EXPIRES_AFTER: Duration = Duration::from_secs(24 * 3600);
+
+fn stream_json(row: &StreamRow) -> serde_json::Value {
+    serde_json::json!({
+        "sid": row.sid,
+        "name": row.name,
+        "permalink": row.permalink,
+        "info": row.info,
+        "status": row.status,
+        "access": row.access,
+        "category": row.category,
+        "hashtags": row.hashtags,
+        "cover": row.cover,
+        "coverBackground": row.cover_background,
+        "rtmpUrl": row.rtmp_url,
+        "rtmpStreamingKey": row.rtmp_streaming_key
+    })
+}
+
+fn channel_error_response(error: ChannelError) -> Response {
+    match error {
+        ChannelError::StreamNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "STREAM_NOT_FOUND" })))
+        }
+        ChannelError::Forbidden => Ok(HttpResponse::Forbidden().finish()),
+        ChannelError::InvalidName => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_NAME" })))
+        }
+        ChannelError::InvalidInfo => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_INFO" })))
+        }
+        ChannelError::InvalidHashtags => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_HASHTAGS" })))
+        }
+        ChannelError::InvalidPermalink => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_PERMALINK" })))
+        }
+        ChannelError::NonUniquePermalink => {
+            Ok(HttpResponse::Conflict().json(json!({ "error": "NON_UNIQUE_PERMALINK" })))
+        }
+        ChannelError::InvalidCategory => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_CATEGORY" })))
+        }
+        ChannelError::InvalidImage => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_IMAGE" })))
+        }
+        ChannelError::FileSystemError(err) => Err(err.into()),
+        ChannelError::DatabaseError(err) => Err(err.into()),
+        ChannelError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+fn channel_response(stream: &StreamRow) -> Response {
+    Ok(HttpResponse::Ok().json(json!({
+        "code": 1i32,
+        "message": "OK",
+        "data": stream_json(stream),
+    })))
+}
+
+/// Distinguishes a missing field (`None`) from an explicit `null` (`Some(None)`).
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ChannelBody {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    permalink: Option<String>,
+    #[serde(default)]
+    info: Option<String>,
+    #[serde(default)]
+    hashtags: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    category: Option<Option<i32>>,
+    #[serde(default)]
+    access: Option<StreamAccess>,
+}
+
+impl From<ChannelBody> for ChannelUpdate {
+    fn from(body: ChannelBody) -> Self {
+        ChannelUpdate {
+            name: body.name,
+            permalink: body.permalink,
+            info: body.info,
+            hashtags: body.hashtags,
+            category: body.category,
+            access: body.access,
+        }
+    }
+}
+
 pub(crate) async fn get_user_streams(user_id: UserId, mysql_client: Data<MySqlClient>) -> Response {
     let mut connection = mysql_client.connection().await?;
 
@@ -20,25 +125,7 @@ pub(crate) async fn get_user_streams(user_id: UserId, mysql_client: Data<MySqlCl
         }
     };
 
-    let streams_json: Vec<_> = stream_rows
-        .into_iter()
-        .map(|row| {
-            serde_json::json!({
-                "sid": row.sid,
-                "name": row.name,
-                "permalink": row.permalink,
-                "info": row.info,
-                "status": row.status,
-                "access": row.access,
-                "category": row.category,
-                "hashtags": row.hashtags,
-                "cover": row.cover,
-                "coverBackground": row.cover_background,
-                "rtmpUrl": row.rtmp_url,
-                "rtmpStreamingKey": row.rtmp_streaming_key
-            })
-        })
-        .collect();
+    let streams_json: Vec<_> = stream_rows.iter().map(stream_json).collect();
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "code": 1i32,
@@ -56,12 +143,17 @@ pub(crate) struct RtmpParameters {
 
 pub(crate) async fn update_rtmp_settings(
     user_id: UserId,
-    stream_id: Path<StreamId>,
+    stream_ref: Path<StreamRef>,
     rtmp_settings: Json<RtmpParameters>,
     mysql_client: Data<MySqlClient>,
 ) -> Response {
     let mut connection = mysql_client.connection().await?;
 
+    let stream_id = match streams::get_single_stream_by_ref(&mut connection, &stream_ref).await? {
+        Some(stream) => stream.sid,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+
     streams::update_channel_rtmp_settings(
         &mut connection,
         &stream_id,
@@ -73,3 +165,143 @@ pub(crate) async fn update_rtmp_settings(
 
     Ok(HttpResponse::Ok().finish())
 }
+
+pub(crate) async fn create_listen_url(
+    user_id: UserId,
+    stream_ref: Path<StreamRef>,
+    mysql_client: Data<MySqlClient>,
+    auth_token_service: Data<AuthTokenService>,
+) -> Response {
+    let mut connection = mysql_client.connection().await?;
+
+    let stream = match streams::get_single_stream_by_ref(&mut connection, &stream_ref).await? {
+        Some(stream) if stream.uid == user_id => stream,
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+
+    let token = auth_token_service.sign_listen_claims(&stream.sid, &LISTEN_URL_EXPIRES_AFTER);
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "code": 1i32,
+        "message": "OK",
+        "data": {
+            "url": format!("/v3/listen/{}?token={}", *stream.sid, token),
+            "token": token,
+            "expiresIn": LISTEN_URL_EXPIRES_AFTER.as_secs(),
+        },
+    })))
+}
+
+pub(crate) async fn get_stream(
+    user_id: UserId,
+    stream_ref: Path<StreamRef>,
+    channel_service: Data<ChannelService>,
+) -> Response {
+    match channel_service
+        .get_user_channel(&user_id, &stream_ref)
+        .await
+    {
+        Ok(stream) => channel_response(&stream),
+        Err(error) => channel_error_response(error),
+    }
+}
+
+pub(crate) async fn create_stream(
+    user_id: UserId,
+    body: Json<ChannelBody>,
+    channel_service: Data<ChannelService>,
+) -> Response {
+    match channel_service
+        .create_channel(&user_id, body.into_inner().into())
+        .await
+    {
+        Ok(stream) => channel_response(&stream),
+        Err(error) => channel_error_response(error),
+    }
+}
+
+pub(crate) async fn update_stream(
+    user_id: UserId,
+    stream_ref: Path<StreamRef>,
+    body: Json<ChannelBody>,
+    channel_service: Data<ChannelService>,
+) -> Response {
+    match channel_service
+        .update_channel(&user_id, &stream_ref, body.into_inner().into())
+        .await
+    {
+        Ok(stream) => channel_response(&stream),
+        Err(error) => channel_error_response(error),
+    }
+}
+
+pub(crate) async fn delete_stream<FS: FileSystem>(
+    user_id: UserId,
+    stream_ref: Path<StreamRef>,
+    channel_service: Data<ChannelService>,
+    stream_service_factory: Data<StreamServiceFactory>,
+    web_egress_client: Data<WebEgressControllerClient>,
+    file_system: Data<FS>,
+) -> Response {
+    let stream_id = match channel_service
+        .get_user_channel(&user_id, &stream_ref)
+        .await
+    {
+        Ok(stream) => stream.sid,
+        Err(error) => return channel_error_response(error),
+    };
+
+    // The outgoing stream has to be stopped while the channel still exists,
+    // otherwise the egress process would report back about an unknown channel.
+    match web_egress_client.stop_stream(&stream_id, &user_id).await {
+        Ok(()) | Err(OutgoingStreamStoppingError::AlreadyStopped) => (),
+        Err(error) => error!(?error, "Unable to stop outgoing stream of deleted channel"),
+    }
+
+    if let Err(error) = channel_service
+        .delete_channel(&user_id, &stream_ref, file_system.get_ref())
+        .await
+    {
+        return channel_error_response(error);
+    }
+
+    if let Err(error) = stream_service_factory
+        .notify_stream_deleted(&stream_id, &user_id)
+        .await
+    {
+        error!(?error, "Unable to notify about deleted channel");
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub(crate) async fn update_stream_cover<FS: FileSystem>(
+    user_id: UserId,
+    stream_ref: Path<StreamRef>,
+    body: Bytes,
+    channel_service: Data<ChannelService>,
+    file_system: Data<FS>,
+) -> Response {
+    match channel_service
+        .update_cover(&user_id, &stream_ref, body.to_vec(), file_system.get_ref())
+        .await
+    {
+        Ok(stream) => channel_response(&stream),
+        Err(error) => channel_error_response(error),
+    }
+}
+
+pub(crate) async fn delete_stream_cover<FS: FileSystem>(
+    user_id: UserId,
+    stream_ref: Path<StreamRef>,
+    channel_service: Data<ChannelService>,
+    file_system: Data<FS>,
+) -> Response {
+    match channel_service
+        .delete_cover(&user_id, &stream_ref, file_system.get_ref())
+        .await
+    {
+        Ok(()) => Ok(HttpResponse::Ok().finish()),
+        Err(error) => channel_error_response(error),
+    }
+}
@@ -0,0 +1,217 @@
+use crate::data_structures::{StreamRef, UserId};
+use crate::http_server::response::Response;
+use crate::services::{TrackImportError, TrackImportService};
+use crate::storage::db::repositories::TrackImportJobRow;
+use crate::storage::fs::FileSystem;
+use actix_web::web::{Data, Path, Payload, Query};
+use actix_web::HttpResponse;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tracing::error;
+
+const MAX_IMPORT_ARCHIVE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+const DEFAULT_ITEMS_PER_REQUEST: i64 = 50;
+
+pub(crate) fn job_json(row: &TrackImportJobRow) -> serde_json::Value {
+    json!({
+        "id": row.id,
+        "streamId": row.stream_id,
+        "source": row.source,
+        "status": row.status,
+        "totalFiles": row.total_files,
+        "processedFiles": row.processed_files,
+        "importedFiles": row.imported_files,
+        "skippedFiles": row.skipped_files,
+        "failedFiles": row.failed_files,
+        "createdAt": row.created_at,
+        "finishedAt": row.finished_at,
+    })
+}
+
+pub(crate) fn track_import_error_response(error: TrackImportError) -> Response {
+    match error {
+        TrackImportError::StreamNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "STREAM_NOT_FOUND" })))
+        }
+        TrackImportError::UserNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "USER_NOT_FOUND" })))
+        }
+        TrackImportError::JobNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "JOB_NOT_FOUND" })))
+        }
+        TrackImportError::InvalidArchive(_) => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_ARCHIVE" })))
+        }
+        TrackImportError::ArchiveTooLarge => {
+            Ok(HttpResponse::PayloadTooLarge().json(json!({ "error": "ARCHIVE_TOO_LARGE" })))
+        }
+        TrackImportError::DirectoryNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "DIRECTORY_NOT_FOUND" })))
+        }
+        TrackImportError::DirectoryImportDisabled => {
+            Ok(HttpResponse::Forbidden().json(json!({ "error": "DIRECTORY_IMPORT_DISABLED" })))
+        }
+        TrackImportError::FileSystemError(err) => Err(err.into()),
+        TrackImportError::DatabaseError(err) => Err(err.into()),
+        TrackImportError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StartTrackImportQuery {
+    #[serde(default)]
+    stream_id: Option<StreamRef>,
+}
+
+/// Accepts the ZIP archive in the request body and starts importing its audio files.
+pub(crate) async fn start_track_import<FS: FileSystem + Clone + 'static>(
+    user_id: UserId,
+    query: Query<StartTrackImportQuery>,
+    mut payload: Payload,
+    track_import_service: Data<TrackImportService>,
+    file_system: Data<FS>,
+) -> Response {
+    let archive_path = std::env::temp_dir().join(format!(
+        "track-import-{}.zip",
+        uuid::Uuid::new_v4().to_simple()
+    ));
+
+    let mut archive_file = tokio::fs::File::create(&archive_path).await?;
+    let mut archive_size = 0u64;
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(error) => {
+                error!(?error, "Unable to receive import archive");
+                let _ = tokio::fs::remove_file(&archive_path).await;
+
+                return Ok(HttpResponse::BadRequest().finish());
+            }
+        };
+
+        archive_size += chunk.len() as u64;
+
+        if archive_size > MAX_IMPORT_ARCHIVE_SIZE {
+            let _ = tokio::fs::remove_file(&archive_path).await;
+
+            return Ok(
+                HttpResponse::PayloadTooLarge().json(json!({ "error": "ARCHIVE_TOO_LARGE" }))
+            );
+        }
+
+        if let Err(error) = archive_file.write_all(&chunk).await {
+            let _ = tokio::fs::remove_file(&archive_path).await;
+
+            return Err(error.into());
+        }
+    }
+
+    archive_file.flush().await?;
+    drop(archive_file);
+
+    match track_import_service
+        .start_zip_import(
+            &user_id,
+            &query.into_inner().stream_id,
+            archive_path,
+            file_system.get_ref(),
+        )
+        .await
+    {
+        Ok(job) => Ok(HttpResponse::Accepted().json(job_json(&job))),
+        Err(error) => track_import_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetTrackImportsQuery {
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    limit: Option<i64>,
+}
+
+pub(crate) async fn get_track_imports(
+    user_id: UserId,
+    query: Query<GetTrackImportsQuery>,
+    track_import_service: Data<TrackImportService>,
+) -> Response {
+    let offset = query.offset;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ITEMS_PER_REQUEST)
+        .min(DEFAULT_ITEMS_PER_REQUEST);
+
+    let (total_count, jobs) = match track_import_service.get_jobs(&user_id, offset, limit).await {
+        Ok(result) => result,
+        Err(error) => return track_import_error_response(error),
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "totalCount": total_count,
+        "items": jobs.iter().map(job_json).collect::<Vec<_>>(),
+        "paginationData": {
+            "limit": limit,
+            "offset": offset
+        }
+    })))
+}
+
+pub(crate) async fn get_track_import(
+    user_id: UserId,
+    path: Path<i32>,
+    track_import_service: Data<TrackImportService>,
+) -> Response {
+    match track_import_service
+        .get_job(&user_id, path.into_inner())
+        .await
+    {
+        Ok(job) => Ok(HttpResponse::Ok().json(job_json(&job))),
+        Err(error) => track_import_error_response(error),
+    }
+}
+
+pub(crate) async fn get_track_import_errors(
+    user_id: UserId,
+    path: Path<i32>,
+    query: Query<GetTrackImportsQuery>,
+    track_import_service: Data<TrackImportService>,
+) -> Response {
+    let offset = query.offset;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ITEMS_PER_REQUEST)
+        .min(DEFAULT_ITEMS_PER_REQUEST);
+
+    let (total_count, errors) = match track_import_service
+        .get_job_errors(&user_id, path.into_inner(), offset, limit)
+        .await
+    {
+        Ok(result) => result,
+        Err(error) => return track_import_error_response(error),
+    };
+
+    let items: Vec<_> = errors
+        .into_iter()
+        .map(|row| {
+            json!({
+                "fileName": row.file_name,
+                "error": row.error,
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "totalCount": total_count,
+        "items": items,
+        "paginationData": {
+            "limit": limit,
+            "offset": offset
+        }
+    })))
+}
@@ -0,0 +1,112 @@
+use crate::data_structures::{TrackId, UserId, DEFAULT_TRACKS_PER_REQUEST};
+use crate::http_server::response::Response;
+use crate::services::{TrackTrashError, TrackTrashService};
+use crate::storage::fs::FileSystem;
+use actix_web::web::{Data, Json, Query};
+use actix_web::HttpResponse;
+use serde::Deserialize;
+use serde_json::json;
+
+fn track_trash_error_response(error: TrackTrashError) -> Response {
+    match error {
+        TrackTrashError::NoTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "NO_TRACKS" })))
+        }
+        TrackTrashError::TooManyTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "TOO_MANY_TRACKS" })))
+        }
+        TrackTrashError::DatabaseError(err) => Err(err.into()),
+        TrackTrashError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetTrashedTracksQuery {
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    limit: Option<i64>,
+}
+
+pub(crate) async fn get_trashed_tracks(
+    user_id: UserId,
+    query: Query<GetTrashedTracksQuery>,
+    track_trash_service: Data<TrackTrashService>,
+) -> Response {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TRACKS_PER_REQUEST)
+        .min(DEFAULT_TRACKS_PER_REQUEST);
+
+    match track_trash_service
+        .get_trashed_tracks(&user_id, query.offset.max(0), limit)
+        .await
+    {
+        Ok(trashed_tracks) => Ok(HttpResponse::Ok().json(json!({
+            "items": trashed_tracks
+                .tracks
+                .iter()
+                .map(|row| json!({
+                    "album": row.track.album,
+                    "artist": row.track.artist,
+                    "color": row.track.color,
+                    "deleted": row.track.deleted,
+                    "duration": row.track.duration,
+                    "filename": row.track.filename,
+                    "genre": row.track.genre,
+                    "tid": row.track.tid,
+                    "title": row.track.title
+                }))
+                .collect::<Vec<_>>(),
+            "totalCount": trashed_tracks.total_count,
+        }))),
+        Err(error) => track_trash_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RestoreTracksBody {
+    track_ids: Vec<TrackId>,
+}
+
+pub(crate) async fn restore_tracks(
+    user_id: UserId,
+    body: Json<RestoreTracksBody>,
+    track_trash_service: Data<TrackTrashService>,
+) -> Response {
+    match track_trash_service
+        .restore_tracks(&user_id, &body.track_ids)
+        .await
+    {
+        Ok(restored_track_ids) => Ok(HttpResponse::Ok().json(json!({
+            "restoredTrackIds": restored_track_ids,
+        }))),
+        Err(error) => track_trash_error_response(error),
+    }
+}
+
+/// The whole trash is purged if `trackIds` is missing.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PurgeTracksBody {
+    #[serde(default)]
+    track_ids: Option<Vec<TrackId>>,
+}
+
+pub(crate) async fn purge_tracks<FS: FileSystem>(
+    user_id: UserId,
+    body: Json<PurgeTracksBody>,
+    track_trash_service: Data<TrackTrashService>,
+    file_system: Data<FS>,
+) -> Response {
+    match track_trash_service
+        .purge_tracks(&user_id, body.track_ids.as_deref(), file_system.get_ref())
+        .await
+    {
+        Ok(purged_tracks) => Ok(HttpResponse::Ok().json(json!({
+            "purgedCount": purged_tracks,
+        }))),
+        Err(error) => track_trash_error_response(error),
+    }
+}
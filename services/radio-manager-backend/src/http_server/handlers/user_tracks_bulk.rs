@@ -0,0 +1,132 @@
+use crate::data_structures::{StreamRef, TrackId, UserId};
+use crate::http_server::response::{invalid_track_query_response, Response};
+use crate::services::{
+    TrackBulkError, TrackBulkItemStatus, TrackBulkOperation, TrackBulkService, TrackSelection,
+};
+use crate::storage::db::track_query::TrackQuery;
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use serde::Deserialize;
+use serde_json::json;
+
+pub(crate) fn track_bulk_error_response(error: TrackBulkError) -> Response {
+    match error {
+        TrackBulkError::NoTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "NO_TRACKS" })))
+        }
+        TrackBulkError::TooManyTracks => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "TOO_MANY_TRACKS" })))
+        }
+        TrackBulkError::StreamNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "STREAM_NOT_FOUND" })))
+        }
+        TrackBulkError::ColorNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "COLOR_NOT_FOUND" })))
+        }
+        TrackBulkError::StreamServiceError(err) => Err(err.into()),
+        TrackBulkError::DatabaseError(err) => Err(err.into()),
+        TrackBulkError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+fn item_status(status: TrackBulkItemStatus) -> &'static str {
+    match status {
+        TrackBulkItemStatus::Done => "DONE",
+        TrackBulkItemStatus::TrackNotFound => "TRACK_NOT_FOUND",
+        TrackBulkItemStatus::NotInStream => "NOT_IN_STREAM",
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub(crate) enum BulkOperationBody {
+    Delete,
+    AddToStream {
+        stream_id: StreamRef,
+        #[serde(default)]
+        position: Option<usize>,
+    },
+    RemoveFromStream {
+        stream_id: StreamRef,
+    },
+    Recolor {
+        color_id: i32,
+    },
+    SetShareable {
+        shareable: bool,
+    },
+}
+
+impl From<BulkOperationBody> for TrackBulkOperation {
+    fn from(body: BulkOperationBody) -> Self {
+        match body {
+            BulkOperationBody::Delete => TrackBulkOperation::Delete,
+            BulkOperationBody::AddToStream {
+                stream_id,
+                position,
+            } => TrackBulkOperation::AddToStream {
+                stream_ref: stream_id,
+                position,
+            },
+            BulkOperationBody::RemoveFromStream { stream_id } => {
+                TrackBulkOperation::RemoveFromStream {
+                    stream_ref: stream_id,
+                }
+            }
+            BulkOperationBody::Recolor { color_id } => TrackBulkOperation::Recolor { color_id },
+            BulkOperationBody::SetShareable { shareable } => TrackBulkOperation::SetShareable {
+                can_be_shared: shareable,
+            },
+        }
+    }
+}
+
+/// The tracks are selected either by `trackIds` or by the `filter` search query.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BulkTracksBody {
+    #[serde(default)]
+    track_ids: Option<Vec<TrackId>>,
+    #[serde(default)]
+    filter: Option<String>,
+    operation: BulkOperationBody,
+}
+
+pub(crate) async fn run_bulk_operation(
+    user_id: UserId,
+    body: Json<BulkTracksBody>,
+    track_bulk_service: Data<TrackBulkService>,
+) -> Response {
+    let body = body.into_inner();
+
+    let selection = match (body.track_ids, body.filter) {
+        (Some(track_ids), None) => TrackSelection::TrackIds(track_ids),
+        (None, Some(filter)) => match TrackQuery::parse(&filter) {
+            Ok(query) => TrackSelection::Query(query),
+            Err(error) => return Ok(invalid_track_query_response(&error)),
+        },
+        _ => {
+            return Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_SELECTION" })));
+        }
+    };
+
+    match track_bulk_service
+        .run_operation(&user_id, &selection, &body.operation.into())
+        .await
+    {
+        Ok(items) => Ok(HttpResponse::Ok().json(json!({
+            "items": items
+                .iter()
+                .map(|item| json!({
+                    "trackId": item.track_id,
+                    "status": item_status(item.status),
+                }))
+                .collect::<Vec<_>>(),
+        }))),
+        Err(error) => track_bulk_error_response(error),
+    }
+}
@@ -0,0 +1,85 @@
+use crate::data_structures::UserId;
+use crate::http_server::response::Response;
+use crate::services::auth::{AuthService, TwoFactorError};
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use serde::Deserialize;
+use serde_json::json;
+
+fn two_factor_error_response(error: TwoFactorError) -> Response {
+    match error {
+        TwoFactorError::UserNotFound => {
+            Ok(HttpResponse::NotFound().json(json!({ "error": "USER_NOT_FOUND" })))
+        }
+        TwoFactorError::AlreadyEnabled => {
+            Ok(HttpResponse::Conflict().json(json!({ "error": "TWO_FACTOR_ALREADY_ENABLED" })))
+        }
+        TwoFactorError::NotEnabled => {
+            Ok(HttpResponse::Conflict().json(json!({ "error": "TWO_FACTOR_NOT_ENABLED" })))
+        }
+        TwoFactorError::EnrollmentNotStarted => {
+            Ok(HttpResponse::Conflict()
+                .json(json!({ "error": "TWO_FACTOR_ENROLLMENT_NOT_STARTED" })))
+        }
+        TwoFactorError::InvalidCode => {
+            Ok(HttpResponse::BadRequest().json(json!({ "error": "INVALID_CODE" })))
+        }
+        TwoFactorError::DatabaseError(err) => Err(err.into()),
+        TwoFactorError::RepositoryError(err) => Err(err.into()),
+    }
+}
+
+pub(crate) async fn start_enrollment(user_id: UserId, auth_service: Data<AuthService>) -> Response {
+    match auth_service.start_two_factor_enrollment(&user_id).await {
+        Ok(enrollment) => Ok(HttpResponse::Ok().json(enrollment)),
+        Err(error) => two_factor_error_response(error),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TwoFactorCodeBody {
+    code: String,
+}
+
+pub(crate) async fn confirm_enrollment(
+    user_id: UserId,
+    body: Json<TwoFactorCodeBody>,
+    auth_service: Data<AuthService>,
+) -> Response {
+    match auth_service
+        .confirm_two_factor_enrollment(&user_id, &body.code)
+        .await
+    {
+        Ok(recovery_codes) => Ok(HttpResponse::Ok().json(json!({
+            "recoveryCodes": recovery_codes,
+        }))),
+        Err(error) => two_factor_error_response(error),
+    }
+}
+
+pub(crate) async fn regenerate_recovery_codes(
+    user_id: UserId,
+    body: Json<TwoFactorCodeBody>,
+    auth_service: Data<AuthService>,
+) -> Response {
+    match auth_service
+        .regenerate_recovery_codes(&user_id, &body.code)
+        .await
+    {
+        Ok(recovery_codes) => Ok(HttpResponse::Ok().json(json!({
+            "recoveryCodes": recovery_codes,
+        }))),
+        Err(error) => two_factor_error_response(error),
+    }
+}
+
+pub(crate) async fn disable(
+    user_id: UserId,
+    body: Json<TwoFactorCodeBody>,
+    auth_service: Data<AuthService>,
+) -> Response {
+    match auth_service.disable_two_factor(&user_id, &body.code).await {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(error) => two_factor_error_response(error),
+    }
+}
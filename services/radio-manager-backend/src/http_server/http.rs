@@ -1,10 +1,18 @@
 use crate::http_server::handlers::{
-    forward_auth, internal_egress_process, internal_radio_streamer, public_auth_v0,
-    public_schedule, public_streams, user_audio_stream, user_audio_tracks, user_audio_tracks_v2,
-    user_outgoing_stream, user_stream_control, user_stream_destinations, user_streams,
+    forward_auth, internal_admin_track_imports, internal_egress_process, internal_radio_streamer,
+    public_artworks, public_auth_v0, public_auth_v1, public_schedule, public_streams,
+    user_airplay_reports, user_audio_stream, user_audio_tracks, user_audio_tracks_v2, user_colors,
+    user_library_export, user_outgoing_stream, user_play_history, user_profile, user_shared_tracks,
+    user_stream_control, user_stream_destinations, user_streams, user_track_imports,
+    user_track_trash, user_tracks_bulk, user_two_factor,
 };
 use crate::pubsub_client::PubsubClient;
 use crate::services::auth::{AuthService, AuthTokenService};
+use crate::services::{
+    AirplayReportService, ArtworkService, ChannelService, ColorLabelService, LibraryExportService,
+    PlayHistoryService, ProfileService, TrackBulkService, TrackImportService, TrackMetadataService,
+    TrackSharingService, TrackTrashService,
+};
 use crate::storage::fs::FileSystem;
 use crate::web_egress_controller_client::WebEgressControllerClient;
 use crate::{Config, MySqlClient, StreamServiceFactory};
@@ -13,6 +21,28 @@ use actix_web::web::Data;
 use actix_web::{web, App, HttpServer};
 use std::io::Result;
 
+const MAX_AVATAR_SIZE: usize = 5 * 1024 * 1024;
+
+const MAX_COVER_SIZE: usize = 10 * 1024 * 1024;
+
+/// Services shared with the request handlers.
+#[derive(Clone)]
+pub(crate) struct Services {
+    pub(crate) auth_service: AuthService,
+    pub(crate) profile_service: ProfileService,
+    pub(crate) channel_service: ChannelService,
+    pub(crate) track_import_service: TrackImportService,
+    pub(crate) library_export_service: LibraryExportService,
+    pub(crate) track_metadata_service: TrackMetadataService,
+    pub(crate) artwork_service: ArtworkService,
+    pub(crate) color_label_service: ColorLabelService,
+    pub(crate) track_bulk_service: TrackBulkService,
+    pub(crate) track_sharing_service: TrackSharingService,
+    pub(crate) track_trash_service: TrackTrashService,
+    pub(crate) play_history_service: PlayHistoryService,
+    pub(crate) airplay_report_service: AirplayReportService,
+}
+
 pub(crate) fn run_server<FS: FileSystem + Send + Sync + Clone + 'static>(
     bind_address: &str,
     mysql_client: MySqlClient,
@@ -22,7 +52,7 @@ pub(crate) fn run_server<FS: FileSystem + Send + Sync + Clone + 'static>(
     pubsub_client: PubsubClient,
     auth_token_service: AuthTokenService,
     web_egress_controller_client: WebEgressControllerClient,
-    auth_service: AuthService,
+    services: Services,
 ) -> Result<Server> {
     let mysql_client = mysql_client.clone();
 
@@ -36,21 +66,50 @@ pub(crate) fn run_server<FS: FileSystem + Send + Sync + Clone + 'static>(
             .app_data(Data::new(stream_service_factory.clone()))
             .app_data(Data::new(pubsub_client.clone()))
             .app_data(Data::new(auth_token_service.clone()))
-            .app_data(Data::new(auth_service.clone()))
+            .app_data(Data::new(services.auth_service.clone()))
+            .app_data(Data::new(services.profile_service.clone()))
+            .app_data(Data::new(services.channel_service.clone()))
+            .app_data(Data::new(services.track_import_service.clone()))
+            .app_data(Data::new(services.library_export_service.clone()))
+            .app_data(Data::new(services.track_metadata_service.clone()))
+            .app_data(Data::new(services.artwork_service.clone()))
+            .app_data(Data::new(services.color_label_service.clone()))
+            .app_data(Data::new(services.track_bulk_service.clone()))
+            .app_data(Data::new(services.track_sharing_service.clone()))
+            .app_data(Data::new(services.track_trash_service.clone()))
+            .app_data(Data::new(services.play_history_service.clone()))
+            .app_data(Data::new(services.airplay_report_service.clone()))
             .app_data(Data::new(web_egress_controller_client.clone()))
             .service(
                 web::scope("/pub")
                     .service(
                         web::scope("/v0/auth")
                             .service(public_auth_v0::login)
+                            .service(public_auth_v0::login_two_factor)
                             .service(public_auth_v0::logout)
                             .service(public_auth_v0::signup)
                             .service(public_auth_v0::reset_password)
                             .service(public_auth_v0::request_password_reset)
                             .service(public_auth_v0::confirm_email),
                     )
+                    .service(
+                        web::scope("/v1/auth")
+                            .service(public_auth_v1::login)
+                            .service(public_auth_v1::login_two_factor)
+                            .service(public_auth_v1::refresh)
+                            .service(public_auth_v1::logout),
+                    )
+                    .route(
+                        "/v0/categories",
+                        web::get().to(public_streams::get_categories),
+                    )
+                    .route(
+                        "/v0/artworks/{artwork_hash}",
+                        web::get().to(public_artworks::get_artwork::<FS>),
+                    )
                     .service(
                         web::scope("/v0/streams")
+                            .route("/", web::get().to(public_streams::get_public_streams))
                             .service(
                                 web::resource("/{stream_id}/outgoing-stream")
                                     .route(web::get().to(user_outgoing_stream::get_outgoing_stream))
@@ -64,12 +123,16 @@ pub(crate) fn run_server<FS: FileSystem + Send + Sync + Clone + 'static>(
                             )
                             .route(
                                 "/{stream_id}/now-playing",
-                                web::get().to(public_schedule::get_now_playing),
+                                web::get().to(public_schedule::get_now_playing::<FS>),
                             )
                             .route(
                                 "/{stream_id}/current-track",
                                 web::get().to(public_schedule::get_current_track),
                             )
+                            .route(
+                                "/{stream_id}/schedule",
+                                web::get().to(public_schedule::get_schedule),
+                            )
                             .route(
                                 "/{stream_id}/info",
                                 web::get().to(public_streams::get_stream_info),
@@ -90,28 +153,104 @@ pub(crate) fn run_server<FS: FileSystem + Send + Sync + Clone + 'static>(
                         "/unused",
                         web::get().to(user_audio_tracks_v2::get_unused_user_audio_tracks),
                     )
+                    .route(
+                        "/new",
+                        web::get().to(user_audio_tracks_v2::get_new_user_audio_tracks),
+                    )
+                    .route(
+                        "/seen",
+                        web::post().to(user_audio_tracks_v2::mark_user_audio_tracks_seen),
+                    )
                     .route(
                         "/channel/{channel_id}",
                         web::get().to(user_audio_tracks_v2::get_channel_audio_tracks),
                     ),
             )
+            .service(
+                web::scope("/v0/tracks/imports")
+                    .route("/", web::get().to(user_track_imports::get_track_imports))
+                    .route(
+                        "/",
+                        web::post().to(user_track_imports::start_track_import::<FS>),
+                    )
+                    .route(
+                        "/{job_id}",
+                        web::get().to(user_track_imports::get_track_import),
+                    )
+                    .route(
+                        "/{job_id}/errors",
+                        web::get().to(user_track_imports::get_track_import_errors),
+                    ),
+            )
             .service(
                 web::scope("/v0/tracks")
                     .route("/", web::get().to(user_audio_tracks::get_user_audio_tracks))
                     .route("/", web::post().to(user_audio_tracks::upload_audio_track))
+                    .route(
+                        "/",
+                        web::patch().to(user_audio_tracks::update_audio_tracks::<FS>),
+                    )
+                    .route(
+                        "/export",
+                        web::post().to(user_library_export::export_audio_tracks::<FS>),
+                    )
+                    .route("/color", web::put().to(user_colors::set_tracks_color))
+                    .route(
+                        "/bulk",
+                        web::post().to(user_tracks_bulk::run_bulk_operation),
+                    )
+                    .route(
+                        "/trash",
+                        web::get().to(user_track_trash::get_trashed_tracks),
+                    )
+                    .route(
+                        "/trash/restore",
+                        web::post().to(user_track_trash::restore_tracks),
+                    )
+                    .route(
+                        "/trash/purge",
+                        web::post().to(user_track_trash::purge_tracks::<FS>),
+                    )
+                    .route(
+                        "/most-played",
+                        web::get().to(user_play_history::get_most_played_tracks),
+                    )
                     .route(
                         "/{track_id}",
-                        web::delete().to(user_audio_tracks::delete_audio_track::<FS>),
+                        web::patch().to(user_audio_tracks::update_audio_track::<FS>),
+                    )
+                    .route(
+                        "/{track_id}",
+                        web::delete().to(user_audio_tracks::delete_audio_track),
                     )
                     .route(
                         "/{track_id}/transcode",
-                        web::get().to(user_audio_stream::transcode_audio_track),
+                        web::get().to(user_audio_stream::transcode_audio_track::<FS>),
                     )
                     .route(
                         "/{track_id}/download",
                         web::get().to(user_audio_tracks::download_audio_track::<FS>),
+                    )
+                    .route(
+                        "/{track_id}/artwork",
+                        web::get().to(user_audio_tracks::get_audio_track_artwork::<FS>),
                     ),
             )
+            .service(
+                web::scope("/v0/shared-tracks")
+                    .route("/", web::get().to(user_shared_tracks::get_shared_tracks))
+                    .route(
+                        "/{track_id}/copy",
+                        web::post().to(user_shared_tracks::copy_shared_track),
+                    ),
+            )
+            .service(
+                web::scope("/v0/colors")
+                    .route("/", web::get().to(user_colors::get_colors))
+                    .route("/", web::post().to(user_colors::create_color))
+                    .route("/{color_id}", web::patch().to(user_colors::update_color))
+                    .route("/{color_id}", web::delete().to(user_colors::delete_color)),
+            )
             .service(web::scope("/v0/streams/{stream_id}/tracks").route(
                 "/",
                 web::get().to(user_audio_tracks::get_user_stream_audio_tracks),
@@ -141,22 +280,63 @@ pub(crate) fn run_server<FS: FileSystem + Send + Sync + Clone + 'static>(
             .service(
                 web::scope("/v0/streams")
                     .route("/", web::get().to(user_streams::get_user_streams))
+                    .route("/", web::post().to(user_streams::create_stream))
+                    .route("/{stream_id}", web::get().to(user_streams::get_stream))
+                    .route("/{stream_id}", web::patch().to(user_streams::update_stream))
+                    .route(
+                        "/{stream_id}",
+                        web::delete().to(user_streams::delete_stream::<FS>),
+                    )
+                    .service(
+                        web::resource("/{stream_id}/cover")
+                            .app_data(web::PayloadConfig::new(MAX_COVER_SIZE))
+                            .route(web::put().to(user_streams::update_stream_cover::<FS>))
+                            .route(web::delete().to(user_streams::delete_stream_cover::<FS>)),
+                    )
                     .route(
                         "/{stream_id}/rtmp-settings",
                         web::post().to(user_streams::update_rtmp_settings),
+                    )
+                    .route(
+                        "/{stream_id}/listen-url",
+                        web::post().to(user_streams::create_listen_url),
+                    )
+                    .route(
+                        "/{stream_id}/export",
+                        web::get().to(user_library_export::export_stream_audio_tracks::<FS>),
+                    )
+                    .route(
+                        "/{stream_id}/play-history",
+                        web::get().to(user_play_history::get_stream_play_history),
+                    )
+                    .route(
+                        "/{stream_id}/airplay-report",
+                        web::get().to(user_airplay_reports::get_airplay_report),
                     ),
             )
             .service(
                 web::scope("/internal/radio-streamer")
                     .route(
                         "/v0/streams/{stream_id}/playing-at/{unix_time}",
-                        web::get().to(internal_radio_streamer::get_playing_at),
+                        web::get().to(internal_radio_streamer::get_playing_at::<FS>),
                     )
                     .route(
                         "/v0/streams/{stream_id}/skip-track",
                         web::post().to(internal_radio_streamer::skip_track),
+                    )
+                    .route(
+                        "/v0/streams/{stream_id}/track-started",
+                        web::post().to(internal_radio_streamer::track_started),
+                    )
+                    .route(
+                        "/v0/streams/{stream_id}/verify-listen-token",
+                        web::get().to(internal_radio_streamer::verify_listen_token),
                     ),
             )
+            .service(web::scope("/internal/admin").route(
+                "/v0/track-imports/directory",
+                web::post().to(internal_admin_track_imports::start_directory_import::<FS>),
+            ))
             .service(
                 web::scope("/internal/web-egress-process")
                     .route(
@@ -176,6 +356,35 @@ pub(crate) fn run_server<FS: FileSystem + Send + Sync + Clone + 'static>(
                         web::post().to(internal_egress_process::handle_stream_error),
                     ),
             )
+            .service(
+                web::scope("/v0/me")
+                    .route("", web::get().to(user_profile::get_profile))
+                    .route("", web::patch().to(user_profile::update_profile))
+                    .route("", web::delete().to(user_profile::delete_account::<FS>))
+                    .route(
+                        "/email/confirm",
+                        web::post().to(user_profile::confirm_email_change),
+                    )
+                    .service(
+                        web::resource("/avatar")
+                            .app_data(web::PayloadConfig::new(MAX_AVATAR_SIZE))
+                            .route(web::put().to(user_profile::update_avatar::<FS>))
+                            .route(web::delete().to(user_profile::delete_avatar::<FS>)),
+                    ),
+            )
+            .service(
+                web::scope("/v0/two-factor")
+                    .route("/enroll", web::post().to(user_two_factor::start_enrollment))
+                    .route(
+                        "/confirm",
+                        web::post().to(user_two_factor::confirm_enrollment),
+                    )
+                    .route(
+                        "/recovery-codes",
+                        web::post().to(user_two_factor::regenerate_recovery_codes),
+                    )
+                    .route("/disable", web::post().to(user_two_factor::disable)),
+            )
             .service(
                 web::scope("/v0/destinations")
                     .route(
@@ -1,6 +1,9 @@
+mod client_ip;
 mod constants;
+mod cursor;
+mod download;
 mod handlers;
 mod http;
 mod response;
 
-pub(crate) use http::run_server;
+pub(crate) use http::{run_server, Services};
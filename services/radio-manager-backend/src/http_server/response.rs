@@ -1,11 +1,13 @@
 use crate::pubsub_client::PubsubClientError;
 use crate::services::StreamServiceError;
 use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::track_query::TrackQueryError;
 use crate::web_egress_controller_client::WebEgressControllerError;
 use actix_http::StatusCode;
 use actix_web::{HttpResponse, ResponseError};
 use serde_json::json;
 use std::fmt::Debug;
+use std::time::Duration;
 use tracing::error;
 
 pub(crate) type Response = Result<HttpResponse, Error>;
@@ -43,3 +45,21 @@ impl ResponseError for Error {
         }
     }
 }
+
+pub(crate) fn too_many_attempts_response(retry_after: &Duration) -> HttpResponse {
+    let retry_after_secs = retry_after.as_secs_f64().ceil() as u64;
+
+    HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", retry_after_secs.to_string()))
+        .json(json!({
+            "error": "TOO_MANY_ATTEMPTS",
+            "retryAfter": retry_after_secs,
+        }))
+}
+
+pub(crate) fn invalid_track_query_response(error: &TrackQueryError) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "error": "INVALID_QUERY",
+        "message": error.to_string(),
+    }))
+}
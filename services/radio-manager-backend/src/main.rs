@@ -10,35 +10,201 @@ mod system;
 mod utils;
 mod web_egress_controller_client;
 
-use crate::config::Config;
+use crate::config::{Config, FileSystemBackend};
+use crate::data_structures::{StreamRef, UserId};
 use crate::mysql_client::MySqlClient;
 use crate::pubsub_client::PubsubClient;
-use crate::services::auth::{AuthService, AuthTokenService};
-use crate::services::StreamServiceFactory;
+use crate::services::auth::{AuthService, AuthTokenService, LoginThrottle, SystemClock};
+use crate::services::email_service::EmailService;
+use crate::services::{
+    AirplayReportService, ArtworkService, ChannelService, ColorLabelService, FileGcService,
+    LibraryExportService, PlayHistoryService, ProfileService, ReportPeriod, StreamServiceFactory,
+    TrackBulkService, TrackImportService, TrackMetadataService, TrackSharingService,
+    TrackTrashService,
+};
 use crate::storage::fs::local::LocalFileSystem;
+use crate::storage::fs::s3::S3FileSystem;
+use crate::storage::fs::FileSystem;
 use crate::web_egress_controller_client::WebEgressControllerClient;
+use chrono::NaiveDate;
 use dotenv::dotenv;
-use http_server::run_server;
+use http_server::{run_server, Services};
 use std::io::Result;
+use std::sync::Arc;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[actix_rt::main]
-async fn main() -> Result<()> {
-    dotenv().ok();
+enum Command {
+    Serve,
+    /// `gc-files [--dry-run]`: runs the file garbage collector once and prints the report.
+    CollectGarbageFiles {
+        dry_run: bool,
+    },
+    /// `import-directory <user-id> <path> [--stream <stream>]`: imports the audio files
+    /// from the server-side directory into the user's library.
+    ImportDirectory {
+        user_id: UserId,
+        path: std::path::PathBuf,
+        stream_ref: Option<StreamRef>,
+    },
+    /// `airplay-report <stream> <from> <to> [--format csv|json]`: prints the airplay report of
+    /// the stream for the days from `from` to `to` inclusive, given as `YYYY-MM-DD`.
+    AirplayReport {
+        stream_ref: StreamRef,
+        from: NaiveDate,
+        to: NaiveDate,
+        json: bool,
+    },
+}
 
-    let config = Config::from_env();
+fn invalid_arguments(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+}
 
-    let bind_address = config.bind_address.clone();
+/// Returns the argument following the option, if the option is given.
+fn option_value<'a>(args: &'a [String], option: &str) -> Result<Option<&'a str>> {
+    match args.iter().position(|arg| arg == option) {
+        Some(index) => match args.get(index + 1) {
+            Some(value) => Ok(Some(value)),
+            None => Err(invalid_arguments(format!(
+                "Value of {} is required",
+                option
+            ))),
+        },
+        None => Ok(None),
+    }
+}
 
-    let subscriber = tracing_subscriber::FmtSubscriber::builder()
-        // all spans/events with a level higher than TRACE (e.g, debug, info, warn, etc.)
-        // will be written to stdout.
-        .with_max_level(config.log_level)
-        // completes the builder.
-        .finish();
+fn parse_command() -> Result<Command> {
+    let args: Vec<_> = std::env::args().skip(1).collect();
 
-    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+    match args.first().map(String::as_str) {
+        None => Ok(Command::Serve),
+        Some("gc-files") => Ok(Command::CollectGarbageFiles {
+            dry_run: args.iter().any(|arg| arg == "--dry-run"),
+        }),
+        Some("import-directory") => {
+            let user_id = args
+                .get(1)
+                .and_then(|value| value.parse::<i32>().ok())
+                .ok_or_else(|| invalid_arguments("User id is required".to_string()))?;
+            let path = args
+                .get(2)
+                .ok_or_else(|| invalid_arguments("Directory path is required".to_string()))?;
+            let stream_ref = option_value(&args, "--stream")?.map(StreamRef::from);
+
+            Ok(Command::ImportDirectory {
+                user_id: UserId::from(user_id),
+                path: path.into(),
+                stream_ref,
+            })
+        }
+        Some("airplay-report") => {
+            let stream_ref = args
+                .get(1)
+                .ok_or_else(|| invalid_arguments("Stream is required".to_string()))?;
+            let from = args
+                .get(2)
+                .and_then(|value| value.parse::<NaiveDate>().ok())
+                .ok_or_else(|| invalid_arguments("Period start date is required".to_string()))?;
+            let to = args
+                .get(3)
+                .and_then(|value| value.parse::<NaiveDate>().ok())
+                .ok_or_else(|| invalid_arguments("Period end date is required".to_string()))?;
+            let json = match option_value(&args, "--format")? {
+                None | Some("csv") => false,
+                Some("json") => true,
+                Some(format) => {
+                    return Err(invalid_arguments(format!(
+                        "Unknown report format: {}",
+                        format
+                    )))
+                }
+            };
+
+            Ok(Command::AirplayReport {
+                stream_ref: StreamRef::from(stream_ref.as_str()),
+                from,
+                to,
+                json,
+            })
+        }
+        Some(command) => Err(invalid_arguments(format!("Unknown command: {}", command))),
+    }
+}
+
+async fn run_file_gc_command<FS: FileSystem>(
+    file_gc_service: &FileGcService,
+    file_system: &FS,
+    dry_run: bool,
+) -> Result<()> {
+    let report = file_gc_service
+        .collect_garbage(file_system, dry_run)
+        .await
+        .map_err(std::io::Error::other)?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("Report is serializable")
+    );
+
+    Ok(())
+}
+
+async fn run_import_directory_command<FS: FileSystem>(
+    track_import_service: &TrackImportService,
+    file_system: &FS,
+    user_id: &UserId,
+    path: std::path::PathBuf,
+    stream_ref: &Option<StreamRef>,
+) -> Result<()> {
+    let job = track_import_service
+        .import_directory(user_id, stream_ref, path, file_system)
+        .await
+        .map_err(std::io::Error::other)?;
+
+    println!(
+        "Import {}: {} imported, {} skipped, {} failed of {} files",
+        job.status, job.imported_files, job.skipped_files, job.failed_files, job.total_files
+    );
+
+    Ok(())
+}
+
+async fn run_airplay_report_command(
+    airplay_report_service: &AirplayReportService,
+    stream_ref: &StreamRef,
+    from: NaiveDate,
+    to: NaiveDate,
+    json: bool,
+) -> Result<()> {
+    let to_io_error = |error| std::io::Error::other(error);
+
+    let period = ReportPeriod::new(from, to).map_err(to_io_error)?;
+    let report = airplay_report_service
+        .generate_report(None, stream_ref, &period)
+        .await
+        .map_err(to_io_error)?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Report is serializable")
+        );
+    } else {
+        print!("{}", report.render_csv());
+    }
+
+    Ok(())
+}
+
+/// Runs the command with the file system of the configured backend.
+async fn serve<FS: FileSystem + Send + Sync + Clone + 'static>(
+    command: Command,
+    config: Config,
+    file_system: FS,
+) -> Result<()> {
+    let bind_address = config.bind_address.clone();
 
     let mysql_client = MySqlClient::new(&config.mysql)
         .await
@@ -55,12 +221,120 @@ async fn main() -> Result<()> {
         &config.legacy_auth_jwt_secret_key,
     );
 
-    let file_system = LocalFileSystem::create(config.file_system_root_path.clone());
-
     let stream_service_factory =
         StreamServiceFactory::create(&mysql_client, &config.radio_streamer, &pubsub_client);
 
-    let auth_service = AuthService::new(mysql_client.clone(), auth_token_service.clone());
+    let login_throttle = LoginThrottle::new(Arc::new(SystemClock));
+
+    let auth_service = AuthService::new(
+        mysql_client.clone(),
+        auth_token_service.clone(),
+        login_throttle,
+    );
+
+    let email_service = EmailService::create(config.smtp.as_ref());
+
+    let profile_service = ProfileService::new(
+        mysql_client.clone(),
+        auth_token_service.clone(),
+        email_service,
+        config.web_server_own_address.as_deref(),
+    );
+
+    let channel_service = ChannelService::new(mysql_client.clone());
+
+    let file_gc_service = FileGcService::new(mysql_client.clone());
+    let file_gc_interval = config.file_gc_interval.map(std::time::Duration::from_secs);
+    let file_gc_dry_run = config.file_gc_dry_run;
+
+    let color_label_service = ColorLabelService::new(mysql_client.clone());
+
+    let artwork_service = ArtworkService::new(mysql_client.clone(), &config.path_to_ffmpeg);
+
+    let track_import_service = TrackImportService::new(
+        mysql_client.clone(),
+        pubsub_client.clone(),
+        artwork_service.clone(),
+        &config.path_to_ffprobe,
+        config
+            .import_directory_root
+            .as_ref()
+            .map(std::path::PathBuf::from),
+    );
+
+    let library_export_service = LibraryExportService::new(mysql_client.clone());
+
+    let track_metadata_service = TrackMetadataService::new(
+        mysql_client.clone(),
+        pubsub_client.clone(),
+        &config.path_to_ffmpeg,
+    );
+
+    let track_bulk_service =
+        TrackBulkService::new(mysql_client.clone(), stream_service_factory.clone());
+
+    let track_sharing_service = TrackSharingService::new(mysql_client.clone());
+
+    let play_history_service = PlayHistoryService::new(mysql_client.clone());
+
+    let airplay_report_service = AirplayReportService::new(mysql_client.clone());
+
+    let track_trash_service = TrackTrashService::new(mysql_client.clone());
+    let trash_purge_interval = config
+        .trash_purge_interval
+        .map(std::time::Duration::from_secs);
+    let trash_retention =
+        std::time::Duration::from_secs(config.trash_retention_days * 24 * 60 * 60);
+
+    match command {
+        Command::Serve => (),
+        Command::CollectGarbageFiles { dry_run } => {
+            return run_file_gc_command(&file_gc_service, &file_system, dry_run).await;
+        }
+        Command::ImportDirectory {
+            user_id,
+            path,
+            stream_ref,
+        } => {
+            return run_import_directory_command(
+                &track_import_service,
+                &file_system,
+                &user_id,
+                path,
+                &stream_ref,
+            )
+            .await;
+        }
+        Command::AirplayReport {
+            stream_ref,
+            from,
+            to,
+            json,
+        } => {
+            return run_airplay_report_command(
+                &airplay_report_service,
+                &stream_ref,
+                from,
+                to,
+                json,
+            )
+            .await;
+        }
+    }
+
+    track_import_service
+        .fail_interrupted_jobs()
+        .await
+        .expect("Unable to fail interrupted track import jobs");
+    track_import_service.schedule_interrupted_jobs_check();
+
+    if let Some(interval) = file_gc_interval {
+        file_gc_service.schedule(file_system.clone(), interval, file_gc_dry_run);
+    }
+
+    if let Some(interval) = trash_purge_interval {
+        track_trash_service.schedule(file_system.clone(), interval, trash_retention);
+    }
 
     let http_server = run_server(
         &bind_address,
@@ -71,10 +345,55 @@ async fn main() -> Result<()> {
         pubsub_client,
         auth_token_service,
         web_egress_controller_client,
-        auth_service,
+        Services {
+            auth_service,
+            profile_service,
+            channel_service,
+            track_import_service,
+            library_export_service,
+            track_metadata_service,
+            artwork_service,
+            color_label_service,
+            track_bulk_service,
+            track_sharing_service,
+            track_trash_service,
+            play_history_service,
+            airplay_report_service,
+        },
     )?;
 
     tracing::info!("Application started");
 
     http_server.await
 }
+
+#[actix_rt::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    let command = parse_command()?;
+
+    let config = Config::from_env();
+
+    let subscriber = tracing_subscriber::FmtSubscriber::builder()
+        // all spans/events with a level higher than TRACE (e.g, debug, info, warn, etc.)
+        // will be written to stdout.
+        .with_max_level(config.log_level)
+        // completes the builder.
+        .finish();
+
+    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+
+    match config.file_system_backend {
+        FileSystemBackend::Local => {
+            let file_system = LocalFileSystem::create(config.file_system_root_path.clone());
+
+            serve(command, config, file_system).await
+        }
+        FileSystemBackend::S3 => {
+            let file_system = S3FileSystem::create(&config.s3).map_err(std::io::Error::other)?;
+
+            serve(command, config, file_system).await
+        }
+    }
+}
@@ -42,6 +42,23 @@ impl PubsubClient {
         .await
     }
 
+    /// Tells the clients to refresh the now playing track of the channel, e.g. when
+    /// the track metadata has been edited, without restarting the playback.
+    pub(crate) async fn publish_now_playing_updated_message(
+        &self,
+        channel_id: &StreamId,
+        user_id: &UserId,
+    ) -> Result<(), PubsubClientError> {
+        self.publish_message(
+            user_id,
+            &serde_json::json!({
+                "channelId": *channel_id,
+                "eventType": "NowPlayingUpdated"
+            }),
+        )
+        .await
+    }
+
     pub(crate) async fn publish_outgoing_stream_started_message(
         &self,
         channel_id: &StreamId,
@@ -114,6 +131,42 @@ impl PubsubClient {
         .await
     }
 
+    pub(crate) async fn publish_track_import_progress_message(
+        &self,
+        user_id: &UserId,
+        job_id: i32,
+        processed_files: i32,
+        total_files: i32,
+    ) -> Result<(), PubsubClientError> {
+        self.publish_message(
+            user_id,
+            &serde_json::json!({
+                "jobId": job_id,
+                "eventType": "TrackImportProgress",
+                "processedFiles": processed_files,
+                "totalFiles": total_files,
+            }),
+        )
+        .await
+    }
+
+    pub(crate) async fn publish_track_import_finished_message(
+        &self,
+        user_id: &UserId,
+        job_id: i32,
+        status: &str,
+    ) -> Result<(), PubsubClientError> {
+        self.publish_message(
+            user_id,
+            &serde_json::json!({
+                "jobId": job_id,
+                "eventType": "TrackImportFinished",
+                "status": status,
+            }),
+        )
+        .await
+    }
+
     async fn publish_message(
         &self,
         user_id: &UserId,
@@ -0,0 +1,354 @@
+use crate::data_structures::{StreamId, StreamRef, TrackId, UserId};
+use crate::mysql_client::MySqlClient;
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::{streams, track_plays, TrackPlayRow};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+const MAX_REPORT_DAYS: i64 = 366;
+
+const MILLIS_PER_HOUR: f64 = 3_600_000.0;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum AirplayReportError {
+    #[error("Stream not found")]
+    StreamNotFound,
+    #[error("Invalid report period")]
+    InvalidPeriod,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Report period: the days from `from` to `to` inclusive, in UTC.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ReportPeriod {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+impl ReportPeriod {
+    pub(crate) fn new(from: NaiveDate, to: NaiveDate) -> Result<Self, AirplayReportError> {
+        if to < from || (to - from).num_days() >= MAX_REPORT_DAYS {
+            return Err(AirplayReportError::InvalidPeriod);
+        }
+
+        Ok(Self { from, to })
+    }
+
+    fn start(&self) -> DateTime<Utc> {
+        self.from.and_hms_opt(0, 0, 0).unwrap().and_utc()
+    }
+
+    fn end(&self) -> DateTime<Utc> {
+        (self.to + Days::new(1))
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AirplayReportTrack {
+    /// `None` if the track has been deleted since it was aired.
+    pub(crate) track_id: Option<TrackId>,
+    pub(crate) artist: String,
+    pub(crate) title: String,
+    pub(crate) album: String,
+    pub(crate) plays: usize,
+    pub(crate) aired_seconds: i64,
+    pub(crate) listeners_at_start: i64,
+    pub(crate) tuning_hours: f64,
+}
+
+/// Airplay of the stream within the period, for the royalty reporting.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AirplayReport {
+    pub(crate) stream_id: StreamId,
+    pub(crate) stream_name: String,
+    pub(crate) from: NaiveDate,
+    pub(crate) to: NaiveDate,
+    pub(crate) total_plays: usize,
+    pub(crate) total_aired_seconds: i64,
+    /// Time spent by all the listeners on the stream within the period, assuming the listeners
+    /// at the start of each play listened to it until it ended.
+    pub(crate) tuning_hours: f64,
+    pub(crate) tracks: Vec<AirplayReportTrack>,
+}
+
+impl AirplayReport {
+    pub(crate) fn file_name(&self, extension: &str) -> String {
+        format!(
+            "{} airplay {} - {}.{}",
+            self.stream_name, self.from, self.to, extension
+        )
+    }
+
+    pub(crate) fn render_csv(&self) -> String {
+        let mut csv = String::from(
+            "track_id,artist,title,album,plays,aired_seconds,listeners_at_start,tuning_hours\n",
+        );
+
+        for track in &self.tracks {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{:.2}\n",
+                track
+                    .track_id
+                    .as_ref()
+                    .map(|track_id| track_id.to_string())
+                    .unwrap_or_default(),
+                escape_csv(&track.artist),
+                escape_csv(&track.title),
+                escape_csv(&track.album),
+                track.plays,
+                track.aired_seconds,
+                track.listeners_at_start,
+                track.tuning_hours
+            ));
+        }
+
+        csv
+    }
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn round_hours(millis: i64) -> f64 {
+    (millis as f64 / MILLIS_PER_HOUR * 100.0).round() / 100.0
+}
+
+/// Builds the report from the plays started within the period, oldest first. A play lasts
+/// until the next one starts if the track was skipped, and until the end of the period
+/// at most. The listeners counted at the start of a play are taken as its audience.
+fn build_report(
+    stream_id: StreamId,
+    stream_name: String,
+    period: &ReportPeriod,
+    plays: Vec<TrackPlayRow>,
+) -> AirplayReport {
+    let period_end = period.end().timestamp_millis();
+
+    let mut tracks: Vec<AirplayReportTrack> = vec![];
+    let mut track_indexes: HashMap<(Option<i32>, String, String, String), usize> = HashMap::new();
+    let mut tuning_millis_by_track: Vec<i64> = vec![];
+    let mut total_aired_millis = 0;
+    let mut total_tuning_millis = 0;
+
+    for (index, play) in plays.iter().enumerate() {
+        let started = play.started_at.timestamp_millis();
+        let next_started = plays
+            .get(index + 1)
+            .map(|next| next.started_at.timestamp_millis())
+            .unwrap_or(period_end);
+        let aired_millis = (play.duration as i64).min(next_started - started).max(0);
+
+        let key = (
+            play.track_id.as_deref().copied(),
+            play.artist.clone(),
+            play.title.clone(),
+            play.album.clone(),
+        );
+        let track_index = *track_indexes.entry(key).or_insert_with(|| {
+            tracks.push(AirplayReportTrack {
+                track_id: play.track_id.clone(),
+                artist: play.artist.clone(),
+                title: play.title.clone(),
+                album: play.album.clone(),
+                plays: 0,
+                aired_seconds: 0,
+                listeners_at_start: 0,
+                tuning_hours: 0.0,
+            });
+            tuning_millis_by_track.push(0);
+
+            tracks.len() - 1
+        });
+
+        let track = &mut tracks[track_index];
+        track.plays += 1;
+        track.aired_seconds += aired_millis;
+        track.listeners_at_start += play.listeners as i64;
+        let tuning_millis = aired_millis * play.listeners as i64;
+        tuning_millis_by_track[track_index] += tuning_millis;
+        total_aired_millis += aired_millis;
+        total_tuning_millis += tuning_millis;
+    }
+
+    for (track, tuning_millis) in tracks.iter_mut().zip(tuning_millis_by_track) {
+        track.aired_seconds /= 1000;
+        track.tuning_hours = round_hours(tuning_millis);
+    }
+
+    tracks.sort_by(|a, b| {
+        b.plays
+            .cmp(&a.plays)
+            .then_with(|| a.artist.cmp(&b.artist))
+            .then_with(|| a.title.cmp(&b.title))
+    });
+
+    AirplayReport {
+        stream_id,
+        stream_name,
+        from: period.from,
+        to: period.to,
+        total_plays: plays.len(),
+        total_aired_seconds: total_aired_millis / 1000,
+        tuning_hours: round_hours(total_tuning_millis),
+        tracks,
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct AirplayReportService {
+    mysql_client: MySqlClient,
+}
+
+impl AirplayReportService {
+    pub(crate) fn new(mysql_client: MySqlClient) -> Self {
+        Self { mysql_client }
+    }
+
+    /// Generates the airplay report of the stream. The stream must belong to the user
+    /// if one is given.
+    pub(crate) async fn generate_report(
+        &self,
+        user_id: Option<&UserId>,
+        stream_ref: &StreamRef,
+        period: &ReportPeriod,
+    ) -> Result<AirplayReport, AirplayReportError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let stream = match streams::get_single_stream_by_ref(&mut connection, stream_ref).await? {
+            Some(stream) if user_id.is_none_or(|user_id| &stream.uid == user_id) => stream,
+            _ => return Err(AirplayReportError::StreamNotFound),
+        };
+
+        let plays = track_plays::get_stream_track_plays_between(
+            &mut connection,
+            &stream.sid,
+            &period.start(),
+            &period.end(),
+        )
+        .await?;
+
+        Ok(build_report(stream.sid, stream.name, period, plays))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn play(track_id: i32, title: &str, started_at: i64, listeners: i32) -> TrackPlayRow {
+        TrackPlayRow {
+            id: 0,
+            user_id: UserId::from(1),
+            stream_id: Some(StreamId::from(1)),
+            track_id: Some(TrackId::from(track_id)),
+            artist: "Artist".to_string(),
+            title: title.to_string(),
+            album: String::new(),
+            duration: 180_000,
+            started_at: Utc.timestamp_millis_opt(started_at).unwrap(),
+            listeners,
+        }
+    }
+
+    #[test]
+    fn test_validates_report_period() {
+        assert!(ReportPeriod::new(date("2026-01-01"), date("2026-01-31")).is_ok());
+        assert!(ReportPeriod::new(date("2026-01-01"), date("2026-01-01")).is_ok());
+        assert!(ReportPeriod::new(date("2025-01-01"), date("2026-01-01")).is_ok());
+        assert!(matches!(
+            ReportPeriod::new(date("2026-01-31"), date("2026-01-01")),
+            Err(AirplayReportError::InvalidPeriod)
+        ));
+        assert!(matches!(
+            ReportPeriod::new(date("2025-01-01"), date("2026-01-02")),
+            Err(AirplayReportError::InvalidPeriod)
+        ));
+    }
+
+    #[test]
+    fn test_builds_report() {
+        let period = ReportPeriod::new(date("1970-01-01"), date("1970-01-01")).unwrap();
+        let plays = vec![
+            play(1, "First", 0, 2),
+            // Skipped after a minute.
+            play(2, "Second", 180_000, 1),
+            play(1, "First", 240_000, 3),
+        ];
+
+        let report = build_report(StreamId::from(1), "Radio".to_string(), &period, plays);
+
+        assert_eq!(3, report.total_plays);
+        assert_eq!(420, report.total_aired_seconds);
+        assert_eq!(0.27, report.tuning_hours);
+
+        assert_eq!(2, report.tracks.len());
+        assert_eq!("First", report.tracks[0].title);
+        assert_eq!(2, report.tracks[0].plays);
+        assert_eq!(360, report.tracks[0].aired_seconds);
+        assert_eq!(5, report.tracks[0].listeners_at_start);
+        assert_eq!(0.25, report.tracks[0].tuning_hours);
+        assert_eq!("Second", report.tracks[1].title);
+        assert_eq!(60, report.tracks[1].aired_seconds);
+        assert_eq!(0.02, report.tracks[1].tuning_hours);
+    }
+
+    #[test]
+    fn test_clips_last_play_to_period_end() {
+        let period = ReportPeriod::new(date("1970-01-01"), date("1970-01-01")).unwrap();
+        let plays = vec![play(1, "First", 86_340_000, 2)];
+
+        let report = build_report(StreamId::from(1), "Radio".to_string(), &period, plays);
+
+        assert_eq!(60, report.total_aired_seconds);
+        assert_eq!(60, report.tracks[0].aired_seconds);
+        assert_eq!(0.03, report.tuning_hours);
+    }
+
+    #[test]
+    fn test_renders_csv() {
+        let report = AirplayReport {
+            stream_id: StreamId::from(1),
+            stream_name: "Radio".to_string(),
+            from: date("2026-01-01"),
+            to: date("2026-01-31"),
+            total_plays: 1,
+            total_aired_seconds: 180,
+            tuning_hours: 1.5,
+            tracks: vec![AirplayReportTrack {
+                track_id: None,
+                artist: "Artist, The".to_string(),
+                title: "Say \"Hi\"".to_string(),
+                album: "Album".to_string(),
+                plays: 1,
+                aired_seconds: 180,
+                listeners_at_start: 4,
+                tuning_hours: 0.2,
+            }],
+        };
+
+        assert_eq!(
+            "track_id,artist,title,album,plays,aired_seconds,listeners_at_start,tuning_hours\n\
+             ,\"Artist, The\",\"Say \"\"Hi\"\"\",Album,1,180,4,0.20\n",
+            report.render_csv()
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use crate::data_structures::{TrackId, UserId};
+use crate::services::ffmpeg_service::{extract_artwork, ExtractArtworkError};
+use crate::services::image_utils::resize_to_square_png;
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::{track_artworks, user_stream_tracks, user_tracks};
+use crate::storage::fs::FileSystem;
+use crate::MySqlClient;
+use serde::Deserialize;
+use sha2::{Digest, Sha512};
+use std::path::Path;
+use tracing::{debug, error, warn};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ArtworkSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl ArtworkSize {
+    pub(crate) const ALL: [ArtworkSize; 3] =
+        [ArtworkSize::Small, ArtworkSize::Medium, ArtworkSize::Large];
+
+    pub(crate) fn pixels(&self) -> u32 {
+        match self {
+            ArtworkSize::Small => 100,
+            ArtworkSize::Medium => 300,
+            ArtworkSize::Large => 600,
+        }
+    }
+}
+
+pub(crate) const ARTWORK_DIRECTORY: &str = "artwork";
+
+/// Path of the artwork variant in the file system: artworks are stored by the hash
+/// of the original picture, so tracks sharing the same picture share the files.
+pub(crate) fn artwork_file_path(artwork_hash: &str, size: ArtworkSize) -> String {
+    format!(
+        "{}/{}",
+        ARTWORK_DIRECTORY,
+        artwork_file_name(artwork_hash, size)
+    )
+}
+
+/// Name of the artwork variant relative to [`ARTWORK_DIRECTORY`].
+pub(crate) fn artwork_file_name(artwork_hash: &str, size: ArtworkSize) -> String {
+    format!(
+        "{}/{}/{}_{}.png",
+        &artwork_hash[..1],
+        &artwork_hash[1..2],
+        artwork_hash,
+        size.pixels()
+    )
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ArtworkError {
+    #[error("Track not found")]
+    TrackNotFound,
+    #[error("Track has no artwork")]
+    ArtworkNotFound,
+    #[error("Invalid image")]
+    InvalidImage,
+    #[error(transparent)]
+    ExtractionError(#[from] ExtractArtworkError),
+    #[error(transparent)]
+    FileSystemError(#[from] std::io::Error),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Track artwork stored in the file system.
+pub(crate) struct Artwork {
+    pub(crate) hash: String,
+    pub(crate) path: String,
+}
+
+#[derive(Clone)]
+pub(crate) struct ArtworkService {
+    mysql_client: MySqlClient,
+    path_to_ffmpeg: String,
+}
+
+impl ArtworkService {
+    pub(crate) fn new(mysql_client: MySqlClient, path_to_ffmpeg: &str) -> Self {
+        Self {
+            mysql_client,
+            path_to_ffmpeg: path_to_ffmpeg.to_string(),
+        }
+    }
+
+    /// Extracts the picture embedded into the audio file and attaches it to the track.
+    /// Returns `false` if the audio file has no picture.
+    pub(crate) async fn extract_track_artwork<FS: FileSystem>(
+        &self,
+        track_id: &TrackId,
+        audio_file: &Path,
+        file_system: &FS,
+    ) -> Result<bool, ArtworkError> {
+        let image_data =
+            match extract_artwork(&self.path_to_ffmpeg, &audio_file.to_string_lossy()).await? {
+                Some(image_data) => image_data,
+                None => {
+                    debug!(?track_id, "Audio file has no embedded artwork");
+                    return Ok(false);
+                }
+            };
+
+        self.store_track_artwork(track_id, image_data, file_system)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Stores the size variants of the picture, unless the same picture is already stored,
+    /// and attaches it to the track.
+    pub(crate) async fn store_track_artwork<FS: FileSystem>(
+        &self,
+        track_id: &TrackId,
+        image_data: Vec<u8>,
+        file_system: &FS,
+    ) -> Result<String, ArtworkError> {
+        let artwork_hash = hex::encode(Sha512::digest(&image_data));
+
+        if !file_system
+            .exists(&artwork_file_path(&artwork_hash, ArtworkSize::Large))
+            .await?
+        {
+            let variants = actix_web::web::block(move || {
+                ArtworkSize::ALL
+                    .iter()
+                    .map(|size| {
+                        resize_to_square_png(&image_data, size.pixels())
+                            .map(|variant_data| (*size, variant_data))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .await
+            .map_err(|error| {
+                error!(?error, "Unable to run artwork resizing task");
+                ArtworkError::InvalidImage
+            })?
+            .map_err(|error| {
+                warn!(?error, "Unable to process artwork image");
+                ArtworkError::InvalidImage
+            })?;
+
+            // The largest variant is written last: its presence marks the artwork as stored.
+            for (size, variant_data) in variants {
+                file_system
+                    .put_file(&artwork_file_path(&artwork_hash, size), variant_data)
+                    .await?;
+            }
+        }
+
+        let mut connection = self.mysql_client.connection().await?;
+
+        track_artworks::set_track_artwork(&mut connection, track_id, &artwork_hash).await?;
+
+        Ok(artwork_hash)
+    }
+
+    /// Returns the artwork of the user's own track or of a track played by a public stream.
+    pub(crate) async fn get_track_artwork(
+        &self,
+        user_id: &UserId,
+        track_id: &TrackId,
+        size: ArtworkSize,
+    ) -> Result<Artwork, ArtworkError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let track = user_tracks::get_single_user_track(&mut connection, track_id)
+            .await?
+            .ok_or(ArtworkError::TrackNotFound)?;
+
+        if &track.track.uid != user_id
+            && !user_stream_tracks::is_track_in_public_stream(&mut connection, track_id).await?
+        {
+            return Err(ArtworkError::TrackNotFound);
+        }
+
+        let artwork_hash = track_artworks::get_track_artwork_hash(&mut connection, track_id)
+            .await?
+            .ok_or(ArtworkError::ArtworkNotFound)?;
+
+        Ok(Artwork {
+            path: artwork_file_path(&artwork_hash, size),
+            hash: artwork_hash,
+        })
+    }
+
+    /// Returns the artwork by its hash. Artwork hashes are only known to those who can see
+    /// the tracks, so the artwork is served to anyone as long as a track is attached to it.
+    pub(crate) async fn get_artwork(
+        &self,
+        artwork_hash: &str,
+        size: ArtworkSize,
+    ) -> Result<Artwork, ArtworkError> {
+        if !is_valid_artwork_hash(artwork_hash) {
+            return Err(ArtworkError::ArtworkNotFound);
+        }
+
+        let mut connection = self.mysql_client.connection().await?;
+
+        if !track_artworks::is_artwork_attached(&mut connection, artwork_hash).await? {
+            return Err(ArtworkError::ArtworkNotFound);
+        }
+
+        Ok(Artwork {
+            path: artwork_file_path(artwork_hash, size),
+            hash: artwork_hash.to_string(),
+        })
+    }
+}
+
+/// Checks that the hash is a hex encoded SHA-512 digest, as produced by
+/// [`ArtworkService::store_track_artwork`].
+fn is_valid_artwork_hash(artwork_hash: &str) -> bool {
+    artwork_hash.len() == 128
+        && artwork_hash
+            .chars()
+            .all(|char| matches!(char, '0'..='9' | 'a'..='f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builds_artwork_file_path() {
+        assert_eq!(
+            "artwork/a/b/abcdef_100.png",
+            artwork_file_path("abcdef", ArtworkSize::Small)
+        );
+        assert_eq!(
+            "artwork/a/b/abcdef_600.png",
+            artwork_file_path("abcdef", ArtworkSize::Large)
+        );
+    }
+
+    #[test]
+    fn test_validates_artwork_hash() {
+        let artwork_hash = hex::encode(Sha512::digest(b"image"));
+
+        assert!(is_valid_artwork_hash(&artwork_hash));
+        assert!(!is_valid_artwork_hash(&artwork_hash.to_uppercase()));
+        assert!(!is_valid_artwork_hash(&artwork_hash[1..]));
+        assert!(!is_valid_artwork_hash(&format!(
+            "../{}",
+            &artwork_hash[3..]
+        )));
+        assert!(!is_valid_artwork_hash(""));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::data_structures::FileId;
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::{files, FileRow};
+use crate::storage::fs::FileSystem;
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use sha2::{Digest, Sha512};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::warn;
+
+/// Path of the audio file in the file system: files are stored by their content hash.
+pub(crate) fn audio_file_path(file_hash: &str, extension: &str) -> String {
+    format!(
+        "audio/{}/{}/{}.{}",
+        &file_hash[..1],
+        &file_hash[1..2],
+        file_hash,
+        extension
+    )
+}
+
+/// Local file deleted when dropped.
+pub(crate) struct TemporaryFile {
+    pub(crate) path: PathBuf,
+}
+
+impl TemporaryFile {
+    pub(crate) fn new(extension: &str) -> Self {
+        Self {
+            path: temporary_file_path(extension),
+        }
+    }
+}
+
+impl Drop for TemporaryFile {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_file(&self.path) {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                warn!(?error, "Unable to delete temporary file");
+            }
+        }
+    }
+}
+
+pub(crate) fn temporary_file_path(extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "audio-{}.{}",
+        uuid::Uuid::new_v4().to_simple(),
+        extension
+    ))
+}
+
+pub(crate) fn compute_file_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha512::new();
+    let mut buffer = [0u8; 65536];
+
+    loop {
+        let size = file.read(&mut buffer)?;
+
+        if size == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..size]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Copies the local file into the file system.
+pub(crate) async fn upload_local_file<FS: FileSystem>(
+    file_system: &FS,
+    local_path: &Path,
+    path: &str,
+) -> std::io::Result<()> {
+    let mut file = tokio::fs::File::open(local_path).await?;
+    let (mut tx, rx) = mpsc::channel(0);
+
+    let read_file = async move {
+        let mut buffer = vec![0u8; 65536];
+
+        loop {
+            let size = file.read(&mut buffer).await?;
+
+            if size == 0 {
+                return Ok::<(), std::io::Error>(());
+            }
+
+            if tx.send(buffer[..size].to_vec()).await.is_err() {
+                return Ok(());
+            }
+        }
+    };
+
+    let (upload_result, read_result) =
+        futures::join!(file_system.put_file_stream(path, rx), read_file);
+
+    if let Err(error) = read_result {
+        // The file system has received the truncated file.
+        if upload_result.is_ok() {
+            let _ = file_system.delete_file(path).await;
+        }
+
+        return Err(error);
+    }
+
+    upload_result
+}
+
+/// Stores the local audio file unless the file with the same hash is already stored,
+/// counting one more use of the file. Should be called within the transaction creating
+/// the reference to the file: the file row stays locked until the transaction ends,
+/// so the file garbage collector can't delete the file in the meantime.
+pub(crate) async fn store_audio_file<FS: FileSystem, E>(
+    connection: &mut MySqlConnection,
+    file_system: &FS,
+    local_path: &Path,
+    file_hash: &str,
+    extension: &str,
+) -> Result<FileId, E>
+where
+    E: From<std::io::Error> + From<RepositoryError>,
+{
+    if let Some(file) = files::lock_file_by_hash(connection, file_hash).await? {
+        files::increment_file_use_count(connection, &file.file_id).await?;
+
+        return Ok(file.file_id);
+    }
+
+    let file_size = tokio::fs::metadata(local_path).await?.len();
+
+    upload_local_file(
+        file_system,
+        local_path,
+        &audio_file_path(file_hash, extension),
+    )
+    .await?;
+
+    Ok(files::create_file(connection, file_size, file_hash, extension).await?)
+}
+
+/// Copies the audio file from the file system into the local file. Fails if fewer or more bytes
+/// than the recorded size of the file are read, so a broken copy is never written back.
+pub(crate) async fn download_to_local_file<FS: FileSystem>(
+    file_system: &FS,
+    audio_file: &FileRow,
+    local_path: &Path,
+) -> std::io::Result<()> {
+    let path = audio_file_path(&audio_file.file_hash, &audio_file.file_extension);
+    let mut contents = file_system.get_file_contents(&path).await?;
+    let mut file = tokio::fs::File::create(local_path).await?;
+    let mut read_size = 0u64;
+
+    while let Some(chunk) = contents.next().await {
+        let chunk = chunk?;
+        read_size += chunk.len() as u64;
+        file.write_all(&chunk).await?;
+    }
+
+    if read_size != audio_file.file_size as u64 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!(
+                "Read {} of {} bytes of {}",
+                read_size, audio_file.file_size, path
+            ),
+        ));
+    }
+
+    file.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builds_audio_file_path() {
+        assert_eq!("audio/a/b/abcdef.mp3", audio_file_path("abcdef", "mp3"));
+    }
+}
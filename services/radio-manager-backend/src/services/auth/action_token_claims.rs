@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub(crate) enum Action {
     ResetPassword,
+    CompleteTwoFactorLogin,
+    ConfirmEmailChange { email: String },
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
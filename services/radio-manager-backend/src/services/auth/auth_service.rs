@@ -1,11 +1,30 @@
 use crate::data_structures::UserId;
-use crate::mysql_client::MySqlClient;
-use crate::services::auth::{AuthTokenService, LegacyAuthTokenClaims, LegacyAuthTokenData};
+use crate::mysql_client::{MySqlClient, MySqlConnection};
+use crate::services::auth::auth_token_service::TOKEN_EXPIRES_AFTER;
+use crate::services::auth::login_throttle::LoginThrottle;
+use crate::services::auth::totp;
+use crate::services::auth::{
+    Action, ActionTokenClaims, AuthTokenService, LegacyAuthTokenClaims, LegacyAuthTokenData,
+};
 use crate::storage::db::repositories::errors::RepositoryError;
-use crate::storage::db::repositories::{legacy_sessions, users};
-use crate::utils::{hash_password, verify_password};
+use crate::storage::db::repositories::{
+    legacy_sessions, refresh_tokens, two_factor_auth, users, TwoFactorAuthRow, UserRow,
+};
+use crate::system::now;
+use crate::utils::{generate_opaque_token, hash_opaque_token, hash_password, verify_password};
+use chrono::Utc;
 use serde::Serialize;
 use std::ops::Deref;
+use std::time::Duration;
+use tracing::warn;
+
+const REFRESH_TOKEN_EXPIRES_AFTER_DAYS: i64 = 30;
+
+const TWO_FACTOR_CHALLENGE_EXPIRES_AFTER: Duration = Duration::from_secs(300);
+
+const TWO_FACTOR_ISSUER: &str = "Myownradio";
+
+const RECOVERY_CODES_COUNT: usize = 10;
 
 #[derive(Serialize)]
 pub(crate) struct LoggedInUser {
@@ -23,10 +42,114 @@ impl Deref for LegacyToken {
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuthTokens {
+    access_token: String,
+    refresh_token: String,
+    expires_in: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TwoFactorEnrollment {
+    secret: String,
+    otpauth_uri: String,
+}
+
+pub(crate) enum LoginResult {
+    LoggedIn(LoggedInUser, AuthTokens),
+    TwoFactorRequired { challenge_token: String },
+}
+
+pub(crate) enum LegacyLoginResult {
+    LoggedIn(LoggedInUser, LegacyToken),
+    TwoFactorRequired { challenge_token: String },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum LoginError {
+    #[error("Bad credentials")]
+    BadCredentials,
+    #[error("Too many failed login attempts")]
+    TooManyAttempts { retry_after: Duration },
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum RefreshError {
+    #[error("Invalid refresh token")]
+    InvalidRefreshToken,
+    #[error("Refresh token expired")]
+    RefreshTokenExpired,
+    #[error("Refresh token reused")]
+    RefreshTokenReused,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum LogoutError {
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum LegacyLoginError {
     #[error("Bad credentials")]
     BadCredentials,
+    #[error("Too many failed login attempts")]
+    TooManyAttempts { retry_after: Duration },
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+impl From<LoginError> for LegacyLoginError {
+    fn from(error: LoginError) -> Self {
+        match error {
+            LoginError::BadCredentials => Self::BadCredentials,
+            LoginError::TooManyAttempts { retry_after } => Self::TooManyAttempts { retry_after },
+            LoginError::DatabaseError(error) => Self::DatabaseError(error),
+            LoginError::RepositoryError(error) => Self::RepositoryError(error),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum TwoFactorLoginError {
+    #[error("Invalid two-factor challenge")]
+    InvalidChallenge,
+    #[error("Invalid two-factor code")]
+    InvalidCode,
+    #[error("Too many failed login attempts")]
+    TooManyAttempts { retry_after: Duration },
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum TwoFactorError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Two-factor authentication is already enabled")]
+    AlreadyEnabled,
+    #[error("Two-factor authentication is not enabled")]
+    NotEnabled,
+    #[error("Two-factor enrollment is not started")]
+    EnrollmentNotStarted,
+    #[error("Invalid two-factor code")]
+    InvalidCode,
     #[error(transparent)]
     DatabaseError(#[from] sqlx::Error),
     #[error(transparent)]
@@ -77,13 +200,19 @@ pub(crate) enum LegacySignupResult {
 pub(crate) struct AuthService {
     mysql_client: MySqlClient,
     token_service: AuthTokenService,
+    login_throttle: LoginThrottle,
 }
 
 impl AuthService {
-    pub(crate) fn new(mysql_client: MySqlClient, token_service: AuthTokenService) -> Self {
+    pub(crate) fn new(
+        mysql_client: MySqlClient,
+        token_service: AuthTokenService,
+        login_throttle: LoginThrottle,
+    ) -> Self {
         Self {
             mysql_client,
             token_service,
+            login_throttle,
         }
     }
 
@@ -91,28 +220,49 @@ impl AuthService {
         &self,
         email: &str,
         password: &str,
-    ) -> Result<(LoggedInUser, LegacyToken), LegacyLoginError> {
+        ip: Option<&str>,
+    ) -> Result<LegacyLoginResult, LegacyLoginError> {
         let mut connection = self.mysql_client.connection().await?;
 
-        let user = match users::get_user_by_email(&mut connection, email).await? {
-            Some(user) => {
-                let hashed_password = user.password.clone().unwrap_or_default();
-                let is_valid =
-                    verify_password(password, &hashed_password).expect("Unable to verify password");
+        let user = self
+            .verify_credentials(&mut connection, email, password, ip)
+            .await?;
 
-                if !is_valid {
-                    return Err(LegacyLoginError::BadCredentials);
-                }
+        if self
+            .is_two_factor_enabled(&mut connection, &user.uid)
+            .await?
+        {
+            return Ok(LegacyLoginResult::TwoFactorRequired {
+                challenge_token: self.sign_two_factor_challenge(&user.uid),
+            });
+        }
 
-                user
-            }
-            None => {
-                return Err(LegacyLoginError::BadCredentials);
-            }
-        };
+        let (user, token) = self.create_legacy_login(&mut connection, user).await?;
 
-        let legacy_session =
-            legacy_sessions::create_legacy_session(&mut connection, &user.uid).await?;
+        Ok(LegacyLoginResult::LoggedIn(user, token))
+    }
+
+    pub(crate) async fn legacy_complete_two_factor_login(
+        &self,
+        challenge_token: &str,
+        code: &str,
+        ip: Option<&str>,
+    ) -> Result<(LoggedInUser, LegacyToken), TwoFactorLoginError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let user = self
+            .verify_two_factor_challenge(&mut connection, challenge_token, code, ip)
+            .await?;
+
+        Ok(self.create_legacy_login(&mut connection, user).await?)
+    }
+
+    async fn create_legacy_login(
+        &self,
+        connection: &mut MySqlConnection,
+        user: UserRow,
+    ) -> Result<(LoggedInUser, LegacyToken), RepositoryError> {
+        let legacy_session = legacy_sessions::create_legacy_session(connection, &user.uid).await?;
 
         let token = self
             .token_service
@@ -132,6 +282,414 @@ impl AuthService {
         ))
     }
 
+    pub(crate) async fn login(
+        &self,
+        email: &str,
+        password: &str,
+        ip: Option<&str>,
+    ) -> Result<LoginResult, LoginError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let user = self
+            .verify_credentials(&mut connection, email, password, ip)
+            .await?;
+
+        if self
+            .is_two_factor_enabled(&mut connection, &user.uid)
+            .await?
+        {
+            return Ok(LoginResult::TwoFactorRequired {
+                challenge_token: self.sign_two_factor_challenge(&user.uid),
+            });
+        }
+
+        let (user, tokens) = self.create_login(&mut connection, user).await?;
+
+        connection.commit().await?;
+
+        Ok(LoginResult::LoggedIn(user, tokens))
+    }
+
+    pub(crate) async fn complete_two_factor_login(
+        &self,
+        challenge_token: &str,
+        code: &str,
+        ip: Option<&str>,
+    ) -> Result<(LoggedInUser, AuthTokens), TwoFactorLoginError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let user = self
+            .verify_two_factor_challenge(&mut connection, challenge_token, code, ip)
+            .await?;
+
+        let (user, tokens) = self.create_login(&mut connection, user).await?;
+
+        connection.commit().await?;
+
+        Ok((user, tokens))
+    }
+
+    async fn create_login(
+        &self,
+        connection: &mut MySqlConnection,
+        user: UserRow,
+    ) -> Result<(LoggedInUser, AuthTokens), RepositoryError> {
+        let family_id = uuid::Uuid::new_v4().to_string();
+        let tokens = issue_tokens(connection, &self.token_service, &user.uid, &family_id).await?;
+
+        Ok((
+            LoggedInUser {
+                id: user.uid,
+                email: user.mail,
+            },
+            tokens,
+        ))
+    }
+
+    /// Exchanges the refresh token for a new pair of tokens. Every refresh token can only be used
+    /// once: presenting an already used one revokes the whole family of tokens issued since login.
+    pub(crate) async fn refresh(&self, refresh_token: &str) -> Result<AuthTokens, RefreshError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let result =
+            rotate_refresh_token(&mut connection, &self.token_service, refresh_token).await;
+
+        if matches!(result, Ok(_) | Err(RefreshError::RefreshTokenReused)) {
+            connection.commit().await?;
+        }
+
+        result
+    }
+
+    pub(crate) async fn logout(&self, refresh_token: &str) -> Result<(), LogoutError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let token_hash = hash_opaque_token(refresh_token);
+
+        if let Some(token_row) =
+            refresh_tokens::get_refresh_token_by_hash(&mut connection, &token_hash).await?
+        {
+            refresh_tokens::revoke_refresh_token_family(&mut connection, &token_row.family_id)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn verify_credentials(
+        &self,
+        connection: &mut MySqlConnection,
+        email: &str,
+        password: &str,
+        ip: Option<&str>,
+    ) -> Result<UserRow, LoginError> {
+        let attempt = match self.login_throttle.begin_attempt(email, ip) {
+            Ok(attempt) => attempt,
+            Err(retry_after) => {
+                warn!(target: "audit", email, ?ip, ?retry_after, "Login attempt throttled");
+
+                return Err(LoginError::TooManyAttempts { retry_after });
+            }
+        };
+
+        let user = users::get_user_by_email(connection, email).await?;
+
+        let is_valid = match &user {
+            Some(user) => {
+                let hashed_password = user.password.clone().unwrap_or_default();
+
+                match verify_password(password, &hashed_password) {
+                    Ok(is_valid) => is_valid,
+                    Err(error) => {
+                        warn!(?error, user_id = ?user.uid, "Unable to verify password against stored hash");
+                        false
+                    }
+                }
+            }
+            None => false,
+        };
+
+        match user {
+            Some(user) if is_valid => {
+                attempt.succeed();
+
+                Ok(user)
+            }
+            _ => {
+                let failures = attempt.fail();
+
+                warn!(target: "audit", email, ?ip, failures, "Failed login attempt");
+
+                Err(LoginError::BadCredentials)
+            }
+        }
+    }
+
+    pub(crate) async fn start_two_factor_enrollment(
+        &self,
+        user_id: &UserId,
+    ) -> Result<TwoFactorEnrollment, TwoFactorError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let user = match users::get_user_by_id(&mut connection, user_id).await? {
+            Some(user) => user,
+            None => return Err(TwoFactorError::UserNotFound),
+        };
+
+        if self.is_two_factor_enabled(&mut connection, user_id).await? {
+            return Err(TwoFactorError::AlreadyEnabled);
+        }
+
+        let secret = totp::generate_secret();
+
+        two_factor_auth::save_pending_two_factor_secret(&mut connection, user_id, &secret).await?;
+
+        connection.commit().await?;
+
+        Ok(TwoFactorEnrollment {
+            otpauth_uri: totp::otpauth_uri(TWO_FACTOR_ISSUER, &user.mail, &secret),
+            secret,
+        })
+    }
+
+    /// Enables two-factor authentication once the user proves that the authenticator app is set up
+    /// and returns the recovery codes. The codes are shown only once and stored hashed.
+    pub(crate) async fn confirm_two_factor_enrollment(
+        &self,
+        user_id: &UserId,
+        code: &str,
+    ) -> Result<Vec<String>, TwoFactorError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let two_factor_auth =
+            match two_factor_auth::get_two_factor_auth(&mut connection, user_id).await? {
+                Some(row) if row.enabled => return Err(TwoFactorError::AlreadyEnabled),
+                Some(row) => row,
+                None => return Err(TwoFactorError::EnrollmentNotStarted),
+            };
+
+        if !self
+            .verify_totp_code(&mut connection, &two_factor_auth, code)
+            .await?
+        {
+            return Err(TwoFactorError::InvalidCode);
+        }
+
+        two_factor_auth::enable_two_factor_auth(&mut connection, user_id).await?;
+        let recovery_codes = self
+            .replace_recovery_codes(&mut connection, user_id)
+            .await?;
+
+        connection.commit().await?;
+
+        Ok(recovery_codes)
+    }
+
+    pub(crate) async fn regenerate_recovery_codes(
+        &self,
+        user_id: &UserId,
+        code: &str,
+    ) -> Result<Vec<String>, TwoFactorError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let two_factor_auth = self
+            .get_enabled_two_factor_auth(&mut connection, user_id)
+            .await?;
+
+        if !self
+            .verify_totp_code(&mut connection, &two_factor_auth, code)
+            .await?
+        {
+            return Err(TwoFactorError::InvalidCode);
+        }
+
+        let recovery_codes = self
+            .replace_recovery_codes(&mut connection, user_id)
+            .await?;
+
+        connection.commit().await?;
+
+        Ok(recovery_codes)
+    }
+
+    pub(crate) async fn disable_two_factor(
+        &self,
+        user_id: &UserId,
+        code: &str,
+    ) -> Result<(), TwoFactorError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let two_factor_auth = self
+            .get_enabled_two_factor_auth(&mut connection, user_id)
+            .await?;
+
+        if !self
+            .verify_second_factor(&mut connection, &two_factor_auth, code)
+            .await?
+        {
+            return Err(TwoFactorError::InvalidCode);
+        }
+
+        two_factor_auth::delete_two_factor_auth(&mut connection, user_id).await?;
+
+        connection.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_enabled_two_factor_auth(
+        &self,
+        connection: &mut MySqlConnection,
+        user_id: &UserId,
+    ) -> Result<TwoFactorAuthRow, TwoFactorError> {
+        match two_factor_auth::get_two_factor_auth(connection, user_id).await? {
+            Some(row) if row.enabled => Ok(row),
+            _ => Err(TwoFactorError::NotEnabled),
+        }
+    }
+
+    async fn is_two_factor_enabled(
+        &self,
+        connection: &mut MySqlConnection,
+        user_id: &UserId,
+    ) -> Result<bool, RepositoryError> {
+        let two_factor_auth = two_factor_auth::get_two_factor_auth(connection, user_id).await?;
+
+        Ok(matches!(two_factor_auth, Some(row) if row.enabled))
+    }
+
+    fn sign_two_factor_challenge(&self, user_id: &UserId) -> String {
+        let now_timestamp = chrono::Utc::now().timestamp() as usize;
+
+        self.token_service.sign_action_claims(ActionTokenClaims {
+            exp: now_timestamp + TWO_FACTOR_CHALLENGE_EXPIRES_AFTER.as_secs() as usize,
+            user_id: user_id.clone(),
+            actions: vec![Action::CompleteTwoFactorLogin],
+        })
+    }
+
+    async fn verify_two_factor_challenge(
+        &self,
+        connection: &mut MySqlConnection,
+        challenge_token: &str,
+        code: &str,
+        ip: Option<&str>,
+    ) -> Result<UserRow, TwoFactorLoginError> {
+        let claims = match self.token_service.verify_action_claims(challenge_token) {
+            Some(claims)
+                if claims
+                    .actions
+                    .iter()
+                    .any(|action| matches!(action, Action::CompleteTwoFactorLogin)) =>
+            {
+                claims
+            }
+            _ => return Err(TwoFactorLoginError::InvalidChallenge),
+        };
+
+        let user = match users::get_user_by_id(connection, &claims.user_id).await? {
+            Some(user) => user,
+            None => return Err(TwoFactorLoginError::InvalidChallenge),
+        };
+
+        let attempt = match self.login_throttle.begin_attempt(&user.mail, ip) {
+            Ok(attempt) => attempt,
+            Err(retry_after) => {
+                warn!(target: "audit", email = user.mail, ?ip, ?retry_after, "Two-factor attempt throttled");
+
+                return Err(TwoFactorLoginError::TooManyAttempts { retry_after });
+            }
+        };
+
+        let two_factor_auth =
+            match two_factor_auth::get_two_factor_auth(connection, &user.uid).await? {
+                Some(row) if row.enabled => row,
+                _ => return Err(TwoFactorLoginError::InvalidChallenge),
+            };
+
+        if !self
+            .verify_second_factor(connection, &two_factor_auth, code)
+            .await?
+        {
+            let failures = attempt.fail();
+
+            warn!(target: "audit", email = user.mail, ?ip, failures, "Failed two-factor attempt");
+
+            return Err(TwoFactorLoginError::InvalidCode);
+        }
+
+        attempt.succeed();
+
+        Ok(user)
+    }
+
+    /// Accepts either a code from the authenticator app or one of the unused recovery codes.
+    async fn verify_second_factor(
+        &self,
+        connection: &mut MySqlConnection,
+        two_factor_auth: &TwoFactorAuthRow,
+        code: &str,
+    ) -> Result<bool, RepositoryError> {
+        if self
+            .verify_totp_code(connection, two_factor_auth, code)
+            .await?
+        {
+            return Ok(true);
+        }
+
+        let code_hash = hash_opaque_token(&normalize_recovery_code(code));
+
+        two_factor_auth::use_recovery_code(connection, &two_factor_auth.user_id, &code_hash).await
+    }
+
+    async fn verify_totp_code(
+        &self,
+        connection: &mut MySqlConnection,
+        two_factor_auth: &TwoFactorAuthRow,
+        code: &str,
+    ) -> Result<bool, RepositoryError> {
+        let unix_time_secs = (now() / 1000) as u64;
+        let last_used_step = two_factor_auth.last_used_step.map(|step| step as u64);
+
+        match totp::verify_code(
+            &two_factor_auth.secret,
+            code,
+            unix_time_secs,
+            last_used_step,
+        ) {
+            Some(step) => {
+                two_factor_auth::update_last_used_step(
+                    connection,
+                    &two_factor_auth.user_id,
+                    step as i64,
+                )
+                .await
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        connection: &mut MySqlConnection,
+        user_id: &UserId,
+    ) -> Result<Vec<String>, RepositoryError> {
+        let recovery_codes: Vec<_> = (0..RECOVERY_CODES_COUNT)
+            .map(|_| {
+                let code = uuid::Uuid::new_v4().to_string().replace("-", "");
+                format!("{}-{}", &code[..5], &code[5..10])
+            })
+            .collect();
+        let code_hashes: Vec<_> = recovery_codes
+            .iter()
+            .map(|code| hash_opaque_token(&normalize_recovery_code(code)))
+            .collect();
+
+        two_factor_auth::replace_recovery_codes(connection, user_id, &code_hashes).await?;
+
+        Ok(recovery_codes)
+    }
+
     pub(crate) async fn legacy_signup(
         &self,
         email: &str,
@@ -189,7 +747,10 @@ impl AuthService {
 
         let old_password = user.password.clone().unwrap_or_default();
 
-        if !verify_password(&old_password, old_password_hash).expect("Unable to hash password") {
+        if !verify_password(&old_password, old_password_hash).unwrap_or_else(|error| {
+            warn!(?error, "Unable to verify password hash");
+            false
+        }) {
             return Err(LegacyResetPasswordError::PasswordHashIsOutOfDate);
         }
 
@@ -207,3 +768,154 @@ impl AuthService {
         Ok(())
     }
 }
+
+/// Marks the refresh token as used and issues the next one of its family. The family is revoked
+/// when the token turns out to be used already; the caller has to commit the revocation.
+async fn rotate_refresh_token(
+    connection: &mut MySqlConnection,
+    token_service: &AuthTokenService,
+    refresh_token: &str,
+) -> Result<AuthTokens, RefreshError> {
+    let token_hash = hash_opaque_token(refresh_token);
+    let token_row = match refresh_tokens::get_refresh_token_by_hash(connection, &token_hash).await?
+    {
+        Some(row) => row,
+        None => return Err(RefreshError::InvalidRefreshToken),
+    };
+
+    if token_row.revoked_at.is_some() {
+        return Err(RefreshError::InvalidRefreshToken);
+    }
+
+    if token_row.used_at.is_some()
+        || !refresh_tokens::mark_refresh_token_used(connection, &token_row.id).await?
+    {
+        warn!(
+            user_id = ?token_row.user_id,
+            family_id = %token_row.family_id,
+            "Refresh token reuse detected, revoking token family"
+        );
+        refresh_tokens::revoke_refresh_token_family(connection, &token_row.family_id).await?;
+
+        return Err(RefreshError::RefreshTokenReused);
+    }
+
+    if token_row.expires_at < Utc::now() {
+        return Err(RefreshError::RefreshTokenExpired);
+    }
+
+    Ok(issue_tokens(
+        connection,
+        token_service,
+        &token_row.user_id,
+        &token_row.family_id,
+    )
+    .await?)
+}
+
+async fn issue_tokens(
+    connection: &mut MySqlConnection,
+    token_service: &AuthTokenService,
+    user_id: &UserId,
+    family_id: &str,
+) -> Result<AuthTokens, RepositoryError> {
+    let refresh_token = generate_opaque_token();
+
+    refresh_tokens::create_refresh_token(
+        connection,
+        user_id,
+        family_id,
+        &hash_opaque_token(&refresh_token),
+        &(Utc::now() + chrono::Duration::days(REFRESH_TOKEN_EXPIRES_AFTER_DAYS)),
+    )
+    .await?;
+
+    Ok(AuthTokens {
+        access_token: token_service.sign_access_claims(user_id),
+        refresh_token,
+        expires_in: TOKEN_EXPIRES_AFTER.as_secs(),
+    })
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|char| char.is_ascii_alphanumeric())
+        .map(|char| char.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::db::test_database::{create_test_user, test_connection};
+
+    async fn login(
+        connection: &mut MySqlConnection,
+        token_service: &AuthTokenService,
+    ) -> AuthTokens {
+        let user_id = create_test_user(connection).await;
+        let family_id = uuid::Uuid::new_v4().to_string();
+
+        issue_tokens(connection, token_service, &user_id, &family_id)
+            .await
+            .unwrap()
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_rotates_refresh_token() {
+        let mut connection = test_connection().await;
+        let token_service = AuthTokenService::create("secret", "legacy-secret");
+        let tokens = login(&mut connection, &token_service).await;
+
+        let rotated = rotate_refresh_token(&mut connection, &token_service, &tokens.refresh_token)
+            .await
+            .unwrap();
+        assert_ne!(tokens.refresh_token, rotated.refresh_token);
+
+        let rotated_again =
+            rotate_refresh_token(&mut connection, &token_service, &rotated.refresh_token)
+                .await
+                .unwrap();
+        assert_ne!(rotated.refresh_token, rotated_again.refresh_token);
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_revokes_token_family_on_reuse() {
+        let mut connection = test_connection().await;
+        let token_service = AuthTokenService::create("secret", "legacy-secret");
+        let tokens = login(&mut connection, &token_service).await;
+        let other_tokens = login(&mut connection, &token_service).await;
+
+        let rotated = rotate_refresh_token(&mut connection, &token_service, &tokens.refresh_token)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            rotate_refresh_token(&mut connection, &token_service, &tokens.refresh_token).await,
+            Err(RefreshError::RefreshTokenReused)
+        ));
+        assert!(matches!(
+            rotate_refresh_token(&mut connection, &token_service, &rotated.refresh_token).await,
+            Err(RefreshError::InvalidRefreshToken)
+        ));
+        assert!(
+            rotate_refresh_token(&mut connection, &token_service, &other_tokens.refresh_token)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_rejects_unknown_refresh_token() {
+        let mut connection = test_connection().await;
+        let token_service = AuthTokenService::create("secret", "legacy-secret");
+
+        assert!(matches!(
+            rotate_refresh_token(&mut connection, &token_service, "unknown").await,
+            Err(RefreshError::InvalidRefreshToken)
+        ));
+    }
+}
@@ -1,12 +1,16 @@
-use super::auth_token_claims::AuthTokenClaims;
+use super::auth_token_claims::{AuthTokenClaim, AuthTokenClaims};
+use crate::data_structures::{StreamId, UserId};
 use crate::services::auth::action_token_claims::ActionTokenClaims;
 use crate::services::auth::legacy_auth_token_claims::LegacyAuthTokenClaims;
+use crate::services::auth::listen_token_claims::ListenTokenClaims;
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use std::collections::HashSet;
 use std::time::Duration;
 use tracing::warn;
 
-const TOKEN_EXPIRES_AFTER: Duration = Duration::from_secs(3600);
+pub(crate) const TOKEN_EXPIRES_AFTER: Duration = Duration::from_secs(3600);
+
+const ACCESS_TOKEN_METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
 
 #[derive(Clone)]
 pub(crate) struct AuthTokenService {
@@ -29,6 +33,23 @@ impl AuthTokenService {
         encode(&header, &claims, &key).expect("Unable to sign claims")
     }
 
+    /// Signs a short-lived token that grants the user access to all of their resources.
+    pub(crate) fn sign_access_claims(&self, user_id: &UserId) -> String {
+        let now_timestamp = chrono::Utc::now().timestamp() as usize;
+
+        self.sign_claims(AuthTokenClaims {
+            exp: now_timestamp + TOKEN_EXPIRES_AFTER.as_secs() as usize,
+            user_id: user_id.clone(),
+            claims: vec![AuthTokenClaim {
+                methods: ACCESS_TOKEN_METHODS
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+                uris: vec!["/".to_string()],
+            }],
+        })
+    }
+
     pub(crate) fn verify_claims(&self, token: &str) -> Option<AuthTokenClaims> {
         let key = DecodingKey::from_secret(self.secret_key.as_ref());
 
@@ -82,4 +103,29 @@ impl AuthTokenService {
             }
         }
     }
+
+    /// Signs a token that grants access to the audio stream of a private or unlisted channel.
+    pub(crate) fn sign_listen_claims(&self, stream_id: &StreamId, expires_in: &Duration) -> String {
+        let key = EncodingKey::from_secret(self.secret_key.as_ref());
+        let header = Header::new(Algorithm::HS256);
+        let now_timestamp = chrono::Utc::now().timestamp() as usize;
+        let claims = ListenTokenClaims {
+            exp: now_timestamp + expires_in.as_secs() as usize,
+            stream_id: stream_id.clone(),
+        };
+
+        encode(&header, &claims, &key).expect("Unable to sign listen claims")
+    }
+
+    pub(crate) fn verify_listen_claims(&self, token: &str) -> Option<ListenTokenClaims> {
+        let key = DecodingKey::from_secret(self.secret_key.as_ref());
+
+        match decode::<ListenTokenClaims>(token, &key, &Validation::default()) {
+            Ok(data) => Some(data.claims),
+            Err(error) => {
+                warn!("Unable to verify listen claims: {}", error);
+                None
+            }
+        }
+    }
 }
@@ -0,0 +1,8 @@
+use crate::data_structures::StreamId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ListenTokenClaims {
+    pub(crate) exp: usize,
+    pub(crate) stream_id: StreamId,
+}
@@ -0,0 +1,461 @@
+use crate::system::now;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Failures that are allowed before progressive delays kick in.
+const FREE_ATTEMPTS: u32 = 3;
+
+const BASE_DELAY: Duration = Duration::from_secs(1);
+
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Failures are forgotten when no new failure has been recorded for this long.
+const FAILURE_WINDOW: Duration = Duration::from_secs(900);
+
+const LOCKOUT_DURATION: Duration = Duration::from_secs(900);
+
+/// Failures after which logging in to the account from the same IP address is locked out.
+const LOGIN_LOCKOUT_THRESHOLD: u32 = 10;
+
+const IP_LOCKOUT_THRESHOLD: u32 = 50;
+
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+pub(crate) trait Clock: Send + Sync {
+    /// Current time in milliseconds.
+    fn now(&self) -> i64;
+}
+
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        now()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct FailedAttempts {
+    count: u32,
+    last_failure_at: i64,
+    locked_until: Option<i64>,
+    /// Attempts that have been allowed but haven't finished yet.
+    in_flight: u32,
+}
+
+impl FailedAttempts {
+    fn retry_after(&self, now: i64, key: &ThrottleKey) -> Option<Duration> {
+        if let Some(locked_until) = self.locked_until {
+            if locked_until > now {
+                return Some(Duration::from_millis((locked_until - now) as u64));
+            }
+        }
+
+        // Attempts in flight may still fail, so they count towards the limits until they finish.
+        let pending_count = self.count + self.in_flight;
+        let exceeds_limits = match key.lockout_threshold {
+            Some(lockout_threshold) => pending_count >= lockout_threshold,
+            None => false,
+        } || (key.progressive && pending_count >= FREE_ATTEMPTS);
+
+        if self.in_flight > 0 && exceeds_limits {
+            return Some(BASE_DELAY);
+        }
+
+        if !key.progressive || self.count < FREE_ATTEMPTS {
+            return None;
+        }
+
+        let exponent = (self.count - FREE_ATTEMPTS).min(16);
+        let delay = BASE_DELAY.saturating_mul(1 << exponent).min(MAX_DELAY);
+        let allowed_at = self.last_failure_at + delay.as_millis() as i64;
+
+        match allowed_at > now {
+            true => Some(Duration::from_millis((allowed_at - now) as u64)),
+            false => None,
+        }
+    }
+
+    fn is_stale(&self, now: i64) -> bool {
+        let is_locked = matches!(self.locked_until, Some(locked_until) if locked_until > now);
+
+        !is_locked && now - self.last_failure_at > FAILURE_WINDOW.as_millis() as i64
+    }
+
+    /// Forgets the failures once they are stale. Attempts in flight are kept.
+    fn forget_stale_failures(&mut self, now: i64) {
+        if self.is_stale(now) {
+            *self = FailedAttempts {
+                in_flight: self.in_flight,
+                ..FailedAttempts::default()
+            };
+        }
+    }
+}
+
+/// Key the failed attempts are counted by.
+struct ThrottleKey {
+    key: String,
+    /// Failures after which the key is locked out, if any.
+    lockout_threshold: Option<u32>,
+    /// Whether the delays grow with each failure after the free attempts.
+    progressive: bool,
+}
+
+/// Tracks failed login attempts and tells how long the client has to wait before the next
+/// attempt is allowed. Delays and the lockout of the account grow per client IP address and
+/// account, so neither clients sharing the address nor anyone guessing passwords from elsewhere
+/// can lock the user out. The IP address is only locked out after many failures.
+///
+/// The failures are kept in memory of the process: they are forgotten on restart and not shared
+/// between instances of the backend, each of which throttles the attempts it receives.
+#[derive(Clone)]
+pub(crate) struct LoginThrottle {
+    clock: Arc<dyn Clock>,
+    attempts: Arc<Mutex<HashMap<String, FailedAttempts>>>,
+}
+
+impl LoginThrottle {
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Reserves a login attempt, or returns the time the client has to wait before trying
+    /// to log in again. The attempt counts as in flight until it finishes, so parallel attempts
+    /// can't get past the delays.
+    pub(crate) fn begin_attempt(
+        &self,
+        email: &str,
+        ip: Option<&str>,
+    ) -> Result<LoginAttempt, Duration> {
+        let now = self.clock.now();
+        let keys = Self::keys(email, ip);
+        let mut attempts = self.attempts.lock().unwrap();
+
+        if attempts.len() > MAX_TRACKED_KEYS {
+            attempts.retain(|_, attempts| attempts.in_flight > 0 || !attempts.is_stale(now));
+        }
+
+        let retry_after = keys
+            .iter()
+            .filter_map(|key| {
+                let entry = attempts.get_mut(&key.key)?;
+                entry.forget_stale_failures(now);
+                entry.retry_after(now, key)
+            })
+            .max();
+
+        if let Some(retry_after) = retry_after {
+            return Err(retry_after);
+        }
+
+        for key in &keys {
+            attempts.entry(key.key.clone()).or_default().in_flight += 1;
+        }
+
+        Ok(LoginAttempt {
+            throttle: self.clone(),
+            keys,
+        })
+    }
+
+    fn record_failure(&self, keys: &[ThrottleKey]) -> u32 {
+        let now = self.clock.now();
+        let mut attempts = self.attempts.lock().unwrap();
+        let mut login_failures = 0;
+
+        for key in keys {
+            let entry = attempts.entry(key.key.clone()).or_default();
+
+            entry.forget_stale_failures(now);
+            entry.count += 1;
+            entry.last_failure_at = now;
+
+            if let Some(lockout_threshold) = key.lockout_threshold {
+                if entry.count.is_multiple_of(lockout_threshold) {
+                    entry.locked_until = Some(now + LOCKOUT_DURATION.as_millis() as i64);
+                }
+            }
+
+            if login_failures == 0 {
+                login_failures = entry.count;
+            }
+        }
+
+        login_failures
+    }
+
+    fn record_success(&self, keys: &[ThrottleKey]) {
+        let mut attempts = self.attempts.lock().unwrap();
+
+        for key in keys {
+            if key.key.starts_with("ip:") {
+                continue;
+            }
+
+            if let Some(entry) = attempts.get_mut(&key.key) {
+                *entry = FailedAttempts {
+                    in_flight: entry.in_flight,
+                    ..FailedAttempts::default()
+                };
+            }
+        }
+    }
+
+    fn release(&self, keys: &[ThrottleKey]) {
+        let now = self.clock.now();
+        let mut attempts = self.attempts.lock().unwrap();
+
+        for key in keys {
+            if let Some(entry) = attempts.get_mut(&key.key) {
+                entry.in_flight = entry.in_flight.saturating_sub(1);
+
+                if entry.in_flight == 0 && (entry.count == 0 || entry.is_stale(now)) {
+                    attempts.remove(&key.key);
+                }
+            }
+        }
+    }
+
+    fn keys(email: &str, ip: Option<&str>) -> Vec<ThrottleKey> {
+        let email = email.to_lowercase();
+        let mut keys = vec![ThrottleKey {
+            key: format!("login:{}:{}", ip.unwrap_or_default(), email),
+            lockout_threshold: Some(LOGIN_LOCKOUT_THRESHOLD),
+            progressive: true,
+        }];
+
+        if let Some(ip) = ip {
+            keys.push(ThrottleKey {
+                key: format!("ip:{}", ip),
+                lockout_threshold: Some(IP_LOCKOUT_THRESHOLD),
+                progressive: false,
+            });
+        }
+
+        keys
+    }
+}
+
+/// Login attempt reserved by [`LoginThrottle::begin_attempt`]. It stops being in flight when
+/// dropped, so an attempt that ends with an error counts neither as a failure nor a success.
+pub(crate) struct LoginAttempt {
+    throttle: LoginThrottle,
+    keys: Vec<ThrottleKey>,
+}
+
+impl LoginAttempt {
+    /// Records the failed attempt and returns the number of consecutive failures
+    /// for the account from the IP address.
+    pub(crate) fn fail(self) -> u32 {
+        self.throttle.record_failure(&self.keys)
+    }
+
+    /// Forgets failed attempts for the account. Failures recorded for the IP address are kept
+    /// so that a single successful login can't be used to keep guessing other accounts.
+    pub(crate) fn succeed(self) {
+        self.throttle.record_success(&self.keys);
+    }
+}
+
+impl Drop for LoginAttempt {
+    fn drop(&mut self) {
+        self.throttle.release(&self.keys);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    struct MockClock {
+        now: AtomicI64,
+    }
+
+    impl MockClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self {
+                now: AtomicI64::new(1_000_000),
+            })
+        }
+
+        fn advance(&self, duration: Duration) {
+            self.now
+                .fetch_add(duration.as_millis() as i64, Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> i64 {
+            self.now.load(Ordering::SeqCst)
+        }
+    }
+
+    const EMAIL: &str = "user@example.com";
+    const IP: Option<&str> = Some("10.0.0.1");
+
+    fn fail(throttle: &LoginThrottle, email: &str, ip: Option<&str>) -> u32 {
+        throttle.begin_attempt(email, ip).unwrap().fail()
+    }
+
+    fn retry_after(throttle: &LoginThrottle, email: &str, ip: Option<&str>) -> Option<Duration> {
+        throttle.begin_attempt(email, ip).err()
+    }
+
+    #[test]
+    fn test_allows_first_attempts_without_delay() {
+        let throttle = LoginThrottle::new(MockClock::new());
+
+        for _ in 0..FREE_ATTEMPTS - 1 {
+            fail(&throttle, EMAIL, IP);
+            assert_eq!(None, retry_after(&throttle, EMAIL, IP));
+        }
+    }
+
+    #[test]
+    fn test_delays_grow_progressively() {
+        let clock = MockClock::new();
+        let throttle = LoginThrottle::new(clock.clone());
+
+        for _ in 0..FREE_ATTEMPTS {
+            fail(&throttle, EMAIL, IP);
+        }
+
+        assert_eq!(
+            Some(Duration::from_secs(1)),
+            retry_after(&throttle, EMAIL, IP)
+        );
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(None, retry_after(&throttle, EMAIL, IP));
+
+        fail(&throttle, EMAIL, IP);
+        assert_eq!(
+            Some(Duration::from_secs(2)),
+            retry_after(&throttle, EMAIL, IP)
+        );
+
+        clock.advance(Duration::from_secs(2));
+        fail(&throttle, EMAIL, IP);
+        assert_eq!(
+            Some(Duration::from_secs(4)),
+            retry_after(&throttle, EMAIL, IP)
+        );
+    }
+
+    #[test]
+    fn test_limits_parallel_attempts() {
+        let throttle = LoginThrottle::new(MockClock::new());
+
+        let attempts = (0..FREE_ATTEMPTS)
+            .map(|_| throttle.begin_attempt(EMAIL, IP).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(Some(BASE_DELAY), retry_after(&throttle, EMAIL, IP));
+
+        drop(attempts);
+        assert_eq!(None, retry_after(&throttle, EMAIL, IP));
+    }
+
+    #[test]
+    fn test_limits_parallel_attempts_after_delay() {
+        let clock = MockClock::new();
+        let throttle = LoginThrottle::new(clock.clone());
+
+        for _ in 0..FREE_ATTEMPTS {
+            fail(&throttle, EMAIL, IP);
+        }
+
+        clock.advance(Duration::from_secs(1));
+
+        let attempt = throttle.begin_attempt(EMAIL, IP).unwrap();
+        assert_eq!(Some(BASE_DELAY), retry_after(&throttle, EMAIL, IP));
+
+        attempt.fail();
+        assert_eq!(
+            Some(Duration::from_secs(2)),
+            retry_after(&throttle, EMAIL, IP)
+        );
+    }
+
+    #[test]
+    fn test_locks_login_after_threshold() {
+        let clock = MockClock::new();
+        let throttle = LoginThrottle::new(clock.clone());
+
+        for _ in 0..LOGIN_LOCKOUT_THRESHOLD {
+            fail(&throttle, EMAIL, IP);
+            clock.advance(MAX_DELAY);
+        }
+
+        assert_eq!(
+            Some(LOCKOUT_DURATION - MAX_DELAY),
+            retry_after(&throttle, EMAIL, IP)
+        );
+        assert_eq!(None, retry_after(&throttle, EMAIL, Some("10.0.0.2")));
+
+        clock.advance(LOCKOUT_DURATION);
+        assert_eq!(None, retry_after(&throttle, EMAIL, IP));
+    }
+
+    #[test]
+    fn test_delays_are_not_shared_between_accounts_and_ips() {
+        let throttle = LoginThrottle::new(MockClock::new());
+
+        for _ in 0..FREE_ATTEMPTS {
+            fail(&throttle, EMAIL, IP);
+        }
+
+        assert!(retry_after(&throttle, EMAIL, IP).is_some());
+        assert_eq!(None, retry_after(&throttle, "another@example.com", IP));
+        assert_eq!(None, retry_after(&throttle, EMAIL, Some("10.0.0.2")));
+    }
+
+    #[test]
+    fn test_locks_ip_after_threshold() {
+        let throttle = LoginThrottle::new(MockClock::new());
+
+        for index in 0..IP_LOCKOUT_THRESHOLD {
+            fail(&throttle, &format!("user{}@example.com", index), IP);
+        }
+
+        assert!(retry_after(&throttle, "another@example.com", IP).is_some());
+        assert_eq!(None, retry_after(&throttle, "another@example.com", None));
+    }
+
+    #[test]
+    fn test_success_resets_account_failures() {
+        let clock = MockClock::new();
+        let throttle = LoginThrottle::new(clock.clone());
+
+        for _ in 0..FREE_ATTEMPTS {
+            fail(&throttle, EMAIL, None);
+        }
+
+        clock.advance(BASE_DELAY);
+        throttle.begin_attempt(EMAIL, None).unwrap().succeed();
+
+        assert_eq!(None, retry_after(&throttle, EMAIL, None));
+        assert!(throttle.attempts.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_forgets_failures_after_window() {
+        let clock = MockClock::new();
+        let throttle = LoginThrottle::new(clock.clone());
+
+        for _ in 0..FREE_ATTEMPTS {
+            fail(&throttle, EMAIL, IP);
+        }
+
+        clock.advance(FAILURE_WINDOW + Duration::from_secs(1));
+
+        assert_eq!(1, fail(&throttle, EMAIL, IP));
+        assert_eq!(None, retry_after(&throttle, EMAIL, IP));
+    }
+}
@@ -4,13 +4,18 @@ mod auth_token_claims;
 mod auth_token_claims_ext;
 mod auth_token_service;
 mod legacy_auth_token_claims;
+mod listen_token_claims;
+mod login_throttle;
+mod totp;
 
-pub(crate) use action_token_claims::Action;
+pub(crate) use action_token_claims::{Action, ActionTokenClaims};
 pub(crate) use auth_service::{
-    AuthService, LegacyLoginError, LegacyLogoutError, LegacyResetPasswordError, LegacySignupError,
-    LegacySignupResult,
+    AuthService, LegacyLoginError, LegacyLoginResult, LegacyLogoutError, LegacyResetPasswordError,
+    LegacySignupError, LegacySignupResult, LoginError, LoginResult, LogoutError, RefreshError,
+    TwoFactorError, TwoFactorLoginError,
 };
 pub(crate) use auth_token_claims::{AuthTokenClaim, AuthTokenClaims};
 pub(crate) use auth_token_claims_ext::IsActionAllowed;
 pub(crate) use auth_token_service::AuthTokenService;
 pub(crate) use legacy_auth_token_claims::{LegacyAuthTokenClaims, LegacyAuthTokenData};
+pub(crate) use login_throttle::{LoginThrottle, SystemClock};
@@ -0,0 +1,191 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+const SECRET_LENGTH: usize = 20;
+
+const TIME_STEP_SECS: u64 = 30;
+
+const CODE_DIGITS: u32 = 6;
+
+/// Number of time steps before and after the current one in which a code is still accepted
+/// to tolerate clock drift between the server and the authenticator app.
+const ALLOWED_DRIFT_STEPS: u64 = 1;
+
+pub(crate) fn generate_secret() -> String {
+    let secret: [u8; SECRET_LENGTH] = rand::random();
+
+    base32_encode(&secret)
+}
+
+pub(crate) fn otpauth_uri(issuer: &str, account_name: &str, secret: &str) -> String {
+    let issuer = urlencoding::encode(issuer);
+    let account_name = urlencoding::encode(account_name);
+
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer, account_name, secret, issuer, CODE_DIGITS, TIME_STEP_SECS
+    )
+}
+
+/// Verifies the code against the base32 encoded secret and returns the time step it was generated for.
+/// Codes from the `last_used_step` or earlier are rejected so that a code can't be replayed.
+pub(crate) fn verify_code(
+    secret: &str,
+    code: &str,
+    unix_time_secs: u64,
+    last_used_step: Option<u64>,
+) -> Option<u64> {
+    let key = base32_decode(secret)?;
+    let code = code.trim();
+
+    if code.len() != CODE_DIGITS as usize || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let code = code.parse::<u32>().ok()?;
+    let current_step = unix_time_secs / TIME_STEP_SECS;
+
+    (current_step.saturating_sub(ALLOWED_DRIFT_STEPS)..=current_step + ALLOWED_DRIFT_STEPS)
+        .filter(|step| last_used_step.is_none_or(|last_used_step| *step > last_used_step))
+        .find(|step| generate_code(&key, *step) == code)
+}
+
+fn generate_code(key: &[u8], step: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    binary % 10u32.pow(CODE_DIGITS)
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for char in encoded.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|c| *c == char.to_ascii_uppercase())? as u32;
+
+        buffer = (buffer << 5) | value;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test secret from RFC 6238, Appendix B.
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_generate_code_matches_rfc_test_vectors() {
+        assert_eq!(287082, generate_code(RFC_SECRET, 59 / TIME_STEP_SECS));
+        assert_eq!(
+            81804,
+            generate_code(RFC_SECRET, 1111111109 / TIME_STEP_SECS)
+        );
+        assert_eq!(
+            50471,
+            generate_code(RFC_SECRET, 1111111111 / TIME_STEP_SECS)
+        );
+        assert_eq!(5924, generate_code(RFC_SECRET, 1234567890 / TIME_STEP_SECS));
+        assert_eq!(
+            279037,
+            generate_code(RFC_SECRET, 2000000000 / TIME_STEP_SECS)
+        );
+    }
+
+    #[test]
+    fn test_base32_roundtrip() {
+        let encoded = base32_encode(RFC_SECRET);
+
+        assert_eq!("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", encoded);
+        assert_eq!(Some(RFC_SECRET.to_vec()), base32_decode(&encoded));
+    }
+
+    #[test]
+    fn test_verify_code_accepts_adjacent_steps() {
+        let secret = base32_encode(RFC_SECRET);
+
+        assert_eq!(
+            Some(1111111109 / TIME_STEP_SECS),
+            verify_code(&secret, "081804", 1111111109, None)
+        );
+        assert_eq!(
+            Some(1111111109 / TIME_STEP_SECS),
+            verify_code(&secret, "081804", 1111111109 + TIME_STEP_SECS, None)
+        );
+        assert_eq!(
+            None,
+            verify_code(&secret, "081804", 1111111109 + 3 * TIME_STEP_SECS, None)
+        );
+    }
+
+    #[test]
+    fn test_verify_code_rejects_replayed_code() {
+        let secret = base32_encode(RFC_SECRET);
+        let step = 1111111109 / TIME_STEP_SECS;
+
+        assert_eq!(None, verify_code(&secret, "081804", 1111111109, Some(step)));
+    }
+
+    #[test]
+    fn test_verify_code_rejects_malformed_input() {
+        assert_eq!(None, verify_code("not base32!", "123456", 0, None));
+        assert_eq!(
+            None,
+            verify_code(&base32_encode(RFC_SECRET), "abcdef", 0, None)
+        );
+    }
+
+    #[test]
+    fn test_verify_code_requires_six_digits() {
+        let secret = base32_encode(RFC_SECRET);
+
+        for code in ["81804", "+81804", "0081804", "08 1804"] {
+            assert_eq!(None, verify_code(&secret, code, 1111111109, None));
+        }
+    }
+}
@@ -0,0 +1,468 @@
+use crate::data_structures::{StreamAccess, StreamId, StreamRef, UserId};
+use crate::mysql_client::{MySqlClient, MySqlConnection};
+use crate::services::image_utils::{get_background_color, resize_to_square_png};
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::streams::StreamFields;
+use crate::storage::db::repositories::{
+    categories, outgoing_streams, streams, user_stream_tracks, StreamRow,
+};
+use crate::storage::fs::FileSystem;
+use crate::utils::{is_valid_permalink, slugify};
+use tracing::{error, warn};
+
+const STREAM_NAME_MIN_LENGTH: usize = 3;
+
+const STREAM_NAME_MAX_LENGTH: usize = 32;
+
+const STREAM_INFO_MAX_LENGTH: usize = 4096;
+
+const STREAM_HASHTAGS_MAX_LENGTH: usize = 4096;
+
+const COVER_SIZE: u32 = 500;
+
+const DEFAULT_PERMALINK: &str = "stream";
+
+const MAX_PERMALINK_SUFFIX: usize = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ChannelError {
+    #[error("Stream not found")]
+    StreamNotFound,
+    #[error("No permission to access this stream")]
+    Forbidden,
+    #[error("Invalid name")]
+    InvalidName,
+    #[error("Invalid info")]
+    InvalidInfo,
+    #[error("Invalid hashtags")]
+    InvalidHashtags,
+    #[error("Invalid permalink")]
+    InvalidPermalink,
+    #[error("Permalink is already taken")]
+    NonUniquePermalink,
+    #[error("Invalid category")]
+    InvalidCategory,
+    #[error("Invalid image")]
+    InvalidImage,
+    #[error(transparent)]
+    FileSystemError(#[from] std::io::Error),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Channel fields to set. Fields set to `None` are left unchanged on update
+/// and take default values on create.
+#[derive(Default)]
+pub(crate) struct ChannelUpdate {
+    pub(crate) name: Option<String>,
+    pub(crate) permalink: Option<String>,
+    pub(crate) info: Option<String>,
+    pub(crate) hashtags: Option<String>,
+    pub(crate) category: Option<Option<i32>>,
+    pub(crate) access: Option<StreamAccess>,
+}
+
+#[derive(Clone)]
+pub(crate) struct ChannelService {
+    mysql_client: MySqlClient,
+}
+
+impl ChannelService {
+    pub(crate) fn new(mysql_client: MySqlClient) -> Self {
+        Self { mysql_client }
+    }
+
+    pub(crate) async fn get_user_channel(
+        &self,
+        user_id: &UserId,
+        stream_ref: &StreamRef,
+    ) -> Result<StreamRow, ChannelError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        get_user_stream(&mut connection, user_id, stream_ref).await
+    }
+
+    pub(crate) async fn create_channel(
+        &self,
+        user_id: &UserId,
+        update: ChannelUpdate,
+    ) -> Result<StreamRow, ChannelError> {
+        let name = match update.name {
+            Some(name) => name.trim().to_string(),
+            None => return Err(ChannelError::InvalidName),
+        };
+        let info = update.info.unwrap_or_default();
+        let hashtags = normalize_hashtags(&update.hashtags.unwrap_or_default());
+        let category = update.category.flatten();
+
+        validate_fields(&name, &info, &hashtags)?;
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        validate_category(&mut connection, &category).await?;
+
+        let permalink = match update.permalink {
+            Some(permalink) => {
+                validate_permalink(&mut connection, &permalink, None).await?;
+                permalink
+            }
+            None => generate_permalink(&mut connection, &name).await?,
+        };
+
+        let stream_id = streams::create_stream(
+            &mut connection,
+            user_id,
+            &StreamFields {
+                name: &name,
+                permalink: &permalink,
+                info: &info,
+                hashtags: &hashtags,
+                category,
+                access: update.access.unwrap_or(StreamAccess::Public),
+            },
+        )
+        .await
+        .map_err(map_permalink_conflict)?;
+
+        let stream = get_user_stream(&mut connection, user_id, &StreamRef::Id(stream_id)).await?;
+
+        connection.commit().await?;
+
+        Ok(stream)
+    }
+
+    pub(crate) async fn update_channel(
+        &self,
+        user_id: &UserId,
+        stream_ref: &StreamRef,
+        update: ChannelUpdate,
+    ) -> Result<StreamRow, ChannelError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let stream = get_user_stream(&mut connection, user_id, stream_ref).await?;
+        let stream_id = stream.sid.clone();
+
+        let name = update
+            .name
+            .map(|name| name.trim().to_string())
+            .unwrap_or(stream.name);
+        let info = update.info.unwrap_or(stream.info);
+        let hashtags = update
+            .hashtags
+            .map(|hashtags| normalize_hashtags(&hashtags))
+            .unwrap_or(stream.hashtags);
+        let category = update.category.unwrap_or(stream.category);
+        let access = update
+            .access
+            .unwrap_or_else(|| StreamAccess::from(stream.access.as_str()));
+
+        validate_fields(&name, &info, &hashtags)?;
+        validate_category(&mut connection, &category).await?;
+
+        let permalink = match update.permalink {
+            Some(permalink) => {
+                validate_permalink(&mut connection, &permalink, Some(&stream_id)).await?;
+                permalink
+            }
+            None => match stream.permalink {
+                Some(permalink) => permalink,
+                None => generate_permalink(&mut connection, &name).await?,
+            },
+        };
+
+        streams::update_stream(
+            &mut connection,
+            &stream_id,
+            &StreamFields {
+                name: &name,
+                permalink: &permalink,
+                info: &info,
+                hashtags: &hashtags,
+                category,
+                access,
+            },
+        )
+        .await
+        .map_err(map_permalink_conflict)?;
+
+        let stream = get_user_stream(&mut connection, user_id, &StreamRef::Id(stream_id)).await?;
+
+        connection.commit().await?;
+
+        Ok(stream)
+    }
+
+    /// Deletes the channel together with its playlist. Playback and outgoing streams
+    /// of the channel should be stopped by the caller.
+    pub(crate) async fn delete_channel<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        stream_ref: &StreamRef,
+        file_system: &FS,
+    ) -> Result<(), ChannelError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let stream = get_user_stream(&mut connection, user_id, stream_ref).await?;
+        let stream_id = stream.sid.clone();
+
+        user_stream_tracks::delete_stream_links(&mut connection, &stream_id).await?;
+        outgoing_streams::delete_channel_outgoing_streams(&mut connection, &stream_id).await?;
+        streams::delete_stream(&mut connection, &stream_id).await?;
+
+        connection.commit().await?;
+
+        if let Some(cover) = stream.cover.filter(|cover| !cover.is_empty()) {
+            delete_cover_file(file_system, &cover).await;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn update_cover<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        stream_ref: &StreamRef,
+        image_data: Vec<u8>,
+        file_system: &FS,
+    ) -> Result<StreamRow, ChannelError> {
+        let (cover_data, cover_background) = actix_web::web::block(move || {
+            Ok::<_, image::ImageError>((
+                resize_to_square_png(&image_data, COVER_SIZE)?,
+                get_background_color(&image_data)?,
+            ))
+        })
+        .await
+        .map_err(|error| {
+            error!(?error, "Unable to run cover resizing task");
+            ChannelError::InvalidImage
+        })?
+        .map_err(|error| {
+            warn!(?error, "Unable to process cover image");
+            ChannelError::InvalidImage
+        })?;
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let stream = get_user_stream(&mut connection, user_id, stream_ref).await?;
+        let stream_id = stream.sid.clone();
+
+        let cover = format!(
+            "stream{:05}_{}.png",
+            *stream_id,
+            uuid::Uuid::new_v4().to_simple()
+        );
+
+        file_system
+            .put_file(&format!("covers/{}", cover), cover_data)
+            .await?;
+
+        streams::update_stream_cover(
+            &mut connection,
+            &stream_id,
+            Some(&cover),
+            Some(&cover_background),
+        )
+        .await?;
+
+        let updated_stream =
+            get_user_stream(&mut connection, user_id, &StreamRef::Id(stream_id)).await?;
+
+        connection.commit().await?;
+
+        if let Some(previous_cover) = stream.cover.filter(|cover| !cover.is_empty()) {
+            delete_cover_file(file_system, &previous_cover).await;
+        }
+
+        Ok(updated_stream)
+    }
+
+    pub(crate) async fn delete_cover<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        stream_ref: &StreamRef,
+        file_system: &FS,
+    ) -> Result<(), ChannelError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let stream = get_user_stream(&mut connection, user_id, stream_ref).await?;
+        let stream_id = stream.sid.clone();
+
+        streams::update_stream_cover(&mut connection, &stream_id, None, None).await?;
+
+        connection.commit().await?;
+
+        if let Some(cover) = stream.cover.filter(|cover| !cover.is_empty()) {
+            delete_cover_file(file_system, &cover).await;
+        }
+
+        Ok(())
+    }
+}
+
+async fn get_user_stream(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    stream_ref: &StreamRef,
+) -> Result<StreamRow, ChannelError> {
+    match streams::get_single_stream_by_ref(connection, stream_ref).await? {
+        Some(stream) if &stream.uid == user_id => Ok(stream),
+        Some(_) => Err(ChannelError::Forbidden),
+        None => Err(ChannelError::StreamNotFound),
+    }
+}
+
+fn validate_fields(name: &str, info: &str, hashtags: &str) -> Result<(), ChannelError> {
+    let name_length = name.chars().count();
+
+    if !(STREAM_NAME_MIN_LENGTH..=STREAM_NAME_MAX_LENGTH).contains(&name_length) {
+        return Err(ChannelError::InvalidName);
+    }
+
+    if info.chars().count() > STREAM_INFO_MAX_LENGTH {
+        return Err(ChannelError::InvalidInfo);
+    }
+
+    if hashtags.chars().count() > STREAM_HASHTAGS_MAX_LENGTH {
+        return Err(ChannelError::InvalidHashtags);
+    }
+
+    Ok(())
+}
+
+async fn validate_category(
+    connection: &mut MySqlConnection,
+    category: &Option<i32>,
+) -> Result<(), ChannelError> {
+    if let Some(category_id) = category {
+        if categories::get_category_by_id(connection, category_id)
+            .await?
+            .is_none()
+        {
+            return Err(ChannelError::InvalidCategory);
+        }
+    }
+
+    Ok(())
+}
+
+async fn validate_permalink(
+    connection: &mut MySqlConnection,
+    permalink: &str,
+    stream_id: Option<&StreamId>,
+) -> Result<(), ChannelError> {
+    if !is_valid_permalink(permalink) {
+        return Err(ChannelError::InvalidPermalink);
+    }
+
+    if streams::is_stream_permalink_taken(connection, permalink, stream_id).await? {
+        return Err(ChannelError::NonUniquePermalink);
+    }
+
+    Ok(())
+}
+
+/// Permalinks are checked before the stream is saved, but a stream saved concurrently can still
+/// take the permalink first, which the unique key on permalinks rejects.
+fn map_permalink_conflict(error: RepositoryError) -> ChannelError {
+    match &error {
+        RepositoryError::DatabaseError(sqlx::Error::Database(database_error))
+            if database_error.is_unique_violation() =>
+        {
+            ChannelError::NonUniquePermalink
+        }
+        _ => ChannelError::RepositoryError(error),
+    }
+}
+
+/// Makes the base of the generated permalink out of the channel name. Names without latin
+/// letters or digits fall back to the default permalink, and names of only digits get it
+/// as a prefix.
+fn permalink_base(name: &str) -> String {
+    let mut base = match slugify(name) {
+        slug if slug.is_empty() => DEFAULT_PERMALINK.to_string(),
+        slug if is_valid_permalink(&slug) => slug,
+        slug => format!("{}-{}", DEFAULT_PERMALINK, slug),
+    };
+
+    base.truncate(240);
+
+    base
+}
+
+/// Generates a unique permalink from the channel name by adding a numeric suffix if needed.
+async fn generate_permalink(
+    connection: &mut MySqlConnection,
+    name: &str,
+) -> Result<String, ChannelError> {
+    let base = permalink_base(name);
+
+    if !streams::is_stream_permalink_taken(connection, &base, None).await? {
+        return Ok(base);
+    }
+
+    for suffix in 1..=MAX_PERMALINK_SUFFIX {
+        let permalink = format!("{}-{}", base, suffix);
+
+        if !streams::is_stream_permalink_taken(connection, &permalink, None).await? {
+            return Ok(permalink);
+        }
+    }
+
+    Err(ChannelError::NonUniquePermalink)
+}
+
+/// Trims hashtags and removes empty and duplicate ones.
+fn normalize_hashtags(hashtags: &str) -> String {
+    let mut normalized: Vec<&str> = vec![];
+
+    for hashtag in hashtags.split(',').map(str::trim) {
+        if !hashtag.is_empty() && !normalized.contains(&hashtag) {
+            normalized.push(hashtag);
+        }
+    }
+
+    normalized.join(", ")
+}
+
+async fn delete_cover_file<FS: FileSystem>(file_system: &FS, cover: &str) {
+    if let Err(error) = file_system.delete_file(&format!("covers/{}", cover)).await {
+        warn!(?error, "Unable to delete cover file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizes_hashtags() {
+        assert_eq!("rock, jazz", normalize_hashtags(" rock,,jazz , rock,"));
+        assert_eq!("", normalize_hashtags(" , "));
+    }
+
+    #[test]
+    fn test_makes_permalink_base() {
+        assert_eq!("jazz-radio", permalink_base("Jazz Radio"));
+        assert_eq!("stream", permalink_base("Радио"));
+        assert_eq!("stream-2024", permalink_base("2024"));
+    }
+
+    #[test]
+    fn test_validates_fields() {
+        assert!(validate_fields("My radio", "", "").is_ok());
+        assert!(matches!(
+            validate_fields("My", "", ""),
+            Err(ChannelError::InvalidName)
+        ));
+        assert!(matches!(
+            validate_fields(&"a".repeat(STREAM_NAME_MAX_LENGTH + 1), "", ""),
+            Err(ChannelError::InvalidName)
+        ));
+        assert!(matches!(
+            validate_fields("My radio", &"a".repeat(STREAM_INFO_MAX_LENGTH + 1), ""),
+            Err(ChannelError::InvalidInfo)
+        ));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::data_structures::{TrackId, UserId};
+use crate::mysql_client::{MySqlClient, MySqlConnection};
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::{colors, user_tracks, ColorRow, ColorStatsRow};
+use crate::utils::dedup_track_ids;
+
+const COLOR_NAME_MAX_LENGTH: usize = 32;
+
+const MAX_USER_COLORS: i64 = 50;
+
+const MAX_RECOLORED_TRACKS: usize = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ColorLabelError {
+    #[error("Color not found")]
+    ColorNotFound,
+    #[error("Preset colors can't be changed")]
+    ColorNotEditable,
+    #[error("Invalid color name")]
+    InvalidName,
+    #[error("Invalid color code")]
+    InvalidCode,
+    #[error("Too many colors")]
+    TooManyColors,
+    #[error("Track not found")]
+    TrackNotFound,
+    #[error("No tracks selected")]
+    NoTracks,
+    #[error("Too many tracks")]
+    TooManyTracks,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Color label with the number and the total duration of the user's tracks having it.
+pub(crate) struct ColorLabel {
+    pub(crate) id: i32,
+    pub(crate) name: String,
+    pub(crate) code: Option<String>,
+    pub(crate) is_preset: bool,
+    pub(crate) tracks_count: i64,
+    pub(crate) tracks_duration: i64,
+}
+
+impl ColorLabel {
+    fn new(row: ColorRow, stats: &[ColorStatsRow]) -> Self {
+        let stats = stats.iter().find(|stats| stats.color == row.color_id);
+
+        Self {
+            id: row.color_id,
+            name: row.color_name,
+            code: row.color_code,
+            is_preset: row.user_id.is_none(),
+            tracks_count: stats.map_or(0, |stats| stats.tracks_count),
+            tracks_duration: stats.map_or(0, |stats| stats.tracks_duration),
+        }
+    }
+}
+
+/// Changes of the color label: `None` keeps the current value.
+#[derive(Debug, Default)]
+pub(crate) struct ColorLabelUpdate {
+    pub(crate) name: Option<String>,
+    pub(crate) code: Option<String>,
+}
+
+fn normalize_color_name(name: &str) -> Result<String, ColorLabelError> {
+    let name = name.trim();
+
+    if name.is_empty() || name.chars().count() > COLOR_NAME_MAX_LENGTH {
+        return Err(ColorLabelError::InvalidName);
+    }
+
+    Ok(name.to_string())
+}
+
+/// Color code must be in `#rrggbb` format.
+fn normalize_color_code(code: &str) -> Result<String, ColorLabelError> {
+    let code = code.trim();
+
+    match code.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(code.to_lowercase())
+        }
+        _ => Err(ColorLabelError::InvalidCode),
+    }
+}
+
+async fn get_editable_color(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    color_id: i32,
+) -> Result<ColorRow, ColorLabelError> {
+    match colors::get_user_color(connection, user_id, color_id).await? {
+        Some(row) if row.user_id.is_none() => Err(ColorLabelError::ColorNotEditable),
+        Some(row) => Ok(row),
+        None => Err(ColorLabelError::ColorNotFound),
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ColorLabelService {
+    mysql_client: MySqlClient,
+}
+
+impl ColorLabelService {
+    pub(crate) fn new(mysql_client: MySqlClient) -> Self {
+        Self { mysql_client }
+    }
+
+    /// Returns the preset colors and the user's own color labels.
+    pub(crate) async fn get_colors(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<ColorLabel>, ColorLabelError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let rows = colors::get_user_colors(&mut connection, user_id).await?;
+        let stats = colors::get_user_color_stats(&mut connection, user_id).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ColorLabel::new(row, &stats))
+            .collect())
+    }
+
+    pub(crate) async fn create_color(
+        &self,
+        user_id: &UserId,
+        name: &str,
+        code: &str,
+    ) -> Result<ColorLabel, ColorLabelError> {
+        let name = normalize_color_name(name)?;
+        let code = normalize_color_code(code)?;
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        colors::lock_user_colors(&mut connection, user_id).await?;
+
+        let user_colors_count = colors::get_user_colors_count(&mut connection, user_id).await?;
+
+        if user_colors_count >= MAX_USER_COLORS {
+            return Err(ColorLabelError::TooManyColors);
+        }
+
+        let color_id = colors::create_user_color(&mut connection, user_id, &name, &code).await?;
+
+        connection.commit().await?;
+
+        Ok(ColorLabel {
+            id: color_id,
+            name,
+            code: Some(code),
+            is_preset: false,
+            tracks_count: 0,
+            tracks_duration: 0,
+        })
+    }
+
+    pub(crate) async fn update_color(
+        &self,
+        user_id: &UserId,
+        color_id: i32,
+        update: &ColorLabelUpdate,
+    ) -> Result<ColorLabel, ColorLabelError> {
+        let name = update
+            .name
+            .as_deref()
+            .map(normalize_color_name)
+            .transpose()?;
+        let code = update
+            .code
+            .as_deref()
+            .map(normalize_color_code)
+            .transpose()?;
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let mut row = get_editable_color(&mut connection, user_id, color_id).await?;
+
+        if let Some(name) = name {
+            row.color_name = name;
+        }
+
+        if let Some(code) = code {
+            row.color_code = Some(code);
+        }
+
+        colors::update_user_color(
+            &mut connection,
+            user_id,
+            color_id,
+            &row.color_name,
+            row.color_code.as_deref().unwrap_or_default(),
+        )
+        .await?;
+
+        let stats = colors::get_user_color_stats(&mut connection, user_id).await?;
+
+        connection.commit().await?;
+
+        Ok(ColorLabel::new(row, &stats))
+    }
+
+    /// Deletes the color label; the tracks having it lose their color.
+    pub(crate) async fn delete_color(
+        &self,
+        user_id: &UserId,
+        color_id: i32,
+    ) -> Result<(), ColorLabelError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        get_editable_color(&mut connection, user_id, color_id).await?;
+
+        colors::delete_user_color(&mut connection, user_id, color_id).await?;
+
+        connection.commit().await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn set_tracks_color(
+        &self,
+        user_id: &UserId,
+        track_ids: &[TrackId],
+        color_id: i32,
+    ) -> Result<(), ColorLabelError> {
+        let unique_track_ids = dedup_track_ids(track_ids);
+
+        if unique_track_ids.is_empty() {
+            return Err(ColorLabelError::NoTracks);
+        }
+
+        if unique_track_ids.len() > MAX_RECOLORED_TRACKS {
+            return Err(ColorLabelError::TooManyTracks);
+        }
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        if colors::get_user_color(&mut connection, user_id, color_id)
+            .await?
+            .is_none()
+        {
+            return Err(ColorLabelError::ColorNotFound);
+        }
+
+        let rows = user_tracks::get_user_tracks_by_ids(&mut connection, user_id, &unique_track_ids)
+            .await?;
+
+        if rows.len() != unique_track_ids.len() {
+            return Err(ColorLabelError::TrackNotFound);
+        }
+
+        user_tracks::update_user_tracks_color(
+            &mut connection,
+            user_id,
+            &unique_track_ids,
+            color_id,
+        )
+        .await?;
+
+        connection.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizes_color_name() {
+        assert_eq!("Chill", normalize_color_name("  Chill ").unwrap());
+        assert!(normalize_color_name("   ").is_err());
+        assert!(normalize_color_name(&"x".repeat(COLOR_NAME_MAX_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_normalizes_color_code() {
+        assert_eq!("#d9a0ff", normalize_color_code("#D9A0FF").unwrap());
+        assert!(normalize_color_code("d9a0ff").is_err());
+        assert!(normalize_color_code("#d9a0f").is_err());
+        assert!(normalize_color_code("#d9a0fg").is_err());
+    }
+}
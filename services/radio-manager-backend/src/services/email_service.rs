@@ -0,0 +1,68 @@
+use crate::config::SmtpConfig;
+use lettre::address::AddressError;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum EmailServiceError {
+    #[error("Invalid email address: {0}")]
+    InvalidAddress(#[from] AddressError),
+    #[error("Unable to build email: {0}")]
+    MessageError(#[from] lettre::error::Error),
+    #[error("Unable to send email: {0}")]
+    SmtpError(#[from] lettre::transport::smtp::Error),
+    #[error("Sending emails is not configured")]
+    NotConfigured,
+}
+
+#[derive(Clone)]
+pub(crate) struct EmailService {
+    smtp_config: Option<SmtpConfig>,
+}
+
+impl EmailService {
+    /// Emails can't be sent if SMTP is not configured.
+    pub(crate) fn create(smtp_config: Option<&SmtpConfig>) -> Self {
+        Self {
+            smtp_config: smtp_config.cloned(),
+        }
+    }
+
+    pub(crate) async fn send(
+        &self,
+        address: &str,
+        subject: &str,
+        body: &str,
+    ) -> Result<(), EmailServiceError> {
+        let smtp_config = self
+            .smtp_config
+            .as_ref()
+            .ok_or(EmailServiceError::NotConfigured)?;
+
+        let sender = Mailbox::new(
+            Some(smtp_config.sender_name.clone()),
+            smtp_config.sender_email.parse()?,
+        );
+
+        let message = Message::builder()
+            .from(sender)
+            .to(Mailbox::new(None, address.parse()?))
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())?;
+
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp_config.host)?
+            .port(smtp_config.port)
+            .credentials(Credentials::new(
+                smtp_config.user.clone(),
+                smtp_config.password.clone(),
+            ))
+            .build();
+
+        transport.send(message).await?;
+
+        Ok(())
+    }
+}
@@ -2,6 +2,8 @@ use async_process::Command;
 use bytes::Bytes;
 use futures::channel::mpsc::Sender;
 use futures::{AsyncReadExt, SinkExt};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::process::Stdio;
 use std::time::Duration;
 
@@ -105,3 +107,295 @@ pub(crate) async fn transcode_audio_file(
 
     Ok(())
 }
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ProbeAudioFileError {
+    #[error("Failed to spawn ffprobe process: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("ffprobe exited with non-zero status")]
+    NonZeroExitStatus,
+    #[error("Unable to parse ffprobe output: {0}")]
+    InvalidOutput(#[from] serde_json::Error),
+    #[error("No audio stream found")]
+    NoAudioStream,
+    #[error("Unknown duration")]
+    UnknownDuration,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct AudioFileMetadata {
+    pub(crate) artist: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) track_number: Option<String>,
+    pub(crate) genre: Option<String>,
+    pub(crate) date: Option<String>,
+    pub(crate) duration: Duration,
+}
+
+#[derive(Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeSection>,
+    format: Option<FfprobeSection>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeSection {
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+fn parse_ffprobe_output(output: &[u8]) -> Result<AudioFileMetadata, ProbeAudioFileError> {
+    let output: FfprobeOutput = serde_json::from_slice(output)?;
+
+    let stream = output
+        .streams
+        .first()
+        .ok_or(ProbeAudioFileError::NoAudioStream)?;
+
+    // Containers like MP3 keep tags in the format section, while Ogg keeps them in the stream.
+    let sections: Vec<_> = output.format.iter().chain(Some(stream)).collect();
+
+    let duration = sections
+        .iter()
+        .filter_map(|section| section.duration.as_ref()?.parse::<f64>().ok())
+        .find(|duration| duration.is_finite() && *duration > 0.0)
+        .ok_or(ProbeAudioFileError::UnknownDuration)?;
+
+    let get_tag = |name: &str| {
+        sections.iter().find_map(|section| {
+            section
+                .tags
+                .iter()
+                .find(|(key, value)| key.eq_ignore_ascii_case(name) && !value.trim().is_empty())
+                .map(|(_, value)| value.trim().to_string())
+        })
+    };
+
+    Ok(AudioFileMetadata {
+        artist: get_tag("artist"),
+        title: get_tag("title"),
+        album: get_tag("album"),
+        track_number: get_tag("track"),
+        genre: get_tag("genre"),
+        date: get_tag("date"),
+        duration: Duration::from_secs_f64(duration),
+    })
+}
+
+/// Reads tags and duration of the audio file using ffprobe.
+pub(crate) async fn probe_audio_file(
+    path_to_ffprobe: &str,
+    input_file: &str,
+) -> Result<AudioFileMetadata, ProbeAudioFileError> {
+    let output = Command::new(path_to_ffprobe)
+        .arg("-v")
+        .arg("quiet")
+        .arg("-print_format")
+        .arg("json")
+        .arg("-show_format")
+        .arg("-show_streams")
+        .arg("-select_streams")
+        .arg("a")
+        .arg(input_file)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(ProbeAudioFileError::NonZeroExitStatus);
+    }
+
+    parse_ffprobe_output(&output.stdout)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum WriteAudioTagsError {
+    #[error("Failed to spawn ffmpeg process: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("ffmpeg exited with non-zero status")]
+    NonZeroExitStatus,
+}
+
+pub(crate) struct AudioTags<'a> {
+    pub(crate) artist: &'a str,
+    pub(crate) title: &'a str,
+    pub(crate) album: &'a str,
+    pub(crate) track_number: &'a str,
+    pub(crate) genre: &'a str,
+    pub(crate) date: &'a str,
+}
+
+fn write_audio_tags_args(input_file: &str, output_file: &str, tags: &AudioTags) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-v",
+        "error",
+        "-nostdin",
+        "-i",
+        input_file,
+        "-map",
+        "0",
+        "-c",
+        "copy",
+        "-map_metadata",
+        "0",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+
+    for (key, value) in [
+        ("artist", tags.artist),
+        ("title", tags.title),
+        ("album", tags.album),
+        ("track", tags.track_number),
+        ("genre", tags.genre),
+        ("date", tags.date),
+    ] {
+        args.push("-metadata".to_string());
+        args.push(format!("{}={}", key, value));
+    }
+
+    // ID3v2.3 is understood by more players than the ffmpeg's default ID3v2.4.
+    if output_file.to_lowercase().ends_with(".mp3") {
+        args.push("-id3v2_version".to_string());
+        args.push("3".to_string());
+    }
+
+    args.push("-y".to_string());
+    args.push(output_file.to_string());
+
+    args
+}
+
+/// Copies the audio file into the output file replacing its tags, without re-encoding the audio.
+/// The container format is chosen by the output file extension.
+pub(crate) async fn write_audio_tags(
+    path_to_ffmpeg: &str,
+    input_file: &str,
+    output_file: &str,
+    tags: &AudioTags<'_>,
+) -> Result<(), WriteAudioTagsError> {
+    let status = Command::new(path_to_ffmpeg)
+        .args(write_audio_tags_args(input_file, output_file, tags))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .await?;
+
+    if !status.success() {
+        return Err(WriteAudioTagsError::NonZeroExitStatus);
+    }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ExtractArtworkError {
+    #[error("Failed to spawn ffmpeg process: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("ffmpeg exited with non-zero status")]
+    NonZeroExitStatus,
+}
+
+/// Returns the picture embedded into the audio file as is, or `None` if the file has no picture.
+pub(crate) async fn extract_artwork(
+    path_to_ffmpeg: &str,
+    input_file: &str,
+) -> Result<Option<Vec<u8>>, ExtractArtworkError> {
+    let output = Command::new(path_to_ffmpeg)
+        .args([
+            "-v",
+            "error",
+            "-nostdin",
+            "-i",
+            input_file,
+            "-map",
+            "0:v:0?",
+            "-c",
+            "copy",
+            "-frames:v",
+            "1",
+            "-f",
+            "image2pipe",
+            "pipe:1",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await?;
+
+    // ffmpeg refuses to write an output without streams, so a missing picture ends up here too.
+    if output.stdout.is_empty() {
+        return Ok(None);
+    }
+
+    if !output.status.success() {
+        return Err(ExtractArtworkError::NonZeroExitStatus);
+    }
+
+    Ok(Some(output.stdout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builds_write_audio_tags_args() {
+        let tags = AudioTags {
+            artist: "Artist",
+            title: "Title = Name",
+            album: "",
+            track_number: "3",
+            genre: "Jazz",
+            date: "1999",
+        };
+
+        assert_eq!(
+            "-v error -nostdin -i in.mp3 -map 0 -c copy -map_metadata 0 \
+             -metadata artist=Artist -metadata title=Title = Name -metadata album= \
+             -metadata track=3 -metadata genre=Jazz -metadata date=1999 \
+             -id3v2_version 3 -y out.mp3",
+            write_audio_tags_args("in.mp3", "out.mp3", &tags).join(" ")
+        );
+        assert!(!write_audio_tags_args("in.ogg", "out.ogg", &tags)
+            .contains(&"-id3v2_version".to_string()));
+    }
+
+    #[test]
+    fn test_parses_ffprobe_output() {
+        let output = br#"{
+            "streams": [{"codec_name": "vorbis", "duration": "181.333000", "tags": {"ARTIST": "Stream Artist", "GENRE": "Jazz"}}],
+            "format": {"format_name": "ogg", "duration": "181.400000", "tags": {"title": "Format Title", "track": "3/12", "artist": " "}}
+        }"#;
+
+        assert_eq!(
+            AudioFileMetadata {
+                artist: Some("Stream Artist".to_string()),
+                title: Some("Format Title".to_string()),
+                album: None,
+                track_number: Some("3/12".to_string()),
+                genre: Some("Jazz".to_string()),
+                date: None,
+                duration: Duration::from_millis(181_400),
+            },
+            parse_ffprobe_output(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_files_without_audio() {
+        assert!(matches!(
+            parse_ffprobe_output(br#"{"streams": [], "format": {"duration": "1.0"}}"#),
+            Err(ProbeAudioFileError::NoAudioStream)
+        ));
+        assert!(matches!(
+            parse_ffprobe_output(br#"{"streams": [{}], "format": {"duration": "N/A"}}"#),
+            Err(ProbeAudioFileError::UnknownDuration)
+        ));
+    }
+}
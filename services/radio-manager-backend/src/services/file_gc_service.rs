@@ -0,0 +1,424 @@
+use crate::data_structures::FileId;
+use crate::mysql_client::MySqlClient;
+use crate::services::{artwork_file_name, ArtworkSize, ARTWORK_DIRECTORY};
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::files::{self, FileUsageRow};
+use crate::storage::db::repositories::{streams, track_artworks, users};
+use crate::storage::fs::utils::GetPath;
+use crate::storage::fs::{FileEntry, FileSystem};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tracing::{error, info, warn};
+
+const AUDIO_DIRECTORY: &str = "audio";
+
+const COVERS_DIRECTORY: &str = "covers";
+
+const AVATARS_DIRECTORY: &str = "avatars";
+
+/// Files modified recently are skipped because they may belong to an upload
+/// which hasn't created its `fs_file` row yet.
+const ORPHANED_FILE_MIN_AGE_SECS: i64 = 3600;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum FileGcError {
+    #[error(transparent)]
+    FileSystem(#[from] std::io::Error),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct FileGcPlan {
+    /// Stored files without `fs_file` row.
+    orphaned_files: Vec<FileEntry>,
+    /// `fs_file` rows not used by any track, with the stored file if it exists.
+    unused_files: Vec<(FileId, Option<FileEntry>)>,
+    /// `fs_file` rows still in use whose stored file doesn't exist.
+    missing_files: Vec<FileId>,
+}
+
+fn plan_file_gc(
+    stored_files: Vec<FileEntry>,
+    file_rows: &[FileUsageRow],
+    now: DateTime<Utc>,
+) -> FileGcPlan {
+    let mut stored_files: HashMap<_, _> = stored_files
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+    let mut plan = FileGcPlan::default();
+    let mut known_paths = HashSet::new();
+
+    for row in file_rows {
+        let path = format!("{}/{}", AUDIO_DIRECTORY, row.file.get_path());
+        let is_unused = row.file.use_count < 1 && row.tracks_count == 0;
+
+        if is_unused {
+            plan.unused_files
+                .push((row.file.file_id.clone(), stored_files.remove(&path)));
+            continue;
+        }
+
+        if !stored_files.contains_key(&path) {
+            plan.missing_files.push(row.file.file_id.clone());
+        }
+
+        known_paths.insert(path);
+    }
+
+    plan.orphaned_files = stored_files
+        .into_values()
+        .filter(|entry| !known_paths.contains(&entry.path))
+        .filter(|entry| is_old_enough(entry, now))
+        .collect();
+    plan.orphaned_files
+        .sort_by(|left, right| left.path.cmp(&right.path));
+
+    plan
+}
+
+fn is_old_enough(entry: &FileEntry, now: DateTime<Utc>) -> bool {
+    now - entry.modified >= Duration::seconds(ORPHANED_FILE_MIN_AGE_SECS)
+}
+
+/// Returns the names of all size variants of the artworks.
+fn artwork_file_names(artwork_hashes: &[String]) -> Vec<String> {
+    artwork_hashes
+        .iter()
+        .flat_map(|artwork_hash| {
+            ArtworkSize::ALL
+                .iter()
+                .map(|size| artwork_file_name(artwork_hash, *size))
+        })
+        .collect()
+}
+
+/// Returns the hash of the artwork the stored file is a size variant of.
+fn artwork_hash_of(path: &str) -> Option<&str> {
+    let file_name = path.strip_prefix(ARTWORK_DIRECTORY)?.rsplit('/').next()?;
+
+    file_name
+        .split_once('_')
+        .map(|(artwork_hash, _)| artwork_hash)
+}
+
+/// Returns the stored images of the directory not referenced by any of the file names.
+fn plan_image_gc(
+    directory: &str,
+    stored_files: Vec<FileEntry>,
+    file_names: &[String],
+    now: DateTime<Utc>,
+) -> Vec<FileEntry> {
+    let known_paths: HashSet<_> = file_names
+        .iter()
+        .map(|file_name| format!("{}/{}", directory, file_name))
+        .collect();
+
+    let mut orphaned_files: Vec<_> = stored_files
+        .into_iter()
+        .filter(|entry| !known_paths.contains(&entry.path))
+        .filter(|entry| is_old_enough(entry, now))
+        .collect();
+    orphaned_files.sort_by(|left, right| left.path.cmp(&right.path));
+
+    orphaned_files
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileGcReport {
+    pub(crate) dry_run: bool,
+    pub(crate) scanned_files: usize,
+    pub(crate) orphaned_files: Vec<String>,
+    pub(crate) unused_files: Vec<FileId>,
+    pub(crate) missing_files: Vec<FileId>,
+    pub(crate) deleted_files: usize,
+    pub(crate) freed_bytes: u64,
+}
+
+/// Reconciles `fs_file` rows with the files actually stored in the file system.
+#[derive(Clone)]
+pub(crate) struct FileGcService {
+    mysql_client: MySqlClient,
+}
+
+impl FileGcService {
+    pub(crate) fn new(mysql_client: MySqlClient) -> Self {
+        Self { mysql_client }
+    }
+
+    /// Deletes stored files without `fs_file` row and unused `fs_file` rows with their files,
+    /// and reports rows whose files are missing. Stream covers, user avatars and track artworks
+    /// no longer referenced are deleted too. Nothing is deleted if `dry_run` is set.
+    pub(crate) async fn collect_garbage<FS: FileSystem>(
+        &self,
+        file_system: &FS,
+        dry_run: bool,
+    ) -> Result<FileGcReport, FileGcError> {
+        let stored_files = file_system.list_files(AUDIO_DIRECTORY).await?;
+        let stored_covers = file_system.list_files(COVERS_DIRECTORY).await?;
+        let stored_avatars = file_system.list_files(AVATARS_DIRECTORY).await?;
+        let stored_artworks = file_system.list_files(ARTWORK_DIRECTORY).await?;
+
+        let (file_rows, covers, avatars, artwork_hashes) = {
+            let mut connection = self.mysql_client.connection().await?;
+            (
+                files::get_files_usage(&mut connection).await?,
+                streams::get_stream_covers(&mut connection).await?,
+                users::get_user_avatars(&mut connection).await?,
+                track_artworks::get_artwork_hashes(&mut connection).await?,
+            )
+        };
+
+        let mut report = FileGcReport {
+            dry_run,
+            scanned_files: stored_files.len()
+                + stored_covers.len()
+                + stored_avatars.len()
+                + stored_artworks.len(),
+            ..FileGcReport::default()
+        };
+
+        let now = Utc::now();
+        let mut plan = plan_file_gc(stored_files, &file_rows, now);
+        plan.orphaned_files
+            .extend(plan_image_gc(COVERS_DIRECTORY, stored_covers, &covers, now));
+        plan.orphaned_files.extend(plan_image_gc(
+            AVATARS_DIRECTORY,
+            stored_avatars,
+            &avatars,
+            now,
+        ));
+        plan.orphaned_files.extend(plan_image_gc(
+            ARTWORK_DIRECTORY,
+            stored_artworks,
+            &artwork_file_names(&artwork_hashes),
+            now,
+        ));
+
+        for file_id in plan.missing_files {
+            warn!(?file_id, "File is missing in the file system");
+            report.missing_files.push(file_id);
+        }
+
+        for entry in plan.orphaned_files {
+            info!(
+                path = entry.path,
+                size = entry.size,
+                dry_run,
+                "Orphaned file"
+            );
+
+            if !dry_run {
+                // Artworks are shared by the tracks with the same picture, so the artwork
+                // may have been attached to a track again since the plan was made.
+                if let Some(artwork_hash) = artwork_hash_of(&entry.path) {
+                    let mut connection = self.mysql_client.connection().await?;
+
+                    if track_artworks::is_artwork_attached(&mut connection, artwork_hash).await? {
+                        info!(path = entry.path, "Artwork is attached again, skipping");
+                        continue;
+                    }
+                }
+
+                if let Err(error) = file_system.delete_file(&entry.path).await {
+                    error!(?error, path = entry.path, "Unable to delete orphaned file");
+                    continue;
+                }
+
+                report.deleted_files += 1;
+                report.freed_bytes += entry.size;
+            }
+
+            report.orphaned_files.push(entry.path);
+        }
+
+        for (file_id, entry) in plan.unused_files {
+            info!(?file_id, dry_run, "Unused file");
+
+            if !dry_run {
+                // The file may have got used since the plan was made, so the row is deleted
+                // only if it's still unused, and the stored file only after the row.
+                let mut connection = self.mysql_client.transaction().await?;
+                let is_deleted = files::delete_unused_file(&mut connection, &file_id).await?;
+                connection.commit().await?;
+
+                if !is_deleted {
+                    info!(?file_id, "File is used again, skipping");
+                    continue;
+                }
+
+                if let Some(entry) = &entry {
+                    if let Err(error) = file_system.delete_file(&entry.path).await {
+                        error!(?error, path = entry.path, "Unable to delete unused file");
+                    } else {
+                        report.deleted_files += 1;
+                        report.freed_bytes += entry.size;
+                    }
+                }
+            }
+
+            report.unused_files.push(file_id);
+        }
+
+        info!(
+            dry_run,
+            scanned_files = report.scanned_files,
+            orphaned_files = report.orphaned_files.len(),
+            unused_files = report.unused_files.len(),
+            missing_files = report.missing_files.len(),
+            deleted_files = report.deleted_files,
+            freed_bytes = report.freed_bytes,
+            "File garbage collection finished"
+        );
+
+        Ok(report)
+    }
+
+    /// Runs the garbage collector periodically in the background.
+    pub(crate) fn schedule<FS: FileSystem + 'static>(
+        &self,
+        file_system: FS,
+        interval: std::time::Duration,
+        dry_run: bool,
+    ) {
+        let service = self.clone();
+
+        actix_rt::spawn(async move {
+            loop {
+                actix_rt::time::sleep(interval).await;
+
+                if let Err(error) = service.collect_garbage(&file_system, dry_run).await {
+                    error!(?error, "File garbage collection failed");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::db::repositories::FileRow;
+
+    fn file_row(file_id: i32, file_hash: &str, use_count: i32, tracks_count: i64) -> FileUsageRow {
+        FileUsageRow {
+            file: FileRow {
+                file_id: FileId::from(file_id),
+                file_size: 1024,
+                file_hash: file_hash.to_string(),
+                file_extension: "mp3".to_string(),
+                server_id: 1,
+                use_count,
+            },
+            tracks_count,
+        }
+    }
+
+    fn file_entry(path: &str, modified: DateTime<Utc>) -> FileEntry {
+        FileEntry {
+            path: path.to_string(),
+            size: 1024,
+            modified,
+        }
+    }
+
+    #[test]
+    fn test_plans_file_gc() {
+        let now = Utc::now();
+        let old = now - Duration::days(1);
+
+        let stored_files = vec![
+            file_entry("audio/a/b/ab01.mp3", old),
+            file_entry("audio/c/d/cd02.mp3", old),
+            file_entry("audio/e/f/ef03.mp3", old),
+            file_entry("audio/1/2/1204.mp3", now),
+        ];
+        let file_rows = vec![
+            file_row(1, "ab01", 1, 1),
+            file_row(2, "cd02", 0, 0),
+            file_row(5, "ff05", 2, 2),
+            file_row(6, "ff06", 0, 0),
+            // Use count is out of sync, but the file is still referenced.
+            file_row(7, "ab07", 0, 1),
+        ];
+
+        let plan = plan_file_gc(stored_files, &file_rows, now);
+
+        assert_eq!(
+            FileGcPlan {
+                orphaned_files: vec![file_entry("audio/e/f/ef03.mp3", old)],
+                unused_files: vec![
+                    (FileId::from(2), Some(file_entry("audio/c/d/cd02.mp3", old))),
+                    (FileId::from(6), None),
+                ],
+                missing_files: vec![FileId::from(5), FileId::from(7)],
+            },
+            plan
+        );
+    }
+
+    #[test]
+    fn test_plans_image_gc() {
+        let now = Utc::now();
+        let old = now - Duration::days(1);
+
+        let stored_files = vec![
+            file_entry("covers/stream00002.png", old),
+            file_entry("covers/stream00001.png", old),
+            file_entry("covers/stream00003.png", old),
+            file_entry("covers/stream00004.png", now),
+        ];
+        let file_names = vec!["stream00003.png".to_string()];
+
+        assert_eq!(
+            vec![
+                file_entry("covers/stream00001.png", old),
+                file_entry("covers/stream00002.png", old),
+            ],
+            plan_image_gc(COVERS_DIRECTORY, stored_files, &file_names, now)
+        );
+    }
+
+    #[test]
+    fn test_plans_artwork_gc() {
+        let now = Utc::now();
+        let old = now - Duration::days(1);
+
+        let stored_files = vec![
+            file_entry("artwork/a/b/abcdef_100.png", old),
+            file_entry("artwork/a/b/abcdef_300.png", old),
+            file_entry("artwork/a/b/abcdef_600.png", old),
+            file_entry("artwork/1/2/123456_100.png", old),
+            file_entry("artwork/1/2/123456_600.png", old),
+        ];
+        let artwork_hashes = vec!["abcdef".to_string()];
+
+        assert_eq!(
+            vec![
+                file_entry("artwork/1/2/123456_100.png", old),
+                file_entry("artwork/1/2/123456_600.png", old),
+            ],
+            plan_image_gc(
+                ARTWORK_DIRECTORY,
+                stored_files,
+                &artwork_file_names(&artwork_hashes),
+                now
+            )
+        );
+    }
+
+    #[test]
+    fn test_gets_artwork_hash_of_stored_file() {
+        assert_eq!(
+            Some("abcdef"),
+            artwork_hash_of("artwork/a/b/abcdef_600.png")
+        );
+        assert_eq!(None, artwork_hash_of("covers/abcdef_600.png"));
+        assert_eq!(None, artwork_hash_of("artwork/a/b/abcdef.png"));
+    }
+}
@@ -0,0 +1,65 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use std::io::Cursor;
+
+/// Crops the image to a square from its center and resizes it to `size` pixels.
+pub(crate) fn resize_to_square_png(
+    image_data: &[u8],
+    size: u32,
+) -> Result<Vec<u8>, image::ImageError> {
+    let image = image::load_from_memory(image_data)?;
+
+    encode_png(&image.resize_to_fill(size, size, FilterType::Lanczos3))
+}
+
+/// Returns the average color of the image in `#rrggbb` format.
+pub(crate) fn get_background_color(image_data: &[u8]) -> Result<String, image::ImageError> {
+    let image = image::load_from_memory(image_data)?;
+    let pixel = image
+        .resize_exact(1, 1, FilterType::Triangle)
+        .get_pixel(0, 0);
+
+    Ok(format!("#{:02x}{:02x}{:02x}", pixel[0], pixel[1], pixel[2]))
+}
+
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, image::ImageError> {
+    let mut image_data = Cursor::new(Vec::new());
+    image.write_to(&mut image_data, ImageOutputFormat::Png)?;
+
+    Ok(image_data.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn create_png(width: u32, height: u32, color: Rgb<u8>) -> Vec<u8> {
+        encode_png(&DynamicImage::ImageRgb8(RgbImage::from_pixel(
+            width, height, color,
+        )))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resizes_image_to_square() {
+        let image_data = resize_to_square_png(&create_png(640, 480, Rgb([0, 0, 0])), 256).unwrap();
+        let image = image::load_from_memory(&image_data).unwrap();
+
+        assert_eq!((256, 256), image.dimensions());
+    }
+
+    #[test]
+    fn test_rejects_non_image_data() {
+        assert!(resize_to_square_png(b"definitely not an image", 256).is_err());
+        assert!(get_background_color(b"definitely not an image").is_err());
+    }
+
+    #[test]
+    fn test_gets_background_color() {
+        assert_eq!(
+            "#1e3c5a",
+            get_background_color(&create_png(8, 8, Rgb([30, 60, 90]))).unwrap()
+        );
+    }
+}
@@ -0,0 +1,613 @@
+use crate::data_structures::{StreamRef, TrackId, UserId};
+use crate::mysql_client::MySqlClient;
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::user_stream_tracks::{
+    get_stream_tracks, GetUserStreamTracksParams,
+};
+use crate::storage::db::repositories::user_tracks::get_user_tracks_by_ids;
+use crate::storage::db::repositories::{streams, FileRow, TrackRow};
+use crate::storage::fs::utils::GetPath;
+use crate::storage::fs::FileSystem;
+use crate::utils::dedup_track_ids;
+use async_zip::base::write::ZipFileWriter;
+use async_zip::error::ZipError;
+use async_zip::{Compression, ZipDateTime, ZipEntryBuilder};
+use chrono::{DateTime, TimeZone, Utc};
+use futures::channel::mpsc;
+use futures::{AsyncWrite, AsyncWriteExt, SinkExt, StreamExt};
+use serde_json::json;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tracing::{debug, error};
+
+/// Limits the number of tracks selected for the export in a single request.
+const MAX_EXPORTED_TRACKS: usize = 1000;
+
+const TRACKS_DIRECTORY: &str = "tracks";
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum LibraryExportError {
+    #[error("Stream not found")]
+    StreamNotFound,
+    #[error("Track not found")]
+    TrackNotFound,
+    #[error("Too many tracks")]
+    TooManyTracks,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(Debug)]
+pub(crate) struct ExportedTrack {
+    /// Path of the track inside the archive.
+    path: String,
+    track: TrackRow,
+    file: FileRow,
+}
+
+/// Tracks to put into the archive in the playlist order.
+#[derive(Debug)]
+pub(crate) struct LibraryExport {
+    pub(crate) title: String,
+    tracks: Vec<ExportedTrack>,
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    sanitized
+        .trim()
+        .trim_matches('.')
+        .chars()
+        .take(150)
+        .collect()
+}
+
+fn track_entry_name(
+    position: usize,
+    tracks_count: usize,
+    track: &TrackRow,
+    file: &FileRow,
+) -> String {
+    let width = tracks_count.to_string().len().max(2);
+    let title = match track.title.trim() {
+        "" => track
+            .filename
+            .rsplit_once('.')
+            .map(|(stem, _)| stem)
+            .unwrap_or(&track.filename),
+        title => title,
+    };
+    let name = match track.artist.trim() {
+        "" => sanitize_file_name(title),
+        artist => sanitize_file_name(&format!("{} - {}", artist, title)),
+    };
+
+    format!(
+        "{}/{:0width$} - {}.{}",
+        TRACKS_DIRECTORY,
+        position + 1,
+        name,
+        file.file_extension,
+        width = width
+    )
+}
+
+impl LibraryExport {
+    fn new(title: String, rows: Vec<(TrackRow, FileRow)>) -> Self {
+        let tracks_count = rows.len();
+        let tracks = rows
+            .into_iter()
+            .enumerate()
+            .map(|(position, (track, file))| ExportedTrack {
+                path: track_entry_name(position, tracks_count, &track, &file),
+                track,
+                file,
+            })
+            .collect();
+
+        Self { title, tracks }
+    }
+
+    fn render_m3u(&self) -> String {
+        let mut playlist = String::from("#EXTM3U\n");
+
+        for exported in &self.tracks {
+            let track = &exported.track;
+            let label = match track.artist.is_empty() {
+                true => track.title.clone(),
+                false => format!("{} - {}", track.artist, track.title),
+            };
+
+            playlist.push_str(&format!(
+                "#EXTINF:{},{}\n{}\n",
+                track.duration / 1000,
+                label.replace('\n', " "),
+                exported.path
+            ));
+        }
+
+        playlist
+    }
+
+    fn render_xspf(&self) -> String {
+        let mut playlist = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+        );
+
+        playlist.push_str(&format!("  <title>{}</title>\n", escape_xml(&self.title)));
+        playlist.push_str("  <trackList>\n");
+
+        for exported in &self.tracks {
+            let track = &exported.track;
+            let location = exported
+                .path
+                .split('/')
+                .map(|component| urlencoding::encode(component).into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            playlist.push_str("    <track>\n");
+            playlist.push_str(&format!("      <location>{}</location>\n", location));
+            for (element, value) in [
+                ("creator", &track.artist),
+                ("title", &track.title),
+                ("album", &track.album),
+            ] {
+                if !value.is_empty() {
+                    playlist.push_str(&format!(
+                        "      <{0}>{1}</{0}>\n",
+                        element,
+                        escape_xml(value)
+                    ));
+                }
+            }
+            if let Ok(track_number) = track.track_number.parse::<u32>() {
+                playlist.push_str(&format!("      <trackNum>{}</trackNum>\n", track_number));
+            }
+            playlist.push_str(&format!("      <duration>{}</duration>\n", track.duration));
+            playlist.push_str("    </track>\n");
+        }
+
+        playlist.push_str("  </trackList>\n</playlist>\n");
+
+        playlist
+    }
+
+    fn render_manifest(&self) -> String {
+        let tracks: Vec<_> = self
+            .tracks
+            .iter()
+            .map(|exported| {
+                let track = &exported.track;
+
+                json!({
+                    "tid": track.tid,
+                    "path": exported.path,
+                    "filename": track.filename,
+                    "artist": track.artist,
+                    "title": track.title,
+                    "album": track.album,
+                    "trackNumber": track.track_number,
+                    "genre": track.genre,
+                    "date": track.date,
+                    "duration": track.duration,
+                    "fileSize": exported.file.file_size,
+                    "hash": exported.file.file_hash,
+                    "uploaded": track.uploaded,
+                })
+            })
+            .collect();
+
+        let manifest = json!({
+            "title": self.title,
+            "exportedAt": Utc::now(),
+            "tracks": tracks,
+        });
+
+        serde_json::to_string_pretty(&manifest).expect("Manifest is serializable")
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+type ArchiveSender = mpsc::Sender<std::io::Result<Vec<u8>>>;
+
+/// Sends the written archive data through the channel, so the archive is streamed
+/// to the client as it's being written.
+struct ChunkWriter {
+    tx: ArchiveSender,
+}
+
+fn broken_pipe<E>(_: E) -> std::io::Error {
+    std::io::Error::from(std::io::ErrorKind::BrokenPipe)
+}
+
+impl AsyncWrite for ChunkWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.tx.poll_ready(cx) {
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(error)) => return Poll::Ready(Err(broken_pipe(error))),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        match self.tx.start_send(Ok(buf.to_vec())) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(error) => Poll::Ready(Err(broken_pipe(error))),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn zip_error(error: ZipError) -> std::io::Error {
+    match error {
+        ZipError::UpstreamReadError(error) => error,
+        error => std::io::Error::other(error),
+    }
+}
+
+fn archive_entry(name: &str, modified: &DateTime<Utc>) -> ZipEntryBuilder {
+    ZipEntryBuilder::new(name.into(), Compression::Stored)
+        .last_modification_date(ZipDateTime::from_chrono(modified))
+}
+
+async fn write_archive<FS: FileSystem>(
+    export: &LibraryExport,
+    file_system: &FS,
+    tx: &ArchiveSender,
+) -> std::io::Result<()> {
+    let mut writer = ZipFileWriter::new(ChunkWriter { tx: tx.clone() });
+
+    for exported in &export.tracks {
+        let modified = Utc
+            .timestamp_opt(exported.track.uploaded, 0)
+            .single()
+            .unwrap_or_default();
+        let mut contents = file_system
+            .get_file_contents(&format!("audio/{}", exported.file.get_path()))
+            .await?;
+
+        let mut entry_writer = writer
+            .write_entry_stream(archive_entry(&exported.path, &modified))
+            .await
+            .map_err(zip_error)?;
+
+        let mut written_size = 0u64;
+
+        while let Some(chunk) = contents.next().await {
+            let chunk = chunk?;
+            written_size += chunk.len() as u64;
+            entry_writer.write_all(&chunk).await?;
+        }
+
+        // A file shorter than recorded is only noticed by its size. Failing the stream tells
+        // the client the archive is broken.
+        if written_size != exported.file.file_size as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Read {} of {} bytes of {}",
+                    written_size,
+                    exported.file.file_size,
+                    exported.file.get_path()
+                ),
+            ));
+        }
+
+        entry_writer.close().await.map_err(zip_error)?;
+    }
+
+    let now = Utc::now();
+
+    for (name, contents) in [
+        ("playlist.m3u", export.render_m3u()),
+        ("playlist.xspf", export.render_xspf()),
+        ("manifest.json", export.render_manifest()),
+    ] {
+        writer
+            .write_entry_whole(archive_entry(name, &now), contents.as_bytes())
+            .await
+            .map_err(zip_error)?;
+    }
+
+    writer.close().await.map_err(zip_error)?;
+
+    Ok(())
+}
+
+/// Exports the user's tracks as a ZIP archive with the playlist files and the metadata manifest.
+#[derive(Clone)]
+pub(crate) struct LibraryExportService {
+    mysql_client: MySqlClient,
+}
+
+impl LibraryExportService {
+    pub(crate) fn new(mysql_client: MySqlClient) -> Self {
+        Self { mysql_client }
+    }
+
+    /// Prepares the export of the selected tracks in the given order.
+    /// Repeated tracks are exported once.
+    pub(crate) async fn export_tracks(
+        &self,
+        user_id: &UserId,
+        track_ids: &[TrackId],
+    ) -> Result<LibraryExport, LibraryExportError> {
+        let track_ids = dedup_track_ids(track_ids);
+
+        if track_ids.len() > MAX_EXPORTED_TRACKS {
+            return Err(LibraryExportError::TooManyTracks);
+        }
+
+        let mut connection = self.mysql_client.connection().await?;
+
+        let mut rows = get_user_tracks_by_ids(&mut connection, user_id, &track_ids).await?;
+        let mut ordered_rows = Vec::with_capacity(track_ids.len());
+
+        for track_id in &track_ids {
+            let position = rows
+                .iter()
+                .position(|row| &row.track.tid == track_id)
+                .ok_or(LibraryExportError::TrackNotFound)?;
+            let row = rows.swap_remove(position);
+
+            ordered_rows.push((row.track, row.file));
+        }
+
+        Ok(LibraryExport::new("Tracks".to_string(), ordered_rows))
+    }
+
+    /// Prepares the export of the whole stream playlist.
+    pub(crate) async fn export_stream(
+        &self,
+        user_id: &UserId,
+        stream_ref: &StreamRef,
+    ) -> Result<LibraryExport, LibraryExportError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let stream = match streams::get_single_stream_by_ref(&mut connection, stream_ref).await? {
+            Some(stream) if &stream.uid == user_id => stream,
+            _ => return Err(LibraryExportError::StreamNotFound),
+        };
+
+        let rows = get_stream_tracks(
+            &mut connection,
+            &stream.sid,
+            &GetUserStreamTracksParams::default(),
+            &None,
+            &None,
+        )
+        .await?;
+
+        Ok(LibraryExport::new(
+            stream.name,
+            rows.into_iter().map(|row| (row.track, row.file)).collect(),
+        ))
+    }
+
+    /// Builds the archive in the background, returning its contents as a stream of chunks.
+    pub(crate) fn stream_archive<FS: FileSystem + 'static>(
+        &self,
+        export: LibraryExport,
+        file_system: FS,
+    ) -> mpsc::Receiver<std::io::Result<Vec<u8>>> {
+        let (mut tx, rx) = mpsc::channel(4);
+
+        actix_rt::spawn(async move {
+            match write_archive(&export, &file_system, &tx).await {
+                Ok(()) => (),
+                Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => {
+                    debug!("Library export download was interrupted by the client");
+                }
+                Err(error) => {
+                    error!(?error, "Unable to write library export archive");
+                    let _ = tx.send(Err(error)).await;
+                }
+            }
+        });
+
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_structures::FileId;
+    use crate::storage::fs::local::LocalFileSystem;
+    use async_zip::base::read::mem::ZipFileReader;
+
+    fn track(tid: i32, artist: &str, title: &str, duration: i64) -> (TrackRow, FileRow) {
+        let track = TrackRow {
+            tid: TrackId::from(tid),
+            file_id: Some(FileId::from(tid)),
+            uid: UserId::from(1),
+            filename: format!("{}.mp3", title),
+            hash: "abcdef".to_string(),
+            ext: "mp3".to_string(),
+            artist: artist.to_string(),
+            title: title.to_string(),
+            album: String::new(),
+            track_number: "3".to_string(),
+            genre: String::new(),
+            date: String::new(),
+            cue: None,
+            buy: None,
+            duration,
+            filesize: 1024,
+            color: 0,
+            uploaded: 0,
+            copy_of: None,
+            used_count: 0,
+            is_new: false,
+            can_be_shared: false,
+            is_deleted: false,
+            deleted: None,
+        };
+        let file = FileRow {
+            file_id: FileId::from(tid),
+            file_size: 1024,
+            file_hash: "abcdef".to_string(),
+            file_extension: "mp3".to_string(),
+            server_id: 1,
+            use_count: 1,
+        };
+
+        (track, file)
+    }
+
+    #[test]
+    fn test_names_track_entries() {
+        let export = LibraryExport::new(
+            "Jazz".to_string(),
+            vec![
+                track(1, "AC/DC", "Back: In Black", 255000),
+                track(2, "", "Intro", 1000),
+            ],
+        );
+
+        assert_eq!(
+            vec![
+                "tracks/01 - AC_DC - Back_ In Black.mp3",
+                "tracks/02 - Intro.mp3"
+            ],
+            export
+                .tracks
+                .iter()
+                .map(|t| t.path.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_renders_playlists() {
+        let export = LibraryExport::new(
+            "Rock & Roll".to_string(),
+            vec![track(1, "Artist", "Song <1>", 255500)],
+        );
+
+        assert_eq!(
+            "#EXTM3U\n#EXTINF:255,Artist - Song <1>\ntracks/01 - Artist - Song _1_.mp3\n",
+            export.render_m3u()
+        );
+
+        let xspf = export.render_xspf();
+
+        assert!(xspf.contains("<title>Rock &amp; Roll</title>"));
+        assert!(xspf.contains("<location>tracks/01%20-%20Artist%20-%20Song%20_1_.mp3</location>"));
+        assert!(xspf.contains("<title>Song &lt;1&gt;</title>"));
+        assert!(xspf.contains("<trackNum>3</trackNum>"));
+        assert!(xspf.contains("<duration>255500</duration>"));
+    }
+
+    #[actix_rt::test]
+    async fn test_writes_readable_archive() {
+        let root_path = std::env::temp_dir().join(format!(
+            "library-export-{}",
+            uuid::Uuid::new_v4().to_simple()
+        ));
+        let file_system = LocalFileSystem::create(root_path.to_string_lossy().to_string());
+        let audio = vec![7u8; 1024];
+        file_system
+            .put_file("audio/a/b/abcdef.mp3", audio.clone())
+            .await
+            .unwrap();
+
+        let export =
+            LibraryExport::new("Jazz".to_string(), vec![track(1, "Artist", "Песня", 1000)]);
+        let (tx, rx) = mpsc::channel(4);
+
+        let (result, chunks) = futures::join!(
+            async move { write_archive(&export, &file_system, &tx).await },
+            rx.collect::<Vec<_>>()
+        );
+        let _ = std::fs::remove_dir_all(&root_path);
+
+        result.unwrap();
+
+        let archive: Vec<u8> = chunks.into_iter().flat_map(Result::unwrap).collect();
+        let reader = ZipFileReader::new(archive).await.unwrap();
+
+        let names: Vec<_> = reader
+            .file()
+            .entries()
+            .iter()
+            .map(|entry| entry.filename().as_str().unwrap().to_string())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "tracks/01 - Artist - Песня.mp3",
+                "playlist.m3u",
+                "playlist.xspf",
+                "manifest.json"
+            ],
+            names
+        );
+
+        let mut contents = vec![];
+        reader
+            .reader_with_entry(0)
+            .await
+            .unwrap()
+            .read_to_end_checked(&mut contents)
+            .await
+            .unwrap();
+
+        assert_eq!(audio, contents);
+    }
+
+    #[actix_rt::test]
+    async fn test_fails_archive_of_truncated_file() {
+        let root_path = std::env::temp_dir().join(format!(
+            "library-export-{}",
+            uuid::Uuid::new_v4().to_simple()
+        ));
+        let file_system = LocalFileSystem::create(root_path.to_string_lossy().to_string());
+        file_system
+            .put_file("audio/a/b/abcdef.mp3", vec![7u8; 1000])
+            .await
+            .unwrap();
+
+        let export = LibraryExport::new("Jazz".to_string(), vec![track(1, "Artist", "Song", 1000)]);
+        let (tx, rx) = mpsc::channel(4);
+
+        let (result, _) = futures::join!(
+            async move { write_archive(&export, &file_system, &tx).await },
+            rx.collect::<Vec<_>>()
+        );
+        let _ = std::fs::remove_dir_all(&root_path);
+
+        assert_eq!(
+            std::io::ErrorKind::UnexpectedEof,
+            result.unwrap_err().kind()
+        );
+    }
+}
@@ -1,8 +1,55 @@
+mod airplay_report_service;
+mod artwork_service;
+mod audio_storage;
 pub(crate) mod auth;
+mod channel_service;
+mod color_label_service;
+pub(crate) mod email_service;
 pub(crate) mod ffmpeg_service;
+mod file_gc_service;
+mod image_utils;
+mod library_export_service;
+mod play_history_service;
+mod profile_service;
 mod stream_service;
 mod stream_service_utils;
+mod track_bulk_service;
+mod track_import_service;
+mod track_metadata_service;
+mod track_sharing_service;
+mod track_trash_service;
+
+pub(crate) use self::airplay_report_service::{
+    AirplayReportError, AirplayReportService, ReportPeriod,
+};
+pub(crate) use self::artwork_service::{
+    artwork_file_name, Artwork, ArtworkError, ArtworkService, ArtworkSize, ARTWORK_DIRECTORY,
+};
+pub(crate) use self::channel_service::{ChannelError, ChannelService, ChannelUpdate};
+pub(crate) use self::color_label_service::{
+    ColorLabel, ColorLabelError, ColorLabelService, ColorLabelUpdate,
+};
+pub(crate) use self::file_gc_service::FileGcService;
+pub(crate) use self::library_export_service::{
+    LibraryExport, LibraryExportError, LibraryExportService,
+};
+pub(crate) use self::play_history_service::{PlayHistoryError, PlayHistoryService};
+pub(crate) use self::profile_service::{
+    ChangeEmailError, DeleteAccountError, ProfileError, ProfileService, ProfileUpdate,
+    UpdateAvatarError, UpdateProfileError,
+};
 
 pub(crate) use self::stream_service::StreamServiceError;
 pub(crate) use self::stream_service::StreamServiceFactory;
-pub(crate) use self::stream_service_utils::get_now_playing;
+pub(crate) use self::stream_service_utils::{
+    get_now_playing, get_now_playing_tracks, get_schedule,
+};
+pub(crate) use self::track_bulk_service::{
+    TrackBulkError, TrackBulkItemStatus, TrackBulkOperation, TrackBulkService, TrackSelection,
+};
+pub(crate) use self::track_import_service::{TrackImportError, TrackImportService};
+pub(crate) use self::track_metadata_service::{
+    TrackMetadataError, TrackMetadataService, TrackMetadataUpdate,
+};
+pub(crate) use self::track_sharing_service::{TrackSharingError, TrackSharingService};
+pub(crate) use self::track_trash_service::{TrackTrashError, TrackTrashService};
@@ -0,0 +1,129 @@
+use crate::data_structures::{StreamId, StreamRef, TrackId, UserId};
+use crate::mysql_client::MySqlClient;
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::{
+    streams, track_plays, user_tracks, MostPlayedTrackRow, TrackPlayRow,
+};
+use chrono::{DateTime, Duration, Utc};
+
+/// The players of the stream report the same track start at slightly different times.
+const TRACK_START_DEDUP_WINDOW: Duration = Duration::seconds(10);
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum PlayHistoryError {
+    #[error("Stream not found")]
+    StreamNotFound,
+    #[error("Track not found")]
+    TrackNotFound,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Records what the radio streamer actually aired and keeps the per-track play counters.
+#[derive(Clone)]
+pub(crate) struct PlayHistoryService {
+    mysql_client: MySqlClient,
+}
+
+impl PlayHistoryService {
+    pub(crate) fn new(mysql_client: MySqlClient) -> Self {
+        Self { mysql_client }
+    }
+
+    /// Adds the track start reported by the radio streamer to the play log. The players of
+    /// the stream report the same start once per output format, so a start of the same track
+    /// reported within `TRACK_START_DEDUP_WINDOW` is counted as played once and the listeners
+    /// of all the formats are summed up.
+    pub(crate) async fn record_track_start(
+        &self,
+        stream_id: &StreamId,
+        track_id: &TrackId,
+        started_at: &DateTime<Utc>,
+        listeners: i32,
+    ) -> Result<(), PlayHistoryError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        streams::lock_stream(&mut connection, stream_id).await?;
+
+        let stream = streams::get_single_stream_by_id(&mut connection, stream_id)
+            .await?
+            .ok_or(PlayHistoryError::StreamNotFound)?;
+
+        let track = user_tracks::get_single_user_track(&mut connection, track_id)
+            .await?
+            .ok_or(PlayHistoryError::TrackNotFound)?;
+
+        if let Some(play_id) = track_plays::get_track_play_id_near(
+            &mut connection,
+            &stream.sid,
+            track_id,
+            started_at,
+            TRACK_START_DEDUP_WINDOW,
+        )
+        .await?
+        {
+            track_plays::add_track_play_listeners(&mut connection, play_id, listeners).await?;
+        } else {
+            let is_new_play = track_plays::record_track_play(
+                &mut connection,
+                &stream.sid,
+                &stream.uid,
+                &track.track,
+                started_at,
+                listeners,
+            )
+            .await?;
+
+            if is_new_play {
+                track_plays::increment_track_plays(&mut connection, track_id, started_at).await?;
+            }
+        }
+
+        connection.commit().await?;
+
+        Ok(())
+    }
+
+    /// Returns the latest tracks aired on the user's stream, newest first.
+    pub(crate) async fn get_stream_play_history(
+        &self,
+        user_id: &UserId,
+        stream_ref: &StreamRef,
+        started_before: &Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<TrackPlayRow>, PlayHistoryError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let stream = match streams::get_single_stream_by_ref(&mut connection, stream_ref).await? {
+            Some(stream) if &stream.uid == user_id => stream,
+            _ => return Err(PlayHistoryError::StreamNotFound),
+        };
+
+        let plays = track_plays::get_stream_track_plays(
+            &mut connection,
+            &stream.sid,
+            started_before,
+            limit,
+        )
+        .await?;
+
+        Ok(plays)
+    }
+
+    pub(crate) async fn get_most_played_tracks(
+        &self,
+        user_id: &UserId,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<MostPlayedTrackRow>, PlayHistoryError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let tracks =
+            track_plays::get_most_played_user_tracks(&mut connection, user_id, offset, limit)
+                .await?;
+
+        Ok(tracks)
+    }
+}
@@ -0,0 +1,432 @@
+use crate::data_structures::UserId;
+use crate::mysql_client::MySqlClient;
+use crate::services::auth::{Action, ActionTokenClaims, AuthTokenService};
+use crate::services::email_service::{EmailService, EmailServiceError};
+use crate::services::image_utils::resize_to_square_png;
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::{
+    colors, files, outgoing_streams, stream_destinations, users, UserRow,
+};
+use crate::storage::fs::utils::GetPath;
+use crate::storage::fs::FileSystem;
+use crate::utils::{is_valid_permalink, verify_password};
+use std::time::Duration;
+use tracing::{error, warn};
+
+const USER_NAME_MAX_LENGTH: usize = 32;
+
+const USER_INFO_MAX_LENGTH: usize = 4096;
+
+const AVATAR_SIZE: u32 = 256;
+
+const EMAIL_CHANGE_EXPIRES_AFTER: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ProfileError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum UpdateProfileError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Invalid name")]
+    InvalidName,
+    #[error("Invalid info")]
+    InvalidInfo,
+    #[error("Invalid permalink")]
+    InvalidPermalink,
+    #[error("Permalink is already taken")]
+    NonUniquePermalink,
+    #[error("Invalid country")]
+    InvalidCountry,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ChangeEmailError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Invalid email address")]
+    InvalidEmailAddress,
+    #[error("Email address is already taken")]
+    NonUniqueEmailAddress,
+    #[error("Invalid or expired confirmation token")]
+    InvalidConfirmationToken,
+    #[error(transparent)]
+    EmailServiceError(#[from] EmailServiceError),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum UpdateAvatarError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Invalid image")]
+    InvalidImage,
+    #[error(transparent)]
+    FileSystemError(#[from] std::io::Error),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum DeleteAccountError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Invalid password")]
+    InvalidPassword,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Profile fields to update. Fields set to `None` are left unchanged.
+#[derive(Default)]
+pub(crate) struct ProfileUpdate {
+    pub(crate) name: Option<String>,
+    pub(crate) info: Option<String>,
+    pub(crate) permalink: Option<String>,
+    pub(crate) country_id: Option<i64>,
+}
+
+#[derive(Clone)]
+pub(crate) struct ProfileService {
+    mysql_client: MySqlClient,
+    token_service: AuthTokenService,
+    email_service: EmailService,
+    web_server_own_address: Option<String>,
+}
+
+impl ProfileService {
+    pub(crate) fn new(
+        mysql_client: MySqlClient,
+        token_service: AuthTokenService,
+        email_service: EmailService,
+        web_server_own_address: Option<&str>,
+    ) -> Self {
+        Self {
+            mysql_client,
+            token_service,
+            email_service,
+            web_server_own_address: web_server_own_address.map(str::to_string),
+        }
+    }
+
+    pub(crate) async fn get_profile(&self, user_id: &UserId) -> Result<UserRow, ProfileError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        match users::get_user_by_id(&mut connection, user_id).await? {
+            Some(user) => Ok(user),
+            None => Err(ProfileError::UserNotFound),
+        }
+    }
+
+    pub(crate) async fn update_profile(
+        &self,
+        user_id: &UserId,
+        update: ProfileUpdate,
+    ) -> Result<UserRow, UpdateProfileError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let user = match users::get_user_by_id(&mut connection, user_id).await? {
+            Some(user) => user,
+            None => return Err(UpdateProfileError::UserNotFound),
+        };
+
+        if let Some(name) = &update.name {
+            if name.chars().count() > USER_NAME_MAX_LENGTH {
+                return Err(UpdateProfileError::InvalidName);
+            }
+        }
+
+        if let Some(info) = &update.info {
+            if info.chars().count() > USER_INFO_MAX_LENGTH {
+                return Err(UpdateProfileError::InvalidInfo);
+            }
+        }
+
+        if let Some(permalink) = &update.permalink {
+            if !is_valid_permalink(permalink) {
+                return Err(UpdateProfileError::InvalidPermalink);
+            }
+
+            if users::is_user_permalink_taken(&mut connection, permalink, user_id).await? {
+                return Err(UpdateProfileError::NonUniquePermalink);
+            }
+        }
+
+        if let Some(country_id) = &update.country_id {
+            if !users::is_country_exists(&mut connection, country_id).await? {
+                return Err(UpdateProfileError::InvalidCountry);
+            }
+        }
+
+        users::update_user_profile(
+            &mut connection,
+            user_id,
+            &update.name.or(user.name),
+            &update.info.or(user.info),
+            &update.permalink.or(user.permalink),
+            &update.country_id.or(user.country_id),
+        )
+        .await?;
+
+        let user = match users::get_user_by_id(&mut connection, user_id).await? {
+            Some(user) => user,
+            None => return Err(UpdateProfileError::UserNotFound),
+        };
+
+        connection.commit().await?;
+
+        Ok(user)
+    }
+
+    /// Sends a confirmation link to the new email address. The email address of the account
+    /// is changed only after the link has been confirmed.
+    pub(crate) async fn request_email_change(
+        &self,
+        user_id: &UserId,
+        email: &str,
+    ) -> Result<(), ChangeEmailError> {
+        if !email_address::EmailAddress::is_valid(email) {
+            return Err(ChangeEmailError::InvalidEmailAddress);
+        }
+
+        let web_server_own_address = self
+            .web_server_own_address
+            .as_deref()
+            .ok_or(EmailServiceError::NotConfigured)?;
+
+        let mut connection = self.mysql_client.connection().await?;
+
+        if users::get_user_by_id(&mut connection, user_id)
+            .await?
+            .is_none()
+        {
+            return Err(ChangeEmailError::UserNotFound);
+        }
+
+        if users::get_user_by_email(&mut connection, email)
+            .await?
+            .is_some()
+        {
+            return Err(ChangeEmailError::NonUniqueEmailAddress);
+        }
+
+        drop(connection);
+
+        let now_timestamp = chrono::Utc::now().timestamp() as usize;
+        let token = self.token_service.sign_action_claims(ActionTokenClaims {
+            exp: now_timestamp + EMAIL_CHANGE_EXPIRES_AFTER.as_secs() as usize,
+            user_id: user_id.clone(),
+            actions: vec![Action::ConfirmEmailChange {
+                email: email.to_string(),
+            }],
+        });
+
+        let body = format!(
+            "To confirm your new email address, open the following link:\n\n{}/confirm-email/{}\n\nIf you didn't request the change, just ignore this email.",
+            web_server_own_address.trim_end_matches('/'),
+            token
+        );
+
+        self.email_service
+            .send(email, "Confirm your new email address", &body)
+            .await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn confirm_email_change(
+        &self,
+        user_id: &UserId,
+        token: &str,
+    ) -> Result<String, ChangeEmailError> {
+        let email = match self.token_service.verify_action_claims(token) {
+            Some(claims) if &claims.user_id == user_id => {
+                claims.actions.into_iter().find_map(|action| match action {
+                    Action::ConfirmEmailChange { email } => Some(email),
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+
+        let email = match email {
+            Some(email) => email,
+            None => return Err(ChangeEmailError::InvalidConfirmationToken),
+        };
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        match users::get_user_by_email(&mut connection, &email).await? {
+            Some(user) if &user.uid == user_id => return Ok(email),
+            Some(_) => return Err(ChangeEmailError::NonUniqueEmailAddress),
+            None => (),
+        }
+
+        users::update_user_email(&mut connection, user_id, &email).await?;
+
+        connection.commit().await?;
+
+        Ok(email)
+    }
+
+    /// Resizes the uploaded image, stores it as the user avatar and returns the avatar file name.
+    pub(crate) async fn update_avatar<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        image_data: Vec<u8>,
+        file_system: &FS,
+    ) -> Result<String, UpdateAvatarError> {
+        let avatar_data =
+            actix_web::web::block(move || resize_to_square_png(&image_data, AVATAR_SIZE))
+                .await
+                .map_err(|error| {
+                    error!(?error, "Unable to run avatar resizing task");
+                    UpdateAvatarError::InvalidImage
+                })?
+                .map_err(|error| {
+                    warn!(?error, "Unable to process avatar image");
+                    UpdateAvatarError::InvalidImage
+                })?;
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let user = match users::get_user_by_id(&mut connection, user_id).await? {
+            Some(user) => user,
+            None => return Err(UpdateAvatarError::UserNotFound),
+        };
+
+        let avatar = format!(
+            "avatar{:05}_{}.png",
+            **user_id,
+            uuid::Uuid::new_v4().to_simple()
+        );
+
+        file_system
+            .put_file(&format!("avatars/{}", avatar), avatar_data)
+            .await?;
+
+        users::update_user_avatar(&mut connection, user_id, Some(&avatar)).await?;
+
+        connection.commit().await?;
+
+        if let Some(previous_avatar) = user.avatar.filter(|avatar| !avatar.is_empty()) {
+            delete_avatar_file(file_system, &previous_avatar).await;
+        }
+
+        Ok(avatar)
+    }
+
+    pub(crate) async fn delete_avatar<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        file_system: &FS,
+    ) -> Result<(), UpdateAvatarError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let user = match users::get_user_by_id(&mut connection, user_id).await? {
+            Some(user) => user,
+            None => return Err(UpdateAvatarError::UserNotFound),
+        };
+
+        users::update_user_avatar(&mut connection, user_id, None).await?;
+
+        connection.commit().await?;
+
+        if let Some(avatar) = user.avatar.filter(|avatar| !avatar.is_empty()) {
+            delete_avatar_file(file_system, &avatar).await;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the account together with its streams, tracks and the audio files
+    /// that are no longer used by any other track.
+    pub(crate) async fn delete_account<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        password: &str,
+        file_system: &FS,
+    ) -> Result<(), DeleteAccountError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let user = match users::get_user_by_id(&mut connection, user_id).await? {
+            Some(user) => user,
+            None => return Err(DeleteAccountError::UserNotFound),
+        };
+
+        let password_hash = user.password.clone().unwrap_or_default();
+
+        if !verify_password(password, &password_hash).unwrap_or_else(|error| {
+            warn!(?error, "Unable to verify password hash");
+            false
+        }) {
+            return Err(DeleteAccountError::InvalidPassword);
+        }
+
+        files::release_user_files(&mut connection, user_id).await?;
+        let unused_files = files::get_unused_user_files(&mut connection, user_id).await?;
+
+        outgoing_streams::delete_user_outgoing_streams(&mut connection, user_id).await?;
+        stream_destinations::delete_user_stream_destinations(&mut connection, user_id).await?;
+        colors::delete_user_colors(&mut connection, user_id).await?;
+        users::delete_user(&mut connection, user_id).await?;
+
+        let mut deleted_files = Vec::with_capacity(unused_files.len());
+
+        for file in unused_files {
+            if files::delete_unused_file(&mut connection, &file.file_id).await? {
+                deleted_files.push(file);
+            }
+        }
+
+        connection.commit().await?;
+
+        warn!(target: "audit", user_id = **user_id, "Account deleted");
+
+        for file in deleted_files.iter() {
+            if let Err(error) = file_system
+                .delete_file(&format!("audio/{}", file.get_path()))
+                .await
+            {
+                warn!(
+                    ?error,
+                    file_id = *file.file_id,
+                    "Unable to delete audio file"
+                );
+            }
+        }
+
+        if let Some(avatar) = user.avatar.filter(|avatar| !avatar.is_empty()) {
+            delete_avatar_file(file_system, &avatar).await;
+        }
+
+        Ok(())
+    }
+}
+
+async fn delete_avatar_file<FS: FileSystem>(file_system: &FS, avatar: &str) {
+    if let Err(error) = file_system
+        .delete_file(&format!("avatars/{}", avatar))
+        .await
+    {
+        warn!(?error, "Unable to delete avatar file");
+    }
+}
@@ -1,11 +1,12 @@
 use crate::config::RadioStreamerConfig;
-use crate::data_structures::{LinkId, OrderId, StreamId, TrackId, UserId};
+use crate::data_structures::{LinkId, OrderId, StreamId, StreamRef, TrackId, UserId};
 use crate::mysql_client::MySqlConnection;
 use crate::pubsub_client::{PubsubClient, PubsubClientError};
 use crate::services::stream_service_utils::get_now_playing;
 use crate::storage::db::repositories::errors::RepositoryError;
 use crate::storage::db::repositories::streams::{
-    get_single_stream_by_id, seek_user_stream_forward, update_stream_status,
+    get_single_stream_by_id, get_single_stream_by_ref, seek_user_stream_forward,
+    update_stream_status,
 };
 use crate::storage::db::repositories::user_stream_tracks::{
     delete_track_by_link_id, get_single_stream_track_by_link_id,
@@ -85,11 +86,11 @@ impl StreamServiceFactory {
 
     pub(crate) async fn create_service_for_user(
         &self,
-        stream_id: &StreamId,
+        stream_ref: &StreamRef,
         user_id: &UserId,
     ) -> Result<StreamService, StreamServiceError> {
         let mut connection = self.mysql_client.connection().await?;
-        let stream_row = get_single_stream_by_id(&mut connection, stream_id).await?;
+        let stream_row = get_single_stream_by_ref(&mut connection, stream_ref).await?;
         drop(connection);
 
         let stream_row = match stream_row {
@@ -102,13 +103,45 @@ impl StreamServiceFactory {
         }
 
         Ok(StreamService::create(
-            stream_id.clone(),
+            stream_row.sid,
             user_id.clone(),
             self.mysql_client.clone(),
             self.radio_streamer_config.clone(),
             self.pubsub_client.clone(),
         ))
     }
+
+    /// Tells the radio streamer and the subscribers to drop the stream that has just been deleted.
+    pub(crate) async fn notify_stream_deleted(
+        &self,
+        stream_id: &StreamId,
+        user_id: &UserId,
+    ) -> Result<(), StreamServiceError> {
+        self.notify_stream_updated(stream_id, user_id).await
+    }
+
+    /// Tells the radio streamer and the subscribers to reload the stream, e.g. after the metadata
+    /// of its tracks has been changed.
+    pub(crate) async fn notify_stream_updated(
+        &self,
+        stream_id: &StreamId,
+        user_id: &UserId,
+    ) -> Result<(), StreamServiceError> {
+        StreamService::create(
+            stream_id.clone(),
+            user_id.clone(),
+            self.mysql_client.clone(),
+            self.radio_streamer_config.clone(),
+            self.pubsub_client.clone(),
+        )
+        .notify_streams();
+
+        self.pubsub_client
+            .publish_restart_channel_message(stream_id, user_id)
+            .await?;
+
+        Ok(())
+    }
 }
 
 pub(crate) struct StreamService {
@@ -136,6 +169,10 @@ impl StreamService {
         }
     }
 
+    pub(crate) fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
     pub(crate) async fn play(&self) -> Result<(), StreamServiceError> {
         let mut connection = self.mysql_client.connection().await?;
         self.play_internal(&mut connection).await?;
@@ -534,17 +571,46 @@ impl StreamService {
     {
         let mut connection = self.mysql_client.transaction().await?;
 
-        let now_playing = get_now_playing(&SystemTime::now(), &self.stream_id, &mut connection)
+        let restart_required = self
+            .update_stream_on_connection(&mut connection, handler)
+            .await?;
+
+        connection.commit().await?;
+
+        if restart_required {
+            self.notify_stream_restarted().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the playlist changes made by the handler within the caller's transaction and
+    /// keeps the currently playing track in place. Returns `true` if the playing track has
+    /// been removed and the stream has to be restarted by `notify_stream_restarted` once
+    /// the transaction is committed.
+    pub(crate) async fn update_stream_on_connection<H>(
+        &self,
+        connection: &mut MySqlConnection,
+        handler: H,
+    ) -> Result<bool, StreamServiceError>
+    where
+        H: for<'a> FnOnce(
+            &'a mut MySqlConnection,
+        ) -> Pin<
+            Box<dyn Future<Output = Result<(), StreamServiceError>> + Send + 'a>,
+        >,
+    {
+        let now_playing = get_now_playing(&SystemTime::now(), &self.stream_id, connection)
             .await
             .map(|option| option.map(|(entry, _, _, _)| entry))?;
 
         debug!("Now playing track before transaction: {:?}", &now_playing);
 
-        handler(&mut connection).await?;
+        handler(connection).await?;
 
         if let Some(now_playing_entry) = now_playing {
             let new_track_offset = get_single_stream_track_by_link_id(
-                &mut connection,
+                connection,
                 &self.stream_id,
                 &now_playing_entry.link.id,
             )
@@ -561,7 +627,7 @@ impl StreamService {
                     );
 
                     seek_user_stream_forward(
-                        &mut connection,
+                        connection,
                         &self.stream_id,
                         &Duration::milliseconds(offset_change),
                     )
@@ -571,7 +637,7 @@ impl StreamService {
                     debug!("Now playing track has been removed during transaction. Moving forward to play the next track");
 
                     let next_track_time_offset = get_single_stream_track_by_order_id(
-                        &mut connection,
+                        connection,
                         &self.stream_id,
                         &now_playing_entry.link.t_order,
                     )
@@ -586,7 +652,7 @@ impl StreamService {
                             );
 
                             update_stream_status(
-                                &mut connection,
+                                connection,
                                 &self.stream_id,
                                 &StreamStatus::Playing,
                                 &Some(now()),
@@ -598,7 +664,7 @@ impl StreamService {
                             debug!("Playlist seems to be empty: stopping the stream");
 
                             update_stream_status(
-                                &mut connection,
+                                connection,
                                 &self.stream_id,
                                 &StreamStatus::Stopped,
                                 &None,
@@ -608,15 +674,21 @@ impl StreamService {
                         }
                     }
 
-                    self.notify_streams();
-
-                    self.pubsub_client
-                        .publish_restart_channel_message(&self.stream_id, &self.user_id)
-                        .await?;
+                    return Ok(true);
                 }
             }
         }
 
+        Ok(false)
+    }
+
+    pub(crate) async fn notify_stream_restarted(&self) -> Result<(), StreamServiceError> {
+        self.notify_streams();
+
+        self.pubsub_client
+            .publish_restart_channel_message(&self.stream_id, &self.user_id)
+            .await?;
+
         Ok(())
     }
 
@@ -2,15 +2,17 @@ use crate::data_structures::StreamId;
 use crate::mysql_client::MySqlConnection;
 use crate::services::StreamServiceError;
 use crate::storage::db::repositories::streams::{
-    get_single_stream_by_id, get_stream_playlist_duration,
+    get_single_stream_by_id, get_stream_playlist_duration, get_streams_playlist_durations,
 };
 use crate::storage::db::repositories::user_stream_tracks::{
     get_current_and_next_stream_track_at_time_offset, get_single_stream_track_at_time_offset,
+    get_stream_tracks, get_stream_tracks_at_time_offsets, GetUserStreamTracksParams,
     TrackFileLinkMergedRow,
 };
-use crate::storage::db::repositories::StreamStatus;
+use crate::storage::db::repositories::{StreamRow, StreamStatus};
 use crate::utils::positive_mod;
 use chrono::Duration;
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub(crate) async fn get_now_playing(
@@ -72,3 +74,273 @@ pub(crate) async fn get_now_playing(
         },
     )
 }
+
+/// Position of the stream within its playlist at `time_millis`, or `None` if the stream
+/// is stopped or its playlist is empty.
+fn playlist_position(
+    stream_row: &StreamRow,
+    time_millis: i64,
+    playlist_duration: i64,
+) -> Option<i64> {
+    if playlist_duration <= 0 {
+        return None;
+    }
+
+    match (
+        &stream_row.status,
+        &stream_row.started,
+        &stream_row.started_from,
+    ) {
+        (StreamStatus::Paused, Some(_), Some(started_from)) => {
+            Some(positive_mod(*started_from, playlist_duration))
+        }
+        (StreamStatus::Playing, Some(started_at), Some(started_from)) => Some(positive_mod(
+            time_millis - started_at + started_from,
+            playlist_duration,
+        )),
+        _ => None,
+    }
+}
+
+/// Returns the tracks now playing on the streams by their ids, using two queries however
+/// many streams there are.
+pub(crate) async fn get_now_playing_tracks(
+    time: &SystemTime,
+    stream_rows: &[&StreamRow],
+    connection: &mut MySqlConnection,
+) -> Result<HashMap<StreamId, TrackFileLinkMergedRow>, StreamServiceError> {
+    let stream_ids: Vec<StreamId> = stream_rows
+        .iter()
+        .filter(|row| !matches!(row.status, StreamStatus::Stopped))
+        .map(|row| row.sid.clone())
+        .collect();
+    let playlist_durations: HashMap<StreamId, i64> =
+        get_streams_playlist_durations(connection, &stream_ids)
+            .await?
+            .into_iter()
+            .collect();
+
+    let time_millis = time.duration_since(UNIX_EPOCH).unwrap().as_millis() as i64;
+    let time_offsets: Vec<(StreamId, i64)> = stream_rows
+        .iter()
+        .filter_map(|row| {
+            let playlist_duration = *playlist_durations.get(&row.sid)?;
+
+            playlist_position(row, time_millis, playlist_duration)
+                .map(|position| (row.sid.clone(), position))
+        })
+        .collect();
+
+    Ok(get_stream_tracks_at_time_offsets(connection, &time_offsets)
+        .await?
+        .into_iter()
+        .map(|track| (track.link.stream_id.clone(), track))
+        .collect())
+}
+
+/// Track of the stream schedule starting at `starts_at`, unix time in milliseconds.
+pub(crate) struct ScheduledTrack {
+    pub(crate) track: TrackFileLinkMergedRow,
+    pub(crate) starts_at: i64,
+}
+
+/// Projects the looping playlist, given as the time offsets and the durations of its tracks
+/// in milliseconds, onto the wall clock. The playlist starts over every `playlist_duration`
+/// from `cycle_start`. Returns the indexes and the start times of the tracks playing between
+/// `from` and `to`, at most `limit` of them.
+fn project_schedule(
+    tracks: &[(i64, i64)],
+    playlist_duration: i64,
+    cycle_start: i64,
+    from: i64,
+    to: i64,
+    limit: usize,
+) -> Vec<(usize, i64)> {
+    let mut schedule = vec![];
+
+    if tracks.is_empty() || playlist_duration <= 0 || to <= from {
+        return schedule;
+    }
+
+    let mut cycle = (from - cycle_start).div_euclid(playlist_duration);
+
+    loop {
+        let playlist_start = cycle_start + cycle * playlist_duration;
+
+        for (index, (time_offset, duration)) in tracks.iter().enumerate() {
+            let starts_at = playlist_start + time_offset;
+
+            if starts_at >= to || schedule.len() >= limit {
+                return schedule;
+            }
+
+            if starts_at + duration > from {
+                schedule.push((index, starts_at));
+            }
+        }
+
+        cycle += 1;
+    }
+}
+
+/// Schedule of the stream around the given time.
+pub(crate) struct StreamSchedule {
+    pub(crate) status: StreamStatus,
+    /// Position within the playlist in milliseconds, `None` if the stream is stopped
+    /// or its playlist is empty.
+    pub(crate) position: Option<i64>,
+    pub(crate) tracks: Vec<ScheduledTrack>,
+}
+
+/// Returns the tracks of the stream between `from` and `to`, unix times in milliseconds,
+/// with their exact start times. A paused stream doesn't advance, so its tracks are listed
+/// from the paused one on as if it was resumed at `time`; a stopped stream has none.
+pub(crate) async fn get_schedule(
+    time: &SystemTime,
+    from: i64,
+    to: i64,
+    limit: usize,
+    stream_id: &StreamId,
+    connection: &mut MySqlConnection,
+) -> Result<StreamSchedule, StreamServiceError> {
+    let stream_row = match get_single_stream_by_id(connection, stream_id).await? {
+        Some(stream_row) => stream_row,
+        None => return Err(StreamServiceError::StreamNotFound),
+    };
+
+    let time_millis = time.duration_since(UNIX_EPOCH).unwrap().as_millis() as i64;
+    let playlist_duration = get_stream_playlist_duration(connection, stream_id)
+        .await?
+        .num_milliseconds();
+
+    let position = match playlist_position(&stream_row, time_millis, playlist_duration) {
+        Some(position) => position,
+        None => {
+            return Ok(StreamSchedule {
+                status: stream_row.status,
+                position: None,
+                tracks: vec![],
+            })
+        }
+    };
+
+    let from = match stream_row.status {
+        StreamStatus::Paused => from.max(time_millis),
+        _ => from,
+    };
+
+    let rows = get_stream_tracks(
+        connection,
+        stream_id,
+        &GetUserStreamTracksParams::default(),
+        &None,
+        &None,
+    )
+    .await?;
+
+    let tracks: Vec<(i64, i64)> = rows
+        .iter()
+        .map(|row| (row.link.time_offset, row.track.duration))
+        .collect();
+    let schedule = project_schedule(
+        &tracks,
+        playlist_duration,
+        time_millis - position,
+        from,
+        to,
+        limit,
+    )
+    .into_iter()
+    .map(|(index, starts_at)| ScheduledTrack {
+        track: rows[index].clone(),
+        starts_at,
+    })
+    .collect();
+
+    Ok(StreamSchedule {
+        status: stream_row.status,
+        position: Some(position),
+        tracks: schedule,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACKS: [(i64, i64); 3] = [(0, 1000), (1000, 2000), (3000, 500)];
+
+    fn stream_row(
+        status: StreamStatus,
+        started: Option<i64>,
+        started_from: Option<i64>,
+    ) -> StreamRow {
+        StreamRow {
+            sid: StreamId::from(1),
+            uid: crate::data_structures::UserId::from(1),
+            name: String::new(),
+            permalink: None,
+            info: String::new(),
+            jingle_interval: 0,
+            status,
+            started,
+            started_from,
+            access: "PUBLIC".to_string(),
+            category: None,
+            hashtags: String::new(),
+            cover: None,
+            cover_background: None,
+            created: 0,
+            rtmp_url: String::new(),
+            rtmp_streaming_key: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_computes_playlist_position() {
+        let playing = stream_row(StreamStatus::Playing, Some(10000), Some(500));
+        assert_eq!(Some(2000), playlist_position(&playing, 15000, 3500));
+
+        let paused = stream_row(StreamStatus::Paused, Some(10000), Some(4000));
+        assert_eq!(Some(500), playlist_position(&paused, 15000, 3500));
+
+        let stopped = stream_row(StreamStatus::Stopped, None, None);
+        assert_eq!(None, playlist_position(&stopped, 15000, 3500));
+        assert_eq!(None, playlist_position(&playing, 15000, 0));
+    }
+
+    #[test]
+    fn test_projects_schedule_within_playlist() {
+        assert_eq!(
+            vec![(1, 11000), (2, 13000)],
+            project_schedule(&TRACKS, 3500, 10000, 11500, 13100, 100)
+        );
+    }
+
+    #[test]
+    fn test_projects_schedule_across_playlist_wrap_around() {
+        assert_eq!(
+            vec![(2, 13000), (0, 13500), (1, 14500)],
+            project_schedule(&TRACKS, 3500, 10000, 13200, 14600, 100)
+        );
+    }
+
+    #[test]
+    fn test_projects_schedule_before_cycle_start() {
+        // The stream started from the middle of the playlist.
+        assert_eq!(
+            vec![(1, 8500), (2, 10500)],
+            project_schedule(&TRACKS, 3500, 7500, 10000, 10600, 100)
+        );
+    }
+
+    #[test]
+    fn test_limits_projected_schedule() {
+        assert_eq!(
+            vec![(0, 0), (1, 1000)],
+            project_schedule(&TRACKS, 3500, 0, 0, 100000, 2)
+        );
+        assert!(project_schedule(&[], 0, 0, 0, 100000, 100).is_empty());
+        assert!(project_schedule(&TRACKS, 3500, 0, 1000, 1000, 100).is_empty());
+    }
+}
@@ -0,0 +1,393 @@
+use crate::data_structures::{StreamId, StreamRef, TrackId, UserId};
+use crate::mysql_client::{MySqlClient, MySqlConnection};
+use crate::services::stream_service::StreamService;
+use crate::services::{StreamServiceError, StreamServiceFactory};
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::user_stream_tracks::{
+    get_stream_ids_having_tracks, get_stream_tracks_count, get_track_ids_in_stream,
+    insert_track_into_stream, optimize_tracks_in_user_stream, remove_tracks_from_stream,
+    shift_stream_tracks, GetUserStreamTracksParams,
+};
+use crate::storage::db::repositories::user_tracks::{
+    get_user_tracks, get_user_tracks_by_ids, GetUserTracksParams, TrackFileMergedRow,
+};
+use crate::storage::db::repositories::{colors, user_tracks};
+use crate::storage::db::track_query::TrackQuery;
+use crate::utils::dedup_track_ids;
+use tracing::error;
+
+const MAX_BULK_TRACKS: usize = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum TrackBulkError {
+    #[error("No tracks selected")]
+    NoTracks,
+    #[error("Too many tracks")]
+    TooManyTracks,
+    #[error("Stream not found")]
+    StreamNotFound,
+    #[error("Color not found")]
+    ColorNotFound,
+    #[error(transparent)]
+    StreamServiceError(#[from] StreamServiceError),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Tracks the bulk operation is applied to: either listed explicitly or matched by the search query.
+pub(crate) enum TrackSelection {
+    TrackIds(Vec<TrackId>),
+    Query(TrackQuery),
+}
+
+pub(crate) enum TrackBulkOperation {
+    /// Moves the tracks to the trash and removes them from the playlists.
+    Delete,
+    /// Inserts the tracks into the stream playlist before the track at the given position,
+    /// or appends them when the position is missing or past the end of the playlist.
+    AddToStream {
+        stream_ref: StreamRef,
+        position: Option<usize>,
+    },
+    RemoveFromStream {
+        stream_ref: StreamRef,
+    },
+    Recolor {
+        color_id: i32,
+    },
+    SetShareable {
+        can_be_shared: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TrackBulkItemStatus {
+    Done,
+    TrackNotFound,
+    NotInStream,
+}
+
+pub(crate) struct TrackBulkItemResult {
+    pub(crate) track_id: TrackId,
+    pub(crate) status: TrackBulkItemStatus,
+}
+
+/// Marks the tracks as done unless a more specific status has already been given to them.
+fn complete_items(items: &mut [TrackBulkItemResult], track_ids: &[TrackId]) {
+    for item in items.iter_mut() {
+        if item.status == TrackBulkItemStatus::TrackNotFound && track_ids.contains(&item.track_id) {
+            item.status = TrackBulkItemStatus::Done;
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct TrackBulkService {
+    mysql_client: MySqlClient,
+    stream_service_factory: StreamServiceFactory,
+}
+
+impl TrackBulkService {
+    pub(crate) fn new(
+        mysql_client: MySqlClient,
+        stream_service_factory: StreamServiceFactory,
+    ) -> Self {
+        Self {
+            mysql_client,
+            stream_service_factory,
+        }
+    }
+
+    /// Applies the operation to the selected tracks in one transaction and returns the result
+    /// for every selected track. Tracks not found in the user's library are left out of the
+    /// operation instead of failing it.
+    pub(crate) async fn run_operation(
+        &self,
+        user_id: &UserId,
+        selection: &TrackSelection,
+        operation: &TrackBulkOperation,
+    ) -> Result<Vec<TrackBulkItemResult>, TrackBulkError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let (rows, mut items) = self
+            .select_tracks(&mut connection, user_id, selection)
+            .await?;
+
+        let track_ids: Vec<TrackId> = rows.iter().map(|row| row.track.tid.clone()).collect();
+
+        let mut restarted_streams: Vec<StreamService> = vec![];
+
+        match operation {
+            TrackBulkOperation::Delete => {
+                for stream_id in get_stream_ids_having_tracks(&mut connection, &track_ids).await? {
+                    let stream_service = self
+                        .stream_service_factory
+                        .create_service(&stream_id)
+                        .await?;
+
+                    if remove_from_stream(&mut connection, &stream_service, &track_ids).await? {
+                        restarted_streams.push(stream_service);
+                    }
+                }
+
+                // The tracks are kept in the trash until restored or purged.
+                user_tracks::trash_user_tracks(&mut connection, user_id, &track_ids).await?;
+            }
+            TrackBulkOperation::AddToStream {
+                stream_ref,
+                position,
+            } => {
+                let stream_service = self.get_stream_service(user_id, stream_ref).await?;
+                let stream_id = stream_service.stream_id().clone();
+                let position = *position;
+                let added_track_ids = track_ids.clone();
+
+                let restart_required = stream_service
+                    .update_stream_on_connection(&mut connection, move |connection| {
+                        Box::pin(async move {
+                            add_to_stream(connection, &stream_id, &added_track_ids, position)
+                                .await?;
+                            Ok(())
+                        })
+                    })
+                    .await?;
+
+                if restart_required {
+                    restarted_streams.push(stream_service);
+                }
+            }
+            TrackBulkOperation::RemoveFromStream { stream_ref } => {
+                let stream_service = self.get_stream_service(user_id, stream_ref).await?;
+
+                let stream_track_ids = get_track_ids_in_stream(
+                    &mut connection,
+                    stream_service.stream_id(),
+                    &track_ids,
+                )
+                .await?;
+
+                for item in items.iter_mut() {
+                    if track_ids.contains(&item.track_id)
+                        && !stream_track_ids.contains(&item.track_id)
+                    {
+                        item.status = TrackBulkItemStatus::NotInStream;
+                    }
+                }
+
+                if remove_from_stream(&mut connection, &stream_service, &stream_track_ids).await? {
+                    restarted_streams.push(stream_service);
+                }
+            }
+            TrackBulkOperation::Recolor { color_id } => {
+                if colors::get_user_color(&mut connection, user_id, *color_id)
+                    .await?
+                    .is_none()
+                {
+                    return Err(TrackBulkError::ColorNotFound);
+                }
+
+                user_tracks::update_user_tracks_color(
+                    &mut connection,
+                    user_id,
+                    &track_ids,
+                    *color_id,
+                )
+                .await?;
+            }
+            TrackBulkOperation::SetShareable { can_be_shared } => {
+                user_tracks::update_user_tracks_shareable(
+                    &mut connection,
+                    user_id,
+                    &track_ids,
+                    *can_be_shared,
+                )
+                .await?;
+            }
+        }
+
+        connection.commit().await?;
+
+        for stream_service in restarted_streams {
+            if let Err(error) = stream_service.notify_stream_restarted().await {
+                error!(?error, "Unable to notify about restarted stream");
+            }
+        }
+
+        complete_items(&mut items, &track_ids);
+
+        Ok(items)
+    }
+
+    /// Returns the selected tracks found in the user's library along with the initial item results.
+    async fn select_tracks(
+        &self,
+        connection: &mut MySqlConnection,
+        user_id: &UserId,
+        selection: &TrackSelection,
+    ) -> Result<(Vec<TrackFileMergedRow>, Vec<TrackBulkItemResult>), TrackBulkError> {
+        let (rows, requested_track_ids) = match selection {
+            TrackSelection::TrackIds(track_ids) => {
+                let track_ids = dedup_track_ids(track_ids);
+
+                if track_ids.is_empty() {
+                    return Err(TrackBulkError::NoTracks);
+                }
+
+                if track_ids.len() > MAX_BULK_TRACKS {
+                    return Err(TrackBulkError::TooManyTracks);
+                }
+
+                let rows = get_user_tracks_by_ids(connection, user_id, &track_ids).await?;
+
+                (rows, track_ids)
+            }
+            TrackSelection::Query(query) => {
+                let params = GetUserTracksParams {
+                    filter: Some(query.clone()),
+                    ..GetUserTracksParams::default()
+                };
+                let rows = get_user_tracks(
+                    connection,
+                    user_id,
+                    &params,
+                    &None,
+                    &Some(MAX_BULK_TRACKS as i64 + 1),
+                )
+                .await?;
+
+                if rows.len() > MAX_BULK_TRACKS {
+                    return Err(TrackBulkError::TooManyTracks);
+                }
+
+                let track_ids = rows.iter().map(|row| row.track.tid.clone()).collect();
+
+                (rows, track_ids)
+            }
+        };
+
+        let items = requested_track_ids
+            .into_iter()
+            .map(|track_id| TrackBulkItemResult {
+                track_id,
+                status: TrackBulkItemStatus::TrackNotFound,
+            })
+            .collect();
+
+        Ok((rows, items))
+    }
+
+    async fn get_stream_service(
+        &self,
+        user_id: &UserId,
+        stream_ref: &StreamRef,
+    ) -> Result<StreamService, TrackBulkError> {
+        match self
+            .stream_service_factory
+            .create_service_for_user(stream_ref, user_id)
+            .await
+        {
+            Ok(stream_service) => Ok(stream_service),
+            Err(StreamServiceError::StreamNotFound | StreamServiceError::Forbidden) => {
+                Err(TrackBulkError::StreamNotFound)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+/// Removes the tracks from the stream playlist. Returns `true` if the stream has to be restarted.
+async fn remove_from_stream(
+    connection: &mut MySqlConnection,
+    stream_service: &StreamService,
+    track_ids: &[TrackId],
+) -> Result<bool, TrackBulkError> {
+    if track_ids.is_empty() {
+        return Ok(false);
+    }
+
+    let stream_id = stream_service.stream_id().clone();
+    let removed_track_ids = track_ids.to_vec();
+
+    let restart_required = stream_service
+        .update_stream_on_connection(connection, move |connection| {
+            Box::pin(async move {
+                remove_tracks_from_stream(connection, &stream_id, &removed_track_ids).await?;
+                optimize_tracks_in_user_stream(connection, &stream_id).await?;
+                Ok(())
+            })
+        })
+        .await?;
+
+    Ok(restart_required)
+}
+
+async fn add_to_stream(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    track_ids: &[TrackId],
+    position: Option<usize>,
+) -> Result<(), RepositoryError> {
+    if track_ids.is_empty() {
+        return Ok(());
+    }
+
+    // Makes the playlist orders sequential, so the position maps to the order directly.
+    optimize_tracks_in_user_stream(connection, stream_id).await?;
+
+    let tracks_count =
+        get_stream_tracks_count(connection, stream_id, &GetUserStreamTracksParams::default())
+            .await? as usize;
+    let first_t_order = position.unwrap_or(tracks_count).min(tracks_count) as i32 + 1;
+
+    shift_stream_tracks(connection, stream_id, first_t_order, track_ids.len() as i32).await?;
+
+    for (index, track_id) in track_ids.iter().enumerate() {
+        insert_track_into_stream(
+            connection,
+            stream_id,
+            track_id,
+            first_t_order + index as i32,
+        )
+        .await?;
+    }
+
+    optimize_tracks_in_user_stream(connection, stream_id).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completes_item_results() {
+        let mut items = vec![
+            TrackBulkItemResult {
+                track_id: TrackId::from(1),
+                status: TrackBulkItemStatus::TrackNotFound,
+            },
+            TrackBulkItemResult {
+                track_id: TrackId::from(2),
+                status: TrackBulkItemStatus::NotInStream,
+            },
+            TrackBulkItemResult {
+                track_id: TrackId::from(3),
+                status: TrackBulkItemStatus::TrackNotFound,
+            },
+        ];
+
+        complete_items(&mut items, &[TrackId::from(1), TrackId::from(2)]);
+
+        assert_eq!(
+            vec![
+                TrackBulkItemStatus::Done,
+                TrackBulkItemStatus::NotInStream,
+                TrackBulkItemStatus::TrackNotFound,
+            ],
+            items.iter().map(|item| item.status).collect::<Vec<_>>()
+        );
+    }
+}
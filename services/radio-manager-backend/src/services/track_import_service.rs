@@ -0,0 +1,937 @@
+use crate::data_structures::{StreamId, StreamRef, TrackImportSource, TrackImportStatus, UserId};
+use crate::mysql_client::MySqlClient;
+use crate::pubsub_client::PubsubClient;
+use crate::services::artwork_service::ArtworkService;
+use crate::services::audio_storage::{compute_file_hash, store_audio_file, temporary_file_path};
+use crate::services::ffmpeg_service::{probe_audio_file, ProbeAudioFileError};
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::track_import_jobs::{self, TrackImportResult};
+use crate::storage::db::repositories::user_tracks::NewTrack;
+use crate::storage::db::repositories::{
+    streams, user_stream_tracks, user_tracks, users, TrackImportJobErrorRow, TrackImportJobRow,
+};
+use crate::storage::fs::FileSystem;
+use async_zip::error::ZipError;
+use async_zip::tokio::read::fs::ZipFileReader;
+use chrono::Utc;
+use futures::AsyncReadExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+
+const SUPPORTED_EXTENSIONS: [&str; 11] = [
+    "mp3", "flac", "aac", "ogg", "m4a", "wav", "mod", "xm", "s3m", "stm", "it",
+];
+
+const MAX_TRACK_DURATION: Duration = Duration::from_secs(4 * 60 * 60);
+
+/// Limits of the uploaded archives, protecting the server from archives which unpack
+/// into much more data than they take.
+const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+const MAX_ARCHIVE_ENTRY_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+const MAX_ARCHIVE_UNCOMPRESSED_SIZE: u64 = 20 * 1024 * 1024 * 1024;
+
+/// Running jobs renew their lease periodically, so the jobs of the stopped processes
+/// can be told apart from the jobs running in the other processes.
+const JOB_LEASE_DURATION: Duration = Duration::from_secs(5 * 60);
+const JOB_LEASE_RENEWAL_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum TrackImportError {
+    #[error("Stream not found")]
+    StreamNotFound,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Import job not found")]
+    JobNotFound,
+    #[error("Invalid archive: {0}")]
+    InvalidArchive(#[from] ZipError),
+    #[error("Archive is too large")]
+    ArchiveTooLarge,
+    #[error("Directory not found")]
+    DirectoryNotFound,
+    #[error("Directory import is disabled")]
+    DirectoryImportDisabled,
+    #[error(transparent)]
+    FileSystemError(#[from] std::io::Error),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Errors of single files, which are recorded into the job and don't stop the import.
+#[derive(thiserror::Error, Debug)]
+enum FileImportError {
+    #[error("Unable to extract the file from the archive")]
+    ExtractionError(#[from] ZipError),
+    #[error("File is too large")]
+    FileTooLarge,
+    #[error("Archive is too large")]
+    ArchiveTooLarge,
+    #[error("Unable to read audio file metadata")]
+    InvalidAudioFile(#[from] ProbeAudioFileError),
+    #[error("Audio file duration is too long")]
+    DurationTooLong,
+    #[error("Unable to store the file")]
+    FileSystemError(#[from] std::io::Error),
+    #[error("Unable to save the track")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Unable to save the track")]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Importable file of the archive.
+struct ArchiveEntry {
+    index: usize,
+    name: String,
+}
+
+enum ImportSource {
+    Zip {
+        archive: ZipFileReader,
+        entries: Vec<ArchiveEntry>,
+    },
+    Directory {
+        root_path: PathBuf,
+        files: Vec<PathBuf>,
+    },
+}
+
+impl ImportSource {
+    fn files_count(&self) -> usize {
+        match self {
+            ImportSource::Zip { entries, .. } => entries.len(),
+            ImportSource::Directory { files, .. } => files.len(),
+        }
+    }
+}
+
+/// File to import, extracted to the local file system.
+struct LocalFile {
+    name: String,
+    path: PathBuf,
+    is_temporary: bool,
+}
+
+impl Drop for LocalFile {
+    fn drop(&mut self) {
+        if self.is_temporary {
+            if let Err(error) = std::fs::remove_file(&self.path) {
+                warn!(?error, "Unable to delete temporary file");
+            }
+        }
+    }
+}
+
+fn get_extension(file_name: &str) -> Option<String> {
+    Path::new(file_name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+fn get_base_name(file_name: &str) -> String {
+    Path::new(file_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Checks whether the file should be imported: hidden files, macOS archive metadata
+/// and files of unsupported formats are ignored.
+fn is_importable_file(path: &str) -> bool {
+    let is_hidden = path
+        .split('/')
+        .any(|component| component.starts_with('.') || component == "__MACOSX");
+
+    !is_hidden
+        && get_extension(path)
+            .map(|extension| SUPPORTED_EXTENSIONS.contains(&extension.as_str()))
+            .unwrap_or(false)
+}
+
+/// Lists the importable files of the archive, rejecting the archives exceeding the limits
+/// by the sizes declared in their central directory. The actual sizes are checked
+/// on extraction.
+fn list_archive_entries(archive: &ZipFileReader) -> Result<Vec<ArchiveEntry>, TrackImportError> {
+    let entries = archive.file().entries();
+
+    if entries.len() > MAX_ARCHIVE_ENTRIES {
+        return Err(TrackImportError::ArchiveTooLarge);
+    }
+
+    let mut importable_entries = vec![];
+    let mut uncompressed_size = 0u64;
+
+    for (index, entry) in entries.iter().enumerate() {
+        let name = String::from_utf8_lossy(entry.filename().as_bytes()).to_string();
+
+        if entry.dir()? || !is_importable_file(&name) {
+            continue;
+        }
+
+        uncompressed_size = uncompressed_size.saturating_add(entry.uncompressed_size());
+        importable_entries.push(ArchiveEntry { index, name });
+    }
+
+    if uncompressed_size > MAX_ARCHIVE_UNCOMPRESSED_SIZE {
+        return Err(TrackImportError::ArchiveTooLarge);
+    }
+
+    Ok(importable_entries)
+}
+
+/// Extracts the entry into a temporary file, checking its checksum and actual size:
+/// the sizes declared in the archive can't be trusted.
+async fn extract_zip_entry(
+    archive: &ZipFileReader,
+    entry: &ArchiveEntry,
+    remaining_size: u64,
+) -> Result<(LocalFile, u64), FileImportError> {
+    let local_file = LocalFile {
+        name: entry.name.clone(),
+        path: temporary_file_path(&get_extension(&entry.name).unwrap_or_default()),
+        is_temporary: true,
+    };
+
+    let mut reader = archive.reader_with_entry(entry.index).await?;
+    let mut output = tokio::fs::File::create(&local_file.path).await?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+
+    loop {
+        let read = reader.read(&mut buffer).await.map_err(ZipError::from)?;
+
+        if read == 0 {
+            break;
+        }
+
+        size += read as u64;
+
+        if size > remaining_size {
+            return Err(FileImportError::ArchiveTooLarge);
+        }
+
+        if size > MAX_ARCHIVE_ENTRY_SIZE {
+            return Err(FileImportError::FileTooLarge);
+        }
+
+        output.write_all(&buffer[..read]).await?;
+    }
+
+    output.flush().await?;
+
+    if reader.compute_hash() != reader.entry().crc32() {
+        return Err(ZipError::CRC32CheckError.into());
+    }
+
+    Ok((local_file, size))
+}
+
+fn list_directory_files(root_path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut directories = vec![root_path.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+
+            if file_type.is_dir() {
+                directories.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Imports audio files into the user's library in the background, tracking the progress
+/// in the `track_import_jobs` table.
+#[derive(Clone)]
+pub(crate) struct TrackImportService {
+    mysql_client: MySqlClient,
+    pubsub_client: PubsubClient,
+    artwork_service: ArtworkService,
+    path_to_ffprobe: String,
+    import_directory_root: Option<PathBuf>,
+}
+
+impl TrackImportService {
+    pub(crate) fn new(
+        mysql_client: MySqlClient,
+        pubsub_client: PubsubClient,
+        artwork_service: ArtworkService,
+        path_to_ffprobe: &str,
+        import_directory_root: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            mysql_client,
+            pubsub_client,
+            artwork_service,
+            path_to_ffprobe: path_to_ffprobe.to_string(),
+            import_directory_root,
+        }
+    }
+
+    /// Marks the jobs whose process has stopped without finishing them as failed.
+    pub(crate) async fn fail_interrupted_jobs(&self) -> Result<(), TrackImportError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let failed_jobs =
+            track_import_jobs::fail_interrupted_track_import_jobs(&mut connection, &Utc::now())
+                .await?;
+
+        if failed_jobs > 0 {
+            warn!(failed_jobs, "Failed interrupted track import jobs");
+        }
+
+        Ok(())
+    }
+
+    /// Periodically fails the jobs interrupted by the stopped processes.
+    pub(crate) fn schedule_interrupted_jobs_check(&self) {
+        let service = self.clone();
+
+        actix_rt::spawn(async move {
+            loop {
+                actix_rt::time::sleep(JOB_LEASE_DURATION).await;
+
+                if let Err(error) = service.fail_interrupted_jobs().await {
+                    error!(?error, "Unable to fail interrupted track import jobs");
+                }
+            }
+        });
+    }
+
+    pub(crate) async fn get_job(
+        &self,
+        user_id: &UserId,
+        job_id: i32,
+    ) -> Result<TrackImportJobRow, TrackImportError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        track_import_jobs::get_user_track_import_job(&mut connection, user_id, job_id)
+            .await?
+            .ok_or(TrackImportError::JobNotFound)
+    }
+
+    pub(crate) async fn get_jobs(
+        &self,
+        user_id: &UserId,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(i64, Vec<TrackImportJobRow>), TrackImportError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let total_count =
+            track_import_jobs::get_user_track_import_jobs_count(&mut connection, user_id).await?;
+        let jobs = track_import_jobs::get_user_track_import_jobs(
+            &mut connection,
+            user_id,
+            &offset,
+            &limit,
+        )
+        .await?;
+
+        Ok((total_count, jobs))
+    }
+
+    pub(crate) async fn get_job_errors(
+        &self,
+        user_id: &UserId,
+        job_id: i32,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(i64, Vec<TrackImportJobErrorRow>), TrackImportError> {
+        let job = self.get_job(user_id, job_id).await?;
+
+        let mut connection = self.mysql_client.connection().await?;
+
+        let total_count =
+            track_import_jobs::get_track_import_job_errors_count(&mut connection, job.id).await?;
+        let errors = track_import_jobs::get_track_import_job_errors(
+            &mut connection,
+            job.id,
+            &offset,
+            &limit,
+        )
+        .await?;
+
+        Ok((total_count, errors))
+    }
+
+    async fn resolve_stream(
+        &self,
+        user_id: &UserId,
+        stream_ref: &Option<StreamRef>,
+    ) -> Result<Option<StreamId>, TrackImportError> {
+        let stream_ref = match stream_ref {
+            Some(stream_ref) => stream_ref,
+            None => return Ok(None),
+        };
+
+        let mut connection = self.mysql_client.connection().await?;
+
+        match streams::get_single_stream_by_ref(&mut connection, stream_ref).await? {
+            Some(stream) if &stream.uid == user_id => Ok(Some(stream.sid)),
+            _ => Err(TrackImportError::StreamNotFound),
+        }
+    }
+
+    async fn create_job(
+        &self,
+        user_id: &UserId,
+        stream_id: &Option<StreamId>,
+        source: TrackImportSource,
+    ) -> Result<TrackImportJobRow, TrackImportError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let lease_expires_at = Utc::now() + JOB_LEASE_DURATION;
+        let job_id = track_import_jobs::create_track_import_job(
+            &mut connection,
+            user_id,
+            stream_id,
+            source,
+            &lease_expires_at,
+        )
+        .await?;
+
+        track_import_jobs::get_user_track_import_job(&mut connection, user_id, job_id)
+            .await?
+            .ok_or(TrackImportError::JobNotFound)
+    }
+
+    /// Starts importing the uploaded ZIP archive in the background.
+    /// The archive file is deleted when the import is finished.
+    pub(crate) async fn start_zip_import<FS: FileSystem + Clone + 'static>(
+        &self,
+        user_id: &UserId,
+        stream_ref: &Option<StreamRef>,
+        archive_path: PathBuf,
+        file_system: &FS,
+    ) -> Result<TrackImportJobRow, TrackImportError> {
+        let result = async {
+            let archive = ZipFileReader::new(&archive_path).await?;
+            let entries = list_archive_entries(&archive)?;
+            let stream_id = self.resolve_stream(user_id, stream_ref).await?;
+            let job = self
+                .create_job(user_id, &stream_id, TrackImportSource::Zip)
+                .await?;
+
+            Ok::<_, TrackImportError>((job, stream_id, ImportSource::Zip { archive, entries }))
+        }
+        .await;
+
+        let (job, stream_id, source) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                let _ = std::fs::remove_file(&archive_path);
+                return Err(error);
+            }
+        };
+
+        let service = self.clone();
+        let user_id = user_id.clone();
+        let file_system = file_system.clone();
+        let job_id = job.id;
+
+        actix_rt::spawn(async move {
+            service
+                .run_job(job_id, &user_id, &stream_id, &source, &file_system)
+                .await;
+
+            if let Err(error) = std::fs::remove_file(archive_path) {
+                warn!(?error, "Unable to delete imported archive");
+            }
+        });
+
+        Ok(job)
+    }
+
+    async fn prepare_directory_import(
+        &self,
+        user_id: &UserId,
+        stream_ref: &Option<StreamRef>,
+        root_path: PathBuf,
+    ) -> Result<(TrackImportJobRow, Option<StreamId>, ImportSource), TrackImportError> {
+        if !root_path.is_dir() {
+            return Err(TrackImportError::DirectoryNotFound);
+        }
+
+        {
+            let mut connection = self.mysql_client.connection().await?;
+
+            if users::get_user_by_id(&mut connection, user_id)
+                .await?
+                .is_none()
+            {
+                return Err(TrackImportError::UserNotFound);
+            }
+        }
+
+        let files = {
+            let root_path = root_path.clone();
+
+            actix_web::web::block(move || list_directory_files(&root_path))
+                .await
+                .map_err(std::io::Error::other)??
+        };
+
+        let files: Vec<_> = files
+            .into_iter()
+            .filter(|path| match path.strip_prefix(&root_path) {
+                Ok(relative_path) => is_importable_file(&relative_path.to_string_lossy()),
+                Err(_) => false,
+            })
+            .collect();
+
+        let stream_id = self.resolve_stream(user_id, stream_ref).await?;
+        let job = self
+            .create_job(user_id, &stream_id, TrackImportSource::Directory)
+            .await?;
+
+        Ok((job, stream_id, ImportSource::Directory { root_path, files }))
+    }
+
+    /// Imports the audio files from the server-side directory and waits for the import to finish.
+    pub(crate) async fn import_directory<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        stream_ref: &Option<StreamRef>,
+        root_path: PathBuf,
+        file_system: &FS,
+    ) -> Result<TrackImportJobRow, TrackImportError> {
+        let (job, stream_id, source) = self
+            .prepare_directory_import(user_id, stream_ref, root_path)
+            .await?;
+
+        self.run_job(job.id, user_id, &stream_id, &source, file_system)
+            .await;
+
+        self.get_job(user_id, job.id).await
+    }
+
+    /// Starts importing the audio files from the directory under the configured import root
+    /// in the background. The path is relative to the import root.
+    pub(crate) async fn start_directory_import<FS: FileSystem + Clone + 'static>(
+        &self,
+        user_id: &UserId,
+        stream_ref: &Option<StreamRef>,
+        path: &Path,
+        file_system: &FS,
+    ) -> Result<TrackImportJobRow, TrackImportError> {
+        let import_directory_root = self
+            .import_directory_root
+            .as_ref()
+            .ok_or(TrackImportError::DirectoryImportDisabled)?;
+
+        let root_path = match (
+            import_directory_root.canonicalize(),
+            import_directory_root.join(path).canonicalize(),
+        ) {
+            (Ok(import_directory_root), Ok(root_path))
+                if root_path.starts_with(&import_directory_root) =>
+            {
+                root_path
+            }
+            _ => return Err(TrackImportError::DirectoryNotFound),
+        };
+
+        let (job, stream_id, source) = self
+            .prepare_directory_import(user_id, stream_ref, root_path)
+            .await?;
+
+        let service = self.clone();
+        let user_id = user_id.clone();
+        let file_system = file_system.clone();
+        let job_id = job.id;
+
+        actix_rt::spawn(async move {
+            service
+                .run_job(job_id, &user_id, &stream_id, &source, &file_system)
+                .await;
+        });
+
+        Ok(job)
+    }
+
+    async fn run_job<FS: FileSystem>(
+        &self,
+        job_id: i32,
+        user_id: &UserId,
+        stream_id: &Option<StreamId>,
+        source: &ImportSource,
+        file_system: &FS,
+    ) {
+        let total_files = source.files_count() as i32;
+
+        info!(job_id, total_files, "Starting track import");
+
+        let lease_renewal = {
+            let mysql_client = self.mysql_client.clone();
+
+            actix_rt::spawn(async move {
+                loop {
+                    actix_rt::time::sleep(JOB_LEASE_RENEWAL_INTERVAL).await;
+
+                    let lease_expires_at = Utc::now() + JOB_LEASE_DURATION;
+
+                    match mysql_client.connection().await {
+                        Ok(mut connection) => {
+                            if let Err(error) = track_import_jobs::renew_track_import_job_lease(
+                                &mut connection,
+                                job_id,
+                                &lease_expires_at,
+                            )
+                            .await
+                            {
+                                error!(?error, job_id, "Unable to renew track import job lease");
+                            }
+                        }
+                        Err(error) => {
+                            error!(?error, job_id, "Unable to renew track import job lease")
+                        }
+                    }
+                }
+            })
+        };
+
+        let status = match self
+            .process_files(job_id, user_id, stream_id, source, file_system)
+            .await
+        {
+            Ok(()) => TrackImportStatus::Finished,
+            Err(error) => {
+                error!(?error, job_id, "Track import failed");
+                TrackImportStatus::Failed
+            }
+        };
+
+        lease_renewal.abort();
+
+        match self.mysql_client.connection().await {
+            Ok(mut connection) => {
+                if let Err(error) =
+                    track_import_jobs::finish_track_import_job(&mut connection, job_id, status)
+                        .await
+                {
+                    error!(?error, job_id, "Unable to finish track import job");
+                }
+            }
+            Err(error) => error!(?error, job_id, "Unable to finish track import job"),
+        }
+
+        if let Err(error) = self
+            .pubsub_client
+            .publish_track_import_finished_message(user_id, job_id, status.as_str())
+            .await
+        {
+            warn!(?error, "Unable to publish track import finished message");
+        }
+
+        info!(job_id, ?status, "Track import finished");
+    }
+
+    async fn process_files<FS: FileSystem>(
+        &self,
+        job_id: i32,
+        user_id: &UserId,
+        stream_id: &Option<StreamId>,
+        source: &ImportSource,
+        file_system: &FS,
+    ) -> Result<(), TrackImportError> {
+        let total_files = source.files_count() as i32;
+        let mut extracted_size = 0u64;
+
+        {
+            let mut connection = self.mysql_client.connection().await?;
+            track_import_jobs::start_track_import_job(&mut connection, job_id, total_files).await?;
+        }
+
+        for index in 0..source.files_count() {
+            let (file_name, result) = match source {
+                ImportSource::Zip { archive, entries } => {
+                    let entry = &entries[index];
+                    let remaining_size = MAX_ARCHIVE_UNCOMPRESSED_SIZE - extracted_size;
+                    let result = match extract_zip_entry(archive, entry, remaining_size).await {
+                        Ok((local_file, size)) => {
+                            extracted_size += size;
+
+                            self.import_file(user_id, stream_id, &local_file, file_system)
+                                .await
+                        }
+                        Err(FileImportError::ArchiveTooLarge) => {
+                            let mut connection = self.mysql_client.connection().await?;
+                            track_import_jobs::add_track_import_job_error(
+                                &mut connection,
+                                job_id,
+                                &entry.name,
+                                &FileImportError::ArchiveTooLarge.to_string(),
+                            )
+                            .await?;
+
+                            return Err(TrackImportError::ArchiveTooLarge);
+                        }
+                        Err(error) => Err(error),
+                    };
+
+                    (entry.name.clone(), result)
+                }
+                ImportSource::Directory { root_path, files } => {
+                    let path = &files[index];
+                    let file_name = path
+                        .strip_prefix(root_path)
+                        .unwrap_or(path)
+                        .to_string_lossy()
+                        .to_string();
+                    let local_file = LocalFile {
+                        name: file_name.clone(),
+                        path: path.clone(),
+                        is_temporary: false,
+                    };
+                    let result = self
+                        .import_file(user_id, stream_id, &local_file, file_system)
+                        .await;
+
+                    (file_name, result)
+                }
+            };
+
+            let mut connection = self.mysql_client.connection().await?;
+
+            let result = match result {
+                Ok(result) => result,
+                Err(error) => {
+                    warn!(?error, job_id, file_name, "Unable to import file");
+                    track_import_jobs::add_track_import_job_error(
+                        &mut connection,
+                        job_id,
+                        &file_name,
+                        &error.to_string(),
+                    )
+                    .await?;
+
+                    TrackImportResult::Failed
+                }
+            };
+
+            track_import_jobs::record_track_import_result(&mut connection, job_id, result).await?;
+
+            if let Err(error) = self
+                .pubsub_client
+                .publish_track_import_progress_message(
+                    user_id,
+                    job_id,
+                    index as i32 + 1,
+                    total_files,
+                )
+                .await
+            {
+                warn!(?error, "Unable to publish track import progress message");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn import_file<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        stream_id: &Option<StreamId>,
+        local_file: &LocalFile,
+        file_system: &FS,
+    ) -> Result<TrackImportResult, FileImportError> {
+        let file_hash = {
+            let path = local_file.path.clone();
+
+            actix_web::web::block(move || compute_file_hash(&path))
+                .await
+                .map_err(std::io::Error::other)??
+        };
+
+        {
+            let mut connection = self.mysql_client.connection().await?;
+
+            if user_tracks::is_track_hash_in_user_library(&mut connection, user_id, &file_hash)
+                .await?
+            {
+                return Ok(TrackImportResult::Skipped);
+            }
+        }
+
+        let metadata =
+            probe_audio_file(&self.path_to_ffprobe, &local_file.path.to_string_lossy()).await?;
+
+        if metadata.duration > MAX_TRACK_DURATION {
+            return Err(FileImportError::DurationTooLong);
+        }
+
+        let extension = get_extension(&local_file.name).unwrap_or_default();
+        let file_size = tokio::fs::metadata(&local_file.path).await?.len();
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let file_id = store_audio_file::<_, FileImportError>(
+            &mut connection,
+            file_system,
+            &local_file.path,
+            &file_hash,
+            &extension,
+        )
+        .await?;
+
+        let file_name = Path::new(&local_file.name)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let base_name = get_base_name(&local_file.name);
+
+        let track_id = user_tracks::create_user_track(
+            &mut connection,
+            user_id,
+            &NewTrack {
+                file_id: &file_id,
+                filename: &file_name,
+                hash: &file_hash,
+                ext: &extension,
+                artist: metadata.artist.as_deref().unwrap_or_default(),
+                title: metadata.title.as_deref().unwrap_or(&base_name),
+                album: metadata.album.as_deref().unwrap_or_default(),
+                track_number: metadata.track_number.as_deref().unwrap_or_default(),
+                genre: metadata.genre.as_deref().unwrap_or_default(),
+                date: metadata.date.as_deref().unwrap_or_default(),
+                duration: metadata.duration.as_millis() as i64,
+                filesize: file_size as i64,
+            },
+        )
+        .await?;
+
+        if let Some(stream_id) = stream_id {
+            user_stream_tracks::append_track_to_stream(&mut connection, stream_id, &track_id)
+                .await?;
+        }
+
+        connection.commit().await?;
+
+        // The track is usable without artwork, so a broken picture doesn't fail the import.
+        if let Err(error) = self
+            .artwork_service
+            .extract_track_artwork(&track_id, &local_file.path, file_system)
+            .await
+        {
+            warn!(?error, ?track_id, "Unable to extract track artwork");
+        }
+
+        Ok(TrackImportResult::Imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_zip::base::write::ZipFileWriter;
+    use async_zip::{Compression, ZipEntryBuilder};
+
+    async fn write_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipFileWriter::new(vec![]);
+
+        for (name, contents) in files {
+            let entry = ZipEntryBuilder::new((*name).into(), Compression::Stored);
+            writer.write_entry_whole(entry, contents).await.unwrap();
+        }
+
+        writer.close().await.unwrap()
+    }
+
+    /// Temporary archive file, deleted when dropped.
+    struct ArchiveFile(PathBuf);
+
+    impl ArchiveFile {
+        fn create(contents: &[u8]) -> Self {
+            let path = temporary_file_path("zip");
+            std::fs::write(&path, contents).unwrap();
+
+            Self(path)
+        }
+    }
+
+    impl Drop for ArchiveFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_filters_importable_files() {
+        assert!(is_importable_file("Artist/Album/01 Song.MP3"));
+        assert!(is_importable_file("song.flac"));
+        assert!(!is_importable_file("Artist/Album/cover.jpg"));
+        assert!(!is_importable_file("__MACOSX/Artist/._01 Song.mp3"));
+        assert!(!is_importable_file("Artist/.hidden/song.mp3"));
+        assert!(!is_importable_file("README"));
+    }
+
+    #[test]
+    fn test_gets_file_name_parts() {
+        assert_eq!(Some("mp3".to_string()), get_extension("Album/01 Song.Mp3"));
+        assert_eq!("01 Song", get_base_name("Album/01 Song.Mp3"));
+    }
+
+    #[actix_rt::test]
+    async fn test_extracts_importable_archive_entries() {
+        let audio = b"ID3 audio data ".repeat(100);
+        let archive = write_archive(&[
+            ("music/", b""),
+            ("music/01 Intro.mp3", &audio),
+            ("music/cover.jpg", b"image"),
+            ("__MACOSX/music/._01 Intro.mp3", b"metadata"),
+        ])
+        .await;
+        let archive_file = ArchiveFile::create(&archive);
+        let archive = ZipFileReader::new(&archive_file.0).await.unwrap();
+
+        let entries = list_archive_entries(&archive).unwrap();
+
+        assert_eq!(
+            vec!["music/01 Intro.mp3"],
+            entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>()
+        );
+
+        let (local_file, size) = extract_zip_entry(&archive, &entries[0], u64::MAX)
+            .await
+            .unwrap();
+
+        assert_eq!(audio.len() as u64, size);
+        assert_eq!(audio, std::fs::read(&local_file.path).unwrap());
+
+        assert!(matches!(
+            extract_zip_entry(&archive, &entries[0], 100).await,
+            Err(FileImportError::ArchiveTooLarge)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_rejects_corrupted_archive_entries() {
+        let mut archive = write_archive(&[("song.mp3", b"audio data")]).await;
+        let position = archive
+            .windows(b"audio data".len())
+            .position(|window| window == b"audio data")
+            .unwrap();
+        archive[position] = b'X';
+        let archive_file = ArchiveFile::create(&archive);
+        let archive = ZipFileReader::new(&archive_file.0).await.unwrap();
+
+        let entries = list_archive_entries(&archive).unwrap();
+
+        assert!(matches!(
+            extract_zip_entry(&archive, &entries[0], u64::MAX).await,
+            Err(FileImportError::ExtractionError(ZipError::CRC32CheckError))
+        ));
+    }
+}
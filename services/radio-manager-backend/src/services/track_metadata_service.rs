@@ -0,0 +1,440 @@
+use crate::data_structures::{StreamId, TrackId, UserId};
+use crate::mysql_client::MySqlClient;
+use crate::pubsub_client::PubsubClient;
+use crate::services::audio_storage::{
+    compute_file_hash, download_to_local_file, store_audio_file, TemporaryFile,
+};
+use crate::services::ffmpeg_service::{write_audio_tags, AudioTags, WriteAudioTagsError};
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::user_tracks::{self, TrackMetadata};
+use crate::storage::db::repositories::{files, streams, StreamStatus, TrackRow};
+use crate::storage::fs::FileSystem;
+use crate::utils::dedup_track_ids;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+const MAX_UPDATED_TRACKS: usize = 100;
+
+const TEXT_TAG_MAX_LENGTH: usize = 255;
+const TRACK_NUMBER_MAX_LENGTH: usize = 11;
+const DATE_MAX_LENGTH: usize = 64;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum TrackMetadataError {
+    #[error("Track not found")]
+    TrackNotFound,
+    #[error("No tracks selected")]
+    NoTracks,
+    #[error("Too many tracks")]
+    TooManyTracks,
+    #[error("Invalid artist")]
+    InvalidArtist,
+    #[error("Invalid title")]
+    InvalidTitle,
+    #[error("Invalid album")]
+    InvalidAlbum,
+    #[error("Invalid track number")]
+    InvalidTrackNumber,
+    #[error("Invalid genre")]
+    InvalidGenre,
+    #[error("Invalid date")]
+    InvalidDate,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+enum WriteTagsError {
+    #[error(transparent)]
+    Ffmpeg(#[from] WriteAudioTagsError),
+    #[error(transparent)]
+    FileSystem(#[from] std::io::Error),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+}
+
+/// Changes of the track metadata: `None` keeps the current value.
+#[derive(Debug, Default)]
+pub(crate) struct TrackMetadataUpdate {
+    pub(crate) artist: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) track_number: Option<String>,
+    pub(crate) genre: Option<String>,
+    pub(crate) date: Option<String>,
+}
+
+/// Trims the value and collapses whitespace, so the fulltext search matches what's displayed.
+fn normalize_tag(value: &str) -> String {
+    value
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn normalize_field(
+    value: &Option<String>,
+    max_length: usize,
+    error: TrackMetadataError,
+) -> Result<Option<String>, TrackMetadataError> {
+    match value {
+        Some(value) => {
+            let value = normalize_tag(value);
+
+            match value.chars().count() > max_length {
+                true => Err(error),
+                false => Ok(Some(value)),
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+impl TrackMetadataUpdate {
+    fn normalize(&self) -> Result<Self, TrackMetadataError> {
+        let update = Self {
+            artist: normalize_field(
+                &self.artist,
+                TEXT_TAG_MAX_LENGTH,
+                TrackMetadataError::InvalidArtist,
+            )?,
+            title: normalize_field(
+                &self.title,
+                TEXT_TAG_MAX_LENGTH,
+                TrackMetadataError::InvalidTitle,
+            )?,
+            album: normalize_field(
+                &self.album,
+                TEXT_TAG_MAX_LENGTH,
+                TrackMetadataError::InvalidAlbum,
+            )?,
+            track_number: normalize_field(
+                &self.track_number,
+                TRACK_NUMBER_MAX_LENGTH,
+                TrackMetadataError::InvalidTrackNumber,
+            )?,
+            genre: normalize_field(
+                &self.genre,
+                TEXT_TAG_MAX_LENGTH,
+                TrackMetadataError::InvalidGenre,
+            )?,
+            date: normalize_field(&self.date, DATE_MAX_LENGTH, TrackMetadataError::InvalidDate)?,
+        };
+
+        if matches!(&update.title, Some(title) if title.is_empty()) {
+            return Err(TrackMetadataError::InvalidTitle);
+        }
+
+        Ok(update)
+    }
+
+    fn apply(&self, track: &mut TrackRow) {
+        for (value, field) in [
+            (&self.artist, &mut track.artist),
+            (&self.title, &mut track.title),
+            (&self.album, &mut track.album),
+            (&self.track_number, &mut track.track_number),
+            (&self.genre, &mut track.genre),
+            (&self.date, &mut track.date),
+        ] {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+    }
+}
+
+/// Edits the metadata of the user's tracks.
+#[derive(Clone)]
+pub(crate) struct TrackMetadataService {
+    mysql_client: MySqlClient,
+    pubsub_client: PubsubClient,
+    path_to_ffmpeg: String,
+    /// Serializes writing the tags, so the audio files aren't rewritten by several
+    /// background tasks at once.
+    write_tags_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl TrackMetadataService {
+    pub(crate) fn new(
+        mysql_client: MySqlClient,
+        pubsub_client: PubsubClient,
+        path_to_ffmpeg: &str,
+    ) -> Self {
+        Self {
+            mysql_client,
+            pubsub_client,
+            path_to_ffmpeg: path_to_ffmpeg.to_string(),
+            write_tags_lock: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// Updates the metadata of the tracks in the database and, if `write_tags` is set, starts
+    /// rewriting the tags of their audio files in the background. Failing to write the tags
+    /// doesn't revert the update.
+    pub(crate) async fn update_tracks<FS: FileSystem + Clone + 'static>(
+        &self,
+        user_id: &UserId,
+        track_ids: &[TrackId],
+        update: &TrackMetadataUpdate,
+        write_tags: bool,
+        file_system: &FS,
+    ) -> Result<Vec<TrackRow>, TrackMetadataError> {
+        let track_ids = dedup_track_ids(track_ids);
+
+        if track_ids.is_empty() {
+            return Err(TrackMetadataError::NoTracks);
+        }
+
+        if track_ids.len() > MAX_UPDATED_TRACKS {
+            return Err(TrackMetadataError::TooManyTracks);
+        }
+
+        let update = update.normalize()?;
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let mut rows =
+            user_tracks::get_user_tracks_by_ids(&mut connection, user_id, &track_ids).await?;
+
+        if rows.len() != track_ids.len() {
+            return Err(TrackMetadataError::TrackNotFound);
+        }
+
+        for row in rows.iter_mut() {
+            update.apply(&mut row.track);
+
+            user_tracks::update_user_track_metadata(
+                &mut connection,
+                &row.track.tid,
+                &TrackMetadata {
+                    artist: &row.track.artist,
+                    title: &row.track.title,
+                    album: &row.track.album,
+                    track_number: &row.track.track_number,
+                    genre: &row.track.genre,
+                    date: &row.track.date,
+                },
+            )
+            .await?;
+        }
+
+        connection.commit().await?;
+
+        if write_tags {
+            let service = self.clone();
+            let user_id = user_id.clone();
+            let track_ids = track_ids.clone();
+            let file_system = file_system.clone();
+
+            actix_rt::spawn(async move {
+                service
+                    .write_tracks_tags(&user_id, &track_ids, &file_system)
+                    .await;
+            });
+        }
+
+        self.notify_streams(user_id, &track_ids).await;
+
+        Ok(rows.into_iter().map(|row| row.track).collect())
+    }
+
+    async fn write_tracks_tags<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        track_ids: &[TrackId],
+        file_system: &FS,
+    ) {
+        let _guard = self.write_tags_lock.lock().await;
+
+        for track_id in track_ids {
+            match self.write_tags(user_id, track_id, file_system).await {
+                Ok(true) => info!(?track_id, "Tags written into audio file"),
+                Ok(false) => warn!(?track_id, "Track has changed while writing tags"),
+                Err(error) => warn!(?error, ?track_id, "Unable to write tags into audio file"),
+            }
+        }
+    }
+
+    /// Writes the current tags of the track into a copy of its audio file and replaces
+    /// the track file with it. Returns whether the track file has been replaced: the track
+    /// may be deleted or get another file meanwhile.
+    async fn write_tags<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        track_id: &TrackId,
+        file_system: &FS,
+    ) -> Result<bool, WriteTagsError> {
+        let row = {
+            let mut connection = self.mysql_client.connection().await?;
+
+            match user_tracks::get_user_tracks_by_ids(
+                &mut connection,
+                user_id,
+                std::slice::from_ref(track_id),
+            )
+            .await?
+            .pop()
+            {
+                Some(row) => row,
+                None => return Ok(false),
+            }
+        };
+        let (track, file) = (row.track, row.file);
+
+        let extension = &file.file_extension;
+        let input_file = TemporaryFile::new(extension);
+        let output_file = TemporaryFile::new(extension);
+
+        download_to_local_file(file_system, &file, &input_file.path).await?;
+
+        write_audio_tags(
+            &self.path_to_ffmpeg,
+            &input_file.path.to_string_lossy(),
+            &output_file.path.to_string_lossy(),
+            &AudioTags {
+                artist: &track.artist,
+                title: &track.title,
+                album: &track.album,
+                track_number: &track.track_number,
+                genre: &track.genre,
+                date: &track.date,
+            },
+        )
+        .await?;
+
+        let file_hash = {
+            let path = output_file.path.clone();
+
+            actix_web::web::block(move || compute_file_hash(&path))
+                .await
+                .map_err(std::io::Error::other)??
+        };
+        let file_size = tokio::fs::metadata(&output_file.path).await?.len();
+
+        if file_hash == file.file_hash {
+            return Ok(true);
+        }
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let file_id = store_audio_file::<_, WriteTagsError>(
+            &mut connection,
+            file_system,
+            &output_file.path,
+            &file_hash,
+            extension,
+        )
+        .await?;
+
+        if !user_tracks::update_user_track_file(
+            &mut connection,
+            &track.tid,
+            &file.file_id,
+            &file_id,
+            &file_hash,
+            file_size as i64,
+        )
+        .await?
+        {
+            // Dropping the transaction reverts counting the use of the new file.
+            return Ok(false);
+        }
+
+        // The previous file is removed by the file garbage collector when it's no longer used.
+        files::decrement_file_use_count(&mut connection, &file.file_id).await?;
+
+        connection.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Tells the clients listening to the running streams having the tracks to refresh
+    /// the now playing track, so they get the new titles without restarting the playback.
+    async fn notify_streams(&self, user_id: &UserId, track_ids: &[TrackId]) {
+        let mut stream_ids: Vec<StreamId> = vec![];
+
+        match self.mysql_client.connection().await {
+            Ok(mut connection) => {
+                for track_id in track_ids {
+                    match streams::get_user_streams_having_track(&mut connection, track_id).await {
+                        Ok(stream_rows) => stream_ids.extend(
+                            stream_rows
+                                .into_iter()
+                                .filter(|row| !matches!(row.status, StreamStatus::Stopped))
+                                .map(|row| row.sid),
+                        ),
+                        Err(error) => error!(?error, "Unable to get streams having track"),
+                    }
+                }
+            }
+            Err(error) => error!(?error, "Unable to get streams having track"),
+        }
+
+        stream_ids.sort_by_key(|stream_id| **stream_id);
+        stream_ids.dedup();
+
+        for stream_id in stream_ids {
+            if let Err(error) = self
+                .pubsub_client
+                .publish_now_playing_updated_message(&stream_id, user_id)
+                .await
+            {
+                error!(?error, "Unable to publish now playing updated message");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizes_metadata_update() {
+        let update = TrackMetadataUpdate {
+            artist: Some("  The \t Band\n".to_string()),
+            title: Some("Song".to_string()),
+            ..TrackMetadataUpdate::default()
+        };
+
+        let normalized = update.normalize().unwrap();
+
+        assert_eq!(Some("The Band".to_string()), normalized.artist);
+        assert_eq!(Some("Song".to_string()), normalized.title);
+        assert_eq!(None, normalized.album);
+    }
+
+    #[test]
+    fn test_validates_metadata_update() {
+        assert!(matches!(
+            TrackMetadataUpdate {
+                title: Some("   ".to_string()),
+                ..TrackMetadataUpdate::default()
+            }
+            .normalize(),
+            Err(TrackMetadataError::InvalidTitle)
+        ));
+        assert!(matches!(
+            TrackMetadataUpdate {
+                genre: Some("a".repeat(TEXT_TAG_MAX_LENGTH + 1)),
+                ..TrackMetadataUpdate::default()
+            }
+            .normalize(),
+            Err(TrackMetadataError::InvalidGenre)
+        ));
+        assert!(matches!(
+            TrackMetadataUpdate {
+                track_number: Some("123456789012".to_string()),
+                ..TrackMetadataUpdate::default()
+            }
+            .normalize(),
+            Err(TrackMetadataError::InvalidTrackNumber)
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::data_structures::{StreamAccess, StreamRef, TrackId, UserId};
+use crate::mysql_client::MySqlClient;
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::user_stream_tracks::{
+    get_stream_tracks, get_stream_tracks_count, is_track_in_public_stream,
+    GetUserStreamTracksParams, TrackFileLinkMergedRow,
+};
+use crate::storage::db::repositories::user_tracks::TrackFileMergedRow;
+use crate::storage::db::repositories::{files, streams, track_artworks, user_tracks, TrackRow};
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum TrackSharingError {
+    #[error("Stream not found")]
+    StreamNotFound,
+    #[error("Track not found")]
+    TrackNotFound,
+    #[error("Track is not shared by its owner")]
+    TrackNotShared,
+    #[error("Track is already in the library")]
+    AlreadyInLibrary,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Page of the shareable tracks of the public stream.
+pub(crate) struct SharedTracks {
+    pub(crate) tracks: Vec<TrackFileLinkMergedRow>,
+    pub(crate) total_count: i64,
+}
+
+/// Checks whether the user may copy the track into their library.
+fn check_copy_permission(track: &TrackRow, user_id: &UserId) -> Result<(), TrackSharingError> {
+    if track.is_deleted || track.file_id.is_none() {
+        return Err(TrackSharingError::TrackNotFound);
+    }
+
+    if &track.uid == user_id {
+        return Err(TrackSharingError::AlreadyInLibrary);
+    }
+
+    if !track.can_be_shared {
+        return Err(TrackSharingError::TrackNotShared);
+    }
+
+    Ok(())
+}
+
+#[derive(Clone)]
+pub(crate) struct TrackSharingService {
+    mysql_client: MySqlClient,
+}
+
+impl TrackSharingService {
+    pub(crate) fn new(mysql_client: MySqlClient) -> Self {
+        Self { mysql_client }
+    }
+
+    /// Returns the tracks of the public stream their owner allowed to share.
+    pub(crate) async fn get_shared_tracks(
+        &self,
+        stream_ref: &StreamRef,
+        offset: i64,
+        limit: i64,
+    ) -> Result<SharedTracks, TrackSharingError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let stream = match streams::get_single_stream_by_ref(&mut connection, stream_ref).await? {
+            Some(stream)
+                if matches!(
+                    StreamAccess::from(stream.access.as_str()),
+                    StreamAccess::Public
+                ) =>
+            {
+                stream
+            }
+            _ => return Err(TrackSharingError::StreamNotFound),
+        };
+
+        let params = GetUserStreamTracksParams {
+            shared_only: true,
+            ..GetUserStreamTracksParams::default()
+        };
+
+        let total_count = get_stream_tracks_count(&mut connection, &stream.sid, &params).await?;
+        let tracks = get_stream_tracks(
+            &mut connection,
+            &stream.sid,
+            &params,
+            &Some(offset),
+            &Some(limit),
+        )
+        .await?;
+
+        Ok(SharedTracks {
+            tracks,
+            total_count,
+        })
+    }
+
+    /// Copies another user's shared track into the user's library. Only tracks listed in the
+    /// playlist of a public stream can be copied. The copy refers to the same audio file, so no
+    /// file is uploaded again.
+    pub(crate) async fn copy_track(
+        &self,
+        user_id: &UserId,
+        track_id: &TrackId,
+    ) -> Result<TrackFileMergedRow, TrackSharingError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let source = user_tracks::get_single_user_track(&mut connection, track_id)
+            .await?
+            .ok_or(TrackSharingError::TrackNotFound)?;
+
+        check_copy_permission(&source.track, user_id)?;
+
+        if !is_track_in_public_stream(&mut connection, track_id).await? {
+            return Err(TrackSharingError::TrackNotFound);
+        }
+
+        if user_tracks::is_track_hash_in_user_library(&mut connection, user_id, &source.track.hash)
+            .await?
+        {
+            return Err(TrackSharingError::AlreadyInLibrary);
+        }
+
+        let copy_id = user_tracks::copy_user_track(&mut connection, user_id, track_id).await?;
+
+        files::increment_file_use_count(&mut connection, &source.file.file_id).await?;
+
+        if let Some(artwork_hash) =
+            track_artworks::get_track_artwork_hash(&mut connection, track_id).await?
+        {
+            track_artworks::set_track_artwork(&mut connection, &copy_id, &artwork_hash).await?;
+        }
+
+        let copy = user_tracks::get_single_user_track(&mut connection, &copy_id)
+            .await?
+            .ok_or(TrackSharingError::TrackNotFound)?;
+
+        connection.commit().await?;
+
+        Ok(copy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_structures::FileId;
+
+    fn track(uid: i32, can_be_shared: bool) -> TrackRow {
+        TrackRow {
+            tid: TrackId::from(1),
+            file_id: Some(FileId::from(1)),
+            uid: UserId::from(uid),
+            filename: "track.mp3".to_string(),
+            hash: "hash".to_string(),
+            ext: "mp3".to_string(),
+            artist: String::new(),
+            title: String::new(),
+            album: String::new(),
+            track_number: String::new(),
+            genre: String::new(),
+            date: String::new(),
+            cue: None,
+            buy: None,
+            duration: 1000,
+            filesize: 1000,
+            color: 0,
+            uploaded: 0,
+            copy_of: None,
+            used_count: 0,
+            is_new: false,
+            can_be_shared,
+            is_deleted: false,
+            deleted: None,
+        }
+    }
+
+    #[test]
+    fn test_checks_copy_permission() {
+        let user_id = UserId::from(2);
+
+        assert!(check_copy_permission(&track(1, true), &user_id).is_ok());
+        assert!(matches!(
+            check_copy_permission(&track(1, false), &user_id),
+            Err(TrackSharingError::TrackNotShared)
+        ));
+        assert!(matches!(
+            check_copy_permission(&track(2, true), &user_id),
+            Err(TrackSharingError::AlreadyInLibrary)
+        ));
+        assert!(matches!(
+            check_copy_permission(
+                &TrackRow {
+                    file_id: None,
+                    ..track(1, true)
+                },
+                &user_id
+            ),
+            Err(TrackSharingError::TrackNotFound)
+        ));
+    }
+}
@@ -0,0 +1,295 @@
+use crate::data_structures::{FileId, TrackId, UserId};
+use crate::mysql_client::MySqlClient;
+use crate::storage::db::repositories::errors::RepositoryError;
+use crate::storage::db::repositories::user_tracks::{self, TrackFileMergedRow};
+use crate::storage::db::repositories::{files, FileRow};
+use crate::storage::fs::utils::GetPath;
+use crate::storage::fs::FileSystem;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+const MAX_TRASH_TRACKS: usize = 1000;
+
+const PURGE_BATCH_SIZE: i64 = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum TrackTrashError {
+    #[error("No tracks selected")]
+    NoTracks,
+    #[error("Too many tracks")]
+    TooManyTracks,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+/// Page of the user's trashed tracks.
+pub(crate) struct TrashedTracks {
+    pub(crate) tracks: Vec<TrackFileMergedRow>,
+    pub(crate) total_count: i64,
+}
+
+fn validate_track_ids(track_ids: &[TrackId]) -> Result<(), TrackTrashError> {
+    if track_ids.is_empty() {
+        return Err(TrackTrashError::NoTracks);
+    }
+
+    if track_ids.len() > MAX_TRASH_TRACKS {
+        return Err(TrackTrashError::TooManyTracks);
+    }
+
+    Ok(())
+}
+
+/// Files referenced by the purged tracks, each listed once.
+fn purged_file_ids(rows: &[TrackFileMergedRow]) -> Vec<FileId> {
+    let mut file_ids: Vec<FileId> = vec![];
+    for row in rows {
+        if !file_ids.contains(&row.file.file_id) {
+            file_ids.push(row.file.file_id.clone());
+        }
+    }
+
+    file_ids
+}
+
+/// Time before which the trashed tracks are expired.
+fn trash_expiration_time(now: SystemTime, retention: Duration) -> i64 {
+    now.checked_sub(retention)
+        .unwrap_or(UNIX_EPOCH)
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Keeps the deleted tracks in the trash until they are restored or purged.
+#[derive(Clone)]
+pub(crate) struct TrackTrashService {
+    mysql_client: MySqlClient,
+}
+
+impl TrackTrashService {
+    pub(crate) fn new(mysql_client: MySqlClient) -> Self {
+        Self { mysql_client }
+    }
+
+    pub(crate) async fn get_trashed_tracks(
+        &self,
+        user_id: &UserId,
+        offset: i64,
+        limit: i64,
+    ) -> Result<TrashedTracks, TrackTrashError> {
+        let mut connection = self.mysql_client.connection().await?;
+
+        let total_count =
+            user_tracks::get_trashed_user_tracks_count(&mut connection, user_id).await?;
+        let tracks = user_tracks::get_trashed_user_tracks(
+            &mut connection,
+            user_id,
+            None,
+            &Some(offset),
+            &Some(limit),
+        )
+        .await?;
+
+        Ok(TrashedTracks {
+            tracks,
+            total_count,
+        })
+    }
+
+    /// Moves the tracks back to the library and returns the restored ones. The tracks
+    /// aren't added back to the playlists they were removed from.
+    pub(crate) async fn restore_tracks(
+        &self,
+        user_id: &UserId,
+        track_ids: &[TrackId],
+    ) -> Result<Vec<TrackId>, TrackTrashError> {
+        validate_track_ids(track_ids)?;
+
+        let mut connection = self.mysql_client.transaction().await?;
+
+        let restored_track_ids: Vec<TrackId> = user_tracks::get_trashed_user_tracks(
+            &mut connection,
+            user_id,
+            Some(track_ids),
+            &None,
+            &None,
+        )
+        .await?
+        .into_iter()
+        .map(|row| row.track.tid)
+        .collect();
+
+        user_tracks::restore_user_tracks(&mut connection, user_id, &restored_track_ids).await?;
+
+        connection.commit().await?;
+
+        Ok(restored_track_ids)
+    }
+
+    /// Permanently deletes the given trashed tracks, or the whole trash if `track_ids`
+    /// is not set. Returns the number of purged tracks.
+    pub(crate) async fn purge_tracks<FS: FileSystem>(
+        &self,
+        user_id: &UserId,
+        track_ids: Option<&[TrackId]>,
+        file_system: &FS,
+    ) -> Result<usize, TrackTrashError> {
+        if let Some(track_ids) = track_ids {
+            validate_track_ids(track_ids)?;
+        }
+
+        let mut purged_tracks = 0;
+
+        loop {
+            let mut connection = self.mysql_client.connection().await?;
+            let rows = user_tracks::get_trashed_user_tracks(
+                &mut connection,
+                user_id,
+                track_ids,
+                &None,
+                &Some(PURGE_BATCH_SIZE),
+            )
+            .await?;
+            drop(connection);
+
+            if rows.is_empty() {
+                break;
+            }
+
+            purged_tracks += self.purge_rows(rows, file_system).await?;
+        }
+
+        Ok(purged_tracks)
+    }
+
+    /// Permanently deletes the tracks of all users kept in the trash longer than `retention`.
+    pub(crate) async fn purge_expired_tracks<FS: FileSystem>(
+        &self,
+        retention: Duration,
+        file_system: &FS,
+    ) -> Result<usize, TrackTrashError> {
+        let deleted_before = trash_expiration_time(SystemTime::now(), retention);
+        let mut purged_tracks = 0;
+
+        loop {
+            let mut connection = self.mysql_client.connection().await?;
+            let rows = user_tracks::get_expired_trashed_tracks(
+                &mut connection,
+                deleted_before,
+                PURGE_BATCH_SIZE,
+            )
+            .await?;
+            drop(connection);
+
+            if rows.is_empty() {
+                break;
+            }
+
+            purged_tracks += self.purge_rows(rows, file_system).await?;
+        }
+
+        info!(purged_tracks, "Expired trashed tracks purged");
+
+        Ok(purged_tracks)
+    }
+
+    /// Runs the purge of the expired trashed tracks periodically in the background.
+    pub(crate) fn schedule<FS: FileSystem + 'static>(
+        &self,
+        file_system: FS,
+        interval: Duration,
+        retention: Duration,
+    ) {
+        let service = self.clone();
+
+        actix_rt::spawn(async move {
+            loop {
+                actix_rt::time::sleep(interval).await;
+
+                if let Err(error) = service.purge_expired_tracks(retention, &file_system).await {
+                    error!(?error, "Unable to purge expired trashed tracks");
+                }
+            }
+        });
+    }
+
+    /// Deletes the tracks and the audio files no longer used by any other track.
+    async fn purge_rows<FS: FileSystem>(
+        &self,
+        rows: Vec<TrackFileMergedRow>,
+        file_system: &FS,
+    ) -> Result<usize, TrackTrashError> {
+        let mut connection = self.mysql_client.transaction().await?;
+
+        // The tracks may have been restored since they were selected.
+        let mut purged_rows = vec![];
+        for row in rows {
+            if user_tracks::delete_trashed_user_track(&mut connection, &row.track.tid).await? {
+                files::decrement_file_use_count(&mut connection, &row.file.file_id).await?;
+                purged_rows.push(row);
+            }
+        }
+
+        let unused_files: Vec<FileRow> =
+            files::get_unused_files(&mut connection, &purged_file_ids(&purged_rows)).await?;
+
+        let mut deleted_files = vec![];
+        for file in unused_files {
+            if files::delete_unused_file(&mut connection, &file.file_id).await? {
+                deleted_files.push(file);
+            }
+        }
+
+        connection.commit().await?;
+
+        for file in deleted_files.iter() {
+            if let Err(error) = file_system
+                .delete_file(&format!("audio/{}", file.get_path()))
+                .await
+            {
+                warn!(
+                    ?error,
+                    file_id = *file.file_id,
+                    "Unable to delete audio file"
+                );
+            }
+        }
+
+        Ok(purged_rows.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculates_trash_expiration_time() {
+        let now = UNIX_EPOCH + Duration::from_secs(10 * 86400);
+
+        assert_eq!(
+            7 * 86400,
+            trash_expiration_time(now, Duration::from_secs(3 * 86400))
+        );
+        assert_eq!(
+            0,
+            trash_expiration_time(now, Duration::from_secs(30 * 86400))
+        );
+    }
+
+    #[test]
+    fn test_validates_track_ids() {
+        assert!(matches!(
+            validate_track_ids(&[]),
+            Err(TrackTrashError::NoTracks)
+        ));
+        assert!(matches!(
+            validate_track_ids(&vec![TrackId::from(1); MAX_TRASH_TRACKS + 1]),
+            Err(TrackTrashError::TooManyTracks)
+        ));
+        assert!(validate_track_ids(&[TrackId::from(1)]).is_ok());
+    }
+}
@@ -1,2 +1,5 @@
 pub(crate) mod repositories;
 pub(crate) mod row_utils;
+#[cfg(test)]
+pub(crate) mod test_database;
+pub(crate) mod track_query;
@@ -0,0 +1,53 @@
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryResult;
+use crate::storage::db::repositories::CategoryRow;
+use sqlx::{Execute, MySql, QueryBuilder};
+use std::ops::DerefMut;
+use tracing::trace;
+
+fn create_select_query_builder<'a>() -> QueryBuilder<'a, MySql> {
+    QueryBuilder::new(
+        r#"
+SELECT `r_categories`.`category_id`,
+       `r_categories`.`category_name`,
+       `r_categories`.`category_permalink`,
+       `r_categories`.`streams_count`
+FROM `r_categories`
+"#,
+    )
+}
+
+pub(crate) async fn get_category_by_id(
+    connection: &mut MySqlConnection,
+    category_id: &i32,
+) -> RepositoryResult<Option<CategoryRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `r_categories`.`category_id` = ");
+    builder.push_bind(category_id);
+    builder.push(" LIMIT 1");
+
+    let query = builder.build_query_as();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let category = query.fetch_optional(connection.deref_mut()).await?;
+
+    Ok(category)
+}
+
+pub(crate) async fn get_categories(
+    connection: &mut MySqlConnection,
+) -> RepositoryResult<Vec<CategoryRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" ORDER BY `r_categories`.`category_name`");
+
+    let query = builder.build_query_as();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let categories = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(categories)
+}
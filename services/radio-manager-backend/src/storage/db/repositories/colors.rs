@@ -0,0 +1,190 @@
+use crate::data_structures::UserId;
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryResult;
+use crate::storage::db::repositories::{ColorRow, ColorStatsRow};
+use sqlx::{query, query_as, Execute};
+use std::ops::DerefMut;
+use tracing::trace;
+
+/// Returns the preset colors followed by the user's own color labels.
+pub(crate) async fn get_user_colors(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<Vec<ColorRow>> {
+    let query = query_as(
+        r#"
+SELECT `color_id`, `color_name`, `color_code`, `user_id`
+FROM `r_colors`
+WHERE `user_id` IS NULL OR `user_id` = ?
+ORDER BY `user_id` IS NOT NULL, `color_id`
+"#,
+    )
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
+/// Returns the color if it's a preset color or the user's own color label.
+pub(crate) async fn get_user_color(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    color_id: i32,
+) -> RepositoryResult<Option<ColorRow>> {
+    let query = query_as(
+        r#"
+SELECT `color_id`, `color_name`, `color_code`, `user_id`
+FROM `r_colors`
+WHERE `color_id` = ? AND (`user_id` IS NULL OR `user_id` = ?)
+"#,
+    )
+    .bind(color_id)
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_optional(connection.deref_mut()).await?)
+}
+
+/// Locks the user until the end of the transaction, so only one transaction at a time
+/// counts and creates the user's color labels.
+pub(crate) async fn lock_user_colors(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let query = query("SELECT `uid` FROM `r_users` WHERE `uid` = ? FOR UPDATE").bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Returns the number of the user's own color labels.
+pub(crate) async fn get_user_colors_count(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<i64> {
+    let query =
+        sqlx::query_scalar("SELECT COUNT(*) FROM `r_colors` WHERE `user_id` = ?").bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_one(connection.deref_mut()).await?)
+}
+
+pub(crate) async fn create_user_color(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    name: &str,
+    code: &str,
+) -> RepositoryResult<i32> {
+    let query =
+        query("INSERT INTO `r_colors` (`color_name`, `color_code`, `user_id`) VALUES (?, ?, ?)")
+            .bind(name)
+            .bind(code)
+            .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.last_insert_id() as i32)
+}
+
+pub(crate) async fn update_user_color(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    color_id: i32,
+    name: &str,
+    code: &str,
+) -> RepositoryResult<()> {
+    let query = query(
+        "UPDATE `r_colors` SET `color_name` = ?, `color_code` = ? WHERE `color_id` = ? AND `user_id` = ?",
+    )
+    .bind(name)
+    .bind(code)
+    .bind(color_id)
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Deletes the user's color label; the tracks having it lose their color.
+pub(crate) async fn delete_user_color(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    color_id: i32,
+) -> RepositoryResult<()> {
+    let update_query = query("UPDATE `r_tracks` SET `color` = 0 WHERE `color` = ?").bind(color_id);
+
+    trace!("Running SQL query: {}", update_query.sql());
+
+    update_query.execute(connection.deref_mut()).await?;
+
+    let delete_query = query("DELETE FROM `r_colors` WHERE `color_id` = ? AND `user_id` = ?")
+        .bind(color_id)
+        .bind(user_id);
+
+    trace!("Running SQL query: {}", delete_query.sql());
+
+    delete_query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Deletes all the user's color labels; the tracks having them lose their color.
+pub(crate) async fn delete_user_colors(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let update_query = query(
+        r#"
+UPDATE `r_tracks`
+JOIN `r_colors` ON `r_colors`.`color_id` = `r_tracks`.`color`
+SET `r_tracks`.`color` = 0
+WHERE `r_colors`.`user_id` = ?
+"#,
+    )
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", update_query.sql());
+
+    update_query.execute(connection.deref_mut()).await?;
+
+    let delete_query = query("DELETE FROM `r_colors` WHERE `user_id` = ?").bind(user_id);
+
+    trace!("Running SQL query: {}", delete_query.sql());
+
+    delete_query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Returns the number and the total duration of the user's tracks for each used color.
+pub(crate) async fn get_user_color_stats(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<Vec<ColorStatsRow>> {
+    let query = query_as(
+        r#"
+SELECT `color`,
+       COUNT(*) AS `tracks_count`,
+       CAST(COALESCE(SUM(`duration`), 0) AS SIGNED) AS `tracks_duration`
+FROM `r_tracks`
+WHERE `uid` = ? AND `is_deleted` = 0
+GROUP BY `color`
+"#,
+    )
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
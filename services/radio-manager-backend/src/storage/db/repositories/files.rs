@@ -0,0 +1,233 @@
+use crate::data_structures::{FileId, UserId};
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryResult;
+use crate::storage::db::repositories::FileRow;
+use sqlx::{query, Execute, FromRow, MySql, QueryBuilder};
+use std::ops::{Deref, DerefMut};
+use tracing::trace;
+
+fn create_select_query_builder<'a>() -> QueryBuilder<'a, MySql> {
+    QueryBuilder::new(
+        r#"
+SELECT DISTINCT `fs_file`.`file_id`,
+       `fs_file`.`file_size`,
+       `fs_file`.`file_hash`,
+       `fs_file`.`file_extension`,
+       `fs_file`.`server_id`,
+       `fs_file`.`use_count`
+FROM `fs_file`
+"#,
+    )
+}
+
+#[derive(FromRow)]
+pub(crate) struct FileUsageRow {
+    #[sqlx(flatten)]
+    pub(crate) file: FileRow,
+    /// Number of tracks referencing the file.
+    pub(crate) tracks_count: i64,
+}
+
+pub(crate) async fn get_files_usage(
+    connection: &mut MySqlConnection,
+) -> RepositoryResult<Vec<FileUsageRow>> {
+    let query = sqlx::query_as(
+        r#"
+SELECT `fs_file`.`file_id`,
+       `fs_file`.`file_size`,
+       `fs_file`.`file_hash`,
+       `fs_file`.`file_extension`,
+       `fs_file`.`server_id`,
+       `fs_file`.`use_count`,
+       CAST(COUNT(`r_tracks`.`tid`) AS SIGNED) AS `tracks_count`
+FROM `fs_file`
+LEFT JOIN `r_tracks` ON `r_tracks`.`file_id` = `fs_file`.`file_id`
+GROUP BY `fs_file`.`file_id`
+"#,
+    );
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let files = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(files)
+}
+
+/// Decrements the use count of every file referenced by the user's tracks
+/// by the number of tracks referencing it.
+pub(crate) async fn release_user_files(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let query = query(
+        r#"
+UPDATE `fs_file`
+JOIN (
+    SELECT `file_id`, COUNT(*) AS `tracks_count`
+    FROM `r_tracks`
+    WHERE `uid` = ?
+    GROUP BY `file_id`
+) AS `user_files` ON `user_files`.`file_id` = `fs_file`.`file_id`
+SET `fs_file`.`use_count` = GREATEST(`fs_file`.`use_count` - `user_files`.`tracks_count`, 0)
+"#,
+    )
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_unused_user_files(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<Vec<FileRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" JOIN `r_tracks` ON `r_tracks`.`file_id` = `fs_file`.`file_id`");
+    builder.push(" WHERE `r_tracks`.`uid` = ");
+    builder.push_bind(user_id);
+    builder.push(" AND `fs_file`.`use_count` < 1");
+
+    let query = builder.build_query_as();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let files = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(files)
+}
+
+/// Returns those of the given files that are no longer used by any track.
+pub(crate) async fn get_unused_files(
+    connection: &mut MySqlConnection,
+    file_ids: &[FileId],
+) -> RepositoryResult<Vec<FileRow>> {
+    if file_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `fs_file`.`use_count` < 1");
+    builder.push(
+        " AND NOT EXISTS (SELECT 1 FROM `r_tracks` WHERE `r_tracks`.`file_id` = `fs_file`.`file_id`)",
+    );
+    builder.push(" AND `fs_file`.`file_id` IN (");
+
+    let mut separated = builder.separated(", ");
+    for file_id in file_ids {
+        separated.push_bind(file_id.deref());
+    }
+
+    builder.push(")");
+
+    let query = builder.build_query_as();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let files = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(files)
+}
+
+/// Gets the file by its hash, locking the row until the end of the transaction,
+/// so the file can't be deleted as unused before its use is counted.
+pub(crate) async fn lock_file_by_hash(
+    connection: &mut MySqlConnection,
+    file_hash: &str,
+) -> RepositoryResult<Option<FileRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `fs_file`.`file_hash` = ");
+    builder.push_bind(file_hash);
+    builder.push(" LIMIT 1 FOR UPDATE");
+
+    let query = builder.build_query_as();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let file = query.fetch_optional(connection.deref_mut()).await?;
+
+    Ok(file)
+}
+
+/// Creates the file with the use count of one.
+pub(crate) async fn create_file(
+    connection: &mut MySqlConnection,
+    file_size: u64,
+    file_hash: &str,
+    file_extension: &str,
+) -> RepositoryResult<FileId> {
+    let query = query(
+        r#"
+INSERT INTO `fs_file` (`file_size`, `file_hash`, `file_extension`, `server_id`, `use_count`)
+VALUES (?, ?, ?, 1, 1)
+"#,
+    )
+    .bind(file_size)
+    .bind(file_hash)
+    .bind(file_extension);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(FileId::from(result.last_insert_id() as i32))
+}
+
+pub(crate) async fn increment_file_use_count(
+    connection: &mut MySqlConnection,
+    file_id: &FileId,
+) -> RepositoryResult<()> {
+    let query = query("UPDATE `fs_file` SET `use_count` = `use_count` + 1 WHERE `file_id` = ?")
+        .bind(file_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn decrement_file_use_count(
+    connection: &mut MySqlConnection,
+    file_id: &FileId,
+) -> RepositoryResult<()> {
+    let query = query(
+        "UPDATE `fs_file` SET `use_count` = GREATEST(`use_count` - 1, 0) WHERE `file_id` = ?",
+    )
+    .bind(file_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Deletes the file row unless the file is still used by any track. Returns whether
+/// the row was deleted: the stored file may be deleted only then.
+pub(crate) async fn delete_unused_file(
+    connection: &mut MySqlConnection,
+    file_id: &FileId,
+) -> RepositoryResult<bool> {
+    let query = query(
+        r#"
+DELETE FROM `fs_file`
+WHERE `fs_file`.`file_id` = ?
+  AND `fs_file`.`use_count` < 1
+  AND NOT EXISTS (SELECT 1 FROM `r_tracks` WHERE `r_tracks`.`file_id` = ?)
+        "#,
+    )
+    .bind(file_id.deref())
+    .bind(file_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -3,11 +3,19 @@ use serde::{Deserialize, Serialize};
 use serde_repr::Serialize_repr;
 use sqlx::types::Json;
 
+pub(crate) mod categories;
+pub(crate) mod colors;
 pub(crate) mod errors;
+pub(crate) mod files;
 pub(crate) mod legacy_sessions;
 pub(crate) mod outgoing_streams;
+pub(crate) mod refresh_tokens;
 pub(crate) mod stream_destinations;
 pub(crate) mod streams;
+pub(crate) mod track_artworks;
+pub(crate) mod track_import_jobs;
+pub(crate) mod track_plays;
+pub(crate) mod two_factor_auth;
 pub(crate) mod user_stream_tracks;
 pub(crate) mod user_tracks;
 pub(crate) mod users;
@@ -93,6 +101,14 @@ pub(crate) struct StreamRow {
     pub(crate) rtmp_streaming_key: String,
 }
 
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct CategoryRow {
+    pub(crate) category_id: i32,
+    pub(crate) category_name: String,
+    pub(crate) category_permalink: String,
+    pub(crate) streams_count: i32,
+}
+
 #[derive(sqlx::FromRow, Clone)]
 pub(crate) struct UserRow {
     pub(crate) uid: UserId,
@@ -105,6 +121,7 @@ pub(crate) struct UserRow {
     pub(crate) rights: Option<i64>,
     pub(crate) registration_date: u64,
     pub(crate) last_visit_date: Option<u64>,
+    pub(crate) permalink: Option<String>,
     pub(crate) avatar: Option<String>,
 }
 
@@ -151,3 +168,93 @@ pub(crate) struct LegacySessionRow {
     pub(crate) permanent: i8,
     pub(crate) expires: chrono::DateTime<chrono::Utc>,
 }
+
+#[allow(dead_code)]
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct RefreshTokenRow {
+    pub(crate) id: i32,
+    pub(crate) user_id: UserId,
+    pub(crate) family_id: String,
+    pub(crate) token_hash: String,
+    pub(crate) expires_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub(crate) revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub(crate) created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct TwoFactorAuthRow {
+    pub(crate) user_id: UserId,
+    pub(crate) secret: String,
+    pub(crate) enabled: bool,
+    pub(crate) last_used_step: Option<i64>,
+    pub(crate) created_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct TrackImportJobRow {
+    pub(crate) id: i32,
+    pub(crate) user_id: UserId,
+    pub(crate) stream_id: Option<StreamId>,
+    pub(crate) source: String,
+    pub(crate) status: String,
+    pub(crate) total_files: i32,
+    pub(crate) processed_files: i32,
+    pub(crate) imported_files: i32,
+    pub(crate) skipped_files: i32,
+    pub(crate) failed_files: i32,
+    pub(crate) created_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct TrackImportJobErrorRow {
+    pub(crate) file_name: String,
+    pub(crate) error: String,
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct ColorRow {
+    pub(crate) color_id: i32,
+    pub(crate) color_name: String,
+    pub(crate) color_code: Option<String>,
+    /// Owner of the color label, `None` for the preset colors shared by all users.
+    pub(crate) user_id: Option<UserId>,
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct ColorStatsRow {
+    pub(crate) color: i32,
+    pub(crate) tracks_count: i64,
+    pub(crate) tracks_duration: i64,
+}
+
+#[allow(dead_code)]
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct TrackPlayRow {
+    pub(crate) id: i64,
+    pub(crate) user_id: UserId,
+    pub(crate) stream_id: Option<StreamId>,
+    /// `None` if the track has been deleted since, the metadata below is kept for the reports.
+    pub(crate) track_id: Option<TrackId>,
+    pub(crate) artist: String,
+    pub(crate) title: String,
+    pub(crate) album: String,
+    pub(crate) duration: i32,
+    pub(crate) started_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) listeners: i32,
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub(crate) struct MostPlayedTrackRow {
+    pub(crate) track_id: TrackId,
+    pub(crate) artist: String,
+    pub(crate) title: String,
+    pub(crate) album: String,
+    pub(crate) duration: i64,
+    pub(crate) plays: i32,
+    pub(crate) last_played_at: Option<chrono::DateTime<chrono::Utc>>,
+}
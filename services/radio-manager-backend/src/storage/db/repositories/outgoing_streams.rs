@@ -52,3 +52,29 @@ pub(crate) async fn create_or_update_outging_stream(
 
     Ok(())
 }
+
+pub(crate) async fn delete_user_outgoing_streams(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let query = query("DELETE FROM `outgoing_streams` WHERE `user_id` = ?").bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn delete_channel_outgoing_streams(
+    connection: &mut MySqlConnection,
+    channel_id: &StreamId,
+) -> RepositoryResult<()> {
+    let query = query("DELETE FROM `outgoing_streams` WHERE `channel_id` = ?").bind(channel_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::data_structures::UserId;
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryResult;
+use crate::storage::db::repositories::RefreshTokenRow;
+use chrono::{DateTime, Utc};
+use sqlx::{query, Execute, MySql, QueryBuilder};
+use std::ops::DerefMut;
+use tracing::trace;
+
+fn create_select_query_builder<'a>() -> QueryBuilder<'a, MySql> {
+    QueryBuilder::new(
+        r#"
+SELECT `id`,
+       `user_id`,
+       `family_id`,
+       `token_hash`,
+       `expires_at`,
+       `used_at`,
+       `revoked_at`,
+       `created_at`
+FROM `refresh_tokens`
+"#,
+    )
+}
+
+pub(crate) async fn create_refresh_token(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    family_id: &str,
+    token_hash: &str,
+    expires_at: &DateTime<Utc>,
+) -> RepositoryResult<()> {
+    let query = query(
+        r#"
+INSERT INTO `refresh_tokens` (`user_id`, `family_id`, `token_hash`, `expires_at`, `created_at`)
+VALUES (?, ?, ?, ?, ?)
+"#,
+    )
+    .bind(user_id)
+    .bind(family_id)
+    .bind(token_hash)
+    .bind(expires_at)
+    .bind(Utc::now());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_refresh_token_by_hash(
+    connection: &mut MySqlConnection,
+    token_hash: &str,
+) -> RepositoryResult<Option<RefreshTokenRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `token_hash` = ");
+    builder.push_bind(token_hash);
+    builder.push(" LIMIT 1");
+
+    let query = builder.build_query_as::<RefreshTokenRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_optional(connection.deref_mut()).await?)
+}
+
+/// Marks the refresh token as used. Returns `false` if the token has already been used or revoked,
+/// which means that a concurrent request rotated it first.
+pub(crate) async fn mark_refresh_token_used(
+    connection: &mut MySqlConnection,
+    id: &i32,
+) -> RepositoryResult<bool> {
+    let query = query(
+        r#"
+UPDATE `refresh_tokens`
+SET `used_at` = ?
+WHERE `id` = ?
+  AND `used_at` IS NULL
+  AND `revoked_at` IS NULL
+"#,
+    )
+    .bind(Utc::now())
+    .bind(id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+pub(crate) async fn revoke_refresh_token_family(
+    connection: &mut MySqlConnection,
+    family_id: &str,
+) -> RepositoryResult<()> {
+    let query = query(
+        "UPDATE `refresh_tokens` SET `revoked_at` = ? WHERE `family_id` = ? AND `revoked_at` IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(family_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
@@ -96,3 +96,16 @@ pub(crate) async fn delete_stream_destination(
 
     Ok(())
 }
+
+pub(crate) async fn delete_user_stream_destinations(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let query = query("DELETE FROM `stream_destinations` WHERE `user_id` = ?").bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
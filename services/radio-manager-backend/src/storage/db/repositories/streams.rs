@@ -1,9 +1,9 @@
-use crate::data_structures::{StreamId, TrackId, UserId};
+use crate::data_structures::{StreamAccess, StreamId, StreamRef, StreamsSorting, TrackId, UserId};
 use crate::mysql_client::MySqlConnection;
 use crate::storage::db::repositories::errors::{RepositoryError, RepositoryResult};
 use crate::storage::db::repositories::{StreamRow, StreamStatus};
 use chrono::Duration;
-use sqlx::{query, Execute, MySql, QueryBuilder, Row};
+use sqlx::{query, query_as, Execute, MySql, QueryBuilder, Row};
 use std::ops::{Deref, DerefMut};
 use tracing::trace;
 
@@ -55,6 +55,38 @@ WHERE `r_link`.`stream_id` = ?
     Ok(Duration::milliseconds(duration))
 }
 
+/// Returns the playlist durations of the streams in milliseconds. Streams without tracks
+/// are omitted.
+pub(crate) async fn get_streams_playlist_durations(
+    connection: &mut MySqlConnection,
+    stream_ids: &[StreamId],
+) -> RepositoryResult<Vec<(StreamId, i64)>> {
+    if stream_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut builder = QueryBuilder::new(
+        r#"
+SELECT `r_link`.`stream_id`, CAST(SUM(`r_tracks`.`duration`) AS SIGNED) as `sum`
+FROM `r_tracks`
+JOIN `r_link` ON `r_tracks`.`tid` = `r_link`.`track_id`
+WHERE `r_link`.`stream_id` IN ("#,
+    );
+
+    let mut separated = builder.separated(", ");
+    for stream_id in stream_ids {
+        separated.push_bind(stream_id.deref());
+    }
+
+    builder.push(") GROUP BY `r_link`.`stream_id`");
+
+    let query = builder.build_query_as::<(StreamId, i64)>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
 pub(crate) async fn get_single_stream_by_id(
     connection: &mut MySqlConnection,
     stream_id: &StreamId,
@@ -63,8 +95,50 @@ pub(crate) async fn get_single_stream_by_id(
 
     builder.push(" WHERE `r_streams`.`sid` = ");
     builder.push_bind(stream_id.deref());
-    builder.push(" OR `r_streams`.`permalink` = ");
-    builder.push_bind(stream_id.deref());
+    builder.push(" LIMIT 1");
+
+    let query = builder.build_query_as();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let stream = query.fetch_optional(connection.deref_mut()).await?;
+
+    Ok(stream)
+}
+
+/// Locks the stream until the end of the transaction, so only one transaction at a time
+/// records the stream's track starts.
+pub(crate) async fn lock_stream(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+) -> RepositoryResult<()> {
+    let query =
+        query("SELECT `sid` FROM `r_streams` WHERE `sid` = ? FOR UPDATE").bind(stream_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_single_stream_by_ref(
+    connection: &mut MySqlConnection,
+    stream_ref: &StreamRef,
+) -> RepositoryResult<Option<StreamRow>> {
+    let mut builder = create_select_query_builder();
+
+    match stream_ref {
+        StreamRef::Id(stream_id) => {
+            builder.push(" WHERE `r_streams`.`sid` = ");
+            builder.push_bind(stream_id.deref());
+        }
+        StreamRef::Permalink(permalink) => {
+            builder.push(" WHERE `r_streams`.`permalink` = ");
+            builder.push_bind(permalink);
+        }
+    }
+
     builder.push(" LIMIT 1");
 
     let query = builder.build_query_as();
@@ -103,9 +177,9 @@ pub(crate) async fn get_user_streams_having_track(
     builder.push(
         r#" WHERE (
 SELECT COUNT(`id`) 
-FROM `r_links` 
-WHERE `r_links`.`stream_id` = `r_streams`.`sid` 
-  AND `r_links`.`track_id` = "#,
+FROM `r_link` 
+WHERE `r_link`.`stream_id` = `r_streams`.`sid` 
+  AND `r_link`.`track_id` = "#,
     );
     builder.push_bind(track_id.deref());
     builder.push(") > 0");
@@ -181,3 +255,239 @@ WHERE `sid` = ? AND `uid` = ?
 
     Ok(())
 }
+
+pub(crate) struct StreamFields<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) permalink: &'a str,
+    pub(crate) info: &'a str,
+    pub(crate) hashtags: &'a str,
+    pub(crate) category: Option<i32>,
+    pub(crate) access: StreamAccess,
+}
+
+pub(crate) async fn create_stream(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    fields: &StreamFields<'_>,
+) -> RepositoryResult<StreamId> {
+    let query = query(
+        r#"
+INSERT INTO `r_streams` (`uid`, `name`, `permalink`, `info`, `hashtags`, `category`, `access`, `created`)
+VALUES (?, ?, ?, ?, ?, ?, ?, UNIX_TIMESTAMP())
+"#,
+    )
+    .bind(user_id)
+    .bind(fields.name)
+    .bind(fields.permalink)
+    .bind(fields.info)
+    .bind(fields.hashtags)
+    .bind(fields.category)
+    .bind(fields.access.as_str());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(StreamId::from(result.last_insert_id() as i32))
+}
+
+pub(crate) async fn update_stream(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    fields: &StreamFields<'_>,
+) -> RepositoryResult<()> {
+    let query = query(
+        r#"
+UPDATE `r_streams`
+SET `name` = ?, `permalink` = ?, `info` = ?, `hashtags` = ?, `category` = ?, `access` = ?
+WHERE `sid` = ?
+"#,
+    )
+    .bind(fields.name)
+    .bind(fields.permalink)
+    .bind(fields.info)
+    .bind(fields.hashtags)
+    .bind(fields.category)
+    .bind(fields.access.as_str())
+    .bind(stream_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Returns the cover file names of all streams.
+pub(crate) async fn get_stream_covers(
+    connection: &mut MySqlConnection,
+) -> RepositoryResult<Vec<String>> {
+    let query = query_as::<_, (String,)>(
+        "SELECT `cover` FROM `r_streams` WHERE `cover` IS NOT NULL AND `cover` != ''",
+    );
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let rows = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(rows.into_iter().map(|(cover,)| cover).collect())
+}
+
+pub(crate) async fn update_stream_cover(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    cover: Option<&str>,
+    cover_background: Option<&str>,
+) -> RepositoryResult<()> {
+    let query = query("UPDATE `r_streams` SET `cover` = ?, `cover_background` = ? WHERE `sid` = ?")
+        .bind(cover)
+        .bind(cover_background)
+        .bind(stream_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Checks whether the permalink is used by another stream.
+pub(crate) async fn is_stream_permalink_taken(
+    connection: &mut MySqlConnection,
+    permalink: &str,
+    except_stream_id: Option<&StreamId>,
+) -> RepositoryResult<bool> {
+    let query = query_as::<_, (i64,)>(
+        "SELECT COUNT(*) FROM `r_streams` WHERE `permalink` = ? AND `sid` != ?",
+    )
+    .bind(permalink)
+    .bind(
+        except_stream_id
+            .map(|stream_id| **stream_id)
+            .unwrap_or_default(),
+    );
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let (count,) = query.fetch_one(connection.deref_mut()).await?;
+
+    Ok(count > 0)
+}
+
+pub(crate) async fn delete_stream(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+) -> RepositoryResult<()> {
+    let query = query("DELETE FROM `r_streams` WHERE `sid` = ?").bind(stream_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct PublicStreamRow {
+    #[sqlx(flatten)]
+    pub(crate) stream: StreamRow,
+    pub(crate) listeners_count: i64,
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct GetPublicStreamsParams {
+    pub(crate) category: Option<i32>,
+    pub(crate) hashtag: Option<String>,
+    pub(crate) search: Option<String>,
+    pub(crate) sorting: StreamsSorting,
+}
+
+fn push_public_streams_conditions<'a>(
+    builder: &mut QueryBuilder<'a, MySql>,
+    params: &'a GetPublicStreamsParams,
+) {
+    builder.push(" WHERE `r_streams`.`access` = 'PUBLIC'");
+
+    if let Some(category) = &params.category {
+        builder.push(" AND `r_streams`.`category` = ");
+        builder.push_bind(category);
+    }
+
+    if let Some(hashtag) = &params.hashtag {
+        builder.push(" AND MATCH(`r_streams`.`hashtags`) AGAINST (");
+        builder.push_bind(hashtag);
+        builder.push(" IN BOOLEAN MODE)");
+    }
+
+    if let Some(search) = &params.search {
+        builder.push(" AND MATCH(`r_streams`.`name`, `r_streams`.`info`) AGAINST (");
+        builder.push_bind(search);
+        builder.push(" IN BOOLEAN MODE)");
+    }
+}
+
+pub(crate) async fn get_public_streams_count(
+    connection: &mut MySqlConnection,
+    params: &GetPublicStreamsParams,
+) -> RepositoryResult<i64> {
+    let mut builder = QueryBuilder::new("SELECT COUNT(*) as `count` FROM `r_streams`");
+
+    push_public_streams_conditions(&mut builder, params);
+
+    let query = builder.build();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let count_row = query.fetch_one(connection.deref_mut()).await?;
+
+    Ok(count_row.get("count"))
+}
+
+pub(crate) async fn get_public_streams(
+    connection: &mut MySqlConnection,
+    params: &GetPublicStreamsParams,
+    offset: &i64,
+    limit: &i64,
+) -> RepositoryResult<Vec<PublicStreamRow>> {
+    let mut builder = QueryBuilder::new(
+        r#"
+SELECT `r_streams`.`sid`,
+       `r_streams`.`uid`,
+       `r_streams`.`name`,
+       `r_streams`.`permalink`,
+       `r_streams`.`info`,
+       `r_streams`.`jingle_interval`,
+       `r_streams`.`status`,
+       `r_streams`.`started`,
+       `r_streams`.`started_from`,
+       `r_streams`.`access`,
+       `r_streams`.`category`,
+       `r_streams`.`hashtags`,
+       `r_streams`.`cover`,
+       `r_streams`.`cover_background`,
+       `r_streams`.`created`,
+       `r_streams`.`rtmp_url`,
+       `r_streams`.`rtmp_streaming_key`,
+       CAST(COALESCE(`r_static_stream_vars`.`listeners_count`, 0) AS SIGNED) AS `listeners_count`
+FROM `r_streams`
+LEFT JOIN `r_static_stream_vars` ON `r_static_stream_vars`.`stream_id` = `r_streams`.`sid`
+"#,
+    );
+
+    push_public_streams_conditions(&mut builder, params);
+
+    builder.push(format_args!(" ORDER BY {}", params.sorting.as_str()));
+
+    builder.push(" LIMIT ");
+    builder.push_bind(offset);
+    builder.push(", ");
+    builder.push_bind(limit);
+
+    let query = builder.build_query_as::<PublicStreamRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let streams = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(streams)
+}
@@ -0,0 +1,71 @@
+use crate::data_structures::TrackId;
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryResult;
+use chrono::Utc;
+use sqlx::{query, Execute};
+use std::ops::DerefMut;
+use tracing::trace;
+
+/// Returns the hash of the artwork attached to the track.
+pub(crate) async fn get_track_artwork_hash(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+) -> RepositoryResult<Option<String>> {
+    let query =
+        sqlx::query_scalar("SELECT `artwork_hash` FROM `track_artworks` WHERE `track_id` = ?")
+            .bind(track_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_optional(connection.deref_mut()).await?)
+}
+
+/// Checks whether any track has the artwork attached.
+pub(crate) async fn is_artwork_attached(
+    connection: &mut MySqlConnection,
+    artwork_hash: &str,
+) -> RepositoryResult<bool> {
+    let query = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM `track_artworks` WHERE `artwork_hash` = ?",
+    )
+    .bind(artwork_hash);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_one(connection.deref_mut()).await? > 0)
+}
+
+/// Returns the hashes of all artworks attached to tracks.
+pub(crate) async fn get_artwork_hashes(
+    connection: &mut MySqlConnection,
+) -> RepositoryResult<Vec<String>> {
+    let query = sqlx::query_scalar("SELECT DISTINCT `artwork_hash` FROM `track_artworks`");
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
+/// Attaches the artwork to the track replacing the previous one.
+pub(crate) async fn set_track_artwork(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+    artwork_hash: &str,
+) -> RepositoryResult<()> {
+    let query = query(
+        r#"
+INSERT INTO `track_artworks` (`track_id`, `artwork_hash`, `created_at`)
+VALUES (?, ?, ?)
+ON DUPLICATE KEY UPDATE `artwork_hash` = VALUES(`artwork_hash`), `created_at` = VALUES(`created_at`)
+"#,
+    )
+    .bind(track_id)
+    .bind(artwork_hash)
+    .bind(Utc::now());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
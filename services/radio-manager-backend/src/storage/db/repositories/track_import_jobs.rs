@@ -0,0 +1,287 @@
+use crate::data_structures::{StreamId, TrackImportSource, TrackImportStatus, UserId};
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryResult;
+use crate::storage::db::repositories::{TrackImportJobErrorRow, TrackImportJobRow};
+use chrono::{DateTime, Utc};
+use sqlx::{query, Execute, MySql, QueryBuilder};
+use std::ops::{Deref, DerefMut};
+use tracing::trace;
+
+fn create_select_query_builder<'a>() -> QueryBuilder<'a, MySql> {
+    QueryBuilder::new(
+        r#"
+SELECT `id`,
+       `user_id`,
+       `stream_id`,
+       `source`,
+       `status`,
+       `total_files`,
+       `processed_files`,
+       `imported_files`,
+       `skipped_files`,
+       `failed_files`,
+       `created_at`,
+       `finished_at`
+FROM `track_import_jobs`
+"#,
+    )
+}
+
+/// Outcome of importing a single file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TrackImportResult {
+    Imported,
+    Skipped,
+    Failed,
+}
+
+impl TrackImportResult {
+    fn counter_column(&self) -> &str {
+        match self {
+            TrackImportResult::Imported => "`imported_files`",
+            TrackImportResult::Skipped => "`skipped_files`",
+            TrackImportResult::Failed => "`failed_files`",
+        }
+    }
+}
+
+pub(crate) async fn create_track_import_job(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    stream_id: &Option<StreamId>,
+    source: TrackImportSource,
+    lease_expires_at: &DateTime<Utc>,
+) -> RepositoryResult<i32> {
+    let query = query(
+        r#"
+INSERT INTO `track_import_jobs` (`user_id`, `stream_id`, `source`, `status`, `created_at`, `lease_expires_at`)
+VALUES (?, ?, ?, ?, ?, ?)
+"#,
+    )
+    .bind(user_id)
+    .bind(stream_id)
+    .bind(source.as_str())
+    .bind(TrackImportStatus::Pending.as_str())
+    .bind(Utc::now())
+    .bind(lease_expires_at);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.last_insert_id() as i32)
+}
+
+pub(crate) async fn get_user_track_import_job(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    job_id: i32,
+) -> RepositoryResult<Option<TrackImportJobRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `id` = ");
+    builder.push_bind(job_id);
+    builder.push(" AND `user_id` = ");
+    builder.push_bind(user_id.deref());
+
+    let query = builder.build_query_as::<TrackImportJobRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_optional(connection.deref_mut()).await?)
+}
+
+pub(crate) async fn get_user_track_import_jobs(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    offset: &i64,
+    limit: &i64,
+) -> RepositoryResult<Vec<TrackImportJobRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `user_id` = ");
+    builder.push_bind(user_id.deref());
+    builder.push(" ORDER BY `id` DESC LIMIT ");
+    builder.push_bind(offset);
+    builder.push(", ");
+    builder.push_bind(limit);
+
+    let query = builder.build_query_as::<TrackImportJobRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
+pub(crate) async fn get_user_track_import_jobs_count(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<i64> {
+    let query = sqlx::query_scalar("SELECT COUNT(*) FROM `track_import_jobs` WHERE `user_id` = ?")
+        .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_one(connection.deref_mut()).await?)
+}
+
+pub(crate) async fn start_track_import_job(
+    connection: &mut MySqlConnection,
+    job_id: i32,
+    total_files: i32,
+) -> RepositoryResult<()> {
+    let query =
+        query("UPDATE `track_import_jobs` SET `status` = ?, `total_files` = ? WHERE `id` = ?")
+            .bind(TrackImportStatus::Running.as_str())
+            .bind(total_files)
+            .bind(job_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn record_track_import_result(
+    connection: &mut MySqlConnection,
+    job_id: i32,
+    result: TrackImportResult,
+) -> RepositoryResult<()> {
+    let mut builder = QueryBuilder::new(
+        "UPDATE `track_import_jobs` SET `processed_files` = `processed_files` + 1, ",
+    );
+
+    builder.push(result.counter_column());
+    builder.push(" = ");
+    builder.push(result.counter_column());
+    builder.push(" + 1 WHERE `id` = ");
+    builder.push_bind(job_id);
+
+    let query = builder.build();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn add_track_import_job_error(
+    connection: &mut MySqlConnection,
+    job_id: i32,
+    file_name: &str,
+    error: &str,
+) -> RepositoryResult<()> {
+    let query = query(
+        "INSERT INTO `track_import_job_errors` (`job_id`, `file_name`, `error`) VALUES (?, ?, ?)",
+    )
+    .bind(job_id)
+    .bind(file_name)
+    .bind(error);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_track_import_job_errors_count(
+    connection: &mut MySqlConnection,
+    job_id: i32,
+) -> RepositoryResult<i64> {
+    let query =
+        sqlx::query_scalar("SELECT COUNT(*) FROM `track_import_job_errors` WHERE `job_id` = ?")
+            .bind(job_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_one(connection.deref_mut()).await?)
+}
+
+pub(crate) async fn get_track_import_job_errors(
+    connection: &mut MySqlConnection,
+    job_id: i32,
+    offset: &i64,
+    limit: &i64,
+) -> RepositoryResult<Vec<TrackImportJobErrorRow>> {
+    let query = sqlx::query_as(
+        r#"
+SELECT `file_name`, `error`
+FROM `track_import_job_errors`
+WHERE `job_id` = ?
+ORDER BY `id`
+LIMIT ?, ?
+"#,
+    )
+    .bind(job_id)
+    .bind(offset)
+    .bind(limit);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
+pub(crate) async fn finish_track_import_job(
+    connection: &mut MySqlConnection,
+    job_id: i32,
+    status: TrackImportStatus,
+) -> RepositoryResult<()> {
+    let query =
+        query("UPDATE `track_import_jobs` SET `status` = ?, `finished_at` = ? WHERE `id` = ?")
+            .bind(status.as_str())
+            .bind(Utc::now())
+            .bind(job_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Extends the lease of the job, telling the other instances that the job is still running.
+pub(crate) async fn renew_track_import_job_lease(
+    connection: &mut MySqlConnection,
+    job_id: i32,
+    lease_expires_at: &DateTime<Utc>,
+) -> RepositoryResult<()> {
+    let query = query("UPDATE `track_import_jobs` SET `lease_expires_at` = ? WHERE `id` = ?")
+        .bind(lease_expires_at)
+        .bind(job_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Marks the unfinished jobs whose lease has expired as failed: the process running them
+/// has stopped without finishing them.
+pub(crate) async fn fail_interrupted_track_import_jobs(
+    connection: &mut MySqlConnection,
+    now: &DateTime<Utc>,
+) -> RepositoryResult<u64> {
+    let query = query(
+        r#"
+UPDATE `track_import_jobs`
+SET `status` = ?, `finished_at` = ?
+WHERE `status` IN (?, ?)
+  AND (`lease_expires_at` IS NULL OR `lease_expires_at` < ?)
+"#,
+    )
+    .bind(TrackImportStatus::Failed.as_str())
+    .bind(now)
+    .bind(TrackImportStatus::Pending.as_str())
+    .bind(TrackImportStatus::Running.as_str())
+    .bind(now);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.rows_affected())
+}
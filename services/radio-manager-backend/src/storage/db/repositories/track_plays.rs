@@ -0,0 +1,286 @@
+use crate::data_structures::{StreamId, TrackId, UserId};
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryResult;
+use crate::storage::db::repositories::{MostPlayedTrackRow, TrackPlayRow, TrackRow};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{query, Execute, MySql, QueryBuilder};
+use std::ops::{Deref, DerefMut};
+use tracing::trace;
+
+/// Adds the track start to the play log. A track start reported again, e.g. by the players
+/// of the same stream in different formats, adds its listeners to the existing entry.
+/// Returns `true` if the entry is new.
+pub(crate) async fn record_track_play(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    user_id: &UserId,
+    track: &TrackRow,
+    started_at: &DateTime<Utc>,
+    listeners: i32,
+) -> RepositoryResult<bool> {
+    let query = query(
+        r#"
+INSERT INTO `track_plays` (`user_id`, `stream_id`, `track_id`, `artist`, `title`, `album`, `duration`,
+                           `started_at`, `listeners`)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+ON DUPLICATE KEY UPDATE `listeners` = `listeners` + VALUES(`listeners`)
+"#,
+    )
+    .bind(user_id.deref())
+    .bind(stream_id.deref())
+    .bind(track.tid.deref())
+    .bind(&track.artist)
+    .bind(&track.title)
+    .bind(&track.album)
+    .bind(track.duration)
+    .bind(started_at)
+    .bind(listeners);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    // MySQL reports two affected rows when the existing entry is updated.
+    Ok(result.rows_affected() == 1)
+}
+
+/// Returns the id of the stream play log entry of the track started within `window`
+/// around the given time, the closest one if there are several.
+pub(crate) async fn get_track_play_id_near(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    track_id: &TrackId,
+    started_at: &DateTime<Utc>,
+    window: Duration,
+) -> RepositoryResult<Option<i64>> {
+    let query = sqlx::query_scalar(
+        r#"
+SELECT `id`
+FROM `track_plays`
+WHERE `stream_id` = ? AND `track_id` = ? AND `started_at` BETWEEN ? AND ?
+ORDER BY ABS(TIMESTAMPDIFF(MICROSECOND, `started_at`, ?))
+LIMIT 1
+"#,
+    )
+    .bind(stream_id.deref())
+    .bind(track_id.deref())
+    .bind(*started_at - window)
+    .bind(*started_at + window)
+    .bind(started_at);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_optional(connection.deref_mut()).await?)
+}
+
+/// Adds the listeners reported by another player of the stream to the play log entry.
+pub(crate) async fn add_track_play_listeners(
+    connection: &mut MySqlConnection,
+    play_id: i64,
+    listeners: i32,
+) -> RepositoryResult<()> {
+    let query = query("UPDATE `track_plays` SET `listeners` = `listeners` + ? WHERE `id` = ?")
+        .bind(listeners)
+        .bind(play_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Increments the play counter of the track.
+pub(crate) async fn increment_track_plays(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+    played_at: &DateTime<Utc>,
+) -> RepositoryResult<()> {
+    let query = query(
+        r#"
+INSERT INTO `mor_track_stat` (`track_id`, `plays`, `last_played_at`)
+VALUES (?, 1, ?)
+ON DUPLICATE KEY UPDATE `plays` = `plays` + 1, `last_played_at` = VALUES(`last_played_at`)
+"#,
+    )
+    .bind(track_id.deref())
+    .bind(played_at);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Returns the latest entries of the stream play log started before the given time.
+pub(crate) async fn get_stream_track_plays(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    started_before: &Option<DateTime<Utc>>,
+    limit: i64,
+) -> RepositoryResult<Vec<TrackPlayRow>> {
+    let mut builder = QueryBuilder::<MySql>::new(
+        r#"
+SELECT `id`, `user_id`, `stream_id`, `track_id`, `artist`, `title`, `album`, `duration`, `started_at`, `listeners`
+FROM `track_plays`
+WHERE `stream_id` = "#,
+    );
+    builder.push_bind(stream_id.deref());
+
+    if let Some(started_before) = started_before {
+        builder.push(" AND `started_at` < ");
+        builder.push_bind(started_before);
+    }
+
+    builder.push(" ORDER BY `started_at` DESC LIMIT ");
+    builder.push_bind(limit);
+
+    let query = builder.build_query_as::<TrackPlayRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
+/// Returns the user's tracks ordered by the number of plays.
+pub(crate) async fn get_most_played_user_tracks(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    offset: i64,
+    limit: i64,
+) -> RepositoryResult<Vec<MostPlayedTrackRow>> {
+    let query = sqlx::query_as(
+        r#"
+SELECT `r_tracks`.`tid` AS `track_id`,
+       `r_tracks`.`artist`,
+       `r_tracks`.`title`,
+       `r_tracks`.`album`,
+       `r_tracks`.`duration`,
+       `mor_track_stat`.`plays`,
+       `mor_track_stat`.`last_played_at`
+FROM `mor_track_stat`
+JOIN `r_tracks` ON `r_tracks`.`tid` = `mor_track_stat`.`track_id`
+WHERE `r_tracks`.`uid` = ? AND `r_tracks`.`is_deleted` = 0 AND `mor_track_stat`.`plays` > 0
+ORDER BY `mor_track_stat`.`plays` DESC, `r_tracks`.`tid` DESC
+LIMIT ?, ?
+"#,
+    )
+    .bind(user_id.deref())
+    .bind(offset)
+    .bind(limit);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
+/// Returns the entries of the stream play log started within the period, oldest first.
+pub(crate) async fn get_stream_track_plays_between(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    from: &DateTime<Utc>,
+    to: &DateTime<Utc>,
+) -> RepositoryResult<Vec<TrackPlayRow>> {
+    let query = sqlx::query_as(
+        r#"
+SELECT `id`, `user_id`, `stream_id`, `track_id`, `artist`, `title`, `album`, `duration`, `started_at`, `listeners`
+FROM `track_plays`
+WHERE `stream_id` = ? AND `started_at` >= ? AND `started_at` < ?
+ORDER BY `started_at`
+"#,
+    )
+    .bind(stream_id.deref())
+    .bind(from)
+    .bind(to);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::db::repositories::user_tracks::get_single_user_track;
+    use crate::storage::db::test_database::{
+        create_test_stream, create_test_track, create_test_user, test_connection,
+    };
+    use chrono::TimeZone;
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_finds_track_play_started_nearby() {
+        let mut connection = test_connection().await;
+        let user_id = create_test_user(&mut connection).await;
+        let stream_id = create_test_stream(&mut connection, &user_id).await;
+        let track_id = create_test_track(&mut connection, &user_id, "A", 100).await;
+        let other_track_id = create_test_track(&mut connection, &user_id, "B", 100).await;
+        let track = get_single_user_track(&mut connection, &track_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .track;
+        let started_at = Utc.timestamp_millis_opt(1_800_000_000_250).unwrap();
+        let window = Duration::seconds(10);
+
+        record_track_play(
+            &mut connection,
+            &stream_id,
+            &user_id,
+            &track,
+            &started_at,
+            1,
+        )
+        .await
+        .unwrap();
+
+        let play_id = get_track_play_id_near(
+            &mut connection,
+            &stream_id,
+            &track_id,
+            &(started_at + Duration::milliseconds(900)),
+            window,
+        )
+        .await
+        .unwrap();
+
+        assert!(play_id.is_some());
+
+        add_track_play_listeners(&mut connection, play_id.unwrap(), 2)
+            .await
+            .unwrap();
+
+        let plays = get_stream_track_plays(&mut connection, &stream_id, &None, 10)
+            .await
+            .unwrap();
+
+        assert_eq!(1, plays.len());
+        assert_eq!(3, plays[0].listeners);
+
+        assert_eq!(
+            None,
+            get_track_play_id_near(
+                &mut connection,
+                &stream_id,
+                &track_id,
+                &(started_at + Duration::seconds(30)),
+                window,
+            )
+            .await
+            .unwrap()
+        );
+        assert_eq!(
+            None,
+            get_track_play_id_near(
+                &mut connection,
+                &stream_id,
+                &other_track_id,
+                &started_at,
+                window,
+            )
+            .await
+            .unwrap()
+        );
+    }
+}
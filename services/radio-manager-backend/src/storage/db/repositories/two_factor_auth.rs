@@ -0,0 +1,171 @@
+use crate::data_structures::UserId;
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::errors::RepositoryResult;
+use crate::storage::db::repositories::TwoFactorAuthRow;
+use chrono::Utc;
+use sqlx::{query, query_as, Execute};
+use std::ops::DerefMut;
+use tracing::trace;
+
+pub(crate) async fn get_two_factor_auth(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<Option<TwoFactorAuthRow>> {
+    let query = query_as(
+        r#"
+SELECT `user_id`, `secret`, `enabled`, `last_used_step`, `created_at`, `updated_at`
+FROM `two_factor_auth`
+WHERE `user_id` = ?
+"#,
+    )
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_optional(connection.deref_mut()).await?)
+}
+
+/// Stores a new secret that has not been confirmed yet. Overwrites any pending secret.
+pub(crate) async fn save_pending_two_factor_secret(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    secret: &str,
+) -> RepositoryResult<()> {
+    let query = query(
+        r#"
+INSERT INTO `two_factor_auth` (`user_id`, `secret`, `enabled`, `last_used_step`, `created_at`, `updated_at`)
+VALUES (?, ?, 0, NULL, ?, ?)
+ON DUPLICATE KEY UPDATE `secret` = VALUES(`secret`), `enabled` = 0, `last_used_step` = NULL, `updated_at` = VALUES(`updated_at`)
+"#,
+    )
+    .bind(user_id)
+    .bind(secret)
+    .bind(Utc::now())
+    .bind(Utc::now());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn enable_two_factor_auth(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let query =
+        query("UPDATE `two_factor_auth` SET `enabled` = 1, `updated_at` = ? WHERE `user_id` = ?")
+            .bind(Utc::now())
+            .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Remembers the time step of the last accepted code. Returns `false` if a code from the same
+/// or a later time step has already been accepted.
+pub(crate) async fn update_last_used_step(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    step: i64,
+) -> RepositoryResult<bool> {
+    let query = query(
+        r#"
+UPDATE `two_factor_auth`
+SET `last_used_step` = ?, `updated_at` = ?
+WHERE `user_id` = ?
+  AND (`last_used_step` IS NULL OR `last_used_step` < ?)
+"#,
+    )
+    .bind(step)
+    .bind(Utc::now())
+    .bind(user_id)
+    .bind(step);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+pub(crate) async fn delete_two_factor_auth(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let query = query("DELETE FROM `two_factor_auth` WHERE `user_id` = ?").bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    delete_recovery_codes(connection, user_id).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn replace_recovery_codes(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    code_hashes: &[String],
+) -> RepositoryResult<()> {
+    delete_recovery_codes(connection, user_id).await?;
+
+    for code_hash in code_hashes {
+        let query = query(
+            "INSERT INTO `two_factor_recovery_codes` (`user_id`, `code_hash`, `created_at`) VALUES (?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .bind(Utc::now());
+
+        trace!("Running SQL query: {}", query.sql());
+
+        query.execute(connection.deref_mut()).await?;
+    }
+
+    Ok(())
+}
+
+/// Marks the recovery code as used. Returns `false` if there is no unused code with this hash.
+pub(crate) async fn use_recovery_code(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    code_hash: &str,
+) -> RepositoryResult<bool> {
+    let query = query(
+        r#"
+UPDATE `two_factor_recovery_codes`
+SET `used_at` = ?
+WHERE `user_id` = ?
+  AND `code_hash` = ?
+  AND `used_at` IS NULL
+"#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .bind(code_hash);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+async fn delete_recovery_codes(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let query = query("DELETE FROM `two_factor_recovery_codes` WHERE `user_id` = ?").bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
@@ -1,12 +1,18 @@
-use crate::data_structures::{LinkId, OrderId, StreamId, TrackId};
+use crate::data_structures::{LinkId, OrderId, StreamAccess, StreamId, TrackId};
 use crate::mysql_client::MySqlConnection;
 use crate::storage::db::repositories::errors::RepositoryResult;
+use crate::storage::db::repositories::user_tracks::push_track_ids;
 use crate::storage::db::repositories::{FileRow, LinkRow, TrackRow};
+use crate::storage::db::track_query::{push_track_query_conditions, TrackQuery};
 use chrono::Duration;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use sqlx::{query, Execute, MySql, QueryBuilder, Row};
 use std::ops::{Deref, DerefMut};
 use tracing::trace;
 
+const UNIQUE_ID_LENGTH: usize = 8;
+
 #[derive(sqlx::FromRow, Clone, Debug)]
 pub(crate) struct TrackFileLinkMergedRow {
     #[sqlx(flatten)]
@@ -65,7 +71,9 @@ JOIN `r_link` ON `r_tracks`.`tid` = `r_link`.`track_id`
 #[derive(Default, Debug)]
 pub(crate) struct GetUserStreamTracksParams {
     pub(crate) color: Option<u32>,
-    pub(crate) filter: Option<String>,
+    pub(crate) filter: Option<TrackQuery>,
+    /// Only the tracks their owners allowed to share.
+    pub(crate) shared_only: bool,
 }
 
 #[tracing::instrument(err, skip(connection))]
@@ -82,11 +90,7 @@ pub(crate) async fn get_stream_tracks_count(
     builder.push_bind(stream_id.deref());
 
     if let Some(filter) = &params.filter {
-        if !filter.is_empty() {
-            builder.push(" AND MATCH(artist, title, genre) AGAINST (");
-            builder.push_bind(filter);
-            builder.push(" IN BOOLEAN MODE)");
-        }
+        push_track_query_conditions(&mut builder, filter);
     };
 
     if let Some(color) = params.color {
@@ -94,6 +98,10 @@ pub(crate) async fn get_stream_tracks_count(
         builder.push_bind(color);
     };
 
+    if params.shared_only {
+        builder.push(" AND `r_tracks`.`can_be_shared` = 1");
+    }
+
     let query = builder.build();
 
     trace!("Running SQL query: {}", query.sql());
@@ -113,23 +121,9 @@ pub(crate) async fn get_stream_tracks(
 ) -> RepositoryResult<Vec<TrackFileLinkMergedRow>> {
     let mut builder = create_select_query_builder();
 
-    builder.push(" WHERE `r_link`.`stream_id` = ");
-    builder.push_bind(stream_id.deref());
-
-    if let Some(filter) = &params.filter {
-        if !filter.is_empty() {
-            builder.push(" AND MATCH(artist, title, genre) AGAINST (");
-            builder.push_bind(filter);
-            builder.push(" IN BOOLEAN MODE)");
-        }
-    };
+    push_stream_tracks_conditions(&mut builder, stream_id, params);
 
-    if let Some(color) = params.color {
-        builder.push(" AND color = ");
-        builder.push_bind(color);
-    };
-
-    builder.push(" ORDER BY `r_link`.`t_order`");
+    builder.push(" ORDER BY `r_link`.`t_order`, `r_link`.`id`");
 
     match (limit, offset) {
         (Some(limit), Some(offset)) => {
@@ -154,6 +148,104 @@ pub(crate) async fn get_stream_tracks(
     Ok(stream_audio_tracks)
 }
 
+/// Keyset pagination position in the stream playlist: the next page starts after the entry
+/// with the given order and id.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct StreamTracksCursor {
+    #[serde(rename = "o")]
+    pub(crate) t_order: OrderId,
+    #[serde(rename = "l")]
+    pub(crate) link_id: LinkId,
+}
+
+impl StreamTracksCursor {
+    /// Cursor pointing right after the playlist entry.
+    pub(crate) fn after(link: &LinkRow) -> Self {
+        Self {
+            t_order: link.t_order.clone(),
+            link_id: link.id.clone(),
+        }
+    }
+}
+
+/// Returns up to `limit` stream playlist entries following the cursor position.
+///
+/// Inserting or removing entries renumbers the playlist, so the page starts after the current
+/// order of the cursor entry. If the entry itself is removed, the page starts at its order.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn get_stream_tracks_after(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    params: &GetUserStreamTracksParams,
+    cursor: &StreamTracksCursor,
+    limit: i64,
+) -> RepositoryResult<Vec<TrackFileLinkMergedRow>> {
+    let current_t_order_query = sqlx::query_scalar::<_, i32>(
+        "SELECT `t_order` FROM `r_link` WHERE `r_link`.`stream_id` = ? AND `r_link`.`id` = ?",
+    )
+    .bind(stream_id.deref())
+    .bind(cursor.link_id.deref());
+
+    trace!("Running SQL query: {}", current_t_order_query.sql());
+
+    let current_t_order = current_t_order_query
+        .fetch_optional(connection.deref_mut())
+        .await?;
+
+    let mut builder = create_select_query_builder();
+
+    push_stream_tracks_conditions(&mut builder, stream_id, params);
+
+    match current_t_order {
+        Some(t_order) => {
+            builder.push(" AND (`r_link`.`t_order` > ");
+            builder.push_bind(t_order);
+            builder.push(" OR (`r_link`.`t_order` = ");
+            builder.push_bind(t_order);
+            builder.push(" AND `r_link`.`id` > ");
+            builder.push_bind(*cursor.link_id);
+            builder.push("))");
+        }
+        None => {
+            builder.push(" AND `r_link`.`t_order` >= ");
+            builder.push_bind(*cursor.t_order);
+        }
+    }
+
+    builder.push(" ORDER BY `r_link`.`t_order`, `r_link`.`id` LIMIT ");
+    builder.push_bind(limit);
+
+    let query = builder.build_query_as::<TrackFileLinkMergedRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let stream_audio_tracks = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(stream_audio_tracks)
+}
+
+fn push_stream_tracks_conditions(
+    builder: &mut QueryBuilder<'_, MySql>,
+    stream_id: &StreamId,
+    params: &GetUserStreamTracksParams,
+) {
+    builder.push(" WHERE `r_link`.`stream_id` = ");
+    builder.push_bind(**stream_id);
+
+    if let Some(filter) = &params.filter {
+        push_track_query_conditions(builder, filter);
+    };
+
+    if let Some(color) = params.color {
+        builder.push(" AND color = ");
+        builder.push_bind(color);
+    };
+
+    if params.shared_only {
+        builder.push(" AND `r_tracks`.`can_be_shared` = 1");
+    }
+}
+
 #[tracing::instrument(err, skip(connection))]
 pub(crate) async fn get_single_stream_track_at_time_offset(
     connection: &mut MySqlConnection,
@@ -182,6 +274,41 @@ pub(crate) async fn get_single_stream_track_at_time_offset(
     }))
 }
 
+/// Returns the tracks playing at the time offsets of the stream playlists, at most one
+/// per stream.
+pub(crate) async fn get_stream_tracks_at_time_offsets(
+    connection: &mut MySqlConnection,
+    time_offsets: &[(StreamId, i64)],
+) -> RepositoryResult<Vec<TrackFileLinkMergedRow>> {
+    if time_offsets.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE ");
+
+    for (index, (stream_id, time_offset)) in time_offsets.iter().enumerate() {
+        if index > 0 {
+            builder.push(" OR ");
+        }
+
+        builder.push("(`r_link`.`stream_id` = ");
+        builder.push_bind(stream_id.deref());
+        builder.push(" AND `r_link`.`time_offset` <= ");
+        builder.push_bind(time_offset);
+        builder.push(" AND `r_link`.`time_offset` + `r_tracks`.`duration` > ");
+        builder.push_bind(time_offset);
+        builder.push(")");
+    }
+
+    let query = builder.build_query_as::<TrackFileLinkMergedRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_all(connection.deref_mut()).await?)
+}
+
 #[tracing::instrument(err, skip(connection))]
 pub(crate) async fn get_current_and_next_stream_track_at_time_offset(
     connection: &mut MySqlConnection,
@@ -283,7 +410,7 @@ pub(crate) async fn remove_tracks_by_track_id(
     track_id: &TrackId,
     stream_id: &StreamId,
 ) -> RepositoryResult<()> {
-    query("DELETE FROM `r_links` WHERE `r_link`.`stream_id` = ? AND `r_link`.`track_id` = ?")
+    query("DELETE FROM `r_link` WHERE `r_link`.`stream_id` = ? AND `r_link`.`track_id` = ?")
         .bind(stream_id.deref())
         .bind(track_id.deref())
         .execute(connection.deref_mut())
@@ -298,7 +425,7 @@ pub(crate) async fn delete_track_by_link_id(
     link_id: &LinkId,
     stream_id: &StreamId,
 ) -> RepositoryResult<()> {
-    query("DELETE FROM `r_links` WHERE `r_link`.`stream_id` = ? AND `r_link`.`id` = ?")
+    query("DELETE FROM `r_link` WHERE `r_link`.`stream_id` = ? AND `r_link`.`id` = ?")
         .bind(stream_id.deref())
         .bind(link_id.deref())
         .execute(connection.deref_mut())
@@ -307,6 +434,21 @@ pub(crate) async fn delete_track_by_link_id(
     Ok(())
 }
 
+/// Deletes all links of the stream one by one so that the `r_link` triggers keep
+/// the track usage counters up to date, which doesn't happen on cascading deletes.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn delete_stream_links(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+) -> RepositoryResult<()> {
+    query("DELETE FROM `r_link` WHERE `r_link`.`stream_id` = ?")
+        .bind(stream_id.deref())
+        .execute(connection.deref_mut())
+        .await?;
+
+    Ok(())
+}
+
 #[tracing::instrument(err, skip(connection))]
 pub(crate) async fn optimize_tracks_in_user_stream(
     mut connection: &mut MySqlConnection,
@@ -338,3 +480,358 @@ pub(crate) async fn optimize_tracks_in_user_stream(
 
     Ok(())
 }
+
+fn generate_unique_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(UNIQUE_ID_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Adds the track to the end of the stream playlist.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn append_track_to_stream(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    track_id: &TrackId,
+) -> RepositoryResult<()> {
+    let unique_id = generate_unique_id();
+
+    let query = query(
+        r#"
+INSERT INTO `r_link` (`stream_id`, `track_id`, `t_order`, `unique_id`, `time_offset`)
+SELECT ?, ?, COALESCE(MAX(`r_link`.`t_order`), 0) + 1, ?, COALESCE(SUM(`r_tracks`.`duration`), 0)
+FROM `r_link`
+JOIN `r_tracks` ON `r_tracks`.`tid` = `r_link`.`track_id`
+WHERE `r_link`.`stream_id` = ?
+"#,
+    )
+    .bind(stream_id.deref())
+    .bind(track_id.deref())
+    .bind(unique_id)
+    .bind(stream_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Adds the track to the stream playlist with the given order. The time offsets
+/// are left for `optimize_tracks_in_user_stream` to recalculate.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn insert_track_into_stream(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    track_id: &TrackId,
+    t_order: i32,
+) -> RepositoryResult<()> {
+    let query = query(
+        r#"
+INSERT INTO `r_link` (`stream_id`, `track_id`, `t_order`, `unique_id`, `time_offset`)
+VALUES (?, ?, ?, ?, 0)
+"#,
+    )
+    .bind(stream_id.deref())
+    .bind(track_id.deref())
+    .bind(t_order)
+    .bind(generate_unique_id());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Moves the stream playlist entries starting from the given order by `count` positions
+/// down, making room for the inserted tracks.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn shift_stream_tracks(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    from_t_order: i32,
+    count: i32,
+) -> RepositoryResult<()> {
+    let query = query(
+        "UPDATE `r_link` SET `t_order` = `t_order` + ? WHERE `stream_id` = ? AND `t_order` >= ?",
+    )
+    .bind(count)
+    .bind(stream_id.deref())
+    .bind(from_t_order);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn remove_tracks_from_stream(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    track_ids: &[TrackId],
+) -> RepositoryResult<()> {
+    if track_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::new("DELETE FROM `r_link` WHERE `stream_id` = ");
+    builder.push_bind(stream_id.deref());
+    builder.push(" AND `track_id`");
+    push_track_ids(&mut builder, track_ids);
+
+    let query = builder.build();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Returns those of the given tracks that are in the stream playlist.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn get_track_ids_in_stream(
+    connection: &mut MySqlConnection,
+    stream_id: &StreamId,
+    track_ids: &[TrackId],
+) -> RepositoryResult<Vec<TrackId>> {
+    if track_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut builder =
+        QueryBuilder::new("SELECT DISTINCT `track_id` FROM `r_link` WHERE `stream_id` = ");
+    builder.push_bind(stream_id.deref());
+    builder.push(" AND `track_id`");
+    push_track_ids(&mut builder, track_ids);
+
+    let query = builder.build_query_scalar::<i32>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let ids = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(ids.into_iter().map(TrackId::from).collect())
+}
+
+/// Checks whether the track is in the playlist of any public stream.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn is_track_in_public_stream(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+) -> RepositoryResult<bool> {
+    let query = sqlx::query_scalar::<_, i64>(
+        r#"
+SELECT COUNT(*)
+FROM `r_link`
+JOIN `r_streams` ON `r_streams`.`sid` = `r_link`.`stream_id`
+WHERE `r_link`.`track_id` = ? AND `r_streams`.`access` = ?
+"#,
+    )
+    .bind(track_id.deref())
+    .bind(StreamAccess::Public.as_str());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    Ok(query.fetch_one(connection.deref_mut()).await? > 0)
+}
+
+/// Returns the streams having any of the given tracks in their playlists.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn get_stream_ids_having_tracks(
+    connection: &mut MySqlConnection,
+    track_ids: &[TrackId],
+) -> RepositoryResult<Vec<StreamId>> {
+    if track_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut builder =
+        QueryBuilder::new("SELECT DISTINCT `stream_id` FROM `r_link` WHERE `track_id`");
+    push_track_ids(&mut builder, track_ids);
+
+    let query = builder.build_query_scalar::<i32>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let ids = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(ids.into_iter().map(StreamId::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::db::test_database::{
+        create_test_stream, create_test_track, create_test_user, test_connection,
+    };
+
+    const PAGE_SIZE: i64 = 2;
+
+    /// Returns the page of playlist entries following the cursor, or the first page,
+    /// and the cursor of the next page.
+    async fn get_page(
+        connection: &mut MySqlConnection,
+        stream_id: &StreamId,
+        cursor: Option<&StreamTracksCursor>,
+    ) -> (Vec<LinkId>, Option<StreamTracksCursor>) {
+        let params = GetUserStreamTracksParams::default();
+        let rows = match cursor {
+            Some(cursor) => {
+                get_stream_tracks_after(connection, stream_id, &params, cursor, PAGE_SIZE)
+                    .await
+                    .unwrap()
+            }
+            None => get_stream_tracks(connection, stream_id, &params, &None, &Some(PAGE_SIZE))
+                .await
+                .unwrap(),
+        };
+        let next_cursor = match rows.last() {
+            Some(row) if rows.len() as i64 == PAGE_SIZE => {
+                Some(StreamTracksCursor::after(&row.link))
+            }
+            _ => None,
+        };
+
+        (
+            rows.into_iter().map(|row| row.link.id).collect(),
+            next_cursor,
+        )
+    }
+
+    async fn get_next_pages(
+        connection: &mut MySqlConnection,
+        stream_id: &StreamId,
+        mut cursor: Option<StreamTracksCursor>,
+    ) -> Vec<LinkId> {
+        let mut link_ids = vec![];
+
+        while let Some(current_cursor) = cursor {
+            let (page, next_cursor) = get_page(connection, stream_id, Some(&current_cursor)).await;
+            link_ids.extend(page);
+            cursor = next_cursor;
+        }
+
+        link_ids
+    }
+
+    async fn get_all_link_ids(
+        connection: &mut MySqlConnection,
+        stream_id: &StreamId,
+    ) -> Vec<LinkId> {
+        get_stream_tracks(
+            connection,
+            stream_id,
+            &GetUserStreamTracksParams::default(),
+            &None,
+            &None,
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.link.id)
+        .collect()
+    }
+
+    async fn create_test_playlist(
+        connection: &mut MySqlConnection,
+        t_orders: &[i32],
+    ) -> (StreamId, Vec<TrackId>) {
+        let user_id = create_test_user(connection).await;
+        let stream_id = create_test_stream(connection, &user_id).await;
+        let mut track_ids = vec![];
+
+        for t_order in t_orders {
+            let track_id = create_test_track(connection, &user_id, "Track", 1000).await;
+            insert_track_into_stream(connection, &stream_id, &track_id, *t_order)
+                .await
+                .unwrap();
+            track_ids.push(track_id);
+        }
+
+        (stream_id, track_ids)
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_pages_stream_tracks_having_equal_orders() {
+        let mut connection = test_connection().await;
+        let (stream_id, _) = create_test_playlist(&mut connection, &[1, 2, 2, 2, 3]).await;
+
+        let all_link_ids = get_all_link_ids(&mut connection, &stream_id).await;
+        let (mut link_ids, cursor) = get_page(&mut connection, &stream_id, None).await;
+        link_ids.extend(get_next_pages(&mut connection, &stream_id, cursor).await);
+
+        assert_eq!(5, all_link_ids.len());
+        assert_eq!(all_link_ids, link_ids);
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_pages_stream_tracks_added_and_removed_between_pages() {
+        let mut connection = test_connection().await;
+        let (stream_id, track_ids) = create_test_playlist(&mut connection, &[1, 2, 3, 4, 5]).await;
+
+        let (first_page, cursor) = get_page(&mut connection, &stream_id, None).await;
+        let all_link_ids = get_all_link_ids(&mut connection, &stream_id).await;
+
+        assert_eq!(all_link_ids[..2], first_page);
+
+        // Entries inserted before the cursor and removed from both sides of it renumber
+        // the playlist, which doesn't make the next pages skip or repeat entries.
+        remove_tracks_by_track_id(&mut connection, &track_ids[0], &stream_id)
+            .await
+            .unwrap();
+        remove_tracks_by_track_id(&mut connection, &track_ids[3], &stream_id)
+            .await
+            .unwrap();
+        shift_stream_tracks(&mut connection, &stream_id, 1, 1)
+            .await
+            .unwrap();
+        insert_track_into_stream(&mut connection, &stream_id, &track_ids[0], 1)
+            .await
+            .unwrap();
+        append_track_to_stream(&mut connection, &stream_id, &track_ids[3])
+            .await
+            .unwrap();
+        optimize_tracks_in_user_stream(&mut connection, &stream_id)
+            .await
+            .unwrap();
+
+        let next_pages = get_next_pages(&mut connection, &stream_id, cursor).await;
+        let all_link_ids_after = get_all_link_ids(&mut connection, &stream_id).await;
+
+        assert_eq!(all_link_ids[2], next_pages[0]);
+        assert_eq!(
+            all_link_ids_after[all_link_ids_after.len() - 3..],
+            next_pages
+        );
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_pages_stream_tracks_after_removed_cursor_entry() {
+        let mut connection = test_connection().await;
+        let (stream_id, track_ids) = create_test_playlist(&mut connection, &[1, 2, 3, 4, 5]).await;
+
+        let (_, cursor) = get_page(&mut connection, &stream_id, None).await;
+        let all_link_ids = get_all_link_ids(&mut connection, &stream_id).await;
+
+        remove_tracks_by_track_id(&mut connection, &track_ids[1], &stream_id)
+            .await
+            .unwrap();
+        optimize_tracks_in_user_stream(&mut connection, &stream_id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            all_link_ids[2..],
+            get_next_pages(&mut connection, &stream_id, cursor).await
+        );
+    }
+}
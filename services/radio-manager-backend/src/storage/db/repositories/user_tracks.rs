@@ -1,7 +1,9 @@
-use crate::data_structures::{SortingColumn, SortingOrder, TrackId, UserId};
+use crate::data_structures::{FileId, SortingColumn, SortingOrder, TrackId, UserId};
 use crate::mysql_client::MySqlConnection;
 use crate::storage::db::repositories::errors::RepositoryResult;
 use crate::storage::db::repositories::{FileRow, TrackRow};
+use crate::storage::db::track_query::{push_track_query_conditions, TrackQuery};
+use serde::{Deserialize, Serialize};
 use sqlx::{query, Execute, FromRow, MySql, QueryBuilder, Row};
 use std::ops::{Deref, DerefMut};
 use tracing::trace;
@@ -63,8 +65,9 @@ impl Deref for TrackFileMergedRow {
 #[derive(Default, Debug)]
 pub(crate) struct GetUserTracksTotalParams {
     pub(crate) color: Option<u32>,
-    pub(crate) filter: Option<String>,
+    pub(crate) filter: Option<TrackQuery>,
     pub(crate) unused: bool,
+    pub(crate) new_only: bool,
 }
 
 pub(crate) async fn get_user_tracks_count(
@@ -76,15 +79,10 @@ pub(crate) async fn get_user_tracks_count(
 
     builder.push(" WHERE `r_tracks`.`uid` = ");
     builder.push_bind(user_id.deref());
+    builder.push(" AND `r_tracks`.`is_deleted` = 0");
 
     if let Some(filter) = &params.filter {
-        if !filter.is_empty() {
-            builder.push(
-                " AND MATCH(`r_tracks`.`artist`, `r_tracks`.`title`, `r_tracks`.`genre`) AGAINST (",
-            );
-            builder.push_bind(filter);
-            builder.push(" IN BOOLEAN MODE)");
-        }
+        push_track_query_conditions(&mut builder, filter);
     };
 
     if let Some(color) = params.color {
@@ -96,6 +94,10 @@ pub(crate) async fn get_user_tracks_count(
         builder.push(" AND `r_tracks`.`used_count` = 0");
     }
 
+    if params.new_only {
+        builder.push(" AND `r_tracks`.`is_new` = 1");
+    }
+
     let query = builder.build();
 
     trace!("Running SQL query: {}", query.sql());
@@ -105,11 +107,58 @@ pub(crate) async fn get_user_tracks_count(
     Ok(count_row.get("count"))
 }
 
+/// Value of the sorting key of a track.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum SortingValue {
+    Number(i64),
+    Text(String),
+}
+
+/// Keyset pagination position: the next page starts after the track with the given sorting key
+/// value and id, so tracks added or removed meanwhile don't shift the pages.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct UserTracksCursor {
+    #[serde(rename = "c")]
+    pub(crate) sorting_column: SortingColumn,
+    #[serde(rename = "o")]
+    pub(crate) sorting_order: SortingOrder,
+    #[serde(rename = "v")]
+    pub(crate) value: SortingValue,
+    #[serde(rename = "t")]
+    pub(crate) track_id: TrackId,
+}
+
+impl UserTracksCursor {
+    /// Cursor pointing right after the track.
+    pub(crate) fn after(
+        track: &TrackRow,
+        sorting_column: SortingColumn,
+        sorting_order: SortingOrder,
+    ) -> Self {
+        let value = match sorting_column {
+            SortingColumn::TrackId => SortingValue::Number(*track.tid as i64),
+            SortingColumn::Title => SortingValue::Text(track.title.clone()),
+            SortingColumn::Artist => SortingValue::Text(track.artist.clone()),
+            SortingColumn::Genre => SortingValue::Text(track.genre.clone()),
+            SortingColumn::Duration => SortingValue::Number(track.duration),
+        };
+
+        Self {
+            sorting_column,
+            sorting_order,
+            value,
+            track_id: track.tid.clone(),
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub(crate) struct GetUserTracksParams {
     pub(crate) color: Option<u32>,
-    pub(crate) filter: Option<String>,
+    pub(crate) filter: Option<TrackQuery>,
     pub(crate) unused: bool,
+    pub(crate) new_only: bool,
     pub(crate) sorting_column: SortingColumn,
     pub(crate) sorting_order: SortingOrder,
 }
@@ -124,17 +173,71 @@ pub(crate) async fn get_user_tracks(
 ) -> RepositoryResult<Vec<TrackFileMergedRow>> {
     let mut builder = create_select_query_builder();
 
+    push_user_tracks_conditions(&mut builder, user_id, params);
+    push_user_tracks_order(&mut builder, params.sorting_column, params.sorting_order);
+
+    match (limit, offset) {
+        (Some(limit), Some(offset)) => {
+            builder.push(" LIMIT ");
+            builder.push_bind(offset);
+            builder.push(", ");
+            builder.push_bind(limit);
+        }
+        (Some(limit), _) => {
+            builder.push(" LIMIT ");
+            builder.push_bind(limit);
+        }
+        _ => (),
+    }
+
+    let query = builder.build_query_as::<TrackFileMergedRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let audio_tracks = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(audio_tracks)
+}
+
+/// Returns up to `limit` user tracks following the cursor position. The sorting of the
+/// cursor is used instead of the one in `params`.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn get_user_tracks_after(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    params: &GetUserTracksParams,
+    cursor: &UserTracksCursor,
+    limit: i64,
+) -> RepositoryResult<Vec<TrackFileMergedRow>> {
+    let mut builder = create_select_query_builder();
+
+    push_user_tracks_conditions(&mut builder, user_id, params);
+    push_cursor_condition(&mut builder, cursor);
+    push_user_tracks_order(&mut builder, cursor.sorting_column, cursor.sorting_order);
+
+    builder.push(" LIMIT ");
+    builder.push_bind(limit);
+
+    let query = builder.build_query_as::<TrackFileMergedRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let audio_tracks = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(audio_tracks)
+}
+
+fn push_user_tracks_conditions(
+    builder: &mut QueryBuilder<'_, MySql>,
+    user_id: &UserId,
+    params: &GetUserTracksParams,
+) {
     builder.push(" WHERE `r_tracks`.`uid` = ");
-    builder.push_bind(user_id.deref());
+    builder.push_bind(**user_id);
+    builder.push(" AND `r_tracks`.`is_deleted` = 0");
 
     if let Some(filter) = &params.filter {
-        if !filter.is_empty() {
-            builder.push(
-                " AND MATCH(`r_tracks`.`artist`, `r_tracks`.`title`, `r_tracks`.`genre`) AGAINST (",
-            );
-            builder.push_bind(filter);
-            builder.push(" IN BOOLEAN MODE)");
-        }
+        push_track_query_conditions(builder, filter);
     };
 
     if let Some(color) = params.color {
@@ -146,11 +249,334 @@ pub(crate) async fn get_user_tracks(
         builder.push(" AND `r_tracks`.`used_count` = 0");
     }
 
+    if params.new_only {
+        builder.push(" AND `r_tracks`.`is_new` = 1");
+    }
+}
+
+/// Tracks having equal sorting keys are ordered by id, so the order is stable between requests.
+fn push_user_tracks_order(
+    builder: &mut QueryBuilder<'_, MySql>,
+    sorting_column: SortingColumn,
+    sorting_order: SortingOrder,
+) {
     builder.push(format_args!(
         " ORDER BY {} {}",
-        params.sorting_column.as_str(),
-        params.sorting_order.as_str()
+        sorting_column.as_str(),
+        sorting_order.as_str()
+    ));
+
+    if sorting_column != SortingColumn::TrackId {
+        builder.push(format_args!(
+            ", {} {}",
+            SortingColumn::TrackId.as_str(),
+            sorting_order.as_str()
+        ));
+    }
+}
+
+fn push_cursor_condition<'a>(builder: &mut QueryBuilder<'a, MySql>, cursor: &'a UserTracksCursor) {
+    let column = cursor.sorting_column.as_str();
+    let operator = match cursor.sorting_order {
+        SortingOrder::Desc => "<",
+        SortingOrder::Asc => ">",
+    };
+
+    if cursor.sorting_column == SortingColumn::TrackId {
+        builder.push(format_args!(" AND {} {} ", column, operator));
+        builder.push_bind(*cursor.track_id);
+
+        return;
+    }
+
+    builder.push(format_args!(" AND ({} {} ", column, operator));
+    push_sorting_value(builder, &cursor.value);
+    builder.push(format_args!(" OR ({} = ", column));
+    push_sorting_value(builder, &cursor.value);
+    builder.push(format_args!(
+        " AND {} {} ",
+        SortingColumn::TrackId.as_str(),
+        operator
     ));
+    builder.push_bind(*cursor.track_id);
+    builder.push("))");
+}
+
+fn push_sorting_value<'a>(builder: &mut QueryBuilder<'a, MySql>, value: &'a SortingValue) {
+    match value {
+        SortingValue::Number(number) => builder.push_bind(*number),
+        SortingValue::Text(text) => builder.push_bind(text),
+    };
+}
+
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn get_single_user_track(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+) -> RepositoryResult<Option<TrackFileMergedRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `r_tracks`.`tid` = ");
+    builder.push_bind(track_id.deref());
+    builder.push(" AND `r_tracks`.`is_deleted` = 0");
+
+    let query = builder.build_query_as::<TrackFileMergedRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let audio_track = query.fetch_optional(connection.deref_mut()).await?;
+
+    Ok(audio_track)
+}
+
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn get_user_tracks_by_ids(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    track_ids: &[TrackId],
+) -> RepositoryResult<Vec<TrackFileMergedRow>> {
+    if track_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `r_tracks`.`uid` = ");
+    builder.push_bind(user_id.deref());
+    builder.push(" AND `r_tracks`.`is_deleted` = 0");
+    builder.push(" AND `r_tracks`.`tid`");
+    push_track_ids(&mut builder, track_ids);
+
+    let query = builder.build_query_as::<TrackFileMergedRow>();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let audio_tracks = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(audio_tracks)
+}
+
+pub(crate) async fn update_user_tracks_color(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    track_ids: &[TrackId],
+    color_id: i32,
+) -> RepositoryResult<()> {
+    if track_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::new("UPDATE `r_tracks` SET `color` = ");
+    builder.push_bind(color_id);
+    builder.push(" WHERE `uid` = ");
+    builder.push_bind(user_id.deref());
+    builder.push(" AND `tid`");
+    push_track_ids(&mut builder, track_ids);
+
+    let query = builder.build();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn update_user_tracks_shareable(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    track_ids: &[TrackId],
+    can_be_shared: bool,
+) -> RepositoryResult<()> {
+    if track_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::new("UPDATE `r_tracks` SET `can_be_shared` = ");
+    builder.push_bind(can_be_shared);
+    builder.push(" WHERE `uid` = ");
+    builder.push_bind(user_id.deref());
+    builder.push(" AND `tid`");
+    push_track_ids(&mut builder, track_ids);
+
+    let query = builder.build();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Clears the `is_new` flag of the user's tracks: the given ones, the ones matching the filter,
+/// or all of them if neither is set. Returns the number of tracks no longer marked as new.
+pub(crate) async fn mark_user_tracks_seen(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    track_ids: Option<&[TrackId]>,
+    filter: Option<&TrackQuery>,
+) -> RepositoryResult<u64> {
+    if matches!(track_ids, Some(track_ids) if track_ids.is_empty()) {
+        return Ok(0);
+    }
+
+    let mut builder = QueryBuilder::new("UPDATE `r_tracks` SET `is_new` = 0 WHERE `uid` = ");
+    builder.push_bind(user_id.deref());
+    builder.push(" AND `r_tracks`.`is_new` = 1 AND `r_tracks`.`is_deleted` = 0");
+
+    if let Some(track_ids) = track_ids {
+        builder.push(" AND `r_tracks`.`tid`");
+        push_track_ids(&mut builder, track_ids);
+    }
+
+    if let Some(filter) = filter {
+        push_track_query_conditions(&mut builder, filter);
+    }
+
+    let query = builder.build();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn delete_user_track(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+) -> RepositoryResult<()> {
+    query("DELETE FROM `r_tracks` WHERE `tid` = ?")
+        .bind(track_id.deref())
+        .execute(connection.deref_mut())
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes the track unless it has been restored from the trash. Returns whether the track
+/// was deleted.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn delete_trashed_user_track(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+) -> RepositoryResult<bool> {
+    let query =
+        query("DELETE FROM `r_tracks` WHERE `tid` = ? AND `is_deleted` = 1").bind(track_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Appends the ` IN (...)` list of the track ids, which must not be empty.
+pub(crate) fn push_track_ids<'a>(builder: &mut QueryBuilder<'a, MySql>, track_ids: &'a [TrackId]) {
+    builder.push(" IN (");
+
+    let mut separated = builder.separated(", ");
+    for track_id in track_ids {
+        separated.push_bind(track_id.deref());
+    }
+
+    builder.push(")");
+}
+
+/// Moves the tracks to the trash: they are hidden from the library until restored or purged.
+pub(crate) async fn trash_user_tracks(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    track_ids: &[TrackId],
+) -> RepositoryResult<()> {
+    if track_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::new(
+        "UPDATE `r_tracks` SET `is_deleted` = 1, `deleted` = UNIX_TIMESTAMP() WHERE `uid` = ",
+    );
+    builder.push_bind(user_id.deref());
+    builder.push(" AND `r_tracks`.`is_deleted` = 0 AND `r_tracks`.`tid`");
+    push_track_ids(&mut builder, track_ids);
+
+    let query = builder.build();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Moves the tracks from the trash back to the library.
+pub(crate) async fn restore_user_tracks(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    track_ids: &[TrackId],
+) -> RepositoryResult<()> {
+    if track_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::new(
+        "UPDATE `r_tracks` SET `is_deleted` = 0, `deleted` = NULL WHERE `uid` = ",
+    );
+    builder.push_bind(user_id.deref());
+    builder.push(" AND `r_tracks`.`is_deleted` = 1 AND `r_tracks`.`tid`");
+    push_track_ids(&mut builder, track_ids);
+
+    let query = builder.build();
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_trashed_user_tracks_count(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<i64> {
+    let query =
+        query("SELECT COUNT(*) AS `count` FROM `r_tracks` WHERE `uid` = ? AND `is_deleted` = 1")
+            .bind(user_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let count_row = query.fetch_one(connection.deref_mut()).await?;
+
+    Ok(count_row.get("count"))
+}
+
+/// Returns the user's trashed tracks, the most recently deleted first. Only the given tracks
+/// are returned if `track_ids` is set.
+#[tracing::instrument(err, skip(connection))]
+pub(crate) async fn get_trashed_user_tracks(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    track_ids: Option<&[TrackId]>,
+    offset: &Option<i64>,
+    limit: &Option<i64>,
+) -> RepositoryResult<Vec<TrackFileMergedRow>> {
+    let mut builder = create_select_query_builder();
+
+    builder.push(" WHERE `r_tracks`.`uid` = ");
+    builder.push_bind(user_id.deref());
+    builder.push(" AND `r_tracks`.`is_deleted` = 1");
+
+    if let Some(track_ids) = track_ids {
+        if track_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        builder.push(" AND `r_tracks`.`tid`");
+        push_track_ids(&mut builder, track_ids);
+    }
+
+    builder.push(" ORDER BY `r_tracks`.`deleted` DESC, `r_tracks`.`tid` DESC");
 
     match (limit, offset) {
         (Some(limit), Some(offset)) => {
@@ -175,34 +601,514 @@ pub(crate) async fn get_user_tracks(
     Ok(audio_tracks)
 }
 
+/// Returns up to `limit` tracks of all users that were moved to the trash before the given time.
 #[tracing::instrument(err, skip(connection))]
-pub(crate) async fn get_single_user_track(
+pub(crate) async fn get_expired_trashed_tracks(
     connection: &mut MySqlConnection,
-    track_id: &TrackId,
-) -> RepositoryResult<Option<TrackFileMergedRow>> {
+    deleted_before: i64,
+    limit: i64,
+) -> RepositoryResult<Vec<TrackFileMergedRow>> {
     let mut builder = create_select_query_builder();
 
-    builder.push(" WHERE `r_tracks`.`tid` = ");
-    builder.push_bind(track_id.deref());
+    builder.push(" WHERE `r_tracks`.`is_deleted` = 1 AND `r_tracks`.`deleted` < ");
+    builder.push_bind(deleted_before);
+    builder.push(" ORDER BY `r_tracks`.`deleted` LIMIT ");
+    builder.push_bind(limit);
 
     let query = builder.build_query_as::<TrackFileMergedRow>();
 
     trace!("Running SQL query: {}", query.sql());
 
-    let audio_track = query.fetch_optional(connection.deref_mut()).await?;
+    let audio_tracks = query.fetch_all(connection.deref_mut()).await?;
 
-    Ok(audio_track)
+    Ok(audio_tracks)
 }
 
-#[tracing::instrument(err, skip(connection))]
-pub(crate) async fn delete_user_track(
+pub(crate) struct NewTrack<'a> {
+    pub(crate) file_id: &'a FileId,
+    pub(crate) filename: &'a str,
+    pub(crate) hash: &'a str,
+    pub(crate) ext: &'a str,
+    pub(crate) artist: &'a str,
+    pub(crate) title: &'a str,
+    pub(crate) album: &'a str,
+    pub(crate) track_number: &'a str,
+    pub(crate) genre: &'a str,
+    pub(crate) date: &'a str,
+    pub(crate) duration: i64,
+    pub(crate) filesize: i64,
+}
+
+pub(crate) async fn create_user_track(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    track: &NewTrack<'_>,
+) -> RepositoryResult<TrackId> {
+    let query = query(
+        r#"
+INSERT INTO `r_tracks` (`file_id`, `uid`, `filename`, `hash`, `ext`, `artist`, `title`, `album`,
+                        `track_number`, `genre`, `date`, `duration`, `filesize`, `color`, `uploaded`)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, UNIX_TIMESTAMP())
+"#,
+    )
+    .bind(track.file_id.deref())
+    .bind(user_id.deref())
+    .bind(track.filename)
+    .bind(track.hash)
+    .bind(track.ext)
+    .bind(track.artist)
+    .bind(track.title)
+    .bind(track.album)
+    .bind(track.track_number)
+    .bind(track.genre)
+    .bind(track.date)
+    .bind(track.duration)
+    .bind(track.filesize);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(TrackId::from(result.last_insert_id() as i32))
+}
+
+/// Creates the copy of another user's track in the user's library. The copy refers to the same
+/// file, keeps the metadata and records the original track in `copy_of`.
+pub(crate) async fn copy_user_track(
     connection: &mut MySqlConnection,
+    user_id: &UserId,
     track_id: &TrackId,
+) -> RepositoryResult<TrackId> {
+    let query = query(
+        r#"
+INSERT INTO `r_tracks` (`file_id`, `uid`, `filename`, `hash`, `ext`, `artist`, `title`, `album`,
+                        `track_number`, `genre`, `date`, `cue`, `buy`, `duration`, `filesize`,
+                        `color`, `uploaded`, `copy_of`)
+SELECT `file_id`, ?, `filename`, `hash`, `ext`, `artist`, `title`, `album`,
+       `track_number`, `genre`, `date`, `cue`, `buy`, `duration`, `filesize`,
+       0, UNIX_TIMESTAMP(), `tid`
+FROM `r_tracks`
+WHERE `tid` = ?
+"#,
+    )
+    .bind(user_id.deref())
+    .bind(track_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(TrackId::from(result.last_insert_id() as i32))
+}
+
+/// Checks whether the user's library already has a track with the given content hash.
+pub(crate) async fn is_track_hash_in_user_library(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    hash: &str,
+) -> RepositoryResult<bool> {
+    let query = query(
+        "SELECT 1 FROM `r_tracks` WHERE `uid` = ? AND `hash` = ? AND `is_deleted` = 0 LIMIT 1",
+    )
+    .bind(user_id.deref())
+    .bind(hash);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let row = query.fetch_optional(connection.deref_mut()).await?;
+
+    Ok(row.is_some())
+}
+
+pub(crate) struct TrackMetadata<'a> {
+    pub(crate) artist: &'a str,
+    pub(crate) title: &'a str,
+    pub(crate) album: &'a str,
+    pub(crate) track_number: &'a str,
+    pub(crate) genre: &'a str,
+    pub(crate) date: &'a str,
+}
+
+pub(crate) async fn update_user_track_metadata(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+    metadata: &TrackMetadata<'_>,
 ) -> RepositoryResult<()> {
-    query("DELETE FROM `r_tracks` WHERE `tid` = ?")
-        .bind(track_id.deref())
-        .execute(connection.deref_mut())
-        .await?;
+    let query = query(
+        r#"
+UPDATE `r_tracks`
+SET `artist` = ?, `title` = ?, `album` = ?, `track_number` = ?, `genre` = ?, `date` = ?
+WHERE `tid` = ?
+"#,
+    )
+    .bind(metadata.artist)
+    .bind(metadata.title)
+    .bind(metadata.album)
+    .bind(metadata.track_number)
+    .bind(metadata.genre)
+    .bind(metadata.date)
+    .bind(track_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
 
     Ok(())
 }
+
+/// Replaces the file of the track unless the track file has been changed meanwhile.
+/// Returns whether the file has been replaced.
+pub(crate) async fn update_user_track_file(
+    connection: &mut MySqlConnection,
+    track_id: &TrackId,
+    previous_file_id: &FileId,
+    file_id: &FileId,
+    hash: &str,
+    filesize: i64,
+) -> RepositoryResult<bool> {
+    let query = query(
+        "UPDATE `r_tracks` SET `file_id` = ?, `hash` = ?, `filesize` = ? WHERE `tid` = ? AND `file_id` = ?",
+    )
+    .bind(file_id.deref())
+    .bind(hash)
+    .bind(filesize)
+    .bind(track_id.deref())
+    .bind(previous_file_id.deref());
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let result = query.execute(connection.deref_mut()).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::db::repositories::user_stream_tracks::append_track_to_stream;
+    use crate::storage::db::test_database::{
+        create_test_stream, create_test_track, create_test_user, test_connection,
+    };
+
+    fn text_cursor(sorting_column: SortingColumn, sorting_order: SortingOrder) -> UserTracksCursor {
+        UserTracksCursor {
+            sorting_column,
+            sorting_order,
+            value: SortingValue::Text("Song".to_string()),
+            track_id: TrackId::from(42),
+        }
+    }
+
+    #[test]
+    fn test_builds_cursor_condition() {
+        let cursor = text_cursor(SortingColumn::Title, SortingOrder::Asc);
+        let mut builder = QueryBuilder::new("");
+        push_cursor_condition(&mut builder, &cursor);
+
+        assert_eq!(
+            " AND (`r_tracks`.`title` > ? OR (`r_tracks`.`title` = ? AND `r_tracks`.`tid` > ?))",
+            builder.sql()
+        );
+
+        let cursor = UserTracksCursor {
+            value: SortingValue::Number(42),
+            ..text_cursor(SortingColumn::TrackId, SortingOrder::Desc)
+        };
+        let mut builder = QueryBuilder::new("");
+        push_cursor_condition(&mut builder, &cursor);
+
+        assert_eq!(" AND `r_tracks`.`tid` < ?", builder.sql());
+    }
+
+    #[test]
+    fn test_orders_tracks_by_id_within_equal_keys() {
+        let mut builder = QueryBuilder::<MySql>::new("");
+        push_user_tracks_order(&mut builder, SortingColumn::Duration, SortingOrder::Desc);
+
+        assert_eq!(
+            " ORDER BY `r_tracks`.`duration` DESC, `r_tracks`.`tid` DESC",
+            builder.sql()
+        );
+    }
+
+    #[test]
+    fn test_serializes_cursor() {
+        let cursor = text_cursor(SortingColumn::Artist, SortingOrder::Desc);
+        let json = serde_json::to_string(&cursor).unwrap();
+
+        assert_eq!(r#"{"c":2,"o":0,"v":"Song","t":42}"#, json);
+        assert_eq!(cursor, serde_json::from_str(&json).unwrap());
+    }
+
+    const PAGE_SIZE: i64 = 2;
+
+    fn sorted_params(
+        sorting_column: SortingColumn,
+        sorting_order: SortingOrder,
+    ) -> GetUserTracksParams {
+        GetUserTracksParams {
+            sorting_column,
+            sorting_order,
+            ..GetUserTracksParams::default()
+        }
+    }
+
+    /// Returns the page of tracks following the cursor, or the first page, and the cursor
+    /// of the next page.
+    async fn get_page(
+        connection: &mut MySqlConnection,
+        user_id: &UserId,
+        params: &GetUserTracksParams,
+        cursor: Option<&UserTracksCursor>,
+    ) -> (Vec<TrackId>, Option<UserTracksCursor>) {
+        let rows = match cursor {
+            Some(cursor) => get_user_tracks_after(connection, user_id, params, cursor, PAGE_SIZE)
+                .await
+                .unwrap(),
+            None => get_user_tracks(connection, user_id, params, &None, &Some(PAGE_SIZE))
+                .await
+                .unwrap(),
+        };
+        let next_cursor = match rows.last() {
+            Some(row) if rows.len() as i64 == PAGE_SIZE => Some(UserTracksCursor::after(
+                &row.track,
+                params.sorting_column,
+                params.sorting_order,
+            )),
+            _ => None,
+        };
+
+        (
+            rows.iter().map(|row| row.track.tid.clone()).collect(),
+            next_cursor,
+        )
+    }
+
+    async fn get_all_pages(
+        connection: &mut MySqlConnection,
+        user_id: &UserId,
+        params: &GetUserTracksParams,
+    ) -> Vec<TrackId> {
+        let (mut track_ids, mut cursor) = get_page(connection, user_id, params, None).await;
+
+        while let Some(current_cursor) = cursor {
+            let (page, next_cursor) =
+                get_page(connection, user_id, params, Some(&current_cursor)).await;
+            track_ids.extend(page);
+            cursor = next_cursor;
+        }
+
+        track_ids
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_pages_tracks_having_equal_sorting_keys() {
+        let mut connection = test_connection().await;
+        let user_id = create_test_user(&mut connection).await;
+
+        for (title, duration) in [("B", 200), ("A", 100), ("B", 200), ("C", 100), ("B", 300)] {
+            create_test_track(&mut connection, &user_id, title, duration).await;
+        }
+
+        for sorting_column in [
+            SortingColumn::TrackId,
+            SortingColumn::Title,
+            SortingColumn::Duration,
+        ] {
+            for sorting_order in [SortingOrder::Asc, SortingOrder::Desc] {
+                let params = sorted_params(sorting_column, sorting_order);
+                let all_track_ids: Vec<_> =
+                    get_user_tracks(&mut connection, &user_id, &params, &None, &None)
+                        .await
+                        .unwrap()
+                        .iter()
+                        .map(|row| row.track.tid.clone())
+                        .collect();
+
+                assert_eq!(5, all_track_ids.len());
+                assert_eq!(
+                    all_track_ids,
+                    get_all_pages(&mut connection, &user_id, &params).await,
+                    "{:?} {:?}",
+                    sorting_column,
+                    sorting_order
+                );
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_pages_tracks_added_and_deleted_between_pages() {
+        for (sorting_order, titles) in [
+            (SortingOrder::Asc, ["A", "B", "C", "D", "E"]),
+            (SortingOrder::Desc, ["E", "D", "C", "B", "A"]),
+        ] {
+            let mut connection = test_connection().await;
+            let user_id = create_test_user(&mut connection).await;
+            let mut track_ids = vec![];
+
+            for title in titles {
+                track_ids.push(create_test_track(&mut connection, &user_id, title, 100).await);
+            }
+
+            let params = sorted_params(SortingColumn::Title, sorting_order);
+            let (first_page, cursor) = get_page(&mut connection, &user_id, &params, None).await;
+
+            assert_eq!(track_ids[..2], first_page);
+
+            // A track sorted before the cursor doesn't show up on the next pages,
+            // one sorted after it does, and a deleted track doesn't shift the pages.
+            let track_id_before =
+                create_test_track(&mut connection, &user_id, titles[0], 100).await;
+            let track_id_after = create_test_track(&mut connection, &user_id, titles[4], 100).await;
+            delete_user_track(&mut connection, &track_ids[2])
+                .await
+                .unwrap();
+
+            let mut next_pages = vec![];
+            let mut cursor = cursor;
+            while let Some(current_cursor) = cursor {
+                let (page, next_cursor) =
+                    get_page(&mut connection, &user_id, &params, Some(&current_cursor)).await;
+                next_pages.extend(page);
+                cursor = next_cursor;
+            }
+
+            let all_track_ids = get_all_pages(&mut connection, &user_id, &params).await;
+            let cursor_position = all_track_ids
+                .iter()
+                .position(|track_id| track_id == &track_ids[1])
+                .unwrap();
+
+            assert_eq!(all_track_ids[cursor_position + 1..], next_pages);
+            assert_eq!(3, next_pages.len());
+            assert!(!next_pages.contains(&track_id_before));
+            assert!(next_pages.contains(&track_id_after));
+            assert!(!next_pages.contains(&track_ids[2]));
+        }
+    }
+
+    async fn get_new_track_ids(connection: &mut MySqlConnection, user_id: &UserId) -> Vec<TrackId> {
+        let params = GetUserTracksParams {
+            new_only: true,
+            ..sorted_params(SortingColumn::TrackId, SortingOrder::Asc)
+        };
+
+        get_user_tracks(connection, user_id, &params, &None, &None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| row.track.tid)
+            .collect()
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_lists_new_tracks() {
+        let mut connection = test_connection().await;
+        let user_id = create_test_user(&mut connection).await;
+        let mut track_ids = vec![];
+
+        for title in ["A", "B", "C"] {
+            track_ids.push(create_test_track(&mut connection, &user_id, title, 100).await);
+        }
+
+        mark_user_tracks_seen(&mut connection, &user_id, Some(&track_ids[1..2]), None)
+            .await
+            .unwrap();
+        trash_user_tracks(&mut connection, &user_id, &track_ids[2..])
+            .await
+            .unwrap();
+
+        let total_params = GetUserTracksTotalParams {
+            new_only: true,
+            ..GetUserTracksTotalParams::default()
+        };
+
+        assert_eq!(
+            vec![track_ids[0].clone()],
+            get_new_track_ids(&mut connection, &user_id).await
+        );
+        assert_eq!(
+            1,
+            get_user_tracks_count(&mut connection, &user_id, &total_params)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_marks_selected_tracks_seen() {
+        let mut connection = test_connection().await;
+        let user_id = create_test_user(&mut connection).await;
+        let other_user_id = create_test_user(&mut connection).await;
+        let mut track_ids = vec![];
+
+        for title in ["Morning", "Evening", "Night", "Noon"] {
+            track_ids.push(create_test_track(&mut connection, &user_id, title, 100).await);
+        }
+        let other_track_id = create_test_track(&mut connection, &other_user_id, "Night", 100).await;
+
+        // Tracks of other users aren't marked even if they are selected.
+        let marked_count = mark_user_tracks_seen(
+            &mut connection,
+            &user_id,
+            Some(&[track_ids[0].clone(), other_track_id.clone()]),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(1, marked_count);
+        assert_eq!(
+            track_ids[1..],
+            get_new_track_ids(&mut connection, &user_id).await
+        );
+
+        let filter = TrackQuery::parse("title:Night").unwrap();
+        let marked_count = mark_user_tracks_seen(&mut connection, &user_id, None, Some(&filter))
+            .await
+            .unwrap();
+
+        assert_eq!(1, marked_count);
+        assert_eq!(
+            vec![track_ids[1].clone(), track_ids[3].clone()],
+            get_new_track_ids(&mut connection, &user_id).await
+        );
+
+        let marked_count = mark_user_tracks_seen(&mut connection, &user_id, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(2, marked_count);
+        assert!(get_new_track_ids(&mut connection, &user_id)
+            .await
+            .is_empty());
+        assert_eq!(
+            vec![other_track_id],
+            get_new_track_ids(&mut connection, &other_user_id).await
+        );
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_clears_new_flag_of_tracks_added_to_stream() {
+        let mut connection = test_connection().await;
+        let user_id = create_test_user(&mut connection).await;
+        let stream_id = create_test_stream(&mut connection, &user_id).await;
+        let first_track_id = create_test_track(&mut connection, &user_id, "A", 100).await;
+        let second_track_id = create_test_track(&mut connection, &user_id, "B", 100).await;
+
+        append_track_to_stream(&mut connection, &stream_id, &first_track_id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![second_track_id],
+            get_new_track_ids(&mut connection, &user_id).await
+        );
+    }
+}
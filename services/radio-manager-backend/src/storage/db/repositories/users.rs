@@ -132,3 +132,122 @@ pub(crate) async fn update_user_password(
 
     Ok(row_count != 0)
 }
+
+pub(crate) async fn is_user_permalink_taken(
+    connection: &mut MySqlConnection,
+    permalink: &str,
+    user_id: &UserId,
+) -> RepositoryResult<bool> {
+    let query = query_as::<_, (i64,)>(
+        "SELECT COUNT(*) FROM `r_users` WHERE `permalink` = ? AND `uid` != ?",
+    )
+    .bind(permalink)
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let (count,) = query.fetch_one(connection.deref_mut()).await?;
+
+    Ok(count > 0)
+}
+
+pub(crate) async fn is_country_exists(
+    connection: &mut MySqlConnection,
+    country_id: &i64,
+) -> RepositoryResult<bool> {
+    let query =
+        query_as::<_, (i64,)>("SELECT COUNT(*) FROM `mor_countries` WHERE `country_id` = ?")
+            .bind(country_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let (count,) = query.fetch_one(connection.deref_mut()).await?;
+
+    Ok(count > 0)
+}
+
+pub(crate) async fn update_user_profile(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    name: &Option<String>,
+    info: &Option<String>,
+    permalink: &Option<String>,
+    country_id: &Option<i64>,
+) -> RepositoryResult<()> {
+    let query = query(
+        "UPDATE `r_users` SET `name` = ?, `info` = ?, `permalink` = ?, `country_id` = ? WHERE `uid` = ?",
+    )
+    .bind(name)
+    .bind(info)
+    .bind(permalink)
+    .bind(country_id)
+    .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn update_user_email(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    email: &str,
+) -> RepositoryResult<()> {
+    let query = query("UPDATE `r_users` SET `mail` = ? WHERE `uid` = ?")
+        .bind(email)
+        .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+pub(crate) async fn update_user_avatar(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    avatar: Option<&str>,
+) -> RepositoryResult<()> {
+    let query = query("UPDATE `r_users` SET `avatar` = ? WHERE `uid` = ?")
+        .bind(avatar)
+        .bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
+
+/// Returns the avatar file names of all users.
+pub(crate) async fn get_user_avatars(
+    connection: &mut MySqlConnection,
+) -> RepositoryResult<Vec<String>> {
+    let query = query_as::<_, (String,)>(
+        "SELECT `avatar` FROM `r_users` WHERE `avatar` IS NOT NULL AND `avatar` != ''",
+    );
+
+    trace!("Running SQL query: {}", query.sql());
+
+    let rows = query.fetch_all(connection.deref_mut()).await?;
+
+    Ok(rows.into_iter().map(|(avatar,)| avatar).collect())
+}
+
+/// Deletes the user. Streams, tracks and stream links are removed by the database
+/// through cascading foreign keys.
+pub(crate) async fn delete_user(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> RepositoryResult<()> {
+    let query = query("DELETE FROM `r_users` WHERE `uid` = ?").bind(user_id);
+
+    trace!("Running SQL query: {}", query.sql());
+
+    query.execute(connection.deref_mut()).await?;
+
+    Ok(())
+}
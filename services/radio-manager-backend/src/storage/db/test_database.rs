@@ -0,0 +1,88 @@
+//! Support of the tests running against a real database. The tests are ignored by default:
+//! they need `TEST_DATABASE_URL` pointing to a database migrated from the `migrations`
+//! directory, and run with `cargo test -- --ignored`.
+use crate::data_structures::{StreamAccess, StreamId, TrackId, UserId};
+use crate::mysql_client::MySqlConnection;
+use crate::storage::db::repositories::streams::{create_stream, StreamFields};
+use crate::storage::db::repositories::user_tracks::{create_user_track, NewTrack};
+use crate::storage::db::repositories::{files, users};
+use sqlx::mysql::MySqlPoolOptions;
+
+/// Begins the transaction every test runs in. It's rolled back when the connection is dropped,
+/// so the tests leave nothing behind and don't see each other's data.
+pub(crate) async fn test_connection() -> MySqlConnection {
+    let database_url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is required");
+    let pool = MySqlPoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await
+        .expect("Unable to connect to the test database");
+
+    MySqlConnection::Transaction(
+        pool.begin()
+            .await
+            .expect("Unable to begin test transaction"),
+    )
+}
+
+fn unique_name() -> String {
+    uuid::Uuid::new_v4().to_string().replace('-', "")
+}
+
+pub(crate) async fn create_test_user(connection: &mut MySqlConnection) -> UserId {
+    let email = format!("{}@example.com", unique_name());
+
+    users::create_user(connection, &email, "")
+        .await
+        .expect("Unable to create test user")
+        .uid
+}
+
+pub(crate) async fn create_test_stream(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+) -> StreamId {
+    let permalink = unique_name();
+    let fields = StreamFields {
+        name: "Test",
+        permalink: &permalink,
+        info: "",
+        hashtags: "",
+        category: None,
+        access: StreamAccess::Public,
+    };
+
+    create_stream(connection, user_id, &fields)
+        .await
+        .expect("Unable to create test stream")
+}
+
+pub(crate) async fn create_test_track(
+    connection: &mut MySqlConnection,
+    user_id: &UserId,
+    title: &str,
+    duration: i64,
+) -> TrackId {
+    let hash = unique_name();
+    let file_id = files::create_file(connection, 1024, &hash, "mp3")
+        .await
+        .expect("Unable to create test file");
+    let track = NewTrack {
+        file_id: &file_id,
+        filename: "test.mp3",
+        hash: &hash,
+        ext: "mp3",
+        artist: "Artist",
+        title,
+        album: "",
+        track_number: "",
+        genre: "",
+        date: "",
+        duration,
+        filesize: 1024,
+    };
+
+    create_user_track(connection, user_id, &track)
+        .await
+        .expect("Unable to create test track")
+}
@@ -0,0 +1,538 @@
+//! Track search query language.
+//!
+//! The query consists of whitespace separated terms, each can be negated with the leading `-`:
+//!
+//! * `word` or `"some phrase"` - full-text search over artist, title and genre;
+//! * `artist:`, `title:`, `album:`, `genre:` - the field contains the value;
+//! * `duration:` - duration in seconds or `minutes:seconds`;
+//! * `added:` - upload date in `YYYY-MM-DD` format;
+//! * `color:` - color label number.
+//!
+//! Words are matched by prefix and all of them must be present, the same way as
+//! [`crate::utils::to_boolean_search_query`] searches public streams. A word followed by
+//! a colon which isn't one of the fields, like `live:` or `12:30`, is searched as text.
+//!
+//! Duration and upload date may be prefixed with `<`, `<=`, `>`, `>=` or `=`, for example
+//! `artist:"Miles Davis" duration:>300 genre:jazz -live color:3 added:<2023-01-01`.
+use chrono::NaiveDate;
+use sqlx::{MySql, QueryBuilder};
+
+const FULL_TEXT_COLUMNS: &str = "`r_tracks`.`artist`, `r_tracks`.`title`, `r_tracks`.`genre`";
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+const FIELDS: [&str; 7] = [
+    "artist", "title", "album", "genre", "duration", "added", "color",
+];
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub(crate) enum TrackQueryError {
+    #[error("Missing value for field \"{0}\"")]
+    MissingValue(String),
+    #[error("Unterminated quote")]
+    UnterminatedQuote,
+    #[error("Invalid duration \"{0}\": expected seconds or minutes:seconds")]
+    InvalidDuration(String),
+    #[error("Invalid date \"{0}\": expected YYYY-MM-DD")]
+    InvalidDate(String),
+    #[error("Invalid color \"{0}\": expected color number")]
+    InvalidColor(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TrackCondition {
+    Text(String),
+    Phrase(String),
+    Artist(String),
+    Title(String),
+    Album(String),
+    Genre(String),
+    /// Duration in milliseconds, compared with one second precision.
+    Duration(Comparison, i64),
+    /// Upload time as unix timestamp of the day start, compared with one day precision.
+    Added(Comparison, i64),
+    Color(u32),
+    /// Full-text search query in boolean mode passed as is, as the v0 API `filter` is.
+    BooleanMode(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TrackQueryTerm {
+    pub(crate) negated: bool,
+    pub(crate) condition: TrackCondition,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TrackQuery {
+    pub(crate) terms: Vec<TrackQueryTerm>,
+}
+
+impl TrackQuery {
+    /// Query searching the text in boolean mode without parsing it, keeping the behavior
+    /// of the v0 API `filter`.
+    pub(crate) fn boolean_mode(text: &str) -> Self {
+        let terms = (!text.is_empty())
+            .then(|| TrackQueryTerm {
+                negated: false,
+                condition: TrackCondition::BooleanMode(text.to_string()),
+            })
+            .into_iter()
+            .collect();
+
+        Self { terms }
+    }
+
+    pub(crate) fn parse(input: &str) -> Result<Self, TrackQueryError> {
+        let mut chars = input.chars().peekable();
+        let mut terms = vec![];
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let negated = chars.next_if_eq(&'-').is_some();
+
+            if chars.next_if_eq(&'"').is_some() {
+                let phrase = read_quoted(&mut chars)?;
+
+                if !phrase.trim().is_empty() {
+                    terms.push(TrackQueryTerm {
+                        negated,
+                        condition: TrackCondition::Phrase(phrase),
+                    });
+                }
+
+                continue;
+            }
+
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ':') {
+                word.push(c);
+            }
+
+            let is_field =
+                FIELDS.contains(&word.to_lowercase().as_str()) && chars.peek() == Some(&':');
+
+            if !is_field {
+                // Colons inside words, like in "12:30", are a part of the text.
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    word.push(c);
+                }
+
+                if !word.is_empty() {
+                    terms.push(TrackQueryTerm {
+                        negated,
+                        condition: TrackCondition::Text(word),
+                    });
+                }
+
+                continue;
+            }
+
+            chars.next();
+
+            let value = if chars.next_if_eq(&'"').is_some() {
+                read_quoted(&mut chars)?
+            } else {
+                let mut value = String::new();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+                value
+            };
+
+            terms.push(TrackQueryTerm {
+                negated,
+                condition: parse_field(&word.to_lowercase(), &value)?,
+            });
+        }
+
+        Ok(Self { terms })
+    }
+}
+
+fn read_quoted(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+) -> Result<String, TrackQueryError> {
+    let mut value = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(value),
+            Some(c) => value.push(c),
+            None => return Err(TrackQueryError::UnterminatedQuote),
+        }
+    }
+}
+
+fn parse_field(field: &str, value: &str) -> Result<TrackCondition, TrackQueryError> {
+    if value.is_empty() {
+        return Err(TrackQueryError::MissingValue(field.to_string()));
+    }
+
+    match field {
+        "artist" => Ok(TrackCondition::Artist(value.to_string())),
+        "title" => Ok(TrackCondition::Title(value.to_string())),
+        "album" => Ok(TrackCondition::Album(value.to_string())),
+        "genre" => Ok(TrackCondition::Genre(value.to_string())),
+        "duration" => {
+            let (comparison, duration) = parse_comparison(value);
+            let seconds = parse_duration_seconds(duration)
+                .ok_or_else(|| TrackQueryError::InvalidDuration(value.to_string()))?;
+
+            Ok(TrackCondition::Duration(comparison, seconds * 1000))
+        }
+        "added" => {
+            let (comparison, date) = parse_comparison(value);
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| TrackQueryError::InvalidDate(value.to_string()))?;
+            let timestamp = date
+                .and_hms_opt(0, 0, 0)
+                .expect("Midnight is a valid time")
+                .and_utc()
+                .timestamp();
+
+            Ok(TrackCondition::Added(comparison, timestamp))
+        }
+        "color" => value
+            .parse()
+            .map(TrackCondition::Color)
+            .map_err(|_| TrackQueryError::InvalidColor(value.to_string())),
+        _ => unreachable!("Field is one of FIELDS"),
+    }
+}
+
+fn parse_comparison(value: &str) -> (Comparison, &str) {
+    for (prefix, comparison) in [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (comparison, rest);
+        }
+    }
+
+    (Comparison::Equal, value)
+}
+
+fn parse_duration_seconds(value: &str) -> Option<i64> {
+    match value.split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes: i64 = minutes.parse().ok()?;
+            let seconds: i64 = seconds.parse().ok()?;
+
+            if seconds >= 60 || minutes < 0 || seconds < 0 {
+                return None;
+            }
+
+            Some(minutes * 60 + seconds)
+        }
+        None => value.parse().ok().filter(|seconds: &i64| *seconds >= 0),
+    }
+}
+
+/// Words are matched by prefix; characters having special meaning in the boolean mode
+/// full-text search are dropped.
+fn full_text_term(condition: &TrackCondition) -> Option<String> {
+    let is_plain_char = |c: &char| !"+-<>()~*\"@".contains(*c);
+
+    match condition {
+        TrackCondition::Text(word) => {
+            let word: String = word.chars().filter(is_plain_char).collect();
+            (!word.is_empty()).then(|| format!("{}*", word))
+        }
+        TrackCondition::Phrase(phrase) => {
+            let phrase: String = phrase.chars().filter(is_plain_char).collect();
+            (!phrase.trim().is_empty()).then(|| format!("\"{}\"", phrase))
+        }
+        _ => None,
+    }
+}
+
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+
+    escaped.push('%');
+    for c in value.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped.push('%');
+
+    escaped
+}
+
+/// Compares the column holding values of `unit` precision: `=` matches the whole unit.
+fn push_range_condition(
+    builder: &mut QueryBuilder<'_, MySql>,
+    column: &str,
+    comparison: Comparison,
+    start: i64,
+    unit: i64,
+) {
+    match comparison {
+        Comparison::Equal => {
+            builder.push(format_args!("{} >= ", column));
+            builder.push_bind(start);
+            builder.push(format_args!(" AND {} < ", column));
+            builder.push_bind(start + unit);
+        }
+        Comparison::Less => {
+            builder.push(format_args!("{} < ", column));
+            builder.push_bind(start);
+        }
+        Comparison::LessOrEqual => {
+            builder.push(format_args!("{} < ", column));
+            builder.push_bind(start + unit);
+        }
+        Comparison::Greater => {
+            builder.push(format_args!("{} >= ", column));
+            builder.push_bind(start + unit);
+        }
+        Comparison::GreaterOrEqual => {
+            builder.push(format_args!("{} >= ", column));
+            builder.push_bind(start);
+        }
+    }
+}
+
+fn push_term_start(builder: &mut QueryBuilder<'_, MySql>, negated: bool) {
+    builder.push(if negated { " AND NOT (" } else { " AND (" });
+}
+
+/// Appends the query terms to the `WHERE` clause of a query selecting from `r_tracks`.
+pub(crate) fn push_track_query_conditions(
+    builder: &mut QueryBuilder<'_, MySql>,
+    query: &TrackQuery,
+) {
+    let mut included_text = vec![];
+    let mut excluded_text = vec![];
+
+    for term in &query.terms {
+        match &term.condition {
+            TrackCondition::Text(_) | TrackCondition::Phrase(_) => {
+                // Words consisting of special characters only don't restrict the search.
+                if let Some(text) = full_text_term(&term.condition) {
+                    if term.negated {
+                        excluded_text.push(text);
+                    } else {
+                        included_text.push(text);
+                    }
+                }
+
+                continue;
+            }
+            TrackCondition::BooleanMode(text) => {
+                push_term_start(builder, term.negated);
+                builder.push(format_args!("MATCH({}) AGAINST (", FULL_TEXT_COLUMNS));
+                builder.push_bind(text.clone());
+                builder.push(" IN BOOLEAN MODE)");
+            }
+            TrackCondition::Artist(value)
+            | TrackCondition::Title(value)
+            | TrackCondition::Album(value)
+            | TrackCondition::Genre(value) => {
+                let column = match &term.condition {
+                    TrackCondition::Artist(_) => "`r_tracks`.`artist`",
+                    TrackCondition::Title(_) => "`r_tracks`.`title`",
+                    TrackCondition::Album(_) => "`r_tracks`.`album`",
+                    _ => "`r_tracks`.`genre`",
+                };
+
+                push_term_start(builder, term.negated);
+                builder.push(format_args!("{} LIKE ", column));
+                builder.push_bind(escape_like(value));
+            }
+            TrackCondition::Duration(comparison, milliseconds) => {
+                push_term_start(builder, term.negated);
+                push_range_condition(
+                    builder,
+                    "`r_tracks`.`duration`",
+                    *comparison,
+                    *milliseconds,
+                    1000,
+                );
+            }
+            TrackCondition::Added(comparison, timestamp) => {
+                push_term_start(builder, term.negated);
+                push_range_condition(
+                    builder,
+                    "`r_tracks`.`uploaded`",
+                    *comparison,
+                    *timestamp,
+                    SECONDS_PER_DAY,
+                );
+            }
+            TrackCondition::Color(color) => {
+                push_term_start(builder, term.negated);
+                builder.push("`r_tracks`.`color` = ");
+                builder.push_bind(*color);
+            }
+        }
+
+        builder.push(")");
+    }
+
+    if !included_text.is_empty() {
+        let terms = included_text
+            .iter()
+            .map(|term| format!("+{}", term))
+            .chain(excluded_text.iter().map(|term| format!("-{}", term)))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        builder.push(format_args!(" AND MATCH({}) AGAINST (", FULL_TEXT_COLUMNS));
+        builder.push_bind(terms);
+        builder.push(" IN BOOLEAN MODE)");
+    } else if !excluded_text.is_empty() {
+        // The boolean mode search doesn't match anything having only excluded terms.
+        builder.push(format_args!(
+            " AND NOT MATCH({}) AGAINST (",
+            FULL_TEXT_COLUMNS
+        ));
+        builder.push_bind(excluded_text.join(" "));
+        builder.push(" IN BOOLEAN MODE)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(negated: bool, condition: TrackCondition) -> TrackQueryTerm {
+        TrackQueryTerm { negated, condition }
+    }
+
+    #[test]
+    fn test_parses_query() {
+        let query = TrackQuery::parse(
+            r#"artist:"Miles Davis" duration:>300 genre:jazz -live color:3 added:<2023-01-01 "kind of""#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                term(false, TrackCondition::Artist("Miles Davis".to_string())),
+                term(
+                    false,
+                    TrackCondition::Duration(Comparison::Greater, 300_000)
+                ),
+                term(false, TrackCondition::Genre("jazz".to_string())),
+                term(true, TrackCondition::Text("live".to_string())),
+                term(false, TrackCondition::Color(3)),
+                term(false, TrackCondition::Added(Comparison::Less, 1672531200)),
+                term(false, TrackCondition::Phrase("kind of".to_string())),
+            ],
+            query.terms
+        );
+    }
+
+    #[test]
+    fn test_parses_text_with_colons() {
+        assert_eq!(
+            vec![
+                term(false, TrackCondition::Text("12:30".to_string())),
+                term(
+                    false,
+                    TrackCondition::Duration(Comparison::LessOrEqual, 330_000)
+                ),
+            ],
+            TrackQuery::parse("12:30 Duration:<=5:30").unwrap().terms
+        );
+        assert_eq!(
+            vec![
+                term(false, TrackCondition::Text("artst:Miles".to_string())),
+                term(true, TrackCondition::Text("live:".to_string())),
+            ],
+            TrackQuery::parse("artst:Miles -live:").unwrap().terms
+        );
+        assert!(TrackQuery::parse("   ").unwrap().terms.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_malformed_queries() {
+        assert_eq!(
+            Err(TrackQueryError::MissingValue("genre".to_string())),
+            TrackQuery::parse("genre: jazz")
+        );
+        assert_eq!(
+            Err(TrackQueryError::UnterminatedQuote),
+            TrackQuery::parse("artist:\"Miles")
+        );
+        assert_eq!(
+            Err(TrackQueryError::InvalidDuration(">5:75".to_string())),
+            TrackQuery::parse("duration:>5:75")
+        );
+        assert_eq!(
+            Err(TrackQueryError::InvalidDate("<2023-13-01".to_string())),
+            TrackQuery::parse("added:<2023-13-01")
+        );
+        assert_eq!(
+            Err(TrackQueryError::InvalidColor("red".to_string())),
+            TrackQuery::parse("color:red")
+        );
+    }
+
+    #[test]
+    fn test_builds_conditions() {
+        let query = TrackQuery::parse(
+            r#"miles -live -"at the" artist:50% duration:300 -added:>=2023-01-01"#,
+        )
+        .unwrap();
+        let mut builder = QueryBuilder::new("");
+        push_track_query_conditions(&mut builder, &query);
+
+        assert_eq!(
+            " AND (`r_tracks`.`artist` LIKE ?) \
+             AND (`r_tracks`.`duration` >= ? AND `r_tracks`.`duration` < ?) \
+             AND NOT (`r_tracks`.`uploaded` >= ?) \
+             AND MATCH(`r_tracks`.`artist`, `r_tracks`.`title`, `r_tracks`.`genre`) AGAINST (? IN BOOLEAN MODE)",
+            builder.sql()
+        );
+        assert_eq!(
+            Some("miles*".to_string()),
+            full_text_term(&TrackCondition::Text("mi(les".to_string()))
+        );
+        assert_eq!("%50\\%\\_%", escape_like("50%_"));
+    }
+
+    #[test]
+    fn test_builds_excluding_full_text_condition() {
+        let mut builder = QueryBuilder::new("");
+        push_track_query_conditions(&mut builder, &TrackQuery::parse("-live").unwrap());
+
+        assert_eq!(
+            " AND NOT MATCH(`r_tracks`.`artist`, `r_tracks`.`title`, `r_tracks`.`genre`) AGAINST (? IN BOOLEAN MODE)",
+            builder.sql()
+        );
+    }
+
+    #[test]
+    fn test_builds_boolean_mode_condition() {
+        let mut builder = QueryBuilder::new("");
+        push_track_query_conditions(&mut builder, &TrackQuery::boolean_mode("miles -live"));
+
+        assert_eq!(
+            " AND (MATCH(`r_tracks`.`artist`, `r_tracks`.`title`, `r_tracks`.`genre`) AGAINST (? IN BOOLEAN MODE))",
+            builder.sql()
+        );
+        assert!(TrackQuery::boolean_mode("").terms.is_empty());
+    }
+}
@@ -1,10 +1,11 @@
-use crate::storage::fs::FileSystem;
+use crate::storage::fs::{FileEntry, FileSystem};
 use crate::utils::TeeResultUtils;
 use async_trait::async_trait;
-use futures::SinkExt;
-use std::io::Read;
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use std::io::SeekFrom;
 use tokio::fs;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tracing::error;
 
 #[derive(Clone)]
@@ -16,59 +17,185 @@ impl LocalFileSystem {
     pub(crate) fn create(root_path: String) -> Self {
         Self { root_path }
     }
+
+    fn full_path(&self, path: &str) -> std::path::PathBuf {
+        std::path::Path::new(&self.root_path).join(path)
+    }
+
+    async fn create_file(&self, path: &str) -> std::io::Result<fs::File> {
+        let full_path = self.full_path(path);
+
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::File::create(&full_path).await
+    }
+}
+
+/// Streams at most `limit` bytes of the file through the channel.
+fn spawn_file_reader(
+    file: fs::File,
+    limit: Option<u64>,
+) -> mpsc::Receiver<std::io::Result<Vec<u8>>> {
+    let (tx, rx) = mpsc::channel(0);
+
+    actix_rt::spawn(async move {
+        let mut tx = tx;
+        let mut file = file;
+        let mut remaining = limit;
+
+        let mut buffer = [0u8; 8192];
+
+        loop {
+            let max_size = match remaining {
+                Some(0) => break,
+                Some(remaining) => buffer.len().min(remaining as usize),
+                None => buffer.len(),
+            };
+
+            let size = match file.read(&mut buffer[..max_size]).await {
+                Ok(size) => size,
+                Err(error) => {
+                    error!(?error, "Error reading chunk from file");
+                    let _ = tx.send(Err(error)).await;
+                    return;
+                }
+            };
+
+            if size == 0 {
+                // EOF reached
+                break;
+            }
+
+            if let Some(remaining) = remaining.as_mut() {
+                *remaining -= size as u64;
+            }
+
+            if let Err(error) = tx.send(Ok(buffer[..size].to_vec())).await {
+                error!(?error, "Error sending chunk through channel");
+                return;
+            }
+        }
+    });
+
+    rx
 }
 
 #[async_trait]
 impl FileSystem for LocalFileSystem {
     async fn delete_file(&self, path: &str) -> std::io::Result<()> {
-        let root_path = self.root_path.clone();
-        let path = path.to_string();
-
-        fs::remove_file(format!("{}/{}", root_path, path))
+        fs::remove_file(self.full_path(path))
             .await
             .tee_err(|error| error!(?error, "Unable to delete file from local file system"))?;
 
         Ok(())
     }
 
+    async fn put_file(&self, path: &str, contents: Vec<u8>) -> std::io::Result<()> {
+        let full_path = self.full_path(path);
+
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::write(&full_path, contents)
+            .await
+            .tee_err(|error| error!(?error, "Unable to write file to local file system"))?;
+
+        Ok(())
+    }
+
+    async fn put_file_stream(
+        &self,
+        path: &str,
+        mut contents: mpsc::Receiver<Vec<u8>>,
+    ) -> std::io::Result<()> {
+        let mut file = self
+            .create_file(path)
+            .await
+            .tee_err(|error| error!(?error, "Unable to create file in local file system"))?;
+
+        while let Some(chunk) = contents.next().await {
+            file.write_all(&chunk)
+                .await
+                .tee_err(|error| error!(?error, "Unable to write file to local file system"))?;
+        }
+
+        file.flush().await?;
+
+        Ok(())
+    }
+
     async fn get_file_contents(
         &self,
         path: &str,
-    ) -> std::io::Result<futures::channel::mpsc::Receiver<Vec<u8>>> {
-        let (tx, rx) = futures::channel::mpsc::channel(0);
+    ) -> std::io::Result<mpsc::Receiver<std::io::Result<Vec<u8>>>> {
+        let file = fs::File::open(self.full_path(path)).await?;
+
+        Ok(spawn_file_reader(file, None))
+    }
+
+    async fn get_file_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> std::io::Result<mpsc::Receiver<std::io::Result<Vec<u8>>>> {
+        let mut file = fs::File::open(self.full_path(path)).await?;
 
-        let root_path = self.root_path.clone();
-        let path = path.to_string();
+        file.seek(SeekFrom::Start(offset)).await?;
 
-        let file = fs::File::open(format!("{}/{}", root_path, path)).await?;
+        Ok(spawn_file_reader(file, Some(length)))
+    }
 
-        actix_rt::spawn(async move {
-            let mut tx = tx;
-            let mut file = file;
+    async fn list_files(&self, prefix: &str) -> std::io::Result<Vec<FileEntry>> {
+        let mut files = vec![];
+        let mut directories = vec![prefix.trim_matches('/').to_string()];
 
-            let mut buffer = [0u8; 8192];
+        while let Some(directory) = directories.pop() {
+            let mut entries = match fs::read_dir(self.full_path(&directory)).await {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
 
-            loop {
-                let size = match file.read(&mut buffer).await {
-                    Ok(size) => size,
-                    Err(error) => {
-                        error!(?error, "Error reading chunk from file");
-                        return;
-                    }
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let path = match directory.is_empty() {
+                    true => name,
+                    false => format!("{}/{}", directory, name),
                 };
+                let metadata = entry.metadata().await?;
 
-                if size == 0 {
-                    // EOF reached
-                    break;
-                }
-
-                if let Err(error) = tx.send(buffer[..size].to_vec()).await {
-                    error!(?error, "Error sending chunk through channel");
-                    return;
+                if metadata.is_dir() {
+                    directories.push(path);
+                } else if metadata.is_file() {
+                    files.push(FileEntry {
+                        path,
+                        size: metadata.len(),
+                        modified: metadata.modified()?.into(),
+                    });
                 }
             }
-        });
+        }
+
+        Ok(files)
+    }
 
-        Ok(rx)
+    async fn exists(&self, path: &str) -> std::io::Result<bool> {
+        match fs::metadata(self.full_path(path)).await {
+            Ok(metadata) => Ok(metadata.is_file()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn presigned_url(
+        &self,
+        _path: &str,
+        _content_disposition: Option<&str>,
+    ) -> std::io::Result<Option<String>> {
+        Ok(None)
     }
 }
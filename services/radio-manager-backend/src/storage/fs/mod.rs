@@ -1,14 +1,59 @@
+mod aws_signature;
 pub(crate) mod local;
+pub(crate) mod s3;
 pub(crate) mod utils;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// File stored in the file system, as returned by [`FileSystem::list_files`].
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FileEntry {
+    /// Path relative to the file system root.
+    pub(crate) path: String,
+    pub(crate) size: u64,
+    pub(crate) modified: DateTime<Utc>,
+}
 
 #[async_trait]
 pub(crate) trait FileSystem {
     async fn delete_file(&self, path: &str) -> std::io::Result<()>;
 
+    async fn put_file(&self, path: &str, contents: Vec<u8>) -> std::io::Result<()>;
+
+    /// Writes the file from the chunks received through the channel, without buffering
+    /// the whole file in memory.
+    async fn put_file_stream(
+        &self,
+        path: &str,
+        contents: futures::channel::mpsc::Receiver<Vec<u8>>,
+    ) -> std::io::Result<()>;
+
+    /// Streams the file through the channel. An error reading the file is sent as the last item.
     async fn get_file_contents(
         &self,
         path: &str,
-    ) -> std::io::Result<futures::channel::mpsc::Receiver<Vec<u8>>>;
+    ) -> std::io::Result<futures::channel::mpsc::Receiver<std::io::Result<Vec<u8>>>>;
+
+    /// Reads `length` bytes of the file starting at `offset`.
+    async fn get_file_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> std::io::Result<futures::channel::mpsc::Receiver<std::io::Result<Vec<u8>>>>;
+
+    /// Recursively lists the files under the given directory.
+    async fn list_files(&self, prefix: &str) -> std::io::Result<Vec<FileEntry>>;
+
+    async fn exists(&self, path: &str) -> std::io::Result<bool>;
+
+    /// Returns a temporary URL to download the file directly from the storage,
+    /// or `None` if the file system doesn't support direct links.
+    /// The storage responds with the given `Content-Disposition` header if it's specified.
+    async fn presigned_url(
+        &self,
+        path: &str,
+        content_disposition: Option<&str>,
+    ) -> std::io::Result<Option<String>>;
 }
@@ -0,0 +1,809 @@
+use crate::config::S3Config;
+use crate::storage::fs::aws_signature::{
+    authorization_header, canonical_query_string, presigned_query_string, uri_encode,
+    AwsCredentials, SignableRequest, UNSIGNED_PAYLOAD,
+};
+use crate::storage::fs::{FileEntry, FileSystem};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use reqwest::{Method, StatusCode, Url};
+use std::io::{Error, ErrorKind, Result};
+use tracing::{error, warn};
+
+/// Size of the parts of multipart uploads. S3 requires all parts except the last one
+/// to be at least 5 MiB.
+const MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+
+fn request_error(error: reqwest::Error) -> Error {
+    Error::other(error)
+}
+
+async fn response_error(response: reqwest::Response) -> Error {
+    let status = response.status();
+
+    if status == StatusCode::NOT_FOUND {
+        return Error::new(ErrorKind::NotFound, "Object not found");
+    }
+
+    let body = response.text().await.unwrap_or_default();
+
+    Error::other(format!(
+        "Unexpected S3 response status {}: {}",
+        status, body
+    ))
+}
+
+/// Returns the text content of the first occurrence of the XML element.
+fn get_xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start_tag = format!("<{}>", name);
+    let end_tag = format!("</{}>", name);
+
+    let start = xml.find(&start_tag)? + start_tag.len();
+    let end = start + xml[start..].find(&end_tag)?;
+
+    Some(&xml[start..end])
+}
+
+/// Returns the text content of every occurrence of the XML element.
+fn get_xml_elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let end_tag = format!("</{}>", name);
+    let mut elements = vec![];
+    let mut rest = xml;
+
+    while let Some(element) = get_xml_element(rest, name) {
+        elements.push(element);
+
+        let end = element.as_ptr() as usize - rest.as_ptr() as usize + element.len();
+        rest = &rest[end + end_tag.len()..];
+    }
+
+    elements
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parses the ListObjectsV2 response into the listed files and the continuation token
+/// of the next page.
+fn parse_list_objects_response(xml: &str) -> Result<(Vec<FileEntry>, Option<String>)> {
+    let invalid_response = || Error::other("Invalid ListObjectsV2 response");
+    let mut files = vec![];
+
+    for contents in get_xml_elements(xml, "Contents") {
+        let path = get_xml_element(contents, "Key").ok_or_else(invalid_response)?;
+        let size = get_xml_element(contents, "Size")
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(invalid_response)?;
+        let modified = get_xml_element(contents, "LastModified")
+            .and_then(|modified| DateTime::parse_from_rfc3339(modified).ok())
+            .ok_or_else(invalid_response)?;
+
+        files.push(FileEntry {
+            path: unescape_xml(path),
+            size,
+            modified: modified.with_timezone(&Utc),
+        });
+    }
+
+    let continuation_token = match get_xml_element(xml, "IsTruncated") {
+        Some("true") => Some(
+            get_xml_element(xml, "NextContinuationToken")
+                .map(unescape_xml)
+                .ok_or_else(invalid_response)?,
+        ),
+        _ => None,
+    };
+
+    Ok((files, continuation_token))
+}
+
+fn complete_multipart_upload_body(etags: &[String]) -> String {
+    let parts: String = etags
+        .iter()
+        .enumerate()
+        .map(|(index, etag)| {
+            format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                index + 1,
+                etag
+            )
+        })
+        .collect();
+
+    format!(
+        "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+        parts
+    )
+}
+
+/// Streams the response body through the channel.
+fn spawn_body_reader(response: reqwest::Response) -> mpsc::Receiver<Result<Vec<u8>>> {
+    let (tx, rx) = mpsc::channel(0);
+
+    actix_rt::spawn(async move {
+        let mut tx = tx;
+        let mut response = response;
+
+        loop {
+            let chunk = match response.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(error) => {
+                    error!(?error, "Error reading chunk from S3 object");
+                    let _ = tx.send(Err(request_error(error))).await;
+                    return;
+                }
+            };
+
+            if let Err(error) = tx.send(Ok(chunk.to_vec())).await {
+                error!(?error, "Error sending chunk through channel");
+                return;
+            }
+        }
+    });
+
+    rx
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum S3ConfigError {
+    #[error("Invalid S3 endpoint: {0}")]
+    InvalidEndpoint(String),
+    #[error("S3 bucket is not configured")]
+    MissingBucket,
+}
+
+/// File system on top of an S3-compatible object storage.
+#[derive(Clone)]
+pub(crate) struct S3FileSystem {
+    client: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    force_path_style: bool,
+    credentials: AwsCredentials,
+    presigned_url_expires_in: u64,
+}
+
+struct ObjectLocation {
+    host: String,
+    path: String,
+}
+
+impl ObjectLocation {
+    fn url(&self, scheme: &str, query: &[(&str, &str)]) -> String {
+        match query.is_empty() {
+            true => format!("{}://{}{}", scheme, self.host, self.path),
+            false => format!(
+                "{}://{}{}?{}",
+                scheme,
+                self.host,
+                self.path,
+                canonical_query_string(query)
+            ),
+        }
+    }
+}
+
+impl S3FileSystem {
+    pub(crate) fn create(config: &S3Config) -> std::result::Result<Self, S3ConfigError> {
+        let endpoint = Url::parse(&config.endpoint)
+            .map_err(|error| S3ConfigError::InvalidEndpoint(error.to_string()))?;
+
+        if config.bucket.is_empty() {
+            return Err(S3ConfigError::MissingBucket);
+        }
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            bucket: config.bucket.clone(),
+            force_path_style: config.force_path_style,
+            credentials: AwsCredentials {
+                access_key_id: config.access_key_id.clone(),
+                secret_access_key: config.secret_access_key.clone(),
+                region: config.region.clone(),
+            },
+            presigned_url_expires_in: config.presigned_url_expires_in,
+        })
+    }
+
+    fn object_location(&self, path: &str) -> ObjectLocation {
+        let endpoint_host = match self.endpoint.port() {
+            Some(port) => format!("{}:{}", self.endpoint.host_str().unwrap_or_default(), port),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let endpoint_path = self.endpoint.path().trim_end_matches('/');
+        let key = uri_encode(path.trim_start_matches('/'), false);
+
+        match self.force_path_style {
+            true => ObjectLocation {
+                host: endpoint_host,
+                path: format!(
+                    "{}/{}/{}",
+                    endpoint_path,
+                    uri_encode(&self.bucket, true),
+                    key
+                ),
+            },
+            false => ObjectLocation {
+                host: format!("{}.{}", self.bucket, endpoint_host),
+                path: format!("{}/{}", endpoint_path, key),
+            },
+        }
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: Option<Vec<u8>>,
+    ) -> Result<reqwest::Response> {
+        let location = self.object_location(path);
+        let time = Utc::now();
+        let amz_date = time.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut signed_headers = headers.to_vec();
+        signed_headers.push(("x-amz-content-sha256", UNSIGNED_PAYLOAD));
+        signed_headers.push(("x-amz-date", &amz_date));
+
+        let authorization = authorization_header(
+            &SignableRequest {
+                method: method.as_str(),
+                host: &location.host,
+                path: &location.path,
+                query: query.to_vec(),
+                headers: signed_headers.clone(),
+                payload_hash: UNSIGNED_PAYLOAD,
+            },
+            &time,
+            &self.credentials,
+        );
+
+        let mut request = self
+            .client
+            .request(method, location.url(self.endpoint.scheme(), query))
+            .header("authorization", authorization);
+
+        for (name, value) in signed_headers {
+            request = request.header(name, value);
+        }
+
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+
+        request.send().await.map_err(request_error)
+    }
+
+    async fn head(&self, path: &str) -> Result<Option<reqwest::Response>> {
+        let response = self.send(Method::HEAD, path, &[], &[], None).await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(Some(response)),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(response_error(response).await),
+        }
+    }
+
+    async fn get(
+        &self,
+        path: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<mpsc::Receiver<Result<Vec<u8>>>> {
+        let response = self.send(Method::GET, path, &[], headers, None).await?;
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        Ok(spawn_body_reader(response))
+    }
+
+    async fn put_object(&self, path: &str, contents: Vec<u8>) -> Result<()> {
+        let response = self
+            .send(Method::PUT, path, &[], &[], Some(contents))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        Ok(())
+    }
+
+    async fn create_multipart_upload(&self, path: &str) -> Result<String> {
+        let response = self
+            .send(Method::POST, path, &[("uploads", "")], &[], None)
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        let body = response.text().await.map_err(request_error)?;
+
+        match get_xml_element(&body, "UploadId") {
+            Some(upload_id) => Ok(upload_id.to_string()),
+            None => Err(Error::other(
+                "No upload id in CreateMultipartUpload response",
+            )),
+        }
+    }
+
+    async fn upload_part(
+        &self,
+        path: &str,
+        upload_id: &str,
+        part_number: usize,
+        contents: Vec<u8>,
+    ) -> Result<String> {
+        let part_number = part_number.to_string();
+        let response = self
+            .send(
+                Method::PUT,
+                path,
+                &[("partNumber", &part_number), ("uploadId", upload_id)],
+                &[],
+                Some(contents),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        match response
+            .headers()
+            .get("etag")
+            .and_then(|etag| etag.to_str().ok())
+        {
+            Some(etag) => Ok(etag.to_string()),
+            None => Err(Error::other("No ETag in UploadPart response")),
+        }
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        path: &str,
+        upload_id: &str,
+        etags: &[String],
+    ) -> Result<()> {
+        let response = self
+            .send(
+                Method::POST,
+                path,
+                &[("uploadId", upload_id)],
+                &[],
+                Some(complete_multipart_upload_body(etags).into_bytes()),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        // The request may fail after the response status has already been sent.
+        let body = response.text().await.map_err(request_error)?;
+
+        if let Some(message) = get_xml_element(&body, "Message") {
+            return Err(Error::other(format!(
+                "Unable to complete multipart upload: {}",
+                message
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, path: &str, upload_id: &str) {
+        let result = self
+            .send(Method::DELETE, path, &[("uploadId", upload_id)], &[], None)
+            .await;
+
+        match result {
+            Ok(response) if response.status().is_success() => (),
+            Ok(response) => {
+                let error = response_error(response).await;
+                warn!(?error, "Unable to abort multipart upload");
+            }
+            Err(error) => warn!(?error, "Unable to abort multipart upload"),
+        }
+    }
+
+    async fn upload_parts(
+        &self,
+        path: &str,
+        upload_id: &str,
+        first_part: Vec<u8>,
+        contents: &mut mpsc::Receiver<Vec<u8>>,
+    ) -> Result<Vec<String>> {
+        let mut etags = vec![self.upload_part(path, upload_id, 1, first_part).await?];
+        let mut buffer = Vec::with_capacity(MULTIPART_PART_SIZE);
+
+        while let Some(chunk) = contents.next().await {
+            buffer.extend_from_slice(&chunk);
+
+            if buffer.len() >= MULTIPART_PART_SIZE {
+                let part = std::mem::replace(&mut buffer, Vec::with_capacity(MULTIPART_PART_SIZE));
+                etags.push(
+                    self.upload_part(path, upload_id, etags.len() + 1, part)
+                        .await?,
+                );
+            }
+        }
+
+        if !buffer.is_empty() {
+            etags.push(
+                self.upload_part(path, upload_id, etags.len() + 1, buffer)
+                    .await?,
+            );
+        }
+
+        Ok(etags)
+    }
+}
+
+#[async_trait]
+impl FileSystem for S3FileSystem {
+    async fn delete_file(&self, path: &str) -> Result<()> {
+        let response = self.send(Method::DELETE, path, &[], &[], None).await?;
+
+        if !response.status().is_success() {
+            let error = response_error(response).await;
+            error!(?error, "Unable to delete file from S3");
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    async fn put_file(&self, path: &str, contents: Vec<u8>) -> Result<()> {
+        self.put_object(path, contents).await
+    }
+
+    async fn put_file_stream(
+        &self,
+        path: &str,
+        mut contents: mpsc::Receiver<Vec<u8>>,
+    ) -> Result<()> {
+        let mut first_part = Vec::with_capacity(MULTIPART_PART_SIZE);
+
+        while first_part.len() < MULTIPART_PART_SIZE {
+            match contents.next().await {
+                Some(chunk) => first_part.extend_from_slice(&chunk),
+                // Small files are uploaded using a single request.
+                None => return self.put_object(path, first_part).await,
+            }
+        }
+
+        let upload_id = self.create_multipart_upload(path).await?;
+
+        let result = match self
+            .upload_parts(path, &upload_id, first_part, &mut contents)
+            .await
+        {
+            Ok(etags) => {
+                self.complete_multipart_upload(path, &upload_id, &etags)
+                    .await
+            }
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = result {
+            error!(?error, "Unable to upload file to S3");
+            self.abort_multipart_upload(path, &upload_id).await;
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    async fn get_file_contents(&self, path: &str) -> Result<mpsc::Receiver<Result<Vec<u8>>>> {
+        self.get(path, &[]).await
+    }
+
+    async fn get_file_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<mpsc::Receiver<Result<Vec<u8>>>> {
+        if length == 0 {
+            let (_, rx) = mpsc::channel(0);
+            return Ok(rx);
+        }
+
+        let range = format!("bytes={}-{}", offset, offset + length - 1);
+
+        self.get(path, &[("range", &range)]).await
+    }
+
+    async fn list_files(&self, prefix: &str) -> Result<Vec<FileEntry>> {
+        let prefix = match prefix.trim_matches('/') {
+            "" => String::new(),
+            prefix => format!("{}/", prefix),
+        };
+        let mut files = vec![];
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix.as_str())];
+
+            if let Some(token) = &continuation_token {
+                query.push(("continuation-token", token.as_str()));
+            }
+
+            let response = self.send(Method::GET, "", &query, &[], None).await?;
+
+            if !response.status().is_success() {
+                return Err(response_error(response).await);
+            }
+
+            let body = response.text().await.map_err(request_error)?;
+            let (page, next_continuation_token) = parse_list_objects_response(&body)?;
+
+            files.extend(page);
+
+            match next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        Ok(files)
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool> {
+        Ok(self.head(path).await?.is_some())
+    }
+
+    async fn presigned_url(
+        &self,
+        path: &str,
+        content_disposition: Option<&str>,
+    ) -> Result<Option<String>> {
+        let location = self.object_location(path);
+        let query = match content_disposition {
+            Some(content_disposition) => {
+                vec![("response-content-disposition", content_disposition)]
+            }
+            None => vec![],
+        };
+
+        let query_string = presigned_query_string(
+            &SignableRequest {
+                method: "GET",
+                host: &location.host,
+                path: &location.path,
+                query,
+                headers: vec![],
+                payload_hash: UNSIGNED_PAYLOAD,
+            },
+            self.presigned_url_expires_in,
+            &Utc::now(),
+            &self.credentials,
+        );
+
+        Ok(Some(format!(
+            "{}?{}",
+            location.url(self.endpoint.scheme(), &[]),
+            query_string
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_bytes, header, header_exists, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn s3_config(endpoint: &str, force_path_style: bool) -> S3Config {
+        S3Config {
+            endpoint: endpoint.to_string(),
+            region: "us-east-1".to_string(),
+            bucket: "media".to_string(),
+            access_key_id: "minio".to_string(),
+            secret_access_key: "minio123".to_string(),
+            force_path_style,
+            presigned_url_expires_in: 3600,
+        }
+    }
+
+    #[test]
+    fn test_builds_object_location() {
+        let file_system = S3FileSystem::create(&s3_config("http://minio:9000", true)).unwrap();
+        let location = file_system.object_location("audio/a/b/ab c.mp3");
+
+        assert_eq!("minio:9000", location.host);
+        assert_eq!("/media/audio/a/b/ab%20c.mp3", location.path);
+        assert_eq!(
+            "http://minio:9000/media/audio/a/b/ab%20c.mp3?partNumber=1&uploadId=a%2Bb",
+            location.url("http", &[("uploadId", "a+b"), ("partNumber", "1")])
+        );
+
+        let file_system =
+            S3FileSystem::create(&s3_config("https://s3.amazonaws.com", false)).unwrap();
+        let location = file_system.object_location("/covers/stream00001.png");
+
+        assert_eq!("media.s3.amazonaws.com", location.host);
+        assert_eq!("/covers/stream00001.png", location.path);
+    }
+
+    #[test]
+    fn test_gets_xml_element() {
+        let xml = "<InitiateMultipartUploadResult><Bucket>media</Bucket>\
+                   <UploadId>VXBsb2FkIElE</UploadId></InitiateMultipartUploadResult>";
+
+        assert_eq!(Some("VXBsb2FkIElE"), get_xml_element(xml, "UploadId"));
+        assert_eq!(None, get_xml_element(xml, "Key"));
+    }
+
+    #[test]
+    fn test_parses_list_objects_response() {
+        let xml = "<ListBucketResult><Name>media</Name>\
+                   <IsTruncated>true</IsTruncated>\
+                   <NextContinuationToken>1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=</NextContinuationToken>\
+                   <Contents><Key>audio/a/b/ab.mp3</Key><LastModified>2026-10-19T12:00:00.000Z</LastModified>\
+                   <ETag>&quot;ab&quot;</ETag><Size>1024</Size></Contents>\
+                   <Contents><Key>audio/tom &amp; jerry.mp3</Key><LastModified>2026-10-19T13:00:00.000Z</LastModified>\
+                   <ETag>&quot;cd&quot;</ETag><Size>2048</Size></Contents>\
+                   </ListBucketResult>";
+
+        let (files, continuation_token) = parse_list_objects_response(xml).unwrap();
+
+        assert_eq!(
+            vec!["audio/a/b/ab.mp3", "audio/tom & jerry.mp3"],
+            files
+                .iter()
+                .map(|file| file.path.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1024, 2048],
+            files.iter().map(|file| file.size).collect::<Vec<_>>()
+        );
+        assert_eq!("2026-10-19T12:00:00+00:00", files[0].modified.to_rfc3339());
+        assert_eq!(
+            Some("1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM="),
+            continuation_token.as_deref()
+        );
+
+        let xml = "<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>";
+
+        assert_eq!((0, None), {
+            let (files, continuation_token) = parse_list_objects_response(xml).unwrap();
+            (files.len(), continuation_token)
+        });
+    }
+
+    #[test]
+    fn test_rejects_invalid_config() {
+        assert!(matches!(
+            S3FileSystem::create(&s3_config("", true)),
+            Err(S3ConfigError::InvalidEndpoint(_))
+        ));
+
+        let mut config = s3_config("http://minio:9000", true);
+        config.bucket = String::new();
+
+        assert!(matches!(
+            S3FileSystem::create(&config),
+            Err(S3ConfigError::MissingBucket)
+        ));
+    }
+
+    async fn read_all(mut contents: mpsc::Receiver<Result<Vec<u8>>>) -> Vec<u8> {
+        let mut buffer = vec![];
+
+        while let Some(chunk) = contents.next().await {
+            buffer.extend(chunk.unwrap());
+        }
+
+        buffer
+    }
+
+    #[actix_rt::test]
+    async fn test_talks_to_s3_server() {
+        let server = MockServer::start().await;
+        let file_system = S3FileSystem::create(&s3_config(&server.uri(), true)).unwrap();
+
+        Mock::given(method("PUT"))
+            .and(path("/media/audio/a.mp3"))
+            .and(header_exists("authorization"))
+            .and(header("x-amz-content-sha256", UNSIGNED_PAYLOAD))
+            .and(body_bytes(b"abcdef".to_vec()))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/media/audio/a.mp3"))
+            .and(header("range", "bytes=2-4"))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(b"cde".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/media/audio/a.mp3"))
+            .and(header_exists("authorization"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/media/audio/missing.mp3"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        file_system
+            .put_file("audio/a.mp3", b"abcdef".to_vec())
+            .await
+            .unwrap();
+
+        let range = file_system
+            .get_file_range("audio/a.mp3", 2, 3)
+            .await
+            .unwrap();
+        assert_eq!(b"cde".to_vec(), read_all(range).await);
+
+        file_system.delete_file("audio/a.mp3").await.unwrap();
+
+        let error = file_system
+            .get_file_contents("audio/missing.mp3")
+            .await
+            .unwrap_err();
+        assert_eq!(ErrorKind::NotFound, error.kind());
+    }
+
+    #[actix_rt::test]
+    async fn test_presigned_url_is_accepted_by_s3_server() {
+        let server = MockServer::start().await;
+        let file_system = S3FileSystem::create(&s3_config(&server.uri(), true)).unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/media/audio/a%20b.mp3"))
+            .and(query_param("X-Amz-Algorithm", "AWS4-HMAC-SHA256"))
+            .and(query_param("X-Amz-Expires", "3600"))
+            .and(query_param("response-content-disposition", "attachment"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"abc".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let url = file_system
+            .presigned_url("audio/a b.mp3", Some("attachment"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(url.starts_with(&format!("{}/media/audio/a%20b.mp3?", server.uri())));
+        assert!(url.contains("X-Amz-Signature="));
+
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("abc", response.text().await.unwrap());
+    }
+
+    #[test]
+    fn test_builds_complete_multipart_upload_body() {
+        assert_eq!(
+            "<CompleteMultipartUpload>\
+             <Part><PartNumber>1</PartNumber><ETag>\"a\"</ETag></Part>\
+             <Part><PartNumber>2</PartNumber><ETag>\"b\"</ETag></Part>\
+             </CompleteMultipartUpload>",
+            complete_multipart_upload_body(&["\"a\"".to_string(), "\"b\"".to_string()])
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::storage::db::repositories::FileRow;
+use crate::storage::fs::FileSystem;
 
 pub(crate) trait GetPath {
     fn get_path(&self) -> String;
@@ -15,3 +16,19 @@ impl GetPath for FileRow {
         )
     }
 }
+
+/// Returns the direct storage link to the audio file if the file system supports it,
+/// or the file server link otherwise. The path is relative to the audio directory.
+pub(crate) async fn get_audio_file_url<FS: FileSystem>(
+    file_system: &FS,
+    file_server_endpoint: &str,
+    file_path: &str,
+) -> std::io::Result<String> {
+    match file_system
+        .presigned_url(&format!("audio/{}", file_path), None)
+        .await?
+    {
+        Some(url) => Ok(url),
+        None => Ok(format!("{}audio/{}", file_server_endpoint, file_path)),
+    }
+}
@@ -1,4 +1,6 @@
+use crate::data_structures::TrackId;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use sha2::{Digest, Sha256};
 
 pub(crate) trait TeeResultUtils<I, E> {
     fn tee_ok<CB>(self, cb: CB) -> Self
@@ -50,3 +52,127 @@ pub(crate) fn hash_password(password: &str) -> Result<String, bcrypt::BcryptErro
 pub(crate) fn verify_password(password: &str, hash: &str) -> Result<bool, bcrypt::BcryptError> {
     verify(password, hash)
 }
+
+pub(crate) fn generate_opaque_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().to_string().replace("-", ""),
+        uuid::Uuid::new_v4().to_string().replace("-", "")
+    )
+}
+
+pub(crate) fn hash_opaque_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+const PERMALINK_MAX_LENGTH: usize = 255;
+
+/// Permalinks may contain only lowercase latin letters, digits and dashes. Permalinks of
+/// only digits are reserved, since they would be mistaken for ids.
+pub(crate) fn is_valid_permalink(permalink: &str) -> bool {
+    !permalink.is_empty()
+        && permalink.len() <= PERMALINK_MAX_LENGTH
+        && !permalink.chars().all(|c| c.is_ascii_digit())
+        && permalink
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Makes a permalink out of the arbitrary text by dropping unsupported characters
+/// and joining the words with dashes.
+pub(crate) fn slugify(text: &str) -> String {
+    let slug = text
+        .to_lowercase()
+        .split(|c: char| c.is_whitespace() || matches!(c, '-' | '_' | '/' | '|' | '+'))
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    slug.chars()
+        .take(PERMALINK_MAX_LENGTH)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string()
+}
+
+/// Converts user input into a full-text search query in boolean mode
+/// requiring every word to be present as a prefix.
+pub(crate) fn to_boolean_search_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| !matches!(c, '+' | '-' | '<' | '>' | '(' | ')' | '~' | '*' | '"' | '@'))
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("+{}*", word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Converts user input into a full-text search query in boolean mode matching the exact phrase.
+pub(crate) fn to_boolean_phrase_query(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "").trim())
+}
+
+/// Removes the repeated track ids, keeping the order of their first occurrences.
+pub(crate) fn dedup_track_ids(track_ids: &[TrackId]) -> Vec<TrackId> {
+    let mut unique_track_ids: Vec<TrackId> = vec![];
+    for track_id in track_ids {
+        if !unique_track_ids.contains(track_id) {
+            unique_track_ids.push(track_id.clone());
+        }
+    }
+
+    unique_track_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validates_permalink() {
+        assert!(is_valid_permalink("my-radio-42"));
+        assert!(!is_valid_permalink(""));
+        assert!(!is_valid_permalink("My Radio"));
+        assert!(!is_valid_permalink("42"));
+        assert!(!is_valid_permalink("радио"));
+        assert!(!is_valid_permalink(&"a".repeat(PERMALINK_MAX_LENGTH + 1)));
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!("my-cool-radio-42", slugify("  My Cool_Radio / 42! "));
+        assert_eq!("rocknroll-radio", slugify("Rock'n'Roll Radio"));
+        assert_eq!("", slugify("Радио"));
+        assert!(is_valid_permalink(&slugify(&"Radio ".repeat(100))));
+    }
+
+    #[test]
+    fn test_converts_text_to_boolean_search_query() {
+        assert_eq!("+rock* +radio*", to_boolean_search_query(" rock  +radio* "));
+        assert_eq!("+rocknroll*", to_boolean_search_query("\"rock-n-roll\" ()"));
+        assert_eq!("", to_boolean_search_query("  "));
+    }
+
+    #[test]
+    fn test_converts_text_to_boolean_phrase_query() {
+        assert_eq!("\"deep house\"", to_boolean_phrase_query(" deep \"house"));
+    }
+
+    #[test]
+    fn test_dedups_track_ids() {
+        let track_ids: Vec<TrackId> = [3, 1, 3, 2, 1].into_iter().map(TrackId::from).collect();
+
+        assert_eq!(
+            vec![TrackId::from(3), TrackId::from(1), TrackId::from(2)],
+            dedup_track_ids(&track_ids)
+        );
+    }
+}
@@ -33,4 +33,7 @@ where
 
     /// Returns whether the channel is closed or not.
     fn is_closed(&self) -> bool;
+
+    /// Returns the number of subscribers of the channel.
+    fn subscribers_count(&self) -> usize;
 }
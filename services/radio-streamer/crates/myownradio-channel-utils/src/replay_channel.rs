@@ -74,6 +74,11 @@ where
     fn is_closed(&self) -> bool {
         self.inner.is_closed()
     }
+
+    /// Returns the number of subscribers of the inner channel.
+    fn subscribers_count(&self) -> usize {
+        self.inner.subscribers_count()
+    }
 }
 
 impl<IN, T> ReplayChannel<IN, T>
@@ -186,4 +186,11 @@ where
 
         *is_closed
     }
+
+    /// Returns the number of subscribers of the channel.
+    ///
+    /// Subscribers that have disconnected are only dropped on the next send.
+    fn subscribers_count(&self) -> usize {
+        self.txs.read().unwrap().len()
+    }
 }
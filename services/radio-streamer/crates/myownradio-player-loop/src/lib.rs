@@ -3,4 +3,6 @@ mod running_time;
 mod types;
 
 pub use player_loop::{PlayerLoop, PlayerLoopError};
-pub use types::{CurrentTrack, NextTrack, NowPlaying, NowPlayingClient, NowPlayingError};
+pub use types::{
+    CurrentTrack, NextTrack, NowPlaying, NowPlayingClient, NowPlayingError, TrackStart,
+};
@@ -1,5 +1,5 @@
 use crate::running_time::RunningTime;
-use crate::types::{CurrentTrack, NowPlaying, NowPlayingClient, NowPlayingError};
+use crate::types::{CurrentTrack, NowPlaying, NowPlayingClient, NowPlayingError, TrackStart};
 use myownradio_ffmpeg_utils::{
     AudioTranscoderAsync, OutputFormat, Packet, TranscoderCreationError, TranscodingError,
 };
@@ -29,6 +29,8 @@ pub struct PlayerLoop<C: NowPlayingClient> {
     initial_time: SystemTime,
     current_track: Option<CurrentTrack>,
     transcoding_attempts: usize,
+    last_track_start: Option<TrackStart>,
+    unreported_track_start: Option<TrackStart>,
 }
 
 impl<C: NowPlayingClient> PlayerLoop<C> {
@@ -42,6 +44,8 @@ impl<C: NowPlayingClient> PlayerLoop<C> {
         let transcoder = None;
         let current_track = None;
         let transcoding_attempts = 0;
+        let last_track_start = None;
+        let unreported_track_start = None;
 
         Ok(Self {
             channel_id,
@@ -52,6 +56,8 @@ impl<C: NowPlayingClient> PlayerLoop<C> {
             initial_time,
             current_track,
             transcoding_attempts,
+            last_track_start,
+            unreported_track_start,
         })
     }
 
@@ -133,6 +139,16 @@ impl<C: NowPlayingClient> PlayerLoop<C> {
                 .api_client
                 .get_now_playing(&self.channel_id, &clock_time)
                 .await?;
+
+            // Restarting the loop right after the track start fetches the same track again,
+            // so each start is reported once.
+            if let Some(track_start) = Self::get_track_start(&now_playing, &clock_time) {
+                if self.last_track_start.as_ref() != Some(&track_start) {
+                    self.last_track_start.replace(track_start.clone());
+                    self.unreported_track_start.replace(track_start);
+                }
+            }
+
             let current_track = self
                 .current_track
                 .insert(Self::get_current_track(now_playing));
@@ -164,6 +180,11 @@ impl<C: NowPlayingClient> PlayerLoop<C> {
             .map(|track| track.title.as_str())
     }
 
+    /// Takes the track started since the previous call, if any.
+    pub fn take_track_start(&mut self) -> Option<TrackStart> {
+        self.unreported_track_start.take()
+    }
+
     /// Get the current running time value.
     pub fn current_running_time(&self) -> &Duration {
         self.running_time.time()
@@ -185,6 +206,26 @@ impl<C: NowPlayingClient> PlayerLoop<C> {
         }
     }
 
+    /// Returns the start of the track that `get_current_track` selects if it is played
+    /// from the beginning. Tracks joined in the middle are not started by the player loop.
+    fn get_track_start(now_playing: &NowPlaying, clock_time: &SystemTime) -> Option<TrackStart> {
+        if now_playing.current.position <= TRACK_POSITION_THRESHOLD {
+            return Some(TrackStart {
+                track_id: now_playing.current.track_id,
+                time: *clock_time - now_playing.current.position,
+            });
+        }
+
+        if now_playing.current.remaining_duration() <= TRACK_POSITION_THRESHOLD {
+            return Some(TrackStart {
+                track_id: now_playing.next.track_id,
+                time: *clock_time + now_playing.current.remaining_duration(),
+            });
+        }
+
+        None
+    }
+
     fn get_current_track(now_playing: NowPlaying) -> CurrentTrack {
         if now_playing.current.position <= TRACK_POSITION_THRESHOLD {
             return CurrentTrack {
@@ -195,6 +236,7 @@ impl<C: NowPlayingClient> PlayerLoop<C> {
 
         if now_playing.current.remaining_duration() <= TRACK_POSITION_THRESHOLD {
             return CurrentTrack {
+                track_id: now_playing.next.track_id,
                 url: now_playing.next.url,
                 title: now_playing.next.title,
                 position: Duration::ZERO,
@@ -249,12 +291,14 @@ mod tests {
 
             Ok(NowPlaying {
                 current: CurrentTrack {
+                    track_id: 1,
                     title: String::from("Sample Track"),
                     url: String::from("tests/fixtures/sample-6s.mp3"),
                     duration,
                     position,
                 },
                 next: NextTrack {
+                    track_id: 1,
                     title: String::from("Sample Track"),
                     url: String::from("tests/fixtures/sample-6s.mp3"),
                     duration,
@@ -293,6 +337,77 @@ mod tests {
         assert_eq!("Sample Track", player_loop.current_title().unwrap());
     }
 
+    #[actix_rt::test]
+    async fn test_take_track_start() {
+        let api_client = MockAPIClient::new();
+        let output_format = OutputFormat::MP3 {
+            bit_rate: 128_000,
+            sampling_rate: 48_000,
+        };
+        let initial_time = SystemTime::UNIX_EPOCH;
+        let mut player_loop =
+            PlayerLoop::create(123, api_client, output_format, initial_time).unwrap();
+
+        assert!(player_loop.take_track_start().is_none());
+        assert!(player_loop.process_next_audio_packets().await.is_ok());
+        assert_eq!(
+            Some(TrackStart {
+                track_id: 1,
+                time: initial_time,
+            }),
+            player_loop.take_track_start()
+        );
+        assert!(player_loop.take_track_start().is_none());
+    }
+
+    #[test]
+    fn test_get_track_start() {
+        let clock_time = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let now_playing = |position: Duration| NowPlaying {
+            current: CurrentTrack {
+                track_id: 1,
+                position,
+                duration: Duration::from_secs(10),
+                url: String::new(),
+                title: String::new(),
+            },
+            next: NextTrack {
+                track_id: 2,
+                duration: Duration::from_secs(10),
+                url: String::new(),
+                title: String::new(),
+            },
+        };
+
+        assert_eq!(
+            Some(TrackStart {
+                track_id: 1,
+                time: clock_time - Duration::from_millis(100),
+            }),
+            PlayerLoop::<MockAPIClient>::get_track_start(
+                &now_playing(Duration::from_millis(100)),
+                &clock_time
+            )
+        );
+        assert_eq!(
+            None,
+            PlayerLoop::<MockAPIClient>::get_track_start(
+                &now_playing(Duration::from_secs(5)),
+                &clock_time
+            )
+        );
+        assert_eq!(
+            Some(TrackStart {
+                track_id: 2,
+                time: clock_time + Duration::from_millis(100),
+            }),
+            PlayerLoop::<MockAPIClient>::get_track_start(
+                &now_playing(Duration::from_millis(9900)),
+                &clock_time
+            )
+        );
+    }
+
     #[actix_rt::test]
     async fn test_restart_player_loop() {
         // Create a mock API client.
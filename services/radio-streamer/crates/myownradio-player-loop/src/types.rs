@@ -2,6 +2,7 @@ use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Clone)]
 pub struct CurrentTrack {
+    pub track_id: u64,
     pub position: Duration,
     pub duration: Duration,
     pub url: String,
@@ -16,11 +17,20 @@ impl CurrentTrack {
 
 #[derive(Debug, Clone)]
 pub struct NextTrack {
+    pub track_id: u64,
     pub duration: Duration,
     pub url: String,
     pub title: String,
 }
 
+/// The track the player loop started to play from the beginning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackStart {
+    pub track_id: u64,
+    /// The time the track starts at according to the channel schedule.
+    pub time: SystemTime,
+}
+
 #[derive(Debug, Clone)]
 pub struct NowPlaying {
     pub current: CurrentTrack,
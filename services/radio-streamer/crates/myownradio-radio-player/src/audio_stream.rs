@@ -54,10 +54,9 @@ impl AudioStream {
         let timed_channel = TimedChannel::new(Duration::from_secs(30), 16);
         let replay_channel = Arc::new(ReplayChannel::new(timed_channel, START_BUFFER_TIME));
 
-        let backend_client = backend_client.clone();
         let player_loop = PlayerLoop::create(
             *channel_id.deref(),
-            backend_client,
+            backend_client.clone(),
             output_format.clone(),
             initial_time,
         )?;
@@ -67,6 +66,8 @@ impl AudioStream {
             let player_loop = player_loop.clone();
             let channel = Arc::clone(&replay_channel);
             let metrics = metrics.clone();
+            let backend_client = backend_client.clone();
+            let channel_id: usize = channel_id.clone().into();
 
             async move {
                 metrics.inc_active_player_loops();
@@ -103,6 +104,28 @@ impl AudioStream {
                         }
                     }
 
+                    if let Some(track_start) = lock
+                        .take_track_start()
+                        .filter(|track_start| track_start.track_id != 0)
+                    {
+                        let backend_client = backend_client.clone();
+                        let listeners = channel.subscribers_count();
+
+                        actix_rt::spawn(async move {
+                            if let Err(error) = backend_client
+                                .report_track_started(
+                                    &channel_id,
+                                    &track_start.track_id,
+                                    &track_start.time,
+                                    listeners,
+                                )
+                                .await
+                            {
+                                warn!(?error, "Unable to report the track start");
+                            }
+                        });
+                    }
+
                     drop(lock);
 
                     for packet in packets {
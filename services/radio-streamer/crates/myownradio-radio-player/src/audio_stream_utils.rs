@@ -16,12 +16,14 @@ impl myownradio_player_loop::NowPlayingClient for BackendClient {
         match BackendClient::get_now_playing(self, &channel_id, time).await {
             Ok(now_playing) => Ok(myownradio_player_loop::NowPlaying {
                 current: myownradio_player_loop::CurrentTrack {
+                    track_id: now_playing.current_track.track_id,
                     url: now_playing.current_track.url,
                     title: now_playing.current_track.title,
                     position: now_playing.current_track.offset,
                     duration: now_playing.current_track.duration,
                 },
                 next: myownradio_player_loop::NextTrack {
+                    track_id: now_playing.next_track.track_id,
                     url: now_playing.next_track.url,
                     title: now_playing.next_track.title,
                     duration: now_playing.next_track.duration,
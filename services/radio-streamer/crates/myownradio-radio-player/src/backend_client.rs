@@ -2,10 +2,16 @@ extern crate serde_millis;
 
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// How long the channel id resolved from a permalink is reused before resolving it again,
+/// so that changed permalinks are picked up.
+const RESOLVED_CHANNEL_ID_TTL: Duration = Duration::from_secs(60);
+
 #[derive(Deserialize, Debug, Serialize)]
 pub struct CurrentTrack {
     #[serde(with = "serde_millis")]
@@ -14,10 +20,16 @@ pub struct CurrentTrack {
     pub url: String,
     #[serde(with = "serde_millis")]
     pub duration: Duration,
+    /// Not returned by backends older than the play log: zero means unknown, and the start
+    /// of such a track isn't reported.
+    #[serde(default)]
+    pub track_id: u64,
 }
 
 #[derive(Deserialize, Debug, Serialize)]
 pub struct NextTrack {
+    #[serde(default)]
+    pub track_id: u64,
     pub title: String,
     pub url: String,
     #[serde(with = "serde_millis")]
@@ -38,10 +50,24 @@ pub struct GetNowPlayingResponse {
     pub data: NowPlaying,
 }
 
+/// Channels of unknown access are treated as private.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChannelAccess {
+    Public,
+    Unlisted,
+    #[default]
+    Private,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct ChannelInfo {
+    #[serde(default)]
+    pub sid: Option<usize>,
     pub name: String,
     pub status: u8,
+    #[serde(default)]
+    pub access: ChannelAccess,
 }
 
 #[derive(Deserialize, Debug)]
@@ -54,6 +80,8 @@ pub struct GetChannelInfoResponse {
 #[derive(Clone)]
 pub struct BackendClient {
     mor_backend_url: String,
+    client: reqwest::Client,
+    resolved_channel_ids: Arc<RwLock<HashMap<String, (usize, Instant)>>>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -76,10 +104,41 @@ pub enum GetChannelInfoError {
     UnexpectedResponse(GetChannelInfoResponse),
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum ResolveChannelIdError {
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    #[error("Channel {0} not found")]
+    ChannelNotFound(String),
+    #[error("Unexpected response: {0:?}")]
+    UnexpectedResponse(GetChannelInfoResponse),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReportTrackStartedError {
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum VerifyListenTokenError {
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    #[error("Channel {0} not found")]
+    ChannelNotFound(usize),
+}
+
 impl BackendClient {
     pub fn new(mor_backend_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Unable to initialize HTTP client");
+
         Self {
             mor_backend_url: mor_backend_url.to_string(),
+            client,
+            resolved_channel_ids: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -88,11 +147,6 @@ impl BackendClient {
         channel_id: &usize,
         time: &SystemTime,
     ) -> Result<NowPlaying, GetNowPlayingError> {
-        let client = reqwest::Client::builder()
-            .timeout(REQUEST_TIMEOUT)
-            .build()
-            .expect("Unable to initialize HTTP client");
-
         let unix_time = time.duration_since(UNIX_EPOCH).unwrap().as_millis();
 
         let url = format!(
@@ -100,7 +154,8 @@ impl BackendClient {
             &self.mor_backend_url, channel_id, &unix_time,
         );
 
-        let response: GetNowPlayingResponse = client
+        let response: GetNowPlayingResponse = self
+            .client
             .get(url)
             .send()
             .await?
@@ -130,11 +185,6 @@ impl BackendClient {
         channel_id: &usize,
         client_id: Option<String>,
     ) -> Result<ChannelInfo, GetChannelInfoError> {
-        let client = reqwest::Client::builder()
-            .timeout(REQUEST_TIMEOUT)
-            .build()
-            .expect("Unable to initialize HTTP client");
-
         let url = format!(
             "{}/pub/v0/streams/{}/info?client_id={}",
             &self.mor_backend_url,
@@ -142,7 +192,8 @@ impl BackendClient {
             &client_id.unwrap_or_default(),
         );
 
-        let response: GetChannelInfoResponse = client
+        let response: GetChannelInfoResponse = self
+            .client
             .get(url)
             .timeout(Duration::from_secs(5))
             .send()
@@ -174,4 +225,123 @@ impl BackendClient {
             GetChannelInfoResponse { .. } => Err(GetChannelInfoError::UnexpectedResponse(response)),
         }
     }
+
+    /// Resolves a channel reference, that is either a numeric channel id or a channel permalink,
+    /// into the channel id. Resolved permalinks are cached for `RESOLVED_CHANNEL_ID_TTL`.
+    pub async fn resolve_channel_id(&self, channel: &str) -> Result<usize, ResolveChannelIdError> {
+        if let Ok(channel_id) = channel.parse::<usize>() {
+            return Ok(channel_id);
+        }
+
+        if channel.is_empty()
+            || !channel
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(ResolveChannelIdError::ChannelNotFound(channel.to_string()));
+        }
+
+        if let Some((channel_id, resolved_at)) =
+            self.resolved_channel_ids.read().unwrap().get(channel)
+        {
+            if resolved_at.elapsed() < RESOLVED_CHANNEL_ID_TTL {
+                return Ok(*channel_id);
+            }
+        }
+
+        let url = format!("{}/pub/v0/streams/{}/info", &self.mor_backend_url, channel);
+
+        let response: GetChannelInfoResponse = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|error| {
+                if matches!(error.status(), Some(StatusCode::NOT_FOUND)) {
+                    ResolveChannelIdError::ChannelNotFound(channel.to_string())
+                } else {
+                    error.into()
+                }
+            })?
+            .json()
+            .await?;
+
+        match response {
+            GetChannelInfoResponse {
+                code,
+                message,
+                data: Some(ChannelInfo { sid: Some(sid), .. }),
+            } if (code == 1 && message == "OK") => {
+                let mut resolved_channel_ids = self.resolved_channel_ids.write().unwrap();
+                resolved_channel_ids
+                    .retain(|_, (_, resolved_at)| resolved_at.elapsed() < RESOLVED_CHANNEL_ID_TTL);
+                resolved_channel_ids.insert(channel.to_string(), (sid, Instant::now()));
+
+                Ok(sid)
+            }
+            GetChannelInfoResponse { .. } => {
+                Err(ResolveChannelIdError::UnexpectedResponse(response))
+            }
+        }
+    }
+
+    /// Reports the track started on the channel to the play history.
+    pub async fn report_track_started(
+        &self,
+        channel_id: &usize,
+        track_id: &u64,
+        time: &SystemTime,
+        listeners: usize,
+    ) -> Result<(), ReportTrackStartedError> {
+        let started_at = time.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+
+        let url = format!(
+            "{}/internal/radio-streamer/v0/streams/{}/track-started",
+            &self.mor_backend_url, channel_id,
+        );
+
+        self.client
+            .post(url)
+            .json(&serde_json::json!({
+                "track_id": track_id,
+                "started_at": started_at,
+                "listeners": listeners,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Returns whether the listen token grants access to the channel.
+    pub async fn verify_listen_token(
+        &self,
+        channel_id: &usize,
+        token: &str,
+    ) -> Result<bool, VerifyListenTokenError> {
+        let url = format!(
+            "{}/internal/radio-streamer/v0/streams/{}/verify-listen-token",
+            &self.mor_backend_url, channel_id,
+        );
+
+        let response = self
+            .client
+            .get(url)
+            .query(&[("token", token)])
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::FORBIDDEN => Ok(false),
+            StatusCode::NOT_FOUND => Err(VerifyListenTokenError::ChannelNotFound(*channel_id)),
+            _ => {
+                response.error_for_status()?;
+
+                Ok(false)
+            }
+        }
+    }
 }
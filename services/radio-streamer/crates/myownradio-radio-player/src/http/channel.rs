@@ -1,6 +1,9 @@
 use super::utils::icy_muxer::{IcyMuxer, ICY_METADATA_INTERVAL};
 use crate::audio_formats::{AudioFormat, AudioFormats};
 use crate::audio_stream::AudioStreamMessage;
+use crate::backend_client::{
+    BackendClient, ChannelAccess, GetChannelInfoError, ResolveChannelIdError,
+};
 use crate::config::Config;
 use crate::stream_compositor::StreamCompositor;
 use crate::types::ChannelId;
@@ -65,20 +68,30 @@ impl Into<OutputFormat> for AudioFormat {
 #[derive(Deserialize, Clone)]
 pub struct GetChannelAudioStreamV3QueryParams {
     format: Option<String>,
+    token: Option<String>,
 }
 
-#[get("/v3/listen/{channel_id}")]
+#[get("/v3/listen/{channel}")]
 pub(crate) async fn get_channel_audio_stream_v3(
     request: HttpRequest,
-    channel_id: web::Path<u64>,
+    channel: web::Path<String>,
     query_params: Query<GetChannelAudioStreamV3QueryParams>,
     stream_compositor: Data<StreamCompositor>,
+    backend_client: Data<Arc<BackendClient>>,
 ) -> impl Responder {
-    let channel_id: ChannelId = channel_id.into_inner().into();
+    let channel_id: ChannelId = match backend_client.resolve_channel_id(&channel).await {
+        Ok(channel_id) => channel_id.into(),
+        Err(ResolveChannelIdError::ChannelNotFound(_)) => {
+            return HttpResponse::NotFound().finish();
+        }
+        Err(error) => {
+            tracing::error!(?error, "Unable to resolve channel");
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let GetChannelAudioStreamV3QueryParams { format, token } = query_params.into_inner();
 
-    let format = query_params
-        .into_inner()
-        .format
+    let format = format
         .and_then(|format| AudioFormats::from_string(&format))
         .unwrap_or_default();
     let is_icy_enabled = request
@@ -89,6 +102,41 @@ pub(crate) async fn get_channel_audio_stream_v3(
     let content_type = format.content_type;
     let output_format: OutputFormat = format.into();
 
+    // The access is checked against the current channel info before any stream is created,
+    // so that unauthorized requests don't start decoding and access changes apply immediately.
+    let channel_info = match backend_client
+        .get_channel_info(&channel_id.clone().into(), None)
+        .await
+    {
+        Ok(channel_info) => channel_info,
+        Err(GetChannelInfoError::ChannelNotFound(_)) => {
+            return HttpResponse::NotFound().finish();
+        }
+        Err(error) => {
+            tracing::error!(?error, "Unable to get channel info");
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    if channel_info.access != ChannelAccess::Public {
+        let token = match token {
+            Some(token) => token,
+            None => return HttpResponse::Unauthorized().finish(),
+        };
+
+        match backend_client
+            .verify_listen_token(&channel_id.clone().into(), &token)
+            .await
+        {
+            Ok(true) => (),
+            Ok(false) => return HttpResponse::Forbidden().finish(),
+            Err(error) => {
+                tracing::error!(?error, "Unable to verify listen token");
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+
     let stream = match stream_compositor
         .get_or_create_audio_stream(&channel_id, &output_format)
         .await
@@ -2,6 +2,7 @@ import z from 'zod'
 
 export enum UserEventType {
   RestartChannel = 'RestartChannel',
+  NowPlayingUpdated = 'NowPlayingUpdated',
   OutgoingStreamStarted = 'OutgoingStreamStarted',
   OutgoingStreamStats = 'OutgoingStreamStats',
   OutgoingStreamFinished = 'OutgoingStreamFinished',
@@ -13,6 +14,11 @@ const RestartChannelUserEventSchema = z.object({
   channelId: z.number(),
 })
 
+const NowPlayingUpdatedUserEventSchema = z.object({
+  eventType: z.literal(UserEventType.NowPlayingUpdated),
+  channelId: z.number(),
+})
+
 const OutgoingStreamStartedUserEventSchema = z.object({
   channelId: z.number(),
   eventType: z.literal(UserEventType.OutgoingStreamStarted),
@@ -37,6 +43,7 @@ const OutgoingStreamErrorUserEventSchema = z.object({
 
 export const UserEventSchema = z.union([
   RestartChannelUserEventSchema,
+  NowPlayingUpdatedUserEventSchema,
   OutgoingStreamStartedUserEventSchema,
   OutgoingStreamStatsUserEventSchema,
   OutgoingStreamFinishedUserEventSchema,
@@ -25,7 +25,10 @@ export const NowPlayingProvider: React.FC<Props> = ({ channelId, children }) =>
   const userEventSource = useUserEvent()
   useEffect(() => {
     return userEventSource.subscribe((event) => {
-      if (event.eventType === UserEventType.RestartChannel && event.channelId === channelId) {
+      const isNowPlayingChanged =
+        event.eventType === UserEventType.RestartChannel ||
+        event.eventType === UserEventType.NowPlayingUpdated
+      if (isNowPlayingChanged && event.channelId === channelId) {
         refresh()
       }
     })